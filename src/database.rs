@@ -0,0 +1,190 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use anyhow::{anyhow, bail, Result};
+
+use crate::schema::{DatabaseSchema, Sequence, TableSchema, View};
+use crate::table::Table;
+
+pub struct Database {
+    data_dir: PathBuf,
+    schema: DatabaseSchema,
+    tables: HashMap<String, Table>,
+    /// Sequence values saved as handed out but not handed out yet, by sequence.
+    reserved: HashMap<String, Reserved>,
+}
+
+struct Reserved {
+    last: i64,
+    remaining: usize,
+}
+
+impl Database {
+    /// Number of sequence values reserved with one save of the schema.
+    pub const SEQUENCE_BLOCK: usize = 32;
+
+    pub fn open<P>(data_dir: P) -> Result<Self>
+    where
+        P: AsRef<Path>,
+    {
+        let data_dir = data_dir.as_ref().to_path_buf();
+        let schema = DatabaseSchema::load(data_dir.join("schema.json"))?;
+
+        Ok(Self {
+            data_dir,
+            schema,
+            tables: HashMap::new(),
+            reserved: HashMap::new(),
+        })
+    }
+
+    pub fn schema(&self) -> &DatabaseSchema {
+        &self.schema
+    }
+
+    pub fn create_table(&mut self, name: &str, mut schema: TableSchema) -> Result<()> {
+        schema.prepare(name)?;
+        self.schema.resolve_references(name, &mut schema)?;
+        self.schema.add_table(name, schema);
+        self.schema.save(self.data_dir.join("schema.json"))
+    }
+
+    /// Removes a table with its file, undoing a CREATE TABLE whose rows could
+    /// not all be inserted.
+    pub fn drop_table(&mut self, name: &str) -> Result<()> {
+        self.tables.remove(name);
+        self.schema.remove_table(name);
+        self.schema.save(self.data_dir.join("schema.json"))?;
+
+        match std::fs::remove_file(self.data_dir.join(format!("{}.db", name))) {
+            Err(err) if err.kind() != std::io::ErrorKind::NotFound => Err(err.into()),
+            _ => Ok(()),
+        }
+    }
+
+    pub fn create_sequence(&mut self, name: &str, sequence: Sequence) -> Result<()> {
+        if self.schema.get_sequence(name).is_some() {
+            bail!("Sequence \"{}\" already exists", name);
+        }
+
+        if sequence.increment == 0 {
+            bail!("INCREMENT must not be zero");
+        }
+
+        self.schema.add_sequence(name, sequence);
+        self.schema.save(self.data_dir.join("schema.json"))
+    }
+
+    pub fn create_view(&mut self, name: &str, view: View) -> Result<()> {
+        self.schema.add_view(name, view);
+        self.schema.save(self.data_dir.join("schema.json"))
+    }
+
+    /// Moves the sequence to its next value. Values are reserved in blocks
+    /// of [`Database::SEQUENCE_BLOCK`], the schema is saved with the end of a
+    /// block before its first value is handed out. No value is given twice,
+    /// even when the tables are not flushed, but the values left in a block
+    /// are skipped once the database is opened again.
+    pub fn nextval(&mut self, name: &str) -> Result<i64> {
+        let sequence = self
+            .schema
+            .get_sequence_mut(name)
+            .ok_or_else(|| anyhow!("Sequence \"{}\" does not exist", name))?;
+
+        if let Some(reserved) = self.reserved.get_mut(name) {
+            if reserved.remaining > 0 {
+                reserved.last += sequence.increment;
+                reserved.remaining -= 1;
+                return Ok(reserved.last);
+            }
+        }
+
+        let value = sequence
+            .next()
+            .ok_or_else(|| anyhow!("Sequence \"{}\" reached its limit", name))?;
+
+        let remaining = (1..Self::SEQUENCE_BLOCK)
+            .take_while(|_| sequence.next().is_some())
+            .count();
+
+        self.schema.save(self.data_dir.join("schema.json"))?;
+        self.reserved.insert(
+            name.to_string(),
+            Reserved {
+                last: value,
+                remaining,
+            },
+        );
+
+        Ok(value)
+    }
+
+    pub fn table(&mut self, name: &str) -> Result<&mut Table> {
+        if !self.tables.contains_key(name) {
+            // Views are expanded by the queries reading them
+            if self.schema.get_view(name).is_some() {
+                bail!("\"{}\" is a view, not a table", name);
+            }
+
+            let schema = self
+                .schema
+                .get_table_schema(name)
+                .ok_or_else(|| anyhow!("Table \"{}\" does not exist", name))?;
+
+            let path = self.data_dir.join(format!("{}.db", name));
+            let table = Table::open(path, schema.clone())?;
+            self.tables.insert(String::from(name), table);
+        }
+
+        Ok(self.tables.get_mut(name).unwrap())
+    }
+
+    pub fn flush(&mut self) {
+        for table in self.tables.values_mut() {
+            table.flush();
+        }
+    }
+}
+
+/// Helpers for tests that need a database on disk.
+#[cfg(test)]
+pub mod testing {
+    use std::path::PathBuf;
+
+    use super::Database;
+
+    /// Directory holding an empty database, removed with its contents when
+    /// dropped, including when the test fails.
+    pub struct TempDatabase {
+        dir: PathBuf,
+    }
+
+    impl TempDatabase {
+        pub fn new(name: &str) -> Self {
+            let dir =
+                std::env::temp_dir().join(format!("mini-sql-{}-{}", name, std::process::id()));
+            let _ = std::fs::remove_dir_all(&dir);
+
+            std::fs::create_dir_all(&dir).unwrap();
+            std::fs::write(dir.join("schema.json"), r#"{"tables":{}}"#).unwrap();
+
+            Self { dir }
+        }
+
+        /// Opens the database, again after a test dropped it to check what was saved.
+        pub fn open(&self) -> Database {
+            Database::open(&self.dir).unwrap()
+        }
+
+        /// Path of a file in the directory, for tests of a single table file.
+        pub fn path(&self, file: &str) -> PathBuf {
+            self.dir.join(file)
+        }
+    }
+
+    impl Drop for TempDatabase {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_dir_all(&self.dir);
+        }
+    }
+}
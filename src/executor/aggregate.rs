@@ -0,0 +1,424 @@
+use std::cmp::Ordering;
+use std::collections::HashMap;
+
+use anyhow::{anyhow, bail, Result};
+
+use super::distinct::Deduplicator;
+use super::{expression, internal_column, spill};
+use crate::sql::{
+    AggregateCall, AggregateFunction, Expression, OrderBy, SelectStatement, Selection, Value,
+};
+
+/// Whether a SELECT statement groups its rows, either explicitly or by
+/// using aggregate functions.
+pub fn is_aggregate(statement: &SelectStatement) -> bool {
+    !statement.group_by.is_empty()
+        || statement.having.is_some()
+        || statement
+            .selections
+            .iter()
+            .any(|selection| contains_aggregate(&selection.expression))
+        || statement
+            .order_by
+            .iter()
+            .any(|order_by| contains_aggregate(&order_by.expression))
+}
+
+pub fn contains_aggregate(expression: &Expression) -> bool {
+    match expression {
+        Expression::Aggregate(_) => true,
+        expression => expression.children().into_iter().any(contains_aggregate),
+    }
+}
+
+/// Rows produced by [`aggregate`], one per group: the GROUP BY values
+/// followed by the aggregate results. The selections and ORDER BY of the
+/// statement are rewritten to refer to those columns.
+pub struct Grouped {
+    pub columns: Vec<String>,
+    pub rows: Vec<Vec<Value>>,
+    pub selections: Vec<Selection>,
+    pub order_by: Vec<OrderBy>,
+}
+
+/// Hash aggregation: groups `rows` by the GROUP BY expressions, computes the
+/// aggregates of every group and filters the groups with HAVING.
+pub fn aggregate(
+    rows: impl Iterator<Item = Result<Vec<Value>>>,
+    columns: &[String],
+    statement: &SelectStatement,
+) -> Result<Grouped> {
+    let mut rewriter = Rewriter {
+        group_by: &statement.group_by,
+        aggregates: Vec::new(),
+    };
+
+    let mut selections = statement.selections.clone();
+
+    for selection in selections.iter_mut() {
+        rewriter.rewrite(&mut selection.expression)?;
+    }
+
+    let mut having = statement.having.clone();
+
+    for expression in having.iter_mut().flat_map(|having| having.expressions_mut()) {
+        rewriter.rewrite(expression)?;
+    }
+
+    let mut order_by = statement.order_by.clone();
+
+    for order_by in order_by.iter_mut() {
+        rewriter.rewrite(&mut order_by.expression)?;
+    }
+
+    let aggregates = rewriter.aggregates;
+
+    // Aggregates over distinct values see each pair of group and value once
+    let mut deduplicators = aggregates
+        .iter()
+        .map(|call| {
+            call.distinct
+                .then(|| Deduplicator::new(2, spill::MEMORY_BUDGET))
+        })
+        .collect::<Vec<_>>();
+
+    let mut index = HashMap::new();
+    let mut groups: Vec<(Vec<Value>, Vec<Accumulator>)> = Vec::new();
+    let mut key = Vec::new();
+
+    for row in rows {
+        let row = row?;
+
+        let values = statement
+            .group_by
+            .iter()
+            .map(|expression| expression::evaluate(expression, columns, &row))
+            .collect::<Result<Vec<_>>>()?;
+
+        key.clear();
+        spill::encode_row(&values, &mut key);
+
+        let group = *index.entry(key.clone()).or_insert_with(|| {
+            let accumulators = aggregates
+                .iter()
+                .map(|call| Accumulator::new(call.function))
+                .collect();
+
+            groups.push((values, accumulators));
+            groups.len() - 1
+        });
+
+        for (position, call) in aggregates.iter().enumerate() {
+            let value = call
+                .argument
+                .as_ref()
+                .map(|argument| expression::evaluate(argument, columns, &row))
+                .transpose()?;
+
+            let value = match (&mut deduplicators[position], value) {
+                (Some(_), None | Some(Value::Null)) => continue,
+                (Some(deduplicator), Some(value)) => {
+                    match deduplicator.push(vec![Value::Int(group as i64), value])? {
+                        Some(mut pair) => pair.pop(),
+                        None => continue,
+                    }
+                }
+                (None, value) => value,
+            };
+
+            groups[group].1[position].update(value)?;
+        }
+    }
+
+    for (position, deduplicator) in deduplicators.into_iter().enumerate() {
+        let Some(deduplicator) = deduplicator else {
+            continue;
+        };
+
+        for pair in deduplicator.finish()? {
+            if let [Value::Int(group), value] = pair?.as_slice() {
+                groups[*group as usize].1[position].update(Some(value.clone()))?;
+            }
+        }
+    }
+
+    // Without GROUP BY the whole input is one group, even when it is empty
+    if groups.is_empty() && statement.group_by.is_empty() {
+        let accumulators = aggregates
+            .iter()
+            .map(|call| Accumulator::new(call.function))
+            .collect();
+
+        groups.push((Vec::new(), accumulators));
+    }
+
+    let columns = (0..statement.group_by.len())
+        .map(group_column)
+        .chain((0..aggregates.len()).map(aggregate_column))
+        .collect::<Vec<_>>();
+
+    let mut rows = Vec::new();
+
+    for (mut values, accumulators) in groups {
+        for accumulator in accumulators {
+            values.push(accumulator.finish()?);
+        }
+
+        if let Some(having) = &having {
+            if !expression::matches(having, &columns, &values)? {
+                continue;
+            }
+        }
+
+        rows.push(values);
+    }
+
+    Ok(Grouped {
+        columns,
+        rows,
+        selections,
+        order_by,
+    })
+}
+
+fn group_column(index: usize) -> String {
+    internal_column("group", index)
+}
+
+fn aggregate_column(index: usize) -> String {
+    internal_column("aggregate", index)
+}
+
+/// Replaces GROUP BY expressions and aggregate calls with references to the
+/// columns of the grouped rows, collecting the aggregates to compute.
+struct Rewriter<'s> {
+    group_by: &'s [Expression],
+    aggregates: Vec<AggregateCall>,
+}
+
+impl Rewriter<'_> {
+    fn rewrite(&mut self, expression: &mut Expression) -> Result<()> {
+        if let Some(index) = self.group_by.iter().position(|e| e == expression) {
+            *expression = Expression::Column(group_column(index));
+            return Ok(());
+        }
+
+        match expression {
+            Expression::Column(name) => bail!(
+                "Column \"{}\" must appear in the GROUP BY clause or be used in an aggregate function",
+                name
+            ),
+            Expression::Aggregate(call) => {
+                if call.argument.as_deref().is_some_and(contains_aggregate) {
+                    bail!("Aggregate function calls cannot be nested");
+                }
+
+                let index = match self.aggregates.iter().position(|a| a == call) {
+                    Some(index) => index,
+                    None => {
+                        self.aggregates.push(call.clone());
+                        self.aggregates.len() - 1
+                    }
+                };
+
+                *expression = Expression::Column(aggregate_column(index));
+            }
+            expression => {
+                for child in expression.children_mut() {
+                    self.rewrite(child)?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Running state of one aggregate function within a group. NULL inputs are
+/// ignored by every function except `COUNT(*)`.
+#[derive(Clone)]
+pub enum Accumulator {
+    Count(i64),
+    Sum(Value),
+    Avg { sum: Value, count: i64 },
+    Min(Value),
+    Max(Value),
+}
+
+impl Accumulator {
+    pub fn new(function: AggregateFunction) -> Self {
+        match function {
+            AggregateFunction::Count => Self::Count(0),
+            AggregateFunction::Sum => Self::Sum(Value::Null),
+            AggregateFunction::Avg => Self::Avg {
+                sum: Value::Null,
+                count: 0,
+            },
+            AggregateFunction::Min => Self::Min(Value::Null),
+            AggregateFunction::Max => Self::Max(Value::Null),
+        }
+    }
+
+    /// Adds a value to the aggregate, `None` stands for a row of `COUNT(*)`.
+    pub fn update(&mut self, value: Option<Value>) -> Result<()> {
+        let value = match value {
+            Some(Value::Null) => return Ok(()),
+            Some(value) => value,
+            None => Value::Null,
+        };
+
+        match self {
+            Self::Count(count) => *count += 1,
+            Self::Sum(sum) => *sum = add(sum, value)?,
+            Self::Avg { sum, count } => {
+                *sum = add(sum, value)?;
+                *count += 1;
+            }
+            Self::Min(min) => {
+                if *min == Value::Null || compare(&value, min)? == Ordering::Less {
+                    *min = value;
+                }
+            }
+            Self::Max(max) => {
+                if *max == Value::Null || compare(&value, max)? == Ordering::Greater {
+                    *max = value;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn finish(self) -> Result<Value> {
+        match self {
+            Self::Count(count) => Ok(Value::Int(count)),
+            Self::Avg { count: 0, .. } => Ok(Value::Null),
+            Self::Avg { sum, count } => {
+                Value::Decimal(sum.to_decimal()?).divide(&Value::Int(count))
+            }
+            Self::Sum(value) | Self::Min(value) | Self::Max(value) => Ok(value),
+        }
+    }
+}
+
+fn add(sum: &Value, value: Value) -> Result<Value> {
+    match sum {
+        Value::Null => Ok(value),
+        sum => sum.add(&value),
+    }
+}
+
+fn compare(a: &Value, b: &Value) -> Result<Ordering> {
+    a.compare(b)
+        .ok_or_else(|| anyhow!("Cannot compare {} with {}", a, b))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sql::parser::Parser;
+    use crate::sql::tokenizer::Tokenizer;
+    use crate::sql::Statement;
+
+    fn select(sql: &str) -> SelectStatement {
+        let tokens = Tokenizer::new(sql).parse().unwrap();
+
+        match Parser::new(tokens).parse().unwrap() {
+            Statement::Select(select) => *select,
+            _ => panic!("Expected a SELECT statement"),
+        }
+    }
+
+    fn rows() -> Vec<Result<Vec<Value>>> {
+        [("a", Some(1)), ("b", Some(5)), ("a", None), ("a", Some(4))]
+            .into_iter()
+            .map(|(city, age)| {
+                Ok(vec![
+                    Value::String(String::from(city)),
+                    age.map_or(Value::Null, Value::Int),
+                ])
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_aggregate_group_by_having() {
+        let columns = vec![String::from("city"), String::from("age")];
+        let statement = select(
+            "SELECT city, COUNT(*), COUNT(age), SUM(age), MAX(age) FROM users GROUP BY city HAVING COUNT(*) > 1",
+        );
+
+        let grouped = aggregate(rows().into_iter(), &columns, &statement).unwrap();
+
+        assert_eq!(
+            grouped.rows,
+            vec![vec![
+                Value::String(String::from("a")),
+                Value::Int(3),
+                Value::Int(2),
+                Value::Int(5),
+                Value::Int(4),
+            ]]
+        );
+        assert_eq!(
+            grouped.selections[0].expression,
+            Expression::Column(group_column(0))
+        );
+    }
+
+    #[test]
+    fn test_aggregate_without_group_by() {
+        let columns = vec![String::from("city"), String::from("age")];
+
+        let grouped = aggregate(
+            std::iter::empty(),
+            &columns,
+            &select("SELECT COUNT(*), AVG(age) FROM users"),
+        )
+        .unwrap();
+
+        assert_eq!(grouped.rows, vec![vec![Value::Int(0), Value::Null]]);
+
+        let error = aggregate(
+            rows().into_iter(),
+            &columns,
+            &select("SELECT city, COUNT(*) FROM users"),
+        )
+        .err()
+        .unwrap();
+
+        assert!(error.to_string().contains("GROUP BY"));
+    }
+
+    #[test]
+    fn test_aggregate_distinct() {
+        let columns = vec![String::from("city"), String::from("age")];
+        let mut rows = rows();
+        rows.push(Ok(vec![Value::String(String::from("b")), Value::Int(5)]));
+        rows.push(Ok(vec![Value::String(String::from("a")), Value::Int(1)]));
+
+        let statement = select(
+            "SELECT city, COUNT(DISTINCT age), SUM(DISTINCT age), SUM(age) FROM users GROUP BY city",
+        );
+
+        let grouped = aggregate(rows.into_iter(), &columns, &statement).unwrap();
+
+        assert_eq!(
+            grouped.rows,
+            vec![
+                vec![
+                    Value::String(String::from("a")),
+                    Value::Int(2),
+                    Value::Int(5),
+                    Value::Int(6),
+                ],
+                vec![
+                    Value::String(String::from("b")),
+                    Value::Int(1),
+                    Value::Int(5),
+                    Value::Int(10),
+                ],
+            ]
+        );
+    }
+}
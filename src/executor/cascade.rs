@@ -0,0 +1,283 @@
+use std::collections::hash_map::Entry;
+use std::collections::{HashMap, HashSet};
+
+use anyhow::{bail, Result};
+
+use super::constraint::{key, list, positions, Constraints};
+use crate::database::Database;
+use crate::schema::{ConstraintKind, ReferentialAction};
+use crate::sql::Value;
+use crate::table::{Row, RowId};
+
+/// Rows of a table by the key their foreign key columns hold.
+type Index = HashMap<Vec<u8>, Vec<(RowId, Row)>>;
+
+/// Deletes the rows of `table` along with what their ON DELETE actions do to
+/// the rows referencing them, which may reach further tables. Every row is
+/// found before any is written, so a row that would be left referencing a
+/// deleted one fails the statement with all tables as they were.
+pub fn delete(db: &mut Database, table: &str, rows: Vec<(RowId, Row)>) -> Result<()> {
+    let mut deleted = HashMap::<String, HashSet<RowId>>::new();
+    let mut nulled = HashMap::<String, HashMap<RowId, Vec<Value>>>::new();
+    let mut indexes = HashMap::<(String, String), Index>::new();
+
+    // Errors raised only if the row they are about is not deleted as well
+    let mut violations = Vec::<(String, RowId, String)>::new();
+
+    let mut queue = rows
+        .into_iter()
+        .map(|(id, row)| (String::from(table), id, row))
+        .collect::<Vec<_>>();
+
+    while let Some((table, id, row)) = queue.pop() {
+        if !deleted.entry(table.clone()).or_default().insert(id) {
+            continue;
+        }
+
+        let schema = db.table(&table)?.schema().clone();
+
+        for (child, constraint) in db.schema().references_to(&table) {
+            let ConstraintKind::ForeignKey {
+                columns,
+                references,
+                on_delete,
+                ..
+            } = &constraint.kind
+            else {
+                continue;
+            };
+
+            let referenced = positions(&schema, references);
+
+            let Some(k) = key(&referenced, row.values()) else {
+                continue;
+            };
+
+            let child_schema = db.table(&child)?.schema().clone();
+            let foreign = positions(&child_schema, columns);
+
+            let index = match indexes.entry((child.clone(), constraint.name.clone())) {
+                Entry::Occupied(entry) => entry.into_mut(),
+                Entry::Vacant(entry) => {
+                    let mut index = Index::new();
+
+                    for entry in db.table(&child)?.scan() {
+                        let (id, row) = entry?;
+
+                        if let Some(k) = key(&foreign, row.values()) {
+                            index.entry(k).or_default().push((id, row));
+                        }
+                    }
+
+                    entry.insert(index)
+                }
+            };
+
+            for (child_id, child_row) in index.get(&k).into_iter().flatten() {
+                match on_delete {
+                    ReferentialAction::NoAction | ReferentialAction::Restrict => {
+                        violations.push((
+                            child.clone(),
+                            *child_id,
+                            format!(
+                                "Update or delete on table \"{}\" violates foreign key constraint \"{}\" on table \"{}\": Key ({})=({}) is still referenced from table \"{}\"",
+                                table,
+                                constraint.name,
+                                child,
+                                references.join(", "),
+                                list(referenced.iter().map(|index| &row.values()[*index])),
+                                child
+                            ),
+                        ));
+                    }
+                    ReferentialAction::Cascade => {
+                        queue.push((child.clone(), *child_id, child_row.clone()))
+                    }
+                    ReferentialAction::SetNull => {
+                        let values = nulled
+                            .entry(child.clone())
+                            .or_default()
+                            .entry(*child_id)
+                            .or_insert_with(|| child_row.values().to_vec());
+
+                        for index in foreign.iter() {
+                            values[*index] = Value::Null;
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    let is_deleted =
+        |table: &str, id: &RowId| deleted.get(table).is_some_and(|rows| rows.contains(id));
+
+    for (table, id, message) in violations {
+        if !is_deleted(&table, &id) {
+            bail!(message);
+        }
+    }
+
+    // Rows set to NULL must still satisfy the constraints of their table,
+    // such as NOT NULL and CHECK
+    for (table, rows) in nulled.iter() {
+        let constraints = Constraints::new(db, table)?;
+
+        for (id, values) in rows {
+            if !is_deleted(table, id) {
+                constraints.check(values, Some(*id))?;
+            }
+        }
+    }
+
+    for (table, rows) in nulled.iter() {
+        for (id, values) in rows {
+            if !is_deleted(table, id) {
+                db.table(table)?.update(*id, &Row::new(values.clone()))?;
+            }
+        }
+    }
+
+    for (table, rows) in deleted {
+        let table = db.table(&table)?;
+
+        for id in rows {
+            table.delete(id)?;
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::testing::TempDatabase;
+    use crate::schema::{Column, ColumnKind, Constraint, TableSchema};
+
+    fn schema(parent: Option<(&str, ReferentialAction)>) -> TableSchema {
+        let mut constraints = vec![Constraint {
+            name: String::new(),
+            kind: ConstraintKind::PrimaryKey {
+                columns: vec![String::from("id")],
+            },
+        }];
+
+        if let Some((table, on_delete)) = parent {
+            constraints.push(Constraint {
+                name: String::new(),
+                kind: ConstraintKind::ForeignKey {
+                    columns: vec![String::from("parent")],
+                    table: String::from(table),
+                    references: Vec::new(),
+                    on_delete,
+                },
+            });
+        }
+
+        TableSchema::new(
+            vec![
+                Column::new("id", ColumnKind::Int),
+                Column::new("parent", ColumnKind::Int),
+            ],
+            constraints,
+        )
+    }
+
+    fn insert(db: &mut Database, table: &str, rows: &[(i64, Option<i64>)]) {
+        let table = db.table(table).unwrap();
+
+        for (id, parent) in rows {
+            let parent = parent.map_or(Value::Null, Value::Int);
+            table
+                .insert(&Row::new(vec![Value::Int(*id), parent]))
+                .unwrap();
+        }
+    }
+
+    fn rows(db: &mut Database, table: &str) -> Vec<(RowId, Row)> {
+        db.table(table)
+            .unwrap()
+            .scan()
+            .collect::<Result<Vec<_>>>()
+            .unwrap()
+    }
+
+    fn values(db: &mut Database, table: &str) -> Vec<Vec<Value>> {
+        let mut values = rows(db, table)
+            .into_iter()
+            .map(|(_, row)| row.into_values())
+            .collect::<Vec<_>>();
+
+        values.sort_by_key(|values| values[0].to_string());
+        values
+    }
+
+    #[test]
+    fn test_delete_cascade() {
+        let dir = TempDatabase::new("cascade");
+        let mut db = dir.open();
+        db.create_table("a", schema(None)).unwrap();
+        db.create_table("b", schema(Some(("a", ReferentialAction::Cascade))))
+            .unwrap();
+        db.create_table("c", schema(Some(("b", ReferentialAction::SetNull))))
+            .unwrap();
+        db.create_table("d", schema(Some(("a", ReferentialAction::Restrict))))
+            .unwrap();
+
+        insert(&mut db, "a", &[(1, None), (2, None)]);
+        insert(&mut db, "b", &[(1, Some(1)), (2, Some(2))]);
+        insert(&mut db, "c", &[(1, Some(1)), (2, Some(2))]);
+        insert(&mut db, "d", &[(1, Some(2))]);
+
+        // Deleting the row d references fails before anything is deleted
+        let all = rows(&mut db, "a");
+        let err = delete(&mut db, "a", all).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Update or delete on table \"a\" violates foreign key constraint \"d_parent_fkey\" on table \"d\": Key (id)=(2) is still referenced from table \"d\""
+        );
+        assert_eq!(rows(&mut db, "b").len(), 2);
+
+        // A row set to NULL must still pass the CHECK constraints of its table
+        let checked = schema(Some(("a", ReferentialAction::SetNull)));
+        let mut constraints = checked.constraints().to_vec();
+        constraints.push(Constraint {
+            name: String::new(),
+            kind: ConstraintKind::Check {
+                expression: String::from("parent IS NOT NULL"),
+            },
+        });
+        db.create_table("e", TableSchema::new(checked.columns().to_vec(), constraints))
+            .unwrap();
+        insert(&mut db, "e", &[(1, Some(1))]);
+
+        let first = |db: &mut Database| {
+            rows(db, "a")
+                .into_iter()
+                .filter(|(_, row)| row.values()[0] == Value::Int(1))
+                .collect::<Vec<_>>()
+        };
+
+        let rows_of_first = first(&mut db);
+        let err = delete(&mut db, "a", rows_of_first).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "New row for relation \"e\" violates check constraint \"e_check\": Failing row contains (1, NULL)"
+        );
+        assert_eq!(rows(&mut db, "b").len(), 2);
+        assert_eq!(values(&mut db, "e"), [[Value::Int(1), Value::Int(1)]]);
+
+        let all = rows(&mut db, "e");
+        delete(&mut db, "e", all).unwrap();
+
+        let rows_of_first = first(&mut db);
+        delete(&mut db, "a", rows_of_first).unwrap();
+
+        assert_eq!(values(&mut db, "b"), [[Value::Int(2), Value::Int(2)]]);
+        assert_eq!(
+            values(&mut db, "c"),
+            [[Value::Int(1), Value::Null], [Value::Int(2), Value::Int(2)]]
+        );
+    }
+}
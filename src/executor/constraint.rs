@@ -0,0 +1,608 @@
+use std::collections::{HashMap, HashSet};
+
+use anyhow::{anyhow, bail, Result};
+
+use super::typecheck::{Checker, Type};
+use super::{expression, row_key, sequence, subquery};
+use crate::database::Database;
+use crate::schema::{Column, ConstraintKind, TableSchema};
+use crate::sql::parser::Parser;
+use crate::sql::tokenizer::Tokenizer;
+use crate::sql::{Expression, Value, WhereExpression};
+use crate::table::{Row, RowId, Table};
+
+/// Change made to a table by a statement, kept to undo it when a later row fails.
+pub enum Change {
+    Inserted(RowId),
+    /// Row now at the id, with its values before the update.
+    Updated(RowId, Row),
+}
+
+/// Undoes `changes`, the last one first.
+pub fn revert(table: &mut Table, changes: Vec<Change>) -> Result<()> {
+    for change in changes.into_iter().rev() {
+        match change {
+            Change::Inserted(id) => table.delete(id)?,
+            Change::Updated(id, row) => {
+                table.update(id, &row)?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Checks that the CHECK conditions and column defaults of a new table are
+/// valid expressions over its columns.
+pub fn validate(table: &str, schema: &TableSchema) -> Result<()> {
+    let (names, types) = columns(table, schema);
+    let checker = Checker::new(&names, &types);
+
+    for constraint in schema.constraints() {
+        if let ConstraintKind::Check { expression } = &constraint.kind {
+            let condition = parse_condition(expression)?;
+
+            if subquery::contains_subquery(&condition) {
+                bail!("Cannot use subquery in check constraint");
+            }
+
+            checker.check_condition(&condition)?;
+        }
+    }
+
+    for column in schema.columns() {
+        if let Some(default) = column.default() {
+            let default = parse_expression(default)?;
+
+            if subquery::expression_contains_subquery(&default) {
+                bail!("Cannot use subquery in DEFAULT expression");
+            }
+
+            let r#type = Checker::new(&[], &[]).check(&default)?;
+
+            if !r#type.assignable_to(column.kind()) {
+                bail!(
+                    "Column \"{}\" is of type {} but default expression is of type {}",
+                    column.name(),
+                    column.kind(),
+                    r#type
+                );
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn columns(table: &str, schema: &TableSchema) -> (Vec<String>, Vec<Type>) {
+    schema
+        .columns()
+        .iter()
+        .map(|c| (format!("{}.{}", table, c.name()), Type::of_kind(c.kind())))
+        .unzip()
+}
+
+fn parse_condition(sql: &str) -> Result<WhereExpression> {
+    Parser::new(Tokenizer::new(sql).parse()?)
+        .parse_condition()
+        .map_err(|err| anyhow!("{}", err))
+}
+
+fn parse_expression(sql: &str) -> Result<Expression> {
+    Parser::new(Tokenizer::new(sql).parse()?)
+        .parse_expression()
+        .map_err(|err| anyhow!("{}", err))
+}
+
+/// Constraints of a table, checked for every row an INSERT or UPDATE writes.
+/// The rows of each key are found by their values in the key columns, read
+/// from the table when the statement starts and kept up to date as it writes.
+///
+/// There is no persistent index: every statement writing to a table with a
+/// PRIMARY KEY or UNIQUE constraint scans the whole table, and one with
+/// foreign keys also scans the tables on the other side of each of them, so
+/// a statement costs time proportional to the size of those tables even when
+/// it writes a single row.
+pub struct Constraints {
+    table: String,
+    names: Vec<String>,
+    columns: Vec<Column>,
+    /// Columns that are NOT NULL or part of the primary key.
+    not_null: Vec<usize>,
+    checks: Vec<(String, WhereExpression)>,
+    defaults: Vec<Option<Expression>>,
+    autoincrement: Option<usize>,
+    keys: Vec<Key>,
+    /// Foreign keys of the table, with the keys of the referenced rows.
+    references: Vec<Reference>,
+    /// Foreign keys of tables referencing this one, with the keys their rows hold.
+    referenced_by: Vec<Reference>,
+}
+
+/// PRIMARY KEY or UNIQUE constraint. Rows with a NULL in the key columns
+/// are not in `rows`, they never conflict.
+pub struct Key {
+    name: String,
+    columns: Vec<usize>,
+    rows: HashMap<Vec<u8>, RowId>,
+}
+
+impl Key {
+    pub fn columns(&self) -> &[usize] {
+        &self.columns
+    }
+
+    fn key(&self, values: &[Value]) -> Option<Vec<u8>> {
+        key(&self.columns, values)
+    }
+}
+
+/// One side of a foreign key, seen from a table: its columns in the table
+/// and the keys held by the table on the other side.
+struct Reference {
+    name: String,
+    columns: Vec<usize>,
+    table: String,
+    /// Columns of the foreign key in the other table.
+    other: Vec<usize>,
+    keys: HashSet<Vec<u8>>,
+}
+
+/// Key of the values in `columns`, rows with a NULL in them have none.
+pub fn key(columns: &[usize], values: &[Value]) -> Option<Vec<u8>> {
+    let values = columns
+        .iter()
+        .map(|index| values[*index].clone())
+        .collect::<Vec<_>>();
+
+    match values.contains(&Value::Null) {
+        true => None,
+        false => Some(row_key(&values)),
+    }
+}
+
+/// Positions of the named columns in the schema of a table.
+pub fn positions(schema: &TableSchema, names: &[String]) -> Vec<usize> {
+    names
+        .iter()
+        .map(|name| {
+            schema
+                .columns()
+                .iter()
+                .position(|c| c.name() == name)
+                .unwrap()
+        })
+        .collect()
+}
+
+fn keys(db: &mut Database, table: &str, columns: &[usize]) -> Result<HashSet<Vec<u8>>> {
+    let mut keys = HashSet::new();
+
+    for entry in db.table(table)?.scan() {
+        let (_, row) = entry?;
+        keys.extend(key(columns, row.values()));
+    }
+
+    Ok(keys)
+}
+
+impl Constraints {
+    pub fn new(db: &mut Database, table: &str) -> Result<Self> {
+        let schema = db.table(table)?.schema().clone();
+        let (names, _) = columns(table, &schema);
+        let columns = schema.columns().to_vec();
+
+        let mut not_null = (0..columns.len())
+            .filter(|index| columns[*index].not_null())
+            .collect::<Vec<_>>();
+
+        let mut checks = Vec::new();
+        let mut unique = Vec::new();
+        let mut references = Vec::new();
+
+        for constraint in schema.constraints() {
+            match &constraint.kind {
+                ConstraintKind::PrimaryKey { columns: key }
+                | ConstraintKind::Unique { columns: key } => {
+                    let key = positions(&schema, key);
+
+                    if matches!(constraint.kind, ConstraintKind::PrimaryKey { .. }) {
+                        not_null.extend_from_slice(&key);
+                    }
+
+                    unique.push(Key {
+                        name: constraint.name.clone(),
+                        columns: key,
+                        rows: HashMap::new(),
+                    });
+                }
+                ConstraintKind::Check { expression } => {
+                    checks.push((constraint.name.clone(), parse_condition(expression)?));
+                }
+                ConstraintKind::ForeignKey {
+                    columns: key,
+                    table: parent,
+                    references: referenced,
+                    ..
+                } => {
+                    let parent_schema = db.table(parent)?.schema().clone();
+                    let other = positions(&parent_schema, referenced);
+
+                    references.push(Reference {
+                        name: constraint.name.clone(),
+                        columns: positions(&schema, key),
+                        table: parent.clone(),
+                        keys: keys(db, parent, &other)?,
+                        other,
+                    });
+                }
+            }
+        }
+
+        let mut referenced_by = Vec::new();
+
+        for (child, constraint) in db.schema().references_to(table) {
+            let ConstraintKind::ForeignKey {
+                columns: key,
+                references: referenced,
+                ..
+            } = &constraint.kind
+            else {
+                continue;
+            };
+
+            let child_schema = db.table(&child)?.schema().clone();
+            let other = positions(&child_schema, key);
+
+            referenced_by.push(Reference {
+                name: constraint.name.clone(),
+                columns: positions(&schema, referenced),
+                keys: keys(db, &child, &other)?,
+                table: child,
+                other,
+            });
+        }
+
+        not_null.sort();
+        not_null.dedup();
+
+        let defaults = columns
+            .iter()
+            .map(|column| column.default().map(parse_expression).transpose())
+            .collect::<Result<Vec<_>>>()?;
+
+        let mut constraints = Self {
+            table: String::from(table),
+            names,
+            columns,
+            not_null,
+            checks,
+            defaults,
+            autoincrement: schema.columns().iter().position(|c| c.autoincrement()),
+            keys: unique,
+            references,
+            referenced_by,
+        };
+
+        if !constraints.keys.is_empty() {
+            for entry in db.table(table)?.scan() {
+                let (id, row) = entry?;
+                constraints.add(id, row.values());
+            }
+        }
+
+        Ok(constraints)
+    }
+
+    pub fn keys(&self) -> &[Key] {
+        &self.keys
+    }
+
+    /// Values of a new row before the INSERT sets any, the DEFAULT of each
+    /// column or NULL. The DEFAULT of a column in `supplied` is not evaluated,
+    /// so that it does not move a sequence, the column is NULL until set.
+    pub fn defaults(&self, db: &mut Database, supplied: &[usize]) -> Result<Vec<Value>> {
+        self.defaults
+            .iter()
+            .enumerate()
+            .map(|(index, default)| match default {
+                _ if supplied.contains(&index) => Ok(Value::Null),
+                Some(default) if sequence::contains_nextval(default) => {
+                    let mut default = default.clone();
+                    sequence::bind(db, &mut default)?;
+                    expression::evaluate(&default, &[], &[])
+                }
+                Some(default) => expression::evaluate(default, &[], &[]),
+                None => Ok(Value::Null),
+            })
+            .collect()
+    }
+
+    /// Row already holding the values of `values` in the columns of a key.
+    pub fn conflict(&self, key: usize, values: &[Value]) -> Option<RowId> {
+        let key = &self.keys[key];
+        key.key(values).and_then(|k| key.rows.get(&k)).copied()
+    }
+
+    /// Inserts the row if it satisfies the constraints, returning it with
+    /// the value of its AUTOINCREMENT column when that was NULL.
+    pub fn insert(
+        &mut self,
+        table: &mut Table,
+        row: Row,
+        changes: &mut Vec<Change>,
+    ) -> Result<(RowId, Row)> {
+        let mut values = row.into_values();
+
+        if let Some(index) = self.autoincrement {
+            match values[index] {
+                Value::Null => values[index] = Value::Int(table.next_autoincrement()?),
+                Value::Int(value) => table.advance_autoincrement(value),
+                _ => {}
+            }
+        }
+
+        let row = Row::new(values);
+        self.check(row.values(), None)?;
+
+        let id = table.insert(&row)?;
+        changes.push(Change::Inserted(id));
+        self.add(id, row.values());
+
+        Ok((id, row))
+    }
+
+    /// Replaces the row at `id` if the new values satisfy the constraints,
+    /// returning the id the row moved to.
+    pub fn update(
+        &mut self,
+        table: &mut Table,
+        id: RowId,
+        old: Row,
+        new: &Row,
+        changes: &mut Vec<Change>,
+    ) -> Result<RowId> {
+        self.check(new.values(), Some(id))?;
+
+        for reference in self.referenced_by.iter() {
+            let Some(k) = key(&reference.columns, old.values()) else {
+                continue;
+            };
+
+            if key(&reference.columns, new.values()).as_ref() != Some(&k)
+                && reference.keys.contains(&k)
+            {
+                bail!(
+                    "Update or delete on table \"{}\" violates foreign key constraint \"{}\" on table \"{}\": Key ({})=({}) is still referenced from table \"{}\"",
+                    self.table,
+                    reference.name,
+                    reference.table,
+                    list(reference.columns.iter().map(|index| self.columns[*index].name())),
+                    list(reference.columns.iter().map(|index| &old.values()[*index])),
+                    reference.table
+                );
+            }
+        }
+
+        if let Some(Value::Int(value)) = self.autoincrement.map(|index| &new.values()[index]) {
+            table.advance_autoincrement(*value);
+        }
+
+        let moved = table.update(id, new)?;
+
+        for key in self.keys.iter_mut() {
+            if let Some(k) = key.key(old.values()) {
+                if key.rows.get(&k) == Some(&id) {
+                    key.rows.remove(&k);
+                }
+            }
+        }
+
+        self.add(moved, new.values());
+        changes.push(Change::Updated(moved, old));
+
+        Ok(moved)
+    }
+
+    fn add(&mut self, id: RowId, values: &[Value]) {
+        for key in self.keys.iter_mut() {
+            if let Some(k) = key.key(values) {
+                key.rows.entry(k).or_insert(id);
+            }
+        }
+
+        // A table referencing itself is on both sides of the foreign key
+        for reference in self
+            .references
+            .iter_mut()
+            .chain(self.referenced_by.iter_mut())
+            .filter(|reference| reference.table == self.table)
+        {
+            reference.keys.extend(key(&reference.other, values));
+        }
+    }
+
+    /// Checks NOT NULL columns, then CHECK conditions, then keys, where the
+    /// row at `except` is the one being updated.
+    pub fn check(&self, values: &[Value], except: Option<RowId>) -> Result<()> {
+        for index in self.not_null.iter() {
+            if values[*index] == Value::Null {
+                bail!(
+                    "Null value in column \"{}\" of relation \"{}\" violates not-null constraint",
+                    self.columns[*index].name(),
+                    self.table
+                );
+            }
+        }
+
+        // NULL passes, only a condition that is false fails
+        for (name, condition) in self.checks.iter() {
+            if expression::fails(condition, &self.names, values)? {
+                bail!(
+                    "New row for relation \"{}\" violates check constraint \"{}\": Failing row contains ({})",
+                    self.table,
+                    name,
+                    list(values.iter())
+                );
+            }
+        }
+
+        for key in self.keys.iter() {
+            let Some(k) = key.key(values) else {
+                continue;
+            };
+
+            match key.rows.get(&k) {
+                Some(id) if Some(*id) != except => bail!(
+                    "Duplicate key value violates unique constraint \"{}\": Key ({})=({}) already exists",
+                    key.name,
+                    list(key.columns.iter().map(|index| self.columns[*index].name())),
+                    list(key.columns.iter().map(|index| &values[*index]))
+                ),
+                _ => {}
+            }
+        }
+
+        for reference in self.references.iter() {
+            let Some(k) = key(&reference.columns, values) else {
+                continue;
+            };
+
+            // A row of a table referencing itself may reference itself
+            let itself =
+                reference.table == self.table && key(&reference.other, values).as_ref() == Some(&k);
+
+            if !itself && !reference.keys.contains(&k) {
+                bail!(
+                    "Insert or update on table \"{}\" violates foreign key constraint \"{}\": Key ({})=({}) is not present in table \"{}\"",
+                    self.table,
+                    reference.name,
+                    list(reference.columns.iter().map(|index| self.columns[*index].name())),
+                    list(reference.columns.iter().map(|index| &values[*index])),
+                    reference.table
+                );
+            }
+        }
+
+        Ok(())
+    }
+}
+
+pub fn list<T: ToString>(items: impl Iterator<Item = T>) -> String {
+    items
+        .map(|item| item.to_string())
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::testing::TempDatabase;
+    use crate::schema::{ColumnKind, Constraint, StringColumn};
+
+    fn schema() -> TableSchema {
+        let mut id = Column::new("id", ColumnKind::Int);
+        id.set_default(Some(String::from("7")));
+
+        let mut name = Column::new("name", ColumnKind::String(StringColumn { length: 8 }));
+        name.set_not_null(true);
+
+        let mut schema = TableSchema::new(
+            vec![id, name, Column::new("age", ColumnKind::Int)],
+            vec![
+                Constraint {
+                    name: String::new(),
+                    kind: ConstraintKind::PrimaryKey {
+                        columns: vec![String::from("id")],
+                    },
+                },
+                Constraint {
+                    name: String::new(),
+                    kind: ConstraintKind::Check {
+                        expression: String::from("age >= 0"),
+                    },
+                },
+            ],
+        );
+
+        schema.prepare("people").unwrap();
+        schema
+    }
+
+    fn row(id: Value, name: &str, age: Value) -> Row {
+        Row::new(vec![id, Value::String(String::from(name)), age])
+    }
+
+    #[test]
+    fn test_constraints() {
+        let dir = TempDatabase::new("constraints");
+        let mut db = dir.open();
+        db.create_table("people", schema()).unwrap();
+
+        let mut constraints = Constraints::new(&mut db, "people").unwrap();
+        assert_eq!(
+            constraints.defaults(&mut db, &[]).unwrap(),
+            [Value::Int(7), Value::Null, Value::Null]
+        );
+        assert_eq!(
+            constraints.defaults(&mut db, &[0, 1]).unwrap(),
+            [Value::Null, Value::Null, Value::Null]
+        );
+
+        let table = db.table("people").unwrap();
+        let mut changes = Vec::new();
+
+        let (id, _) = constraints
+            .insert(
+                table,
+                row(Value::Int(1), "ann", Value::Int(30)),
+                &mut changes,
+            )
+            .unwrap();
+
+        let err = constraints
+            .insert(table, row(Value::Int(1), "bob", Value::Null), &mut changes)
+            .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Duplicate key value violates unique constraint \"people_pkey\": Key (id)=(1) already exists"
+        );
+
+        let err = constraints
+            .insert(table, row(Value::Null, "bob", Value::Null), &mut changes)
+            .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Null value in column \"id\" of relation \"people\" violates not-null constraint"
+        );
+
+        let err = constraints
+            .insert(
+                table,
+                row(Value::Int(2), "bob", Value::Int(-1)),
+                &mut changes,
+            )
+            .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "New row for relation \"people\" violates check constraint \"people_check\": Failing row contains (2, bob, -1)"
+        );
+
+        // The updated row may keep its own key
+        let old = table.get(id).unwrap();
+        let id = constraints
+            .update(
+                table,
+                id,
+                old,
+                &row(Value::Int(1), "ann", Value::Int(31)),
+                &mut changes,
+            )
+            .unwrap();
+        assert_eq!(constraints.conflict(0, &[Value::Int(1)]), Some(id));
+
+        revert(table, changes).unwrap();
+        assert_eq!(table.scan().count(), 0);
+    }
+}
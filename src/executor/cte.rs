@@ -0,0 +1,409 @@
+use std::collections::HashSet;
+
+use anyhow::{bail, Result};
+
+use super::{compound, row_key, run, set_types};
+use crate::database::Database;
+use crate::sql::{
+    CommonTableExpression, Expression, Query, SelectStatement, SetExpression, SetOperator,
+    TableReference, TableSource, Value, WhereExpression,
+};
+
+/// Replaces every reference to a common table expression of the query by
+/// its definition and removes its WITH clause. Expressions referenced once
+/// are inlined as derived tables, the others are computed once and their
+/// rows substituted. Unreferenced ones never run.
+pub fn plan(db: &mut Database, query: &mut Query) -> Result<()> {
+    let mut with = std::mem::take(&mut query.with);
+
+    for index in 0..with.len() {
+        let (done, later) = with.split_at_mut(index + 1);
+        let (table, earlier) = done.split_last_mut().unwrap();
+        let name = table.name.clone();
+
+        if earlier.iter().any(|other| other.name == name) {
+            bail!("WITH query name \"{}\" specified more than once", name);
+        }
+
+        let references = later
+            .iter_mut()
+            .map(|other| count_query(&mut other.query, &name))
+            .sum::<usize>()
+            + count_set(&mut query.body, &name);
+
+        if references == 0 {
+            continue;
+        }
+
+        // Without RECURSIVE the name inside the definition refers to a table
+        let source = if query.recursive && count_query(&mut table.query, &name) > 0 {
+            iterate(db, table)?
+        } else {
+            match &table.query {
+                Query {
+                    with,
+                    body: SetExpression::Select(statement),
+                    ..
+                } if with.is_empty() && references == 1 && table.columns.is_empty() => {
+                    TableSource::Subquery(statement.clone())
+                }
+                definition => {
+                    let (result, _) = run(db, definition.clone())?;
+
+                    TableSource::Rows {
+                        columns: rename(table, result.columns)?,
+                        rows: result.rows,
+                    }
+                }
+            }
+        };
+
+        for other in later.iter_mut() {
+            substitute_query(&mut other.query, &name, &source);
+        }
+
+        substitute_set(&mut query.body, &name, &source);
+    }
+
+    Ok(())
+}
+
+/// Iterations after which a recursive query is taken not to terminate.
+const MAX_ITERATIONS: usize = 10_000;
+
+/// Rows a recursive query may produce before it is stopped.
+const MAX_ROWS: usize = 1_000_000;
+
+/// Evaluates a recursive common table expression: the rows of the
+/// non-recursive term are the first working table, the recursive term is
+/// then run on the rows added last until it produces no new ones.
+fn iterate(db: &mut Database, table: &CommonTableExpression) -> Result<TableSource> {
+    let Query {
+        with,
+        body:
+            SetExpression::Operation {
+                operator: SetOperator::Union,
+                left,
+                right,
+                all,
+            },
+        order_by,
+        pagination,
+        ..
+    } = &table.query
+    else {
+        bail!(
+            "Recursive query \"{}\" does not have the form non-recursive-term UNION [ALL] recursive-term",
+            table.name
+        );
+    };
+
+    if !with.is_empty() || !order_by.is_empty() || pagination.is_some() {
+        bail!(
+            "WITH, ORDER BY and LIMIT are not supported in recursive query \"{}\"",
+            table.name
+        );
+    }
+
+    let mut anchor = (**left).clone();
+
+    if count_set(&mut anchor, &table.name) > 0 {
+        bail!(
+            "Recursive reference to query \"{}\" must not appear within its non-recursive term",
+            table.name
+        );
+    }
+
+    let (result, types) = compound(db, anchor)?;
+    let columns = rename(table, result.columns)?;
+
+    // Without ALL, rows already produced are dropped, which also ends cycles
+    let mut seen = HashSet::new();
+    let mut fresh = |row: &Vec<Value>| *all || seen.insert(row_key(row));
+
+    let mut working = result
+        .rows
+        .into_iter()
+        .filter(&mut fresh)
+        .collect::<Vec<_>>();
+    let mut rows = Vec::new();
+    let mut iterations = 0;
+
+    while !working.is_empty() {
+        rows.extend(working.iter().cloned());
+        iterations += 1;
+
+        // An outer LIMIT does not stop the iteration, every row is computed first
+        if iterations > MAX_ITERATIONS || rows.len() > MAX_ROWS {
+            bail!(
+                "Recursive query \"{}\" exceeded {} iterations or {} rows, it may not terminate",
+                table.name,
+                MAX_ITERATIONS,
+                MAX_ROWS
+            );
+        }
+
+        let mut term = (**right).clone();
+        let source = TableSource::Rows {
+            columns: columns.clone(),
+            rows: working,
+        };
+
+        substitute_set(&mut term, &table.name, &source);
+
+        let (result, term_types) = compound(db, term)?;
+        set_types(SetOperator::Union, &types, &term_types)?;
+
+        working = result.rows.into_iter().filter(&mut fresh).collect();
+    }
+
+    Ok(TableSource::Rows { columns, rows })
+}
+
+/// Column names of a common table expression, its column list replaces the
+/// names of the first columns of its query.
+fn rename(table: &CommonTableExpression, mut columns: Vec<String>) -> Result<Vec<String>> {
+    if table.columns.len() > columns.len() {
+        bail!(
+            "WITH query \"{}\" has {} columns available but {} columns specified",
+            table.name,
+            columns.len(),
+            table.columns.len()
+        );
+    }
+
+    for (column, name) in columns.iter_mut().zip(table.columns.iter()) {
+        column.clone_from(name);
+    }
+
+    Ok(columns)
+}
+
+fn count_query(query: &mut Query, name: &str) -> usize {
+    let mut count = 0;
+    visit_query(query, name, &mut |_| count += 1);
+    count
+}
+
+fn count_set(expression: &mut SetExpression, name: &str) -> usize {
+    let mut count = 0;
+    visit_set(expression, name, &mut |_| count += 1);
+    count
+}
+
+fn substitute_query(query: &mut Query, name: &str, source: &TableSource) {
+    visit_query(query, name, &mut |table| replace(table, source));
+}
+
+fn substitute_set(expression: &mut SetExpression, name: &str, source: &TableSource) {
+    visit_set(expression, name, &mut |table| replace(table, source));
+}
+
+/// Keeps the name as the qualifier of the columns unless the reference has an alias.
+fn replace(table: &mut TableReference, source: &TableSource) {
+    if let TableSource::Table(name) = &table.source {
+        table.alias.get_or_insert_with(|| name.clone());
+    }
+
+    table.source = source.clone();
+}
+
+/// Calls `f` for every reference to the table `name`, in the FROM clauses
+/// of the statement and of all its subqueries.
+fn visit(statement: &mut SelectStatement, name: &str, f: &mut dyn FnMut(&mut TableReference)) {
+    let tables = std::iter::once(&mut statement.table)
+        .chain(statement.joins.iter_mut().map(|join| &mut join.table));
+
+    for table in tables {
+        match &mut table.source {
+            TableSource::Table(table_name) if table_name == name => f(table),
+            TableSource::Subquery(subquery) => visit(subquery, name, f),
+            _ => {}
+        }
+    }
+
+    let conditions = statement
+        .joins
+        .iter_mut()
+        .filter_map(|join| join.on.as_mut())
+        .chain(statement.r#where.as_mut())
+        .chain(statement.having.as_mut());
+
+    for condition in conditions {
+        visit_condition(condition, name, f);
+    }
+
+    let expressions = statement
+        .selections
+        .iter_mut()
+        .map(|selection| &mut selection.expression)
+        .chain(statement.group_by.iter_mut())
+        .chain(
+            statement
+                .order_by
+                .iter_mut()
+                .map(|order_by| &mut order_by.expression),
+        );
+
+    for expression in expressions {
+        visit_expression(expression, name, f);
+    }
+}
+
+/// Like [`visit`], a WITH clause defining `name` again hides the outer one
+/// from the queries after that definition.
+fn visit_query(query: &mut Query, name: &str, f: &mut dyn FnMut(&mut TableReference)) {
+    for table in query.with.iter_mut() {
+        visit_query(&mut table.query, name, f);
+
+        if table.name == name {
+            return;
+        }
+    }
+
+    visit_set(&mut query.body, name, f);
+}
+
+fn visit_set(expression: &mut SetExpression, name: &str, f: &mut dyn FnMut(&mut TableReference)) {
+    match expression {
+        SetExpression::Select(statement) => visit(statement, name, f),
+        SetExpression::Operation { left, right, .. } => {
+            visit_set(left, name, f);
+            visit_set(right, name, f);
+        }
+    }
+}
+
+fn visit_condition(
+    condition: &mut WhereExpression,
+    name: &str,
+    f: &mut dyn FnMut(&mut TableReference),
+) {
+    match condition {
+        WhereExpression::And(conditions) | WhereExpression::Or(conditions) => {
+            for condition in conditions.iter_mut() {
+                visit_condition(condition, name, f);
+            }
+        }
+        WhereExpression::Not(condition) => visit_condition(condition, name, f),
+        WhereExpression::InSubquery {
+            expression,
+            subquery,
+        } => {
+            visit_expression(expression, name, f);
+            visit(subquery, name, f);
+        }
+        WhereExpression::Exists(subquery) => visit(subquery, name, f),
+        condition => {
+            for expression in condition.expressions_mut() {
+                visit_expression(expression, name, f);
+            }
+        }
+    }
+}
+
+fn visit_expression(
+    expression: &mut Expression,
+    name: &str,
+    f: &mut dyn FnMut(&mut TableReference),
+) {
+    match expression {
+        Expression::Subquery(subquery) => visit(subquery, name, f),
+        Expression::Condition(condition) => visit_condition(condition, name, f),
+        // The conditions of CASE may hold subqueries of their own
+        Expression::Case(case) => {
+            for branch in case.branches.iter_mut() {
+                visit_condition(&mut branch.condition, name, f);
+                visit_expression(&mut branch.result, name, f);
+            }
+
+            if let Some(r#else) = &mut case.r#else {
+                visit_expression(r#else, name, f);
+            }
+        }
+        expression => {
+            for child in expression.children_mut() {
+                visit_expression(child, name, f);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::testing::TempDatabase;
+    use crate::executor::execute_sql;
+    use crate::sql::parser::Parser;
+    use crate::sql::tokenizer::Tokenizer;
+    use crate::sql::Statement;
+
+    fn query(sql: &str) -> Query {
+        let tokens = Tokenizer::new(sql).parse().unwrap();
+
+        match Parser::new(tokens).parse().unwrap() {
+            Statement::Query(query) => *query,
+            _ => panic!("Expected a query"),
+        }
+    }
+
+    #[test]
+    fn test_count_references() {
+        let mut query = query(
+            "WITH t AS (SELECT id FROM users) \
+             SELECT t.id FROM t JOIN t AS u ON t.id = u.id \
+             WHERE EXISTS (SELECT id FROM t WHERE id > 1) \
+             AND id IN (SELECT id FROM (SELECT id FROM t) AS d)",
+        );
+
+        assert_eq!(count_set(&mut query.body, "t"), 4);
+        assert_eq!(count_set(&mut query.body, "users"), 0);
+        assert_eq!(count_query(&mut query.with[0].query, "users"), 1);
+    }
+
+    #[test]
+    fn test_substitute_keeps_qualifier() {
+        let mut query = query("WITH t AS (SELECT id FROM users) SELECT t.id FROM t");
+        let source = TableSource::Rows {
+            columns: vec![String::from("id")],
+            rows: Vec::new(),
+        };
+
+        substitute_set(&mut query.body, "t", &source);
+
+        let SetExpression::Select(body) = query.body else {
+            panic!("Expected a SELECT statement");
+        };
+
+        assert_eq!(body.table.alias.as_deref(), Some("t"));
+        assert_eq!(body.table.source, source);
+    }
+
+    #[test]
+    fn test_recursion_limit() {
+        let dir = TempDatabase::new("cte-limit");
+        let mut db = dir.open();
+        execute_sql(&mut db, "CREATE TABLE one (n int)").unwrap();
+        execute_sql(&mut db, "INSERT INTO one VALUES (1)").unwrap();
+
+        let result = execute_sql(
+            &mut db,
+            "WITH RECURSIVE t (n) AS (SELECT n FROM one UNION ALL SELECT n + 1 FROM t WHERE n < 100) \
+             SELECT n FROM t",
+        )
+        .unwrap()
+        .unwrap();
+        assert_eq!(result.rows.len(), 100);
+
+        let err = execute_sql(
+            &mut db,
+            "WITH RECURSIVE t (n) AS (SELECT n FROM one UNION ALL SELECT n + 1 FROM t) \
+             SELECT n FROM t LIMIT 5",
+        )
+        .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Recursive query \"t\" exceeded 10000 iterations or 1000000 rows, it may not terminate"
+        );
+    }
+}
@@ -0,0 +1,193 @@
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashSet;
+use std::hash::{Hash, Hasher};
+
+use anyhow::Result;
+
+use super::spill::{self, SpillReader, SpillWriter};
+use crate::sql::Value;
+
+/// Number of files the rows are split into once the keys exceed the budget.
+const PARTITIONS: usize = 16;
+
+/// Hash-based removal of duplicate rows, rows are equal when their first
+/// `key_len` values are, or all of them for shorter rows. NULLs are equal here.
+///
+/// While the keys seen fit in the memory budget, [`Deduplicator::push`]
+/// returns every row with a new key right away. Once they do not, the keys
+/// in memory still filter the rows, but rows with other keys are written to
+/// partitions by the hash of their key. [`Deduplicator::finish`] then reads
+/// the partitions one by one, each with its own set of keys, so equal rows
+/// always meet in the same one.
+pub struct Deduplicator {
+    key_len: usize,
+    budget: usize,
+    level: u32,
+    seen: HashSet<Vec<u8>>,
+    size: usize,
+    partitions: Vec<SpillWriter>,
+}
+
+impl Deduplicator {
+    pub fn new(key_len: usize, budget: usize) -> Self {
+        Self::with_level(key_len, budget, 0)
+    }
+
+    // Partitions of partitions are split with a different hash
+    fn with_level(key_len: usize, budget: usize, level: u32) -> Self {
+        Self {
+            key_len,
+            budget,
+            level,
+            seen: HashSet::new(),
+            size: 0,
+            partitions: Vec::new(),
+        }
+    }
+
+    /// Returns the row if its key was not seen before and it is not spilled.
+    pub fn push(&mut self, row: Vec<Value>) -> Result<Option<Vec<Value>>> {
+        let mut key = Vec::new();
+        spill::encode_key(&row[..self.key_len.min(row.len())], &mut key);
+
+        if self.seen.contains(&key) {
+            return Ok(None);
+        }
+
+        if !self.partitions.is_empty() {
+            let mut hasher = DefaultHasher::new();
+            (self.level, &key).hash(&mut hasher);
+
+            let partition = hasher.finish() as usize % PARTITIONS;
+            self.partitions[partition].write(&row)?;
+
+            return Ok(None);
+        }
+
+        self.size += key.len() + std::mem::size_of::<Vec<u8>>();
+        self.seen.insert(key);
+
+        if self.size > self.budget {
+            self.partitions = (0..PARTITIONS)
+                .map(|_| SpillWriter::new())
+                .collect::<Result<_>>()?;
+        }
+
+        Ok(Some(row))
+    }
+
+    /// Rows that were spilled, without duplicates.
+    pub fn finish(self) -> Result<Remaining> {
+        let mut pending = Vec::new();
+
+        for partition in self.partitions {
+            pending.push((partition.finish()?, self.level + 1));
+        }
+
+        Ok(Remaining {
+            key_len: self.key_len,
+            budget: self.budget,
+            pending,
+            current: None,
+        })
+    }
+}
+
+/// Iterator over the rows [`Deduplicator::push`] did not return.
+pub struct Remaining {
+    key_len: usize,
+    budget: usize,
+    pending: Vec<(SpillReader, u32)>,
+    current: Option<(SpillReader, Deduplicator)>,
+}
+
+impl Iterator for Remaining {
+    type Item = Result<Vec<Value>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some((reader, deduplicator)) = &mut self.current {
+                match reader.next() {
+                    Some(Ok(row)) => match deduplicator.push(row) {
+                        Ok(Some(row)) => return Some(Ok(row)),
+                        Ok(None) => continue,
+                        Err(err) => return Some(Err(err)),
+                    },
+                    Some(Err(err)) => return Some(Err(err)),
+                    None => {
+                        // A partition too large for the budget is split again
+                        let (_, deduplicator) = self.current.take().unwrap();
+
+                        match deduplicator.finish() {
+                            Ok(remaining) => self.pending.extend(remaining.pending),
+                            Err(err) => return Some(Err(err)),
+                        }
+                    }
+                }
+            }
+
+            let (reader, level) = self.pending.pop()?;
+            let deduplicator = Deduplicator::with_level(self.key_len, self.budget, level);
+            self.current = Some((reader, deduplicator));
+        }
+    }
+}
+
+/// Removes duplicate rows, keeping the first of each with `budget` bytes of keys in memory.
+pub fn distinct(
+    rows: impl Iterator<Item = Result<Vec<Value>>>,
+    key_len: usize,
+    budget: usize,
+) -> Result<Vec<Vec<Value>>> {
+    let mut deduplicator = Deduplicator::new(key_len, budget);
+    let mut unique = Vec::new();
+
+    for row in rows {
+        unique.extend(deduplicator.push(row?)?);
+    }
+
+    for row in deduplicator.finish()? {
+        unique.push(row?);
+    }
+
+    Ok(unique)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_distinct_spills() {
+        let rows = (0..2000)
+            .map(|i| Ok(vec![Value::Int(i % 300), Value::Int(i)]))
+            .collect::<Vec<_>>();
+
+        // A budget of a few keys forces every level to spill
+        let mut unique = distinct(rows.into_iter(), 1, 256).unwrap();
+        unique.sort_by_key(|row| match row[0] {
+            Value::Int(value) => value,
+            _ => unreachable!(),
+        });
+
+        let expected = (0..300)
+            .map(|i| vec![Value::Int(i), Value::Int(i)])
+            .collect::<Vec<_>>();
+
+        assert_eq!(unique, expected);
+    }
+
+    #[test]
+    fn test_distinct_nulls() {
+        let rows = vec![
+            Ok(vec![Value::Null]),
+            Ok(vec![Value::Int(1)]),
+            Ok(vec![Value::Null]),
+        ];
+
+        assert_eq!(
+            distinct(rows.into_iter(), 1, spill::MEMORY_BUDGET).unwrap(),
+            vec![vec![Value::Null], vec![Value::Int(1)]]
+        );
+    }
+}
@@ -0,0 +1,422 @@
+use std::cmp::Ordering;
+
+use anyhow::{anyhow, bail, Result};
+
+use super::function;
+use crate::sql::{BinaryOperator, Expression, Operator, Value, WhereExpression};
+
+/// Looks up a column of `row` by name, `columns` holds the names in row order.
+fn column<'r>(name: &str, columns: &[String], row: &'r [Value]) -> Result<&'r Value> {
+    Ok(&row[find_column(name, columns)?])
+}
+
+/// Finds the index of a column. Columns of joined tables are named
+/// `table.column` and can be referenced without the table when unambiguous.
+pub fn find_column(name: &str, columns: &[String]) -> Result<usize> {
+    if let Some(index) = columns.iter().position(|column| column == name) {
+        return Ok(index);
+    }
+
+    if name.contains('.') {
+        bail!("Column \"{}\" does not exist", name);
+    }
+
+    let mut matches = columns
+        .iter()
+        .enumerate()
+        .filter(|(_, column)| column.rsplit_once('.').is_some_and(|(_, column)| column == name));
+
+    match (matches.next(), matches.next()) {
+        (Some((index, _)), None) => Ok(index),
+        (Some(_), Some(_)) => bail!("Column reference \"{}\" is ambiguous", name),
+        _ => bail!("Column \"{}\" does not exist", name),
+    }
+}
+
+pub fn evaluate(expression: &Expression, columns: &[String], row: &[Value]) -> Result<Value> {
+    match expression {
+        Expression::Value(value) => Ok(value.clone()),
+        Expression::Column(name) => column(name, columns, row).cloned(),
+        Expression::Wildcard => bail!("* is only allowed in a select list"),
+        Expression::Function(call) => {
+            let arguments = call
+                .arguments
+                .iter()
+                .map(|argument| evaluate(argument, columns, row))
+                .collect::<Result<Vec<_>>>()?;
+
+            function::call(&call.name, arguments)
+        }
+        Expression::Aggregate(call) => bail!(
+            "Aggregate function {}() is not allowed here",
+            call.function.name()
+        ),
+        Expression::Negate(expression) => evaluate(expression, columns, row)?.negate(),
+        Expression::Binary {
+            left,
+            operator,
+            right,
+        } => {
+            let left = evaluate(left, columns, row)?;
+            let right = evaluate(right, columns, row)?;
+
+            if left == Value::Null || right == Value::Null {
+                return Ok(Value::Null);
+            }
+
+            match operator {
+                BinaryOperator::Add => left.add(&right),
+                BinaryOperator::Subtract => left.subtract(&right),
+                BinaryOperator::Multiply => left.multiply(&right),
+                BinaryOperator::Divide => left.divide(&right),
+                BinaryOperator::Concat => Ok(left.concat(&right)),
+                BinaryOperator::JsonGet => left.json_get(&right, false),
+                BinaryOperator::JsonGetText => left.json_get(&right, true),
+            }
+        }
+        Expression::Case(case) => {
+            for branch in case.branches.iter() {
+                if matches(&branch.condition, columns, row)? {
+                    return evaluate(&branch.result, columns, row);
+                }
+            }
+
+            match &case.r#else {
+                Some(expression) => evaluate(expression, columns, row),
+                None => Ok(Value::Null),
+            }
+        }
+        Expression::Cast { expression, kind } => evaluate(expression, columns, row)?.cast(kind),
+        Expression::Condition(condition) => {
+            Ok(truth(condition, columns, row)?.map_or(Value::Null, Value::Boolean))
+        }
+        // Subqueries are replaced by their results before rows are evaluated
+        Expression::Subquery(_) => bail!("Subqueries are not supported here"),
+        Expression::Window(call) => bail!(
+            "Window function {}() is not allowed here",
+            call.function.name()
+        ),
+    }
+}
+
+/// Evaluates a WHERE clause against a row.
+pub fn matches(expression: &WhereExpression, columns: &[String], row: &[Value]) -> Result<bool> {
+    Ok(truth(expression, columns, row)? == Some(true))
+}
+
+/// True when the condition is false for the row, unknown is not a failure.
+/// This is how CHECK constraints treat NULL.
+pub fn fails(expression: &WhereExpression, columns: &[String], row: &[Value]) -> Result<bool> {
+    Ok(truth(expression, columns, row)? == Some(false))
+}
+
+/// Three-valued logic: comparisons with NULL are unknown (`None`).
+fn truth(expression: &WhereExpression, columns: &[String], row: &[Value]) -> Result<Option<bool>> {
+    match expression {
+        WhereExpression::And(expressions) => {
+            let mut result = Some(true);
+
+            for expression in expressions {
+                match truth(expression, columns, row)? {
+                    Some(false) => return Ok(Some(false)),
+                    None => result = None,
+                    Some(true) => {}
+                }
+            }
+
+            Ok(result)
+        }
+        WhereExpression::Or(expressions) => {
+            let mut result = Some(false);
+
+            for expression in expressions {
+                match truth(expression, columns, row)? {
+                    Some(true) => return Ok(Some(true)),
+                    None => result = None,
+                    Some(false) => {}
+                }
+            }
+
+            Ok(result)
+        }
+        WhereExpression::Not(expression) => Ok(truth(expression, columns, row)?.map(|t| !t)),
+        WhereExpression::Condition(condition) => {
+            let left = evaluate(&condition.field, columns, row)?;
+            let right = evaluate(&condition.value, columns, row)?;
+
+            if left == Value::Null || right == Value::Null {
+                return Ok(None);
+            }
+
+            let ordering = || compare(&left, &right);
+
+            let result = match condition.operator {
+                Operator::GreaterThan => ordering()? == Ordering::Greater,
+                Operator::GreaterThanEquals => ordering()? != Ordering::Less,
+                Operator::Equals => ordering()? == Ordering::Equal,
+                Operator::NotEquals => ordering()? != Ordering::Equal,
+                Operator::SmallerThanEquals => ordering()? != Ordering::Greater,
+                Operator::SmallerThan => ordering()? == Ordering::Less,
+                Operator::Like => like(text(&left, "LIKE")?, text(&right, "LIKE")?, false)?,
+                Operator::ILike => like(text(&left, "ILIKE")?, text(&right, "ILIKE")?, true)?,
+                Operator::Contains => contains(&left, &right)?,
+            };
+
+            Ok(Some(result))
+        }
+        WhereExpression::In { expression, list } => {
+            // Lists from subqueries can be empty, nothing is in them, not even NULL
+            if list.is_empty() {
+                return Ok(Some(false));
+            }
+
+            let value = evaluate(expression, columns, row)?;
+
+            if value == Value::Null {
+                return Ok(None);
+            }
+
+            // Not finding the value is unknown when the list holds a NULL
+            let mut result = Some(false);
+
+            for item in list {
+                let item = evaluate(item, columns, row)?;
+
+                if item == Value::Null {
+                    result = None;
+                } else if compare(&value, &item)? == Ordering::Equal {
+                    return Ok(Some(true));
+                }
+            }
+
+            Ok(result)
+        }
+        WhereExpression::IsNull(expression) => {
+            Ok(Some(evaluate(expression, columns, row)? == Value::Null))
+        }
+        WhereExpression::InSubquery { .. } | WhereExpression::Exists(_) => {
+            bail!("Subqueries are not supported here")
+        }
+    }
+}
+
+fn compare(left: &Value, right: &Value) -> Result<Ordering> {
+    left.compare(right)
+        .ok_or_else(|| anyhow!("Cannot compare {} with {}", left, right))
+}
+
+/// Text operand of a pattern operator, JSON strings are unwrapped.
+fn text<'v>(value: &'v Value, operator: &str) -> Result<&'v str> {
+    match value {
+        Value::String(text) | Value::Json(serde_json::Value::String(text)) => Ok(text),
+        _ => bail!("{} expects text, got {}", operator, value),
+    }
+}
+
+/// Substring test of `CONTAINS`, on text or on the bytes of blobs.
+fn contains(left: &Value, right: &Value) -> Result<bool> {
+    match (left, right) {
+        (Value::Blob(bytes), Value::Blob(needle)) => {
+            Ok(needle.is_empty() || bytes.windows(needle.len()).any(|w| w == needle))
+        }
+        _ => Ok(text(left, "CONTAINS")?.contains(text(right, "CONTAINS")?)),
+    }
+}
+
+enum PatternToken {
+    /// `%`
+    Any,
+    /// `_`
+    One,
+    Char(char),
+}
+
+/// Matches `text` against a LIKE pattern, a backslash makes the next
+/// character of the pattern match literally.
+fn like(text: &str, pattern: &str, case_insensitive: bool) -> Result<bool> {
+    let fold = |input: &str| match case_insensitive {
+        true => input.to_lowercase(),
+        false => String::from(input),
+    };
+
+    let text = fold(text).chars().collect::<Vec<_>>();
+    let pattern = fold(pattern);
+
+    let mut tokens = Vec::new();
+    let mut chars = pattern.chars();
+
+    while let Some(c) = chars.next() {
+        tokens.push(match c {
+            '%' => PatternToken::Any,
+            '_' => PatternToken::One,
+            '\\' => PatternToken::Char(
+                chars
+                    .next()
+                    .ok_or_else(|| anyhow!("LIKE pattern must not end with an escape character"))?,
+            ),
+            c => PatternToken::Char(c),
+        });
+    }
+
+    // Greedy match that retries from the last '%' with one more character
+    // consumed by it whenever the rest of the pattern fails
+    let (mut t, mut p) = (0, 0);
+    let mut retry = None;
+
+    while t < text.len() {
+        match tokens.get(p) {
+            Some(PatternToken::Any) => {
+                retry = Some((p, t));
+                p += 1;
+                continue;
+            }
+            Some(PatternToken::One) => {
+                t += 1;
+                p += 1;
+                continue;
+            }
+            Some(PatternToken::Char(c)) if *c == text[t] => {
+                t += 1;
+                p += 1;
+                continue;
+            }
+            _ => {}
+        }
+
+        match retry {
+            Some((any, start)) => {
+                retry = Some((any, start + 1));
+                p = any + 1;
+                t = start + 1;
+            }
+            None => return Ok(false),
+        }
+    }
+
+    Ok(tokens[p..]
+        .iter()
+        .all(|token| matches!(token, PatternToken::Any)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sql::parser::Parser;
+    use crate::sql::tokenizer::Tokenizer;
+    use crate::types::date::{Date, Timestamp};
+
+    #[test]
+    fn test_like() {
+        let cases = [
+            ("hello", "hello", true),
+            ("hello", "h%", true),
+            ("hello", "%llo", true),
+            ("hello", "h_l%o", true),
+            ("hello", "%l%l%", true),
+            ("hello", "h_lo", false),
+            ("hello", "%x%", false),
+            ("", "%", true),
+            ("50%", "50\\%", true),
+            ("500", "50\\%", false),
+            ("aab", "%ab", true),
+        ];
+
+        for (text, pattern, expected) in cases {
+            assert_eq!(like(text, pattern, false).unwrap(), expected, "{} LIKE {}", text, pattern);
+        }
+
+        assert!(like("Hello", "hel%", true).unwrap());
+        assert!(!like("Hello", "hel%", false).unwrap());
+    }
+
+    #[test]
+    fn test_in_with_null() {
+        let columns = vec![String::from("id")];
+        let list = |values: Vec<Value>| WhereExpression::In {
+            expression: Expression::Column(String::from("id")),
+            list: values.into_iter().map(Expression::Value).collect(),
+        };
+
+        let row = [Value::Int(2)];
+        assert_eq!(
+            truth(&list(vec![Value::Int(1), Value::Int(2)]), &columns, &row).unwrap(),
+            Some(true)
+        );
+        assert_eq!(
+            truth(&list(vec![Value::Int(1), Value::Null]), &columns, &row).unwrap(),
+            None
+        );
+        assert_eq!(
+            truth(&list(vec![Value::Int(1)]), &columns, &[Value::Null]).unwrap(),
+            None
+        );
+    }
+
+    #[test]
+    fn test_is_null() {
+        let columns = vec![String::from("id")];
+        let is_null = WhereExpression::IsNull(Expression::Column(String::from("id")));
+        let is_not_null = WhereExpression::Not(Box::new(is_null.clone()));
+
+        assert_eq!(truth(&is_null, &columns, &[Value::Null]).unwrap(), Some(true));
+        assert_eq!(truth(&is_null, &columns, &[Value::Int(1)]).unwrap(), Some(false));
+        assert_eq!(truth(&is_not_null, &columns, &[Value::Null]).unwrap(), Some(false));
+        assert_eq!(truth(&is_not_null, &columns, &[Value::Int(1)]).unwrap(), Some(true));
+    }
+
+    #[test]
+    fn test_json_operators() {
+        let columns = vec![String::from("data")];
+        let row = [Value::Json(
+            serde_json::from_str(r#"{"a": {"b": "text", "c": null}}"#).unwrap(),
+        )];
+
+        let get = |sql: &str| {
+            let expression = Parser::new(Tokenizer::new(sql).parse().unwrap())
+                .parse_expression()
+                .unwrap();
+
+            evaluate(&expression, &columns, &row).unwrap()
+        };
+
+        assert_eq!(get("data->\"a\"->\"b\""), Value::Json(serde_json::Value::from("text")));
+        assert_eq!(get("data->\"a\"->>\"b\""), Value::String(String::from("text")));
+        assert_eq!(get("data->\"a\"->\"c\""), Value::Json(serde_json::Value::Null));
+        assert_eq!(get("data->\"a\"->>\"c\""), Value::Null);
+        assert_eq!(get("data->\"missing\"->\"b\""), Value::Null);
+    }
+
+    #[test]
+    fn test_interval_overflow() {
+        let columns = vec![String::from("d"), String::from("ts")];
+        let row = [
+            Value::Date(Date::parse("2026-01-31").unwrap()),
+            Value::Timestamp(Timestamp::parse("2026-01-31 12:30:00").unwrap()),
+        ];
+
+        let get = |sql: &str| {
+            let expression = Parser::new(Tokenizer::new(sql).parse().unwrap())
+                .parse_expression()
+                .unwrap();
+
+            evaluate(&expression, &columns, &row)
+        };
+
+        let cases = [
+            "d + INTERVAL \"2147483647 months\"",
+            "INTERVAL \"2147483647 months\" + INTERVAL \"2147483647 months\"",
+            "ts - INTERVAL \"-2147483648 days\"",
+            "-INTERVAL \"-2147483648 months\"",
+            "ts + INTERVAL \"1000000 years 1 second\"",
+        ];
+
+        for sql in cases {
+            assert!(get(sql).is_err(), "{}", sql);
+        }
+
+        assert_eq!(
+            get("d + INTERVAL \"1 month\"").unwrap(),
+            Value::Date(Date::parse("2026-02-28").unwrap())
+        );
+    }
+}
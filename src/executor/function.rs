@@ -0,0 +1,149 @@
+use std::cell::Cell;
+
+use anyhow::{anyhow, bail, Result};
+
+use super::typecheck::Type;
+use crate::sql::Value;
+use crate::types::date::{Timestamp, TruncUnit};
+use crate::types::json;
+
+thread_local! {
+    /// Start of the running statement, returned by `now()` for all of its rows.
+    static STATEMENT_TIME: Cell<Option<Timestamp>> = const { Cell::new(None) };
+}
+
+/// Keeps `now()` and `current_date` at the current time until it is dropped.
+pub struct StatementTime(());
+
+impl StatementTime {
+    pub fn start() -> Self {
+        STATEMENT_TIME.with(|time| time.set(Some(Timestamp::now())));
+        Self(())
+    }
+}
+
+impl Drop for StatementTime {
+    fn drop(&mut self) {
+        STATEMENT_TIME.with(|time| time.set(None));
+    }
+}
+
+fn now() -> Timestamp {
+    STATEMENT_TIME
+        .with(|time| time.get())
+        .unwrap_or_else(Timestamp::now)
+}
+
+/// Calls a scalar function by name.
+pub fn call(name: &str, arguments: Vec<Value>) -> Result<Value> {
+    match (name.to_lowercase().as_str(), arguments.as_slice()) {
+        ("now", []) => Ok(Value::Timestamp(now())),
+        ("current_date", []) => Ok(Value::Date(now().date())),
+        ("date_trunc", [Value::Null, _] | [_, Value::Null]) => Ok(Value::Null),
+        ("date_trunc", [Value::String(unit), value]) => {
+            let unit = TruncUnit::parse(unit)?;
+
+            match value {
+                Value::Timestamp(timestamp) => Ok(Value::Timestamp(timestamp.truncate(unit)?)),
+                Value::Date(date) => Ok(Value::Date(date.truncate(unit)?)),
+                _ => bail!("date_trunc expects a date or timestamp, got {}", value),
+            }
+        }
+        ("round", [Value::Null] | [Value::Null, _] | [_, Value::Null]) => Ok(Value::Null),
+        ("round", [value]) => Ok(Value::Decimal(value.to_decimal()?.rescale(0)?)),
+        ("round", [value, Value::Int(scale)]) => {
+            let scale = u32::try_from(*scale)
+                .map_err(|_| anyhow!("round expects a non-negative scale, got {}", scale))?;
+
+            Ok(Value::Decimal(value.to_decimal()?.rescale(scale)?))
+        }
+        ("json_extract", [Value::Null, _]) => Ok(Value::Null),
+        ("json_extract", [Value::Json(document), Value::String(path)]) => {
+            let path = json::parse_path(path)?;
+
+            match json::extract(document, &path) {
+                Some(value) => Ok(Value::from_json(value)),
+                None => Ok(Value::Null),
+            }
+        }
+        // Sequences are read by the executor before evaluation
+        ("nextval", _) => bail!(
+            "nextval() is only supported in the selections of a query without grouping, in UPDATE SET and in DEFAULT"
+        ),
+        _ => bail!(
+            "Function {}() does not exist or does not accept {} argument(s)",
+            name,
+            arguments.len()
+        ),
+    }
+}
+
+/// Type of the result of a call, checked before any row is read.
+pub fn return_type(name: &str, arguments: &[Type]) -> Result<Type> {
+    match (name.to_lowercase().as_str(), arguments) {
+        ("now", []) => Ok(Type::Timestamp),
+        ("current_date", []) => Ok(Type::Date),
+        ("date_trunc", [Type::Text | Type::Unknown, value]) => match value {
+            Type::Date | Type::Timestamp | Type::Unknown => Ok(*value),
+            _ => bail!("date_trunc expects a date or timestamp, got {}", value),
+        },
+        ("round", [Type::Int | Type::Decimal | Type::Text | Type::Unknown]) => Ok(Type::Decimal),
+        (
+            "round",
+            [Type::Int | Type::Decimal | Type::Text | Type::Unknown, Type::Int | Type::Unknown],
+        ) => Ok(Type::Decimal),
+        ("json_extract", [Type::Json | Type::Unknown, Type::Text | Type::Unknown]) => {
+            Ok(Type::Unknown)
+        }
+        ("nextval", [Type::Text | Type::Unknown]) => Ok(Type::Int),
+        _ => bail!(
+            "Function {}() does not exist or does not accept arguments of type ({})",
+            name,
+            arguments
+                .iter()
+                .map(|argument| argument.to_string())
+                .collect::<Vec<_>>()
+                .join(", ")
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_now_is_fixed_per_statement() {
+        let time = StatementTime::start();
+        let first = call("now", Vec::new()).unwrap();
+
+        std::thread::sleep(std::time::Duration::from_millis(2));
+        assert_eq!(call("NOW", Vec::new()).unwrap(), first);
+
+        drop(time);
+        assert_ne!(call("now", Vec::new()).unwrap(), first);
+
+        assert_eq!(
+            call(
+                "date_trunc",
+                vec![
+                    Value::String(String::from("quarter")),
+                    Value::Timestamp(Timestamp::parse("2026-05-17 08:00:00").unwrap())
+                ]
+            )
+            .unwrap()
+            .to_string(),
+            "2026-04-01 00:00:00"
+        );
+    }
+
+    #[test]
+    fn test_null_arguments() {
+        let unit = || Value::String(String::from("month"));
+
+        assert_eq!(call("date_trunc", vec![unit(), Value::Null]).unwrap(), Value::Null);
+        assert_eq!(call("round", vec![Value::Null]).unwrap(), Value::Null);
+        assert_eq!(call("round", vec![Value::Null, Value::Int(2)]).unwrap(), Value::Null);
+        assert_eq!(call("round", vec![Value::Int(1), Value::Null]).unwrap(), Value::Null);
+    }
+}
@@ -0,0 +1,340 @@
+use std::collections::HashMap;
+
+use anyhow::Result;
+
+use super::typecheck::{Checker, Type};
+use super::{expression, spill};
+use crate::sql::{Expression, JoinKind, Operator, Value, WhereCondition, WhereExpression};
+
+/// Rows of the right side of a join, read into memory before the join runs.
+pub struct Relation {
+    pub columns: Vec<String>,
+    pub types: Vec<Type>,
+    pub rows: Vec<Vec<Value>>,
+}
+
+enum Strategy {
+    /// Compares every left row with every right row.
+    NestedLoop,
+    /// Looks up the right rows by the values of equality conditions between
+    /// both sides, the rest of the ON clause is checked for each candidate.
+    Hash {
+        left_keys: Vec<Expression>,
+        buckets: HashMap<Vec<u8>, Vec<usize>>,
+    },
+}
+
+/// One join of a FROM clause, combining rows of everything to its left with
+/// the rows of a [`Relation`].
+pub struct Join {
+    kind: JoinKind,
+    columns: Vec<String>,
+    left_width: usize,
+    right: Relation,
+    condition: Option<WhereExpression>,
+    strategy: Strategy,
+}
+
+impl Join {
+    pub fn new(
+        kind: JoinKind,
+        left_columns: &[String],
+        left_types: &[Type],
+        right: Relation,
+        on: Option<WhereExpression>,
+    ) -> Result<Self> {
+        let columns = left_columns
+            .iter()
+            .chain(right.columns.iter())
+            .cloned()
+            .collect::<Vec<_>>();
+
+        let conditions = match on {
+            None => Vec::new(),
+            Some(WhereExpression::And(conditions)) => conditions,
+            Some(condition) => vec![condition],
+        };
+
+        let left_checker = Checker::new(left_columns, left_types);
+        let right_checker = Checker::new(&right.columns, &right.types);
+
+        // Equal values of different types, such as 1 and 1.0, are encoded
+        // differently, so only keys of the same type are looked up
+        let same_type = |left: &Expression, right: &Expression| {
+            let types = (left_checker.check(left), right_checker.check(right));
+            matches!(types, (Ok(left), Ok(right)) if left == right && left != Type::Unknown)
+        };
+
+        let mut left_keys = Vec::new();
+        let mut right_keys = Vec::new();
+        let mut residual = Vec::new();
+
+        for condition in conditions {
+            if let WhereExpression::Condition(WhereCondition {
+                field,
+                operator: Operator::Equals,
+                value,
+            }) = &condition
+            {
+                if resolves(field, left_columns)
+                    && resolves(value, &right.columns)
+                    && same_type(field, value)
+                {
+                    left_keys.push(field.clone());
+                    right_keys.push(value.clone());
+                    continue;
+                }
+
+                if resolves(value, left_columns)
+                    && resolves(field, &right.columns)
+                    && same_type(value, field)
+                {
+                    left_keys.push(value.clone());
+                    right_keys.push(field.clone());
+                    continue;
+                }
+            }
+
+            residual.push(condition);
+        }
+
+        let condition = match residual.len() {
+            0 => None,
+            1 => residual.pop(),
+            _ => Some(WhereExpression::And(residual)),
+        };
+
+        let strategy = if left_keys.is_empty() {
+            Strategy::NestedLoop
+        } else {
+            let mut buckets = HashMap::new();
+
+            for (index, row) in right.rows.iter().enumerate() {
+                if let Some(key) = hash_key(&right_keys, &right.columns, row)? {
+                    buckets.entry(key).or_insert_with(Vec::new).push(index);
+                }
+            }
+
+            Strategy::Hash { left_keys, buckets }
+        };
+
+        Ok(Self {
+            kind,
+            columns,
+            left_width: left_columns.len(),
+            right,
+            condition,
+            strategy,
+        })
+    }
+
+    /// Names of the joined columns: the left columns followed by the right ones.
+    pub fn columns(&self) -> &[String] {
+        &self.columns
+    }
+
+    /// Joined rows for one row of the left side.
+    pub fn probe(&self, left: &[Value]) -> Result<Vec<Vec<Value>>> {
+        let candidates: Box<dyn Iterator<Item = usize>> = match &self.strategy {
+            Strategy::NestedLoop => Box::new(0..self.right.rows.len()),
+            Strategy::Hash { left_keys, buckets } => {
+                match hash_key(left_keys, &self.columns[..self.left_width], left)? {
+                    Some(key) => Box::new(buckets.get(&key).into_iter().flatten().copied()),
+                    None => Box::new(std::iter::empty()),
+                }
+            }
+        };
+
+        let mut rows = Vec::new();
+
+        for index in candidates {
+            let mut row = left.to_vec();
+            row.extend(self.right.rows[index].iter().cloned());
+
+            if let Some(condition) = &self.condition {
+                if !expression::matches(condition, &self.columns, &row)? {
+                    continue;
+                }
+            }
+
+            rows.push(row);
+        }
+
+        if rows.is_empty() && self.kind == JoinKind::Left {
+            let mut row = left.to_vec();
+            row.resize(self.columns.len(), Value::Null);
+            rows.push(row);
+        }
+
+        Ok(rows)
+    }
+}
+
+/// Encodes the values of the join keys, `None` when one of them is NULL and
+/// the row can therefore not match anything.
+fn hash_key(keys: &[Expression], columns: &[String], row: &[Value]) -> Result<Option<Vec<u8>>> {
+    let values = keys
+        .iter()
+        .map(|key| expression::evaluate(key, columns, row))
+        .collect::<Result<Vec<_>>>()?;
+
+    if values.contains(&Value::Null) {
+        return Ok(None);
+    }
+
+    let mut key = Vec::new();
+    spill::encode_key(&values, &mut key);
+    Ok(Some(key))
+}
+
+/// Whether every column the expression references belongs to `columns`.
+fn resolves(expression: &Expression, columns: &[String]) -> bool {
+    match expression {
+        Expression::Column(name) => expression::find_column(name, columns).is_ok(),
+        Expression::Aggregate(_) => false,
+        expression => expression
+            .children()
+            .into_iter()
+            .all(|child| resolves(child, columns)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::decimal::Decimal;
+
+    fn columns(names: &[&str]) -> Vec<String> {
+        names.iter().map(|name| String::from(*name)).collect()
+    }
+
+    fn condition(field: &str, value: &str) -> WhereExpression {
+        WhereExpression::Condition(WhereCondition {
+            field: Expression::Column(String::from(field)),
+            operator: Operator::Equals,
+            value: Expression::Column(String::from(value)),
+        })
+    }
+
+    fn orders() -> Relation {
+        Relation {
+            columns: columns(&["o.user_id", "o.total"]),
+            types: vec![Type::Int, Type::Int],
+            rows: vec![
+                vec![Value::Int(1), Value::Int(10)],
+                vec![Value::Int(1), Value::Int(20)],
+                vec![Value::Null, Value::Int(30)],
+            ],
+        }
+    }
+
+    #[test]
+    fn test_hash_join() {
+        let left = columns(&["u.id", "u.name"]);
+        let join = Join::new(
+            JoinKind::Left,
+            &left,
+            &[Type::Int, Type::Text],
+            orders(),
+            Some(condition("user_id", "u.id")),
+        )
+        .unwrap();
+
+        assert!(matches!(join.strategy, Strategy::Hash { .. }));
+        assert!(join.condition.is_none());
+
+        let rows = join
+            .probe(&[Value::Int(1), Value::String(String::from("ann"))])
+            .unwrap();
+        assert_eq!(rows.len(), 2);
+        assert_eq!(rows[1][3], Value::Int(20));
+
+        let rows = join
+            .probe(&[Value::Int(2), Value::String(String::from("bob"))])
+            .unwrap();
+        assert_eq!(
+            rows,
+            vec![vec![
+                Value::Int(2),
+                Value::String(String::from("bob")),
+                Value::Null,
+                Value::Null,
+            ]]
+        );
+    }
+
+    #[test]
+    fn test_nested_loop_join() {
+        let left = columns(&["u.id", "u.name"]);
+        let on = WhereExpression::Condition(WhereCondition {
+            field: Expression::Column(String::from("o.total")),
+            operator: Operator::GreaterThan,
+            value: Expression::Column(String::from("u.id")),
+        });
+
+        let types = [Type::Int, Type::Text];
+        let join = Join::new(JoinKind::Inner, &left, &types, orders(), Some(on)).unwrap();
+        assert!(matches!(join.strategy, Strategy::NestedLoop));
+
+        let rows = join
+            .probe(&[Value::Int(15), Value::String(String::from("ann"))])
+            .unwrap();
+        assert_eq!(rows.len(), 2);
+
+        let cross = Join::new(JoinKind::Cross, &left, &types, orders(), None).unwrap();
+        let rows = cross
+            .probe(&[Value::Int(15), Value::String(String::from("ann"))])
+            .unwrap();
+        assert_eq!(rows.len(), 3);
+        assert_eq!(cross.columns().len(), 4);
+    }
+
+    #[test]
+    fn test_join_keys_of_different_types() {
+        let decimals = |values: &[(i128, u32)]| Relation {
+            columns: columns(&["b.y"]),
+            types: vec![Type::Decimal],
+            rows: values
+                .iter()
+                .map(|(mantissa, scale)| vec![Value::Decimal(Decimal::new(*mantissa, *scale))])
+                .collect(),
+        };
+
+        // INT = DECIMAL is checked for each pair, as 1 and 1.0 are encoded differently
+        let join = Join::new(
+            JoinKind::Inner,
+            &columns(&["a.x"]),
+            &[Type::Int],
+            decimals(&[(10, 1)]),
+            Some(condition("a.x", "b.y")),
+        )
+        .unwrap();
+
+        assert!(matches!(join.strategy, Strategy::NestedLoop));
+        assert_eq!(join.probe(&[Value::Int(1)]).unwrap().len(), 1);
+
+        // Decimals of different scales find each other
+        let join = Join::new(
+            JoinKind::Inner,
+            &columns(&["a.x"]),
+            &[Type::Decimal],
+            decimals(&[(10, 1), (15, 1)]),
+            Some(condition("a.x", "b.y")),
+        )
+        .unwrap();
+
+        assert!(matches!(join.strategy, Strategy::Hash { .. }));
+        assert_eq!(
+            join.probe(&[Value::Decimal(Decimal::new(100, 2))])
+                .unwrap()
+                .len(),
+            1
+        );
+        assert_eq!(
+            join.probe(&[Value::Decimal(Decimal::new(150, 2))])
+                .unwrap()
+                .len(),
+            1
+        );
+    }
+}
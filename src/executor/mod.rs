@@ -0,0 +1,1939 @@
+mod aggregate;
+mod cascade;
+mod constraint;
+mod cte;
+mod distinct;
+mod expression;
+mod function;
+mod join;
+mod sequence;
+mod sort;
+mod spill;
+mod subquery;
+mod typecheck;
+mod upsert;
+mod view;
+mod window;
+
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::fmt::{self, Display, Formatter};
+
+use anyhow::{bail, Result};
+
+use crate::database::Database;
+use crate::sql::{
+    CreateTableStatement, DeleteStatement, Direction, Distinct, Expression, InsertSource, InsertStatement, OrderBy,
+    Pagination, Query, Returning, SelectStatement, Selection, SetExpression, SetOperator, Statement, TableDefinition,
+    TableReference, TableSource, UpdateStatement, Value, WhereExpression, WindowCall,
+};
+use crate::schema::{Column, ColumnKind, DecimalColumn, Sequence, TableSchema};
+use crate::table::{Row, RowId};
+use crate::types::decimal::Decimal;
+use distinct::Deduplicator;
+use join::{Join, Relation};
+use sort::{SortKey, Sorter};
+use typecheck::{Checker, Type};
+use constraint::Constraints;
+use upsert::Conflicts;
+
+/// Rows produced by a statement, printed by the REPL.
+#[derive(Debug)]
+pub struct ResultSet {
+    pub columns: Vec<String>,
+    pub rows: Vec<Vec<Value>>,
+}
+
+impl Display for ResultSet {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        if self.rows.is_empty() {
+            return writeln!(f, "No data");
+        }
+
+        write!(f, "|")?;
+
+        for column in self.columns.iter() {
+            write!(f, " {} |", column)?;
+        }
+
+        writeln!(f)?;
+
+        for row in self.rows.iter() {
+            write!(f, "|")?;
+
+            for value in row.iter() {
+                write!(f, " {} |", value)?;
+            }
+
+            writeln!(f)?;
+        }
+
+        Ok(())
+    }
+}
+
+pub fn execute(db: &mut Database, statement: Statement) -> Result<Option<ResultSet>> {
+    let _time = function::StatementTime::start();
+
+    match statement {
+        Statement::Select(statement) => select(db, *statement).map(Some),
+        Statement::Query(statement) => run(db, *statement).map(|(result, _)| Some(result)),
+        Statement::Insert(mut statement) => {
+            let returning = returning(db, &statement.table, statement.returning.take())?;
+            let rows = insert(db, statement)?;
+            returning.map(|returning| returning.result(rows)).transpose()
+        }
+        Statement::Delete(mut statement) => {
+            let returning = returning(db, &statement.table, statement.returning.take())?;
+            let rows = delete(db, statement)?;
+            returning.map(|returning| returning.result(rows)).transpose()
+        }
+        Statement::Update(mut statement) => {
+            let returning = returning(db, &statement.table, statement.returning.take())?;
+            let rows = update(db, statement)?;
+            returning.map(|returning| returning.result(rows)).transpose()
+        }
+        Statement::CreateTable(statement) => {
+            create_table(db, statement)?;
+            Ok(None)
+        }
+        Statement::CreateSequence(statement) => {
+            // Descending sequences count down from -1
+            let increment = statement.increment.unwrap_or(1);
+            let start = statement.start.unwrap_or(increment.signum());
+
+            db.create_sequence(&statement.name, Sequence::new(start, increment))?;
+            Ok(None)
+        }
+        Statement::CreateView(statement) => {
+            view::create(db, statement)?;
+            Ok(None)
+        }
+    }
+}
+
+/// Collects the rows matching `where` before the caller modifies the table,
+/// so changes made while scanning are never visited twice.
+fn matching_rows(
+    db: &mut Database,
+    table: &str,
+    names: &[String],
+    r#where: Option<&WhereExpression>,
+) -> Result<Vec<(RowId, Row)>> {
+    let correlated = r#where.filter(|r#where| subquery::contains_subquery(r#where));
+    let mut rows = Vec::new();
+
+    for entry in db.table(table)?.scan() {
+        let (id, row) = entry?;
+
+        if let Some(r#where) = r#where.filter(|_| correlated.is_none()) {
+            if !expression::matches(r#where, names, row.values())? {
+                continue;
+            }
+        }
+
+        rows.push((id, row));
+    }
+
+    // Correlated subqueries read tables themselves, so they run once the scan is done
+    if let Some(r#where) = correlated {
+        let mut matching = Vec::new();
+
+        for (id, row) in rows {
+            if subquery::matches(db, r#where, names, row.values())? {
+                matching.push((id, row));
+            }
+        }
+
+        rows = matching;
+    }
+
+    Ok(rows)
+}
+
+fn select(db: &mut Database, statement: SelectStatement) -> Result<ResultSet> {
+    query(db, statement).map(|(result, _)| result)
+}
+
+/// Runs a query with common table expressions or set operations,
+/// returning its rows and the types of its columns.
+fn run(db: &mut Database, mut query: Query) -> Result<(ResultSet, Vec<Type>)> {
+    cte::plan(db, &mut query)?;
+
+    let (mut result, types) = compound(db, query.body)?;
+
+    // ORDER BY refers to the columns of the combined rows by their names or
+    // positions, both are replaced by internal names as headers may repeat
+    if !query.order_by.is_empty() || query.pagination.is_some() {
+        let names = (0..result.columns.len()).map(position_column).collect::<Vec<_>>();
+        let checker = Checker::new(&names, &types);
+
+        for order_by in query.order_by.iter_mut() {
+            match ordinal(&order_by.expression, names.len())? {
+                Some(index) => order_by.expression = Expression::Column(names[index].clone()),
+                None => rename_columns(&mut order_by.expression, &result.columns, &names)?,
+            }
+
+            checker.check(&order_by.expression)?;
+        }
+
+        result.rows = order(
+            result.rows.into_iter().map(Ok),
+            &names,
+            &query.order_by,
+            query.pagination.as_ref(),
+            None,
+            |row| Ok(row.to_vec()),
+        )?;
+    }
+
+    Ok((result, types))
+}
+
+/// Runs the SELECT statements of a set expression, returning their combined
+/// rows and the types of its columns, which are named after the first one.
+fn compound(db: &mut Database, expression: SetExpression) -> Result<(ResultSet, Vec<Type>)> {
+    match expression {
+        SetExpression::Select(statement) => query(db, *statement),
+        SetExpression::Operation {
+            operator,
+            left,
+            right,
+            all,
+        } => {
+            let (mut result, types) = compound(db, *left)?;
+            let (mut right, right_types) = compound(db, *right)?;
+            let types = set_types(operator, &types, &right_types)?;
+
+            conform(&mut result.rows, &types);
+            conform(&mut right.rows, &types);
+            result.rows = combine(operator, all, result.rows, right.rows)?;
+
+            Ok((result, types))
+        }
+    }
+}
+
+/// Types of the columns of a set operation, both sides must have as many
+/// columns and each pair of them must hold values of compatible types.
+fn set_types(operator: SetOperator, left: &[Type], right: &[Type]) -> Result<Vec<Type>> {
+    if left.len() != right.len() {
+        bail!("Each {} query must have the same number of columns", operator.name());
+    }
+
+    left.iter()
+        .zip(right.iter())
+        .map(|(&left, &right)| match left.common(right) {
+            Some(common) => Ok(common),
+            None => bail!(
+                "{} types {} and {} cannot be matched",
+                operator.name(),
+                left,
+                right
+            ),
+        })
+        .collect()
+}
+
+/// Converts the values of a side of a set operation to the common types of
+/// the columns, only integers in columns combined with decimals change.
+fn conform(rows: &mut [Vec<Value>], types: &[Type]) {
+    for row in rows.iter_mut() {
+        for (value, r#type) in row.iter_mut().zip(types) {
+            if let (Value::Int(int), Type::Decimal) = (&value, r#type) {
+                *value = Value::Decimal(Decimal::from_int(*int));
+            }
+        }
+    }
+}
+
+/// Combines the rows of both sides of a set operation by hashing them.
+/// Without ALL the result has no duplicates. With ALL, a row occurring m
+/// times on the left and n times on the right occurs m + n times after
+/// UNION, min(m, n) times after INTERSECT and m - n times after EXCEPT.
+fn combine(
+    operator: SetOperator,
+    all: bool,
+    left: Vec<Vec<Value>>,
+    right: Vec<Vec<Value>>,
+) -> Result<Vec<Vec<Value>>> {
+    if operator == SetOperator::Union {
+        let mut rows = left;
+        rows.extend(right);
+
+        return match all {
+            true => Ok(rows),
+            false => distinct::distinct(rows.into_iter().map(Ok), usize::MAX, spill::MEMORY_BUDGET),
+        };
+    }
+
+    let mut counts = HashMap::<_, usize>::new();
+
+    for row in right.iter() {
+        *counts.entry(row_key(row)).or_default() += 1;
+    }
+
+    let mut seen = std::collections::HashSet::new();
+
+    let rows = left
+        .into_iter()
+        .filter(|row| {
+            let key = row_key(row);
+
+            // With ALL every row on the right matches only one on the left
+            let matched = match counts.get_mut(&key) {
+                Some(count) if *count > 0 => {
+                    if all {
+                        *count -= 1;
+                    }
+
+                    true
+                }
+                _ => false,
+            };
+
+            (matched == (operator == SetOperator::Intersect)) && (all || seen.insert(key))
+        })
+        .collect();
+
+    Ok(rows)
+}
+
+fn row_key(row: &[Value]) -> Vec<u8> {
+    let mut key = Vec::new();
+    spill::encode_key(row, &mut key);
+    key
+}
+
+/// Runs a SELECT statement, returning its rows and the types of its columns.
+fn query(db: &mut Database, mut statement: SelectStatement) -> Result<(ResultSet, Vec<Type>)> {
+    view::expand(db, &mut statement.table)?;
+
+    for join in statement.joins.iter_mut() {
+        view::expand(db, &mut join.table)?;
+    }
+
+    statement.selections = subquery::expand_selections(db, &statement)?;
+    resolve_aliases(&mut statement)?;
+    order_distinct(&mut statement)?;
+    narrow_derived(db, &mut statement)?;
+
+    let (mut names, mut types, first) = from_item(db, &statement.table)?;
+    let mut qualifiers = vec![String::from(statement.table.qualifier())];
+    let mut joins = Vec::new();
+
+    // Joined tables are read into memory, only the first table is streamed
+    for join in statement.joins.iter_mut() {
+        let qualifier = String::from(join.table.qualifier());
+
+        if qualifiers.contains(&qualifier) {
+            bail!("Table name \"{}\" specified more than once", qualifier);
+        }
+
+        qualifiers.push(qualifier);
+
+        let (right_names, right_types, rows) = from_item(db, &join.table)?;
+        types.extend(right_types.iter().copied());
+
+        if let Some(on) = &mut join.on {
+            let joined = names.iter().chain(right_names.iter()).cloned().collect::<Vec<_>>();
+            subquery::bind_condition(db, on, &joined, None)?;
+
+            if subquery::contains_subquery(on) {
+                bail!("Correlated subqueries are only supported in WHERE");
+            }
+
+            Checker::new(&joined, &types).check_condition(on)?;
+        }
+
+        let right = Relation {
+            columns: right_names,
+            types: right_types,
+            rows: rows.read(db)?,
+        };
+
+        let left_types = &types[..names.len()];
+        let join = Join::new(join.kind, &names, left_types, right, join.on.clone())?;
+        names = join.columns().to_vec();
+        joins.push(join);
+    }
+
+    // Headers are taken before subqueries are replaced by their values
+    let columns = statement
+        .selections
+        .iter()
+        .map(|selection| selection.name())
+        .collect();
+
+    let correlated = bind_subqueries(db, &mut statement, &names)?;
+    let windows = bind_windows(&mut statement)?;
+    let sequences = bind_sequences(&mut statement, &windows)?;
+
+    // Rows are filtered with the columns of the FROM clause, the values of
+    // correlated subqueries and then of window functions are appended
+    // afterwards, those of nextval() calls only to the rows returned
+    let mut output_names = names.clone();
+    output_names.extend((0..correlated.len()).map(subquery_column));
+    types.extend(correlated.iter().map(|_| Type::Unknown));
+
+    let window_types = {
+        let checker = Checker::new(&output_names, &types);
+
+        windows
+            .iter()
+            .map(|call| checker.check_window(call))
+            .collect::<Result<Vec<_>>>()?
+    };
+
+    let window_names = output_names.clone();
+    output_names.extend((0..windows.len()).map(window_column));
+    types.extend(window_types);
+    output_names.extend((0..sequences.len()).map(sequence::column));
+    types.extend(sequences.iter().map(|_| Type::Int));
+
+    let output_types = check_select(&statement, &output_names, &types)?;
+
+    let mut source: Box<dyn Iterator<Item = Result<Vec<Value>>>> = match first {
+        Rows::Stored(name) => Box::new(
+            db.table(&name)?
+                .scan()
+                .map(|entry| entry.map(|(_, row)| row.into_values())),
+        ),
+        Rows::Derived(rows) => Box::new(rows.into_iter().map(Ok)),
+    };
+
+    for join in joins.iter() {
+        source = Box::new(source.flat_map(|row| match row.and_then(|row| join.probe(&row)) {
+            Ok(rows) => rows.into_iter().map(Ok).collect::<Vec<_>>(),
+            Err(err) => vec![Err(err)],
+        }));
+    }
+
+    let r#where = statement.r#where.as_ref();
+
+    let source: Box<dyn Iterator<Item = Result<Vec<Value>>>> =
+        match r#where.filter(|r#where| subquery::contains_subquery(r#where)) {
+            // Correlated subqueries read tables themselves, so the rows are
+            // read before they run
+            Some(r#where) => {
+                let rows = source.collect::<Result<Vec<_>>>()?;
+                let mut matching = Vec::new();
+
+                for row in rows {
+                    if subquery::matches(db, r#where, &names, &row)? {
+                        matching.push(row);
+                    }
+                }
+
+                Box::new(matching.into_iter().map(Ok))
+            }
+            None => Box::new(source.filter_map(|row| {
+                let values = match row {
+                    Ok(values) => values,
+                    Err(err) => return Some(Err(err)),
+                };
+
+                match r#where.map_or(Ok(true), |r#where| {
+                    expression::matches(r#where, &names, &values)
+                }) {
+                    Ok(true) => Some(Ok(values)),
+                    Ok(false) => None,
+                    Err(err) => Some(Err(err)),
+                }
+            })),
+        };
+
+    let source: Box<dyn Iterator<Item = Result<Vec<Value>>>> = match correlated.is_empty() {
+        true => source,
+        false => {
+            let rows = source.collect::<Result<Vec<_>>>()?;
+            let mut extended = Vec::with_capacity(rows.len());
+
+            for mut row in rows {
+                for subquery in correlated.iter() {
+                    let mut subquery = subquery.clone();
+                    subquery::bind_expression(db, &mut subquery, &names, Some(&row))?;
+                    row.push(expression::evaluate(&subquery, &names, &row)?);
+                }
+
+                extended.push(row);
+            }
+
+            Box::new(extended.into_iter().map(Ok))
+        }
+    };
+
+    // Window functions need every row of their partition
+    let source: Box<dyn Iterator<Item = Result<Vec<Value>>>> = match windows.is_empty() {
+        true => source,
+        false => {
+            let rows = source.collect::<Result<Vec<_>>>()?;
+            Box::new(window::compute(rows, &window_names, &windows)?.into_iter().map(Ok))
+        }
+    };
+
+    let pagination = statement.pagination.as_ref();
+
+    // Sequences are moved once for each row returned, so the selections are
+    // evaluated after sorting and pagination
+    if !sequences.is_empty() {
+        let rows = order(source, &output_names, &statement.order_by, pagination, None, |row| {
+            Ok(row.to_vec())
+        })?;
+
+        let mut projected = Vec::with_capacity(rows.len());
+
+        for mut row in rows {
+            for name in sequences.iter() {
+                row.push(Value::Int(db.nextval(name)?));
+            }
+
+            projected.push(
+                statement
+                    .selections
+                    .iter()
+                    .map(|selection| expression::evaluate(&selection.expression, &output_names, &row))
+                    .collect::<Result<Vec<_>>>()?,
+            );
+        }
+
+        return Ok((ResultSet { columns, rows: projected }, output_types));
+    }
+
+    if !aggregate::is_aggregate(&statement) {
+        let rows = output(
+            source,
+            &output_names,
+            &statement.selections,
+            &statement.order_by,
+            pagination,
+            statement.distinct.as_ref(),
+        )?;
+
+        return Ok((ResultSet { columns, rows }, output_types));
+    }
+
+    let grouped = aggregate::aggregate(source, &names, &statement)?;
+    let rows = output(
+        grouped.rows.into_iter().map(Ok),
+        &grouped.columns,
+        &grouped.selections,
+        &grouped.order_by,
+        pagination,
+        statement.distinct.as_ref(),
+    )?;
+
+    Ok((ResultSet { columns, rows }, output_types))
+}
+
+/// Where the rows of an item of a FROM clause come from.
+enum Rows {
+    Stored(String),
+    Derived(Vec<Vec<Value>>),
+}
+
+impl Rows {
+    fn read(self, db: &mut Database) -> Result<Vec<Vec<Value>>> {
+        match self {
+            Rows::Stored(name) => db
+                .table(&name)?
+                .scan()
+                .map(|entry| entry.map(|(_, row)| row.into_values()))
+                .collect(),
+            Rows::Derived(rows) => Ok(rows),
+        }
+    }
+}
+
+/// Names of the columns of an item of a FROM clause, qualified by its alias,
+/// their types and its rows. Derived tables are computed here, views have
+/// been expanded into them before.
+fn from_item(db: &mut Database, table: &TableReference) -> Result<(Vec<String>, Vec<Type>, Rows)> {
+    match &table.source {
+        TableSource::Table(name) => {
+            let (names, types) = db
+                .table(name)?
+                .schema()
+                .columns()
+                .iter()
+                .map(|c| (format!("{}.{}", table.qualifier(), c.name()), Type::of_kind(c.kind())))
+                .unzip();
+
+            Ok((names, types, Rows::Stored(name.clone())))
+        }
+        TableSource::Subquery(subquery) => {
+            let (result, types) = query(db, (**subquery).clone())?;
+
+            let names = result
+                .columns
+                .iter()
+                .map(|column| format!("{}.{}", table.qualifier(), column))
+                .collect();
+
+            Ok((names, types, Rows::Derived(result.rows)))
+        }
+        TableSource::Rows { columns, rows } => {
+            let names = columns
+                .iter()
+                .map(|column| format!("{}.{}", table.qualifier(), column))
+                .collect();
+
+            // Columns that are NULL in every row match any type
+            let types = (0..columns.len())
+                .map(|index| {
+                    rows.iter()
+                        .map(|row| Type::of_value(&row[index]))
+                        .find(|kind| *kind != Type::Unknown)
+                        .unwrap_or(Type::Unknown)
+                })
+                .collect();
+
+            Ok((names, types, Rows::Derived(rows.clone())))
+        }
+    }
+}
+
+/// Trims the derived tables of a query to the rows and columns it uses, so
+/// their nextval() calls only run for what it returns. Selections it never
+/// references are replaced by NULL and, when it returns the rows of its only
+/// derived table as they come, its LIMIT is passed on.
+fn narrow_derived(db: &mut Database, statement: &mut SelectStatement) -> Result<()> {
+    let referenced = referenced_columns(statement);
+
+    let limit = statement
+        .pagination
+        .as_ref()
+        .and_then(|pagination| Some(pagination.limit? + pagination.offset.unwrap_or(0)))
+        .filter(|_| {
+            statement.joins.is_empty()
+                && statement.r#where.is_none()
+                && statement.distinct.is_none()
+                && statement.order_by.is_empty()
+                && !aggregate::is_aggregate(statement)
+                && !statement
+                    .selections
+                    .iter()
+                    .any(|selection| contains_window(&selection.expression))
+        });
+
+    if let TableSource::Subquery(subquery) = &mut statement.table.source {
+        if let Some(limit) = limit {
+            let pagination = subquery.pagination.get_or_insert(Pagination {
+                limit: None,
+                offset: None,
+            });
+            pagination.limit = Some(pagination.limit.map_or(limit, |inner| inner.min(limit)));
+        }
+    }
+
+    let Some(referenced) = referenced else {
+        return Ok(());
+    };
+
+    let tables = std::iter::once(&mut statement.table)
+        .chain(statement.joins.iter_mut().map(|join| &mut join.table));
+
+    for table in tables {
+        let qualifier = String::from(table.qualifier());
+
+        let TableSource::Subquery(subquery) = &mut table.source else {
+            continue;
+        };
+
+        // Grouping and DISTINCT depend on every selection
+        if aggregate::is_aggregate(subquery) || subquery.distinct.is_some() {
+            continue;
+        }
+
+        subquery.selections = subquery::expand_selections(db, subquery)?;
+
+        // Its ORDER BY may refer to the selections by their names
+        let ordered = column_names(subquery.order_by.iter().map(|order_by| &order_by.expression));
+
+        for selection in subquery.selections.iter_mut() {
+            let name = selection.name();
+            let qualified = format!("{}.{}", qualifier, name);
+
+            let used = referenced
+                .iter()
+                .chain(ordered.iter())
+                .any(|column| *column == name || *column == qualified);
+
+            if !used {
+                selection.expression = Expression::Value(Value::Null);
+                selection.alias = Some(name);
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Names of the columns referenced by a query, `None` when it has subqueries,
+/// which may reference any of them.
+fn referenced_columns(statement: &SelectStatement) -> Option<Vec<String>> {
+    let conditions = statement
+        .r#where
+        .iter()
+        .chain(statement.having.iter())
+        .chain(statement.joins.iter().filter_map(|join| join.on.as_ref()));
+
+    let mut expressions = statement
+        .selections
+        .iter()
+        .map(|selection| &selection.expression)
+        .chain(statement.group_by.iter())
+        .chain(statement.order_by.iter().map(|order_by| &order_by.expression))
+        .chain(conditions.clone().flat_map(|condition| condition.expressions()))
+        .collect::<Vec<_>>();
+
+    if let Some(Distinct::On(on)) = &statement.distinct {
+        expressions.extend(on.iter());
+    }
+
+    let subqueries = conditions.clone().any(subquery::contains_subquery)
+        || expressions.iter().any(|expression| subquery::expression_contains_subquery(expression));
+
+    (!subqueries).then(|| column_names(expressions))
+}
+
+fn column_names<'a>(expressions: impl IntoIterator<Item = &'a Expression>) -> Vec<String> {
+    let mut expressions = expressions.into_iter().collect::<Vec<_>>();
+    let mut names = Vec::new();
+
+    while let Some(expression) = expressions.pop() {
+        if let Expression::Column(name) = expression {
+            names.push(name.clone());
+        }
+
+        expressions.extend(expression.children());
+    }
+
+    names
+}
+
+fn contains_window(expression: &Expression) -> bool {
+    matches!(expression, Expression::Window(_)) || expression.children().into_iter().any(contains_window)
+}
+
+/// Executes the subqueries of a SELECT that do not depend on its rows.
+/// Correlated subqueries stay in WHERE, where they run per row. Scalar ones
+/// in the selections and ORDER BY of a query without grouping are replaced
+/// by columns appended to each row, their subqueries are returned in order.
+fn bind_subqueries(
+    db: &mut Database,
+    statement: &mut SelectStatement,
+    names: &[String],
+) -> Result<Vec<Expression>> {
+    if let Some(r#where) = &mut statement.r#where {
+        subquery::bind_condition(db, r#where, names, None)?;
+    }
+
+    for expression in statement.group_by.iter_mut() {
+        subquery::bind_expression(db, expression, names, None)?;
+    }
+
+    if let Some(having) = &mut statement.having {
+        subquery::bind_condition(db, having, names, None)?;
+    }
+
+    for selection in statement.selections.iter_mut() {
+        subquery::bind_expression(db, &mut selection.expression, names, None)?;
+    }
+
+    for order_by in statement.order_by.iter_mut() {
+        subquery::bind_expression(db, &mut order_by.expression, names, None)?;
+    }
+
+    let mut correlated = Vec::new();
+
+    if !aggregate::is_aggregate(statement) {
+        let expressions = statement
+            .selections
+            .iter_mut()
+            .map(|selection| &mut selection.expression)
+            .chain(statement.order_by.iter_mut().map(|order_by| &mut order_by.expression));
+
+        for expression in expressions {
+            extract_subqueries(expression, &mut correlated);
+        }
+    }
+
+    let remaining = statement
+        .selections
+        .iter()
+        .map(|selection| &selection.expression)
+        .chain(statement.group_by.iter())
+        .chain(statement.order_by.iter().map(|order_by| &order_by.expression))
+        .any(subquery::expression_contains_subquery)
+        || statement.having.as_ref().is_some_and(subquery::contains_subquery);
+
+    if remaining {
+        bail!(
+            "Correlated subqueries are only supported in WHERE and as values in a query without grouping"
+        );
+    }
+
+    Ok(correlated)
+}
+
+/// Name of a column the executor adds to rows, like the value of a subquery
+/// or of an aggregate. Identifiers cannot contain '#', so these never clash
+/// with table columns.
+fn internal_column(kind: &str, index: usize) -> String {
+    format!("#{}{}", kind, index)
+}
+
+fn position_column(index: usize) -> String {
+    internal_column("position", index)
+}
+
+/// Replaces the column references of an expression by the names at the same
+/// positions in `names`.
+fn rename_columns(expression: &mut Expression, columns: &[String], names: &[String]) -> Result<()> {
+    if let Expression::Column(name) = expression {
+        *name = names[expression::find_column(name, columns)?].clone();
+    }
+
+    for child in expression.children_mut() {
+        rename_columns(child, columns, names)?;
+    }
+
+    Ok(())
+}
+
+fn subquery_column(index: usize) -> String {
+    internal_column("subquery", index)
+}
+
+fn extract_subqueries(expression: &mut Expression, subqueries: &mut Vec<Expression>) {
+    match expression {
+        Expression::Subquery(_) => {
+            let column = Expression::Column(subquery_column(subqueries.len()));
+            subqueries.push(std::mem::replace(expression, column));
+        }
+        expression => {
+            for child in expression.children_mut() {
+                extract_subqueries(child, subqueries);
+            }
+        }
+    }
+}
+
+/// Replaces the nextval() calls in the selections of a query without grouping
+/// by columns appended to each row returned, returning their sequences in
+/// order. Window functions are bound before, their arguments may not move a
+/// sequence.
+fn bind_sequences(statement: &mut SelectStatement, windows: &[WindowCall]) -> Result<Vec<String>> {
+    let mut sequences = Vec::new();
+
+    if !aggregate::is_aggregate(statement) {
+        for selection in statement.selections.iter_mut() {
+            sequence::extract(&mut selection.expression, &mut sequences)?;
+        }
+    }
+
+    let remaining = statement
+        .selections
+        .iter()
+        .map(|selection| &selection.expression)
+        .chain(statement.group_by.iter())
+        .chain(statement.order_by.iter().map(|order_by| &order_by.expression))
+        .chain(windows.iter().flat_map(|call| {
+            call.arguments
+                .iter()
+                .chain(call.window.partition_by.iter())
+                .chain(call.window.order_by.iter().map(|order_by| &order_by.expression))
+        }))
+        .any(sequence::contains_nextval);
+
+    if remaining {
+        bail!("nextval() is only supported in the selections of a query without grouping");
+    }
+
+    // Which rows are duplicates depends on values that are only known once
+    // they are returned
+    if !sequences.is_empty() && statement.distinct.is_some() {
+        bail!("nextval() is not supported in a SELECT DISTINCT");
+    }
+
+    Ok(sequences)
+}
+
+/// Replaces the window function calls in the selections and ORDER BY of a
+/// query without grouping by columns appended to each row, returning the
+/// calls in order.
+fn bind_windows(statement: &mut SelectStatement) -> Result<Vec<WindowCall>> {
+    let mut windows = Vec::new();
+
+    let expressions = statement
+        .selections
+        .iter_mut()
+        .map(|selection| &mut selection.expression)
+        .chain(statement.order_by.iter_mut().map(|order_by| &mut order_by.expression));
+
+    for expression in expressions {
+        extract_windows(expression, &mut windows);
+    }
+
+    if !windows.is_empty() && aggregate::is_aggregate(statement) {
+        bail!("Window functions are not supported in a query with grouping");
+    }
+
+    Ok(windows)
+}
+
+fn window_column(index: usize) -> String {
+    internal_column("window", index)
+}
+
+// Calls nested in the arguments of another are left in place, the checker
+// rejects them there
+fn extract_windows(expression: &mut Expression, windows: &mut Vec<WindowCall>) {
+    match expression {
+        Expression::Window(_) => {
+            let column = Expression::Column(window_column(windows.len()));
+
+            if let Expression::Window(call) = std::mem::replace(expression, column) {
+                windows.push(call);
+            }
+        }
+        expression => {
+            for child in expression.children_mut() {
+                extract_windows(child, windows);
+            }
+        }
+    }
+}
+
+/// Checks the types of every expression of a SELECT before any row is read,
+/// returning the types of the selections.
+fn check_select(
+    statement: &SelectStatement,
+    names: &[String],
+    types: &[Type],
+) -> Result<Vec<Type>> {
+    let checker = Checker::new(names, types);
+
+    if let Some(r#where) = &statement.r#where {
+        checker.check_condition(r#where)?;
+    }
+
+    for expression in statement.group_by.iter() {
+        checker.check(expression)?;
+    }
+
+    let checker = Checker::with_aggregates(names, types);
+
+    let selections = statement
+        .selections
+        .iter()
+        .map(|selection| checker.check(&selection.expression))
+        .collect::<Result<Vec<_>>>()?;
+
+    if let Some(having) = &statement.having {
+        checker.check_condition(having)?;
+    }
+
+    for order_by in statement.order_by.iter() {
+        checker.check(&order_by.expression)?;
+    }
+
+    Ok(selections)
+}
+
+/// Replaces references to output column aliases in ORDER BY and HAVING with
+/// the aliased expressions, aliases take precedence over table columns. An
+/// ORDER BY item that is a number refers to the selection at that position.
+fn resolve_aliases(statement: &mut SelectStatement) -> Result<()> {
+    let selections = &statement.selections;
+
+    for order_by in statement.order_by.iter_mut() {
+        match ordinal(&order_by.expression, selections.len())? {
+            Some(index) => order_by.expression = selections[index].expression.clone(),
+            None => substitute_aliases(&mut order_by.expression, selections),
+        }
+    }
+
+    for expression in statement.having.iter_mut().flat_map(|h| h.expressions_mut()) {
+        substitute_aliases(expression, selections);
+    }
+
+    if let Some(Distinct::On(expressions)) = &mut statement.distinct {
+        for expression in expressions.iter_mut() {
+            substitute_aliases(expression, selections);
+        }
+    }
+
+    Ok(())
+}
+
+/// Index of the output column an ORDER BY item refers to by its position,
+/// such as `ORDER BY 2`.
+fn ordinal(expression: &Expression, count: usize) -> Result<Option<usize>> {
+    match expression {
+        Expression::Value(Value::Int(position)) => match usize::try_from(*position) {
+            Ok(position) if (1..=count).contains(&position) => Ok(Some(position - 1)),
+            _ => bail!("ORDER BY position {} is not in select list", position),
+        },
+        _ => Ok(None),
+    }
+}
+
+/// Checks the ORDER BY of a SELECT DISTINCT, rows are compared after the
+/// selections are evaluated. DISTINCT ON keeps the first row of each set in
+/// the order of the ORDER BY, which has to start with its expressions and
+/// defaults to them.
+fn order_distinct(statement: &mut SelectStatement) -> Result<()> {
+    match &statement.distinct {
+        None => {}
+        Some(Distinct::Rows) => {
+            let selected = |order_by: &OrderBy| {
+                statement
+                    .selections
+                    .iter()
+                    .any(|selection| selection.expression == order_by.expression)
+            };
+
+            if !statement.order_by.iter().all(selected) {
+                bail!("For SELECT DISTINCT, ORDER BY expressions must appear in select list");
+            }
+        }
+        Some(Distinct::On(expressions)) if statement.order_by.is_empty() => {
+            statement.order_by = expressions
+                .iter()
+                .map(|expression| OrderBy {
+                    expression: expression.clone(),
+                    direction: Direction::Ascending,
+                    nulls: None,
+                })
+                .collect();
+        }
+        Some(Distinct::On(expressions)) => {
+            let leading = statement.order_by.iter().take(expressions.len());
+
+            if statement.order_by.len() < expressions.len()
+                || !leading.into_iter().all(|order_by| expressions.contains(&order_by.expression))
+            {
+                bail!("SELECT DISTINCT ON expressions must match initial ORDER BY expressions");
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn substitute_aliases(expression: &mut Expression, selections: &[Selection]) {
+    match expression {
+        Expression::Column(name) => {
+            let aliased = selections
+                .iter()
+                .find(|selection| selection.alias.as_ref() == Some(name))
+                .map(|selection| selection.expression.clone());
+
+            if let Some(aliased) = aliased {
+                *expression = aliased;
+            }
+        }
+        // Aggregate arguments are evaluated per input row, where aliases do not exist yet
+        Expression::Aggregate(_) => {}
+        expression => {
+            for child in expression.children_mut() {
+                substitute_aliases(child, selections);
+            }
+        }
+    }
+}
+
+/// Evaluates the selections for `rows`, sorting and paginating the result.
+/// Without ORDER BY reading stops as soon as the limit is reached.
+fn output(
+    rows: impl Iterator<Item = Result<Vec<Value>>>,
+    names: &[String],
+    selections: &[Selection],
+    order_by: &[OrderBy],
+    pagination: Option<&Pagination>,
+    distinct: Option<&Distinct>,
+) -> Result<Vec<Vec<Value>>> {
+    let project = |row: &[Value]| {
+        selections
+            .iter()
+            .map(|selection| expression::evaluate(&selection.expression, names, row))
+            .collect::<Result<Vec<_>>>()
+    };
+
+    order(rows, names, order_by, pagination, distinct, project)
+}
+
+/// Sorts, deduplicates and paginates `rows`, projecting each of them with
+/// `project`. DISTINCT ON expects its expressions to lead the ORDER BY.
+fn order(
+    rows: impl Iterator<Item = Result<Vec<Value>>>,
+    names: &[String],
+    order_by: &[OrderBy],
+    pagination: Option<&Pagination>,
+    distinct: Option<&Distinct>,
+    project: impl Fn(&[Value]) -> Result<Vec<Value>>,
+) -> Result<Vec<Vec<Value>>> {
+    let (limit, offset) = match pagination {
+        Some(pagination) => (
+            pagination.limit.unwrap_or(usize::MAX),
+            pagination.offset.unwrap_or(0),
+        ),
+        None => (usize::MAX, 0),
+    };
+
+    if order_by.is_empty() {
+        let Some(Distinct::Rows) = distinct else {
+            return rows
+                .skip(offset)
+                .take(limit)
+                .map(|row| project(&row?))
+                .collect();
+        };
+
+        // Reading stops as soon as enough distinct rows were found
+        let wanted = offset.saturating_add(limit);
+        let mut deduplicator = Deduplicator::new(usize::MAX, spill::MEMORY_BUDGET);
+        let mut unique = Vec::new();
+
+        for row in rows {
+            if unique.len() >= wanted {
+                break;
+            }
+
+            unique.extend(deduplicator.push(project(&row?)?)?);
+        }
+
+        if unique.len() < wanted {
+            for row in deduplicator.finish()?.take(wanted - unique.len()) {
+                unique.push(row?);
+            }
+        }
+
+        return Ok(unique.into_iter().skip(offset).take(limit).collect());
+    }
+
+    let keys = order_by.iter().map(SortKey::of).collect::<Vec<_>>();
+
+    // Rows are sorted with the values of the ORDER BY expressions in front
+    // of the selected values, which are split off again afterwards
+    let key_count = keys.len();
+    let on_keys = match distinct {
+        Some(Distinct::On(expressions)) => keys[..expressions.len()].to_vec(),
+        _ => Vec::new(),
+    };
+
+    let mut sorter = Sorter::new(keys, spill::MEMORY_BUDGET);
+
+    // The ORDER BY of a SELECT DISTINCT only uses selected values, so
+    // duplicate rows have equal sort rows and are removed before sorting
+    let mut deduplicator = match distinct {
+        Some(Distinct::Rows) => Some(Deduplicator::new(usize::MAX, spill::MEMORY_BUDGET)),
+        _ => None,
+    };
+
+    for row in rows {
+        let row = row?;
+
+        let mut sort_row = order_by
+            .iter()
+            .map(|order_by| expression::evaluate(&order_by.expression, names, &row))
+            .collect::<Result<Vec<_>>>()?;
+
+        sort_row.extend(project(&row)?);
+
+        match &mut deduplicator {
+            Some(deduplicator) => {
+                if let Some(sort_row) = deduplicator.push(sort_row)? {
+                    sorter.push(sort_row)?;
+                }
+            }
+            None => sorter.push(sort_row)?,
+        }
+    }
+
+    if let Some(deduplicator) = deduplicator {
+        for sort_row in deduplicator.finish()? {
+            sorter.push(sort_row?)?;
+        }
+    }
+
+    // Rows with equal DISTINCT ON values follow each other once sorted
+    let mut previous: Option<Vec<Value>> = None;
+
+    sorter
+        .finish()?
+        .filter(|row| match row {
+            Ok(row) if !on_keys.is_empty() => {
+                let first = previous.as_ref().is_none_or(|previous| {
+                    sort::compare_rows(&on_keys, previous, row) != Ordering::Equal
+                });
+
+                if first {
+                    previous = Some(row[..on_keys.len()].to_vec());
+                }
+
+                first
+            }
+            _ => true,
+        })
+        .skip(offset)
+        .take(limit)
+        .map(|row| Ok(row?.split_off(key_count)))
+        .collect()
+}
+
+/// Inserts the rows of a VALUES clause or a query. With a column list, the
+/// values are mapped to the columns by name and the other columns take their
+/// DEFAULT, or NULL.
+/// Either all rows are inserted, or updated for ON CONFLICT DO UPDATE, or
+/// none. Returns the rows written.
+fn insert(db: &mut Database, statement: InsertStatement) -> Result<Vec<Vec<Value>>> {
+    let columns = db.table(&statement.table)?.schema().columns().to_vec();
+
+    let targets = match &statement.columns {
+        Some(names) => {
+            let mut targets = Vec::with_capacity(names.len());
+
+            for name in names {
+                let Some(index) = columns.iter().position(|c| c.name() == name) else {
+                    bail!(
+                        "Column \"{}\" of table \"{}\" does not exist",
+                        name,
+                        statement.table
+                    );
+                };
+
+                if targets.contains(&index) {
+                    bail!("Column \"{}\" specified more than once", name);
+                }
+
+                targets.push(index);
+            }
+
+            targets
+        }
+        None => (0..columns.len()).collect(),
+    };
+
+    // The query reads all its rows first, so it may select from the table itself
+    let source = match statement.source {
+        InsertSource::Values(rows) => rows,
+        InsertSource::Query(query) => {
+            let (result, types) = run(db, *query)?;
+
+            for (index, r#type) in targets.iter().zip(types.iter()) {
+                let column = &columns[*index];
+
+                if !r#type.assignable_to(column.kind()) {
+                    bail!(
+                        "Column \"{}\" is of type {} but the expression is of type {}",
+                        column.name(),
+                        column.kind(),
+                        r#type
+                    );
+                }
+            }
+
+            result
+                .rows
+                .into_iter()
+                .map(|row| row.into_iter().map(Expression::Value).collect())
+                .collect()
+        }
+    };
+
+    let mut constraints = Constraints::new(db, &statement.table)?;
+    let mut rows = Vec::with_capacity(source.len());
+
+    for values in source {
+        if values.len() > targets.len() {
+            bail!("INSERT has more expressions than target columns");
+        }
+
+        if values.len() < targets.len() {
+            bail!("INSERT has more target columns than expressions");
+        }
+
+        let mut row = constraints.defaults(db, &targets)?;
+
+        // Expressions of VALUES are evaluated without a row, they cannot reference columns
+        for (index, mut value) in targets.iter().zip(values) {
+            sequence::bind(db, &mut value)?;
+            row[*index] = expression::evaluate(&value, &[], &[])?;
+        }
+
+        rows.push(Row::new(row));
+    }
+
+    let mut conflicts = match statement.on_conflict {
+        Some(on_conflict) => Some(Conflicts::new(
+            db,
+            &statement.table,
+            on_conflict,
+            &constraints,
+        )?),
+        None => None,
+    };
+
+    let table = db.table(&statement.table)?;
+    let mut changes = Vec::with_capacity(rows.len());
+    let mut written = Vec::with_capacity(rows.len());
+
+    // A row the table cannot store, such as a string that is too long,
+    // undoes the changes made for the rows before it
+    let result = rows.into_iter().try_for_each(|row| {
+        let row = match &mut conflicts {
+            Some(conflicts) => conflicts.insert(&mut constraints, table, row, &mut changes)?,
+            None => Some(constraints.insert(table, row, &mut changes)?.1),
+        };
+
+        written.extend(row.map(Row::into_values));
+        Ok(())
+    });
+
+    if let Err(err) = result {
+        constraint::revert(table, changes)?;
+        return Err(err);
+    }
+
+    Ok(written)
+}
+
+/// Creates a table from a schema, given by CREATE TABLE or read from a
+/// schema file, after checking its CHECK and DEFAULT expressions.
+pub fn create_table_from_schema(db: &mut Database, name: &str, schema: TableSchema) -> Result<()> {
+    check_name_is_free(db, name)?;
+    constraint::validate(name, &schema)?;
+    db.create_table(name, schema)
+}
+
+fn check_name_is_free(db: &Database, name: &str) -> Result<()> {
+    if db.schema().get_table_schema(name).is_some() {
+        bail!("Table \"{}\" already exists", name);
+    }
+
+    if db.schema().get_view(name).is_some() {
+        bail!("View \"{}\" already exists", name);
+    }
+
+    Ok(())
+}
+
+/// Creates a table from its column definitions, or with the columns of a
+/// query and inserts its rows.
+fn create_table(db: &mut Database, statement: CreateTableStatement) -> Result<()> {
+    let (renamed, query) = match statement.definition {
+        TableDefinition::Schema(schema) => {
+            return create_table_from_schema(db, &statement.table, schema);
+        }
+        TableDefinition::Query { columns, query } => (columns, query),
+    };
+
+    check_name_is_free(db, &statement.table)?;
+
+    let (result, types) = run(db, *query)?;
+
+    if renamed.len() > result.columns.len() {
+        bail!("CREATE TABLE AS specifies too many column names");
+    }
+
+    let mut names = result.columns;
+
+    for (column, name) in names.iter_mut().zip(renamed) {
+        *column = name;
+    }
+
+    let mut columns = Vec::with_capacity(names.len());
+
+    for (index, (name, r#type)) in names.iter().zip(types).enumerate() {
+        if names[..index].contains(name) {
+            bail!("Column \"{}\" specified more than once", name);
+        }
+
+        columns.push(Column::new(name, column_kind(name, r#type, &result.rows, index)?));
+    }
+
+    db.create_table(&statement.table, TableSchema::new(columns, Vec::new()))?;
+
+    let inserted = db.table(&statement.table).and_then(|table| {
+        result
+            .rows
+            .into_iter()
+            .try_for_each(|row| table.insert(&Row::new(row)).map(|_| ()))
+    });
+
+    // A row that cannot be stored leaves no table behind
+    if let Err(err) = inserted {
+        db.drop_table(&statement.table)?;
+        return Err(err);
+    }
+
+    Ok(())
+}
+
+/// Column type storing the values of an output column of a query. Values
+/// only typed at runtime decide by the first that is not NULL, decimals keep
+/// the largest scale of the values and columns of NULLs become TEXT.
+fn column_kind(name: &str, r#type: Type, rows: &[Vec<Value>], index: usize) -> Result<ColumnKind> {
+    let r#type = match r#type {
+        Type::Unknown => rows
+            .iter()
+            .map(|row| Type::of_value(&row[index]))
+            .find(|r#type| *r#type != Type::Unknown)
+            .unwrap_or(Type::Unknown),
+        r#type => r#type,
+    };
+
+    Ok(match r#type {
+        Type::Unknown | Type::Text => ColumnKind::Text,
+        Type::Int => ColumnKind::BigInt,
+        Type::Decimal => ColumnKind::Decimal(DecimalColumn {
+            precision: Decimal::MAX_PRECISION,
+            scale: rows
+                .iter()
+                .filter_map(|row| match &row[index] {
+                    Value::Decimal(decimal) => Some(decimal.scale()),
+                    _ => None,
+                })
+                .max()
+                .unwrap_or(0),
+        }),
+        Type::Blob => ColumnKind::Blob,
+        Type::Date => ColumnKind::Date,
+        Type::Time => ColumnKind::Time,
+        Type::Timestamp => ColumnKind::Timestamp,
+        Type::Json => ColumnKind::Json,
+        Type::Interval | Type::Boolean => bail!(
+            "Column \"{}\" of type {} cannot be stored in a table",
+            name,
+            r#type
+        ),
+    })
+}
+
+/// Deletes the matching rows, returning their values.
+fn delete(db: &mut Database, statement: DeleteStatement) -> Result<Vec<Vec<Value>>> {
+    let (names, types) = table_columns(db, &statement.table)?;
+    let mut r#where = statement.r#where;
+
+    if let Some(r#where) = &mut r#where {
+        subquery::bind_condition(db, r#where, &names, None)?;
+        Checker::new(&names, &types).check_condition(r#where)?;
+    }
+
+    let rows = matching_rows(db, &statement.table, &names, r#where.as_ref())?;
+    let deleted = rows.iter().map(|(_, row)| row.values().to_vec()).collect();
+
+    cascade::delete(db, &statement.table, rows)?;
+
+    Ok(deleted)
+}
+
+/// Updates the matching rows, returning their new values.
+fn update(db: &mut Database, statement: UpdateStatement) -> Result<Vec<Vec<Value>>> {
+    let (names, types) = table_columns(db, &statement.table)?;
+    let checker = Checker::new(&names, &types);
+    let mut r#where = statement.r#where;
+
+    if let Some(r#where) = &mut r#where {
+        subquery::bind_condition(db, r#where, &names, None)?;
+        checker.check_condition(r#where)?;
+    }
+
+    let schema = db.table(&statement.table)?.schema().clone();
+    let mut assignments = Vec::new();
+
+    for mut assignment in statement.assignments {
+        let column = schema
+            .columns()
+            .iter()
+            .position(|c| c.name() == assignment.field);
+
+        let Some(index) = column else {
+            bail!("Column \"{}\" does not exist", assignment.field);
+        };
+
+        subquery::bind_expression(db, &mut assignment.value, &names, None)?;
+
+        if subquery::expression_contains_subquery(&assignment.value) {
+            bail!("Correlated subqueries are only supported in WHERE");
+        }
+
+        let kind = schema.columns()[index].kind();
+        let r#type = checker.check(&assignment.value)?;
+
+        if !r#type.assignable_to(kind) {
+            bail!(
+                "Column \"{}\" is of type {} but the expression is of type {}",
+                assignment.field,
+                kind,
+                r#type
+            );
+        }
+
+        assignments.push((index, assignment.value));
+    }
+
+    let rows = matching_rows(db, &statement.table, &names, r#where.as_ref())?;
+    let mut constraints = Constraints::new(db, &statement.table)?;
+
+    let mut changes = Vec::with_capacity(rows.len());
+    let mut updated = Vec::with_capacity(rows.len());
+
+    // A row that violates a constraint undoes the updates before it
+    let result = rows.into_iter().try_for_each(|(id, row)| {
+        // Every expression sees the values of the row before the update
+        let mut values = row.values().to_vec();
+
+        for (index, value) in assignments.iter() {
+            values[*index] = match sequence::contains_nextval(value) {
+                true => {
+                    let mut value = value.clone();
+                    sequence::bind(db, &mut value)?;
+                    expression::evaluate(&value, &names, row.values())?
+                }
+                false => expression::evaluate(value, &names, row.values())?,
+            };
+        }
+
+        let new = Row::new(values);
+        let table = db.table(&statement.table)?;
+        constraints.update(table, id, row, &new, &mut changes)?;
+        updated.push(new.into_values());
+        Ok(())
+    });
+
+    if let Err(err) = result {
+        constraint::revert(db.table(&statement.table)?, changes)?;
+        return Err(err);
+    }
+
+    Ok(updated)
+}
+
+/// Selections of a RETURNING clause, checked against the columns of the
+/// table before the statement writes anything.
+struct Projection {
+    names: Vec<String>,
+    selections: Vec<Selection>,
+}
+
+impl Projection {
+    /// Evaluates the selections for the rows written by the statement.
+    fn result(self, rows: Vec<Vec<Value>>) -> Result<ResultSet> {
+        let columns = self.selections.iter().map(|selection| selection.name()).collect();
+        let rows = output(rows.into_iter().map(Ok), &self.names, &self.selections, &[], None, None)?;
+
+        Ok(ResultSet { columns, rows })
+    }
+}
+
+fn returning(db: &mut Database, table: &str, returning: Option<Returning>) -> Result<Option<Projection>> {
+    let Some(returning) = returning else {
+        return Ok(None);
+    };
+
+    let (names, types) = table_columns(db, table)?;
+
+    let mut selections = match returning {
+        Returning::All => names
+            .iter()
+            .map(|name| Selection {
+                expression: Expression::Column(name.clone()),
+                alias: None,
+            })
+            .collect(),
+        Returning::Selections(selections) => selections,
+    };
+
+    let checker = Checker::new(&names, &types);
+
+    for selection in selections.iter_mut() {
+        subquery::bind_expression(db, &mut selection.expression, &names, None)?;
+
+        if subquery::expression_contains_subquery(&selection.expression) {
+            bail!("Correlated subqueries are not supported in RETURNING");
+        }
+
+        checker.check(&selection.expression)?;
+    }
+
+    Ok(Some(Projection { names, selections }))
+}
+
+/// Names and types of the columns of a table for statements on a single
+/// table, qualified by the table name like the columns of a FROM clause.
+fn table_columns(db: &mut Database, table: &str) -> Result<(Vec<String>, Vec<Type>)> {
+    Ok(db
+        .table(table)?
+        .schema()
+        .columns()
+        .iter()
+        .map(|c| (format!("{}.{}", table, c.name()), Type::of_kind(c.kind())))
+        .unzip())
+}
+
+/// Parses and executes one statement, for tests running SQL end to end.
+#[cfg(test)]
+fn execute_sql(db: &mut Database, sql: &str) -> Result<Option<ResultSet>> {
+    let tokens = crate::sql::tokenizer::Tokenizer::new(sql).parse()?;
+    let statement = crate::sql::parser::Parser::new(tokens)
+        .parse()
+        .map_err(|err| anyhow::anyhow!("{}", err))?;
+
+    execute(db, statement)
+}
+
+/// Executes a statement that must fail, returning its error message.
+#[cfg(test)]
+fn execute_error(db: &mut Database, sql: &str) -> String {
+    match execute_sql(db, sql) {
+        Ok(_) => panic!("Expected \"{}\" to fail", sql),
+        Err(err) => err.to_string(),
+    }
+}
+
+/// Rows returned by a query, each with its values separated by spaces.
+#[cfg(test)]
+fn query_rows(db: &mut Database, sql: &str) -> Vec<String> {
+    let result = execute_sql(db, sql).unwrap().unwrap();
+
+    result
+        .rows
+        .iter()
+        .map(|row| {
+            row.iter()
+                .map(|v| v.to_string())
+                .collect::<Vec<_>>()
+                .join(" ")
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::testing::TempDatabase;
+    use crate::schema::{Constraint, ConstraintKind};
+    use crate::sql::parser::Parser;
+    use crate::sql::tokenizer::Tokenizer;
+    use crate::sql::AggregateFunction;
+
+    fn select(sql: &str) -> SelectStatement {
+        let tokens = Tokenizer::new(sql).parse().unwrap();
+
+        match Parser::new(tokens).parse().unwrap() {
+            Statement::Select(select) => *select,
+            _ => panic!("Expected a SELECT statement"),
+        }
+    }
+
+    #[test]
+    fn test_resolve_aliases() {
+        let mut statement = select(
+            "SELECT city AS town, count(*) total FROM users GROUP BY city HAVING total > 1 ORDER BY total DESC, town",
+        );
+
+        resolve_aliases(&mut statement).unwrap();
+
+        assert!(matches!(
+            &statement.order_by[0].expression,
+            Expression::Aggregate(call) if call.function == AggregateFunction::Count
+        ));
+        assert_eq!(
+            statement.order_by[1].expression,
+            Expression::Column(String::from("city"))
+        );
+
+        let Some(WhereExpression::Condition(having)) = &statement.having else {
+            panic!("Expected a condition");
+        };
+
+        assert!(matches!(having.field, Expression::Aggregate(_)));
+    }
+
+    #[test]
+    fn test_order_by_position() {
+        let dir = TempDatabase::new("order-by-position");
+        let mut db = dir.open();
+
+        execute_sql(&mut db, "CREATE TABLE a (s TEXT, x INT)").unwrap();
+        execute_sql(&mut db, "INSERT INTO a VALUES (\"p\", 1), (\"q\", NULL), (\"r\", 2)").unwrap();
+
+        assert_eq!(
+            query_rows(&mut db, "SELECT s, x FROM a ORDER BY 2 DESC NULLS LAST"),
+            ["r 2", "p 1", "q NULL"]
+        );
+        assert_eq!(
+            query_rows(&mut db, "SELECT x * 10 AS tens, s FROM a WHERE x IS NOT NULL ORDER BY 1 DESC"),
+            ["20 r", "10 p"]
+        );
+        assert_eq!(
+            execute_error(&mut db, "SELECT s, x FROM a ORDER BY 3"),
+            "ORDER BY position 3 is not in select list"
+        );
+    }
+
+    #[test]
+    fn test_combine() {
+        let rows = |values: &[i64]| {
+            values
+                .iter()
+                .map(|value| vec![Value::Int(*value)])
+                .collect::<Vec<_>>()
+        };
+
+        let left = rows(&[1, 1, 1, 2, 3]);
+        let right = rows(&[1, 1, 3, 4]);
+
+        let cases = [
+            (SetOperator::Union, false, rows(&[1, 2, 3, 4])),
+            (SetOperator::Union, true, rows(&[1, 1, 1, 2, 3, 1, 1, 3, 4])),
+            (SetOperator::Intersect, false, rows(&[1, 3])),
+            (SetOperator::Intersect, true, rows(&[1, 1, 3])),
+            (SetOperator::Except, false, rows(&[2])),
+            (SetOperator::Except, true, rows(&[1, 2])),
+        ];
+
+        for (operator, all, expected) in cases {
+            assert_eq!(
+                combine(operator, all, left.clone(), right.clone()).unwrap(),
+                expected,
+                "{} {}",
+                operator.name(),
+                all
+            );
+        }
+
+        assert_eq!(
+            combine(SetOperator::Union, false, vec![vec![Value::Null]], vec![vec![Value::Null]])
+                .unwrap(),
+            vec![vec![Value::Null]]
+        );
+    }
+
+    #[test]
+    fn test_set_operations_of_different_types() {
+        let dir = TempDatabase::new("set-types");
+        let mut db = dir.open();
+
+        execute_sql(&mut db, "CREATE TABLE a (x INT)").unwrap();
+        execute_sql(&mut db, "CREATE TABLE b (y DECIMAL(10, 1))").unwrap();
+        execute_sql(&mut db, "INSERT INTO a VALUES (1), (2)").unwrap();
+        execute_sql(&mut db, "INSERT INTO b VALUES (1.0), (2.5)").unwrap();
+
+        let mut rows = |sql: &str| {
+            let result = execute_sql(&mut db, sql).unwrap().unwrap();
+
+            result
+                .rows
+                .iter()
+                .map(|row| row[0].to_string())
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(
+            rows("SELECT x FROM a UNION SELECT y FROM b ORDER BY x"),
+            ["1", "2", "2.5"]
+        );
+        assert_eq!(rows("SELECT x FROM a INTERSECT SELECT y FROM b"), ["1"]);
+        assert_eq!(rows("SELECT y FROM b EXCEPT SELECT x FROM a"), ["2.5"]);
+    }
+
+    #[test]
+    fn test_set_operation_order_by_position() {
+        let dir = TempDatabase::new("set-order");
+        let mut db = dir.open();
+
+        execute_sql(&mut db, "CREATE TABLE a (x INT)").unwrap();
+        execute_sql(&mut db, "INSERT INTO a VALUES (1), (2)").unwrap();
+
+        assert_eq!(
+            query_rows(&mut db, "SELECT x FROM a UNION SELECT x + 5 FROM a ORDER BY 1 DESC"),
+            ["7", "6", "2", "1"]
+        );
+        assert_eq!(
+            query_rows(&mut db, "SELECT x, x * 2 FROM a UNION ALL SELECT x, 0 FROM a ORDER BY 2, x DESC LIMIT 3"),
+            ["2 0", "1 0", "1 2"]
+        );
+        assert_eq!(
+            execute_error(&mut db, "SELECT x FROM a UNION SELECT x FROM a ORDER BY 2"),
+            "ORDER BY position 2 is not in select list"
+        );
+    }
+
+    #[test]
+    fn test_select_conditions() {
+        let dir = TempDatabase::new("select-conditions");
+        let mut db = dir.open();
+
+        execute_sql(&mut db, "CREATE TABLE d (a TIMESTAMP, ts TIMESTAMP)").unwrap();
+        execute_sql(
+            &mut db,
+            "INSERT INTO d VALUES (\"2026-01-01 00:00:00\", \"2026-02-01 00:00:00\"), (\"2026-03-01 00:00:00\", NULL)",
+        )
+        .unwrap();
+
+        assert_eq!(
+            query_rows(
+                &mut db,
+                "SELECT a < ts, now() > ts, ts IS NOT NULL, a < ts OR ts IS NULL AS either FROM d"
+            ),
+            ["true true true true", "NULL NULL false true"]
+        );
+
+        let result = execute_sql(&mut db, "SELECT a < ts AS earlier FROM d").unwrap().unwrap();
+        assert_eq!(result.columns, ["earlier"]);
+
+        assert_eq!(
+            execute_error(&mut db, "SELECT a < 1 FROM d"),
+            "Cannot compare timestamp with int"
+        );
+        assert_eq!(
+            execute_error(&mut db, "CREATE TABLE e AS SELECT a < ts AS earlier FROM d"),
+            "Column \"earlier\" of type boolean cannot be stored in a table"
+        );
+    }
+
+    #[test]
+    fn test_select_star() {
+        let dir = TempDatabase::new("select-star");
+        let mut db = dir.open();
+
+        execute_sql(&mut db, "CREATE TABLE t (id INT, name TEXT)").unwrap();
+        execute_sql(&mut db, "CREATE TABLE u (id INT, city TEXT)").unwrap();
+        execute_sql(&mut db, "INSERT INTO t VALUES (1, \"ann\"), (2, \"bob\"), (2, \"bob\")").unwrap();
+        execute_sql(&mut db, "INSERT INTO u VALUES (1, \"oslo\")").unwrap();
+
+        let result = execute_sql(&mut db, "SELECT * FROM t").unwrap().unwrap();
+        assert_eq!(result.columns, ["id", "name"]);
+        assert_eq!(result.rows.len(), 3);
+
+        assert_eq!(
+            query_rows(&mut db, "SELECT DISTINCT * FROM t ORDER BY id DESC"),
+            ["2 bob", "1 ann"]
+        );
+        assert_eq!(
+            query_rows(&mut db, "SELECT *, t.id * 10 AS tens FROM t JOIN u ON t.id = u.id"),
+            ["1 ann 1 oslo 10"]
+        );
+        assert_eq!(
+            query_rows(&mut db, "SELECT s.name FROM (SELECT * FROM t WHERE id = 1) AS s"),
+            ["ann"]
+        );
+        assert_eq!(
+            query_rows(
+                &mut db,
+                "SELECT id FROM u WHERE EXISTS (SELECT * FROM (SELECT * FROM t) AS s WHERE s.id = u.id)"
+            ),
+            ["1"]
+        );
+    }
+
+    #[test]
+    fn test_insert_values_expressions() {
+        let dir = TempDatabase::new("insert-expressions");
+        let mut db = dir.open();
+
+        execute_sql(&mut db, "CREATE TABLE t (id INT, total DECIMAL(10, 2), name TEXT)").unwrap();
+        execute_sql(
+            &mut db,
+            "INSERT INTO t VALUES (1 + 1, round(1.256, 2), \"a\" || \"b\"), (-3, NULL, NULL)",
+        )
+        .unwrap();
+
+        assert_eq!(
+            query_rows(&mut db, "SELECT id, total, name FROM t"),
+            ["2 1.26 ab", "-3 NULL NULL"]
+        );
+        assert_eq!(
+            execute_error(&mut db, "INSERT INTO t (id) VALUES (id + 1)"),
+            "Column \"id\" does not exist"
+        );
+    }
+
+    #[test]
+    fn test_create_table_from_schema() {
+        let dir = TempDatabase::new("create-from-schema");
+        let mut db = dir.open();
+
+        let schema = |expression: &str| {
+            let check = Constraint {
+                name: String::new(),
+                kind: ConstraintKind::Check {
+                    expression: String::from(expression),
+                },
+            };
+
+            TableSchema::new(vec![Column::new("id", ColumnKind::Int)], vec![check])
+        };
+
+        let err = create_table_from_schema(&mut db, "t", schema("nosuch > 0")).unwrap_err();
+        assert_eq!(err.to_string(), "Column \"nosuch\" does not exist");
+        assert!(db.schema().get_table_schema("t").is_none());
+
+        create_table_from_schema(&mut db, "t", schema("id > 0")).unwrap();
+        let err = create_table_from_schema(&mut db, "t", schema("id > 0")).unwrap_err();
+        assert_eq!(err.to_string(), "Table \"t\" already exists");
+    }
+
+    #[test]
+    fn test_create_table_as_is_atomic() {
+        let dir = TempDatabase::new("create-table-as");
+        let mut db = dir.open();
+
+        execute_sql(&mut db, "CREATE TABLE one (id INT)").unwrap();
+        execute_sql(&mut db, "INSERT INTO one (id) VALUES (1), (3)").unwrap();
+
+        // The second value does not fit into the scale taken from the first row
+        let err = execute_sql(
+            &mut db,
+            "CREATE TABLE big AS SELECT CASE WHEN id = 1 THEN 0.000000001 \
+             ELSE id * 10000000000000000000000000000000.0 END AS n FROM one ORDER BY id",
+        )
+        .unwrap_err();
+
+        assert!(err.to_string().contains("30000000000000000000000000000000"), "{}", err);
+        assert!(db.schema().get_table_schema("big").is_none());
+        assert!(db.table("big").is_err());
+
+        db.flush();
+        drop(db);
+        assert!(dir.open().schema().get_table_schema("big").is_none());
+
+        let mut db = dir.open();
+        execute_sql(&mut db, "CREATE TABLE big AS SELECT id AS n FROM one").unwrap();
+        let result = execute_sql(&mut db, "SELECT n FROM big").unwrap().unwrap();
+        assert_eq!(result.rows.len(), 2);
+
+        // Integer results are stored in 64-bit columns
+        execute_sql(&mut db, "INSERT INTO one (id) VALUES (2147483647), (2147483647)").unwrap();
+        execute_sql(&mut db, "CREATE TABLE total AS SELECT SUM(id) AS total FROM one").unwrap();
+        assert_eq!(
+            db.schema().get_table_schema("total").unwrap().columns()[0].kind(),
+            &ColumnKind::BigInt
+        );
+        assert_eq!(query_rows(&mut db, "SELECT total FROM total"), vec!["4294967298"]);
+    }
+
+    #[test]
+    fn test_column_kind() {
+        let rows = vec![
+            vec![Value::Null, Value::Decimal(Decimal::parse("1.5").unwrap())],
+            vec![Value::Int(1), Value::Decimal(Decimal::parse("2.25").unwrap())],
+        ];
+
+        assert_eq!(column_kind("a", Type::Unknown, &rows, 0).unwrap(), ColumnKind::BigInt);
+        assert_eq!(
+            column_kind("b", Type::Decimal, &rows, 1).unwrap(),
+            ColumnKind::Decimal(DecimalColumn {
+                precision: Decimal::MAX_PRECISION,
+                scale: 2,
+            })
+        );
+        assert_eq!(column_kind("c", Type::Unknown, &[], 0).unwrap(), ColumnKind::Text);
+        assert!(column_kind("d", Type::Interval, &[], 0).is_err());
+    }
+}
@@ -0,0 +1,160 @@
+use anyhow::{bail, Result};
+
+use super::internal_column;
+use crate::database::Database;
+use crate::sql::{Expression, Value};
+
+fn is_nextval(expression: &Expression) -> bool {
+    matches!(expression, Expression::Function(call) if call.name.eq_ignore_ascii_case("nextval"))
+}
+
+pub fn contains_nextval(expression: &Expression) -> bool {
+    is_nextval(expression) || expression.children().into_iter().any(contains_nextval)
+}
+
+/// Name of the sequence of a nextval() call, given as a string.
+fn sequence(expression: &Expression) -> Result<String> {
+    match expression {
+        Expression::Function(call) => match call.arguments.as_slice() {
+            [Expression::Value(Value::String(name))] => Ok(name.clone()),
+            _ => bail!("nextval() expects the name of a sequence"),
+        },
+        _ => bail!("Expected a call of nextval()"),
+    }
+}
+
+/// Replaces the nextval() calls of an expression by the next values of their
+/// sequences, before it is evaluated for one row.
+pub fn bind(db: &mut Database, expression: &mut Expression) -> Result<()> {
+    if is_nextval(expression) {
+        let value = db.nextval(&sequence(expression)?)?;
+        *expression = Expression::Value(Value::Int(value));
+        return Ok(());
+    }
+
+    for child in expression.children_mut() {
+        bind(db, child)?;
+    }
+
+    Ok(())
+}
+
+/// Replaces the nextval() calls of an expression by columns appended to
+/// each row, pushing the names of their sequences in order.
+pub fn extract(expression: &mut Expression, sequences: &mut Vec<String>) -> Result<()> {
+    if is_nextval(expression) {
+        sequences.push(sequence(expression)?);
+        *expression = Expression::Column(column(sequences.len() - 1));
+        return Ok(());
+    }
+
+    for child in expression.children_mut() {
+        extract(child, sequences)?;
+    }
+
+    Ok(())
+}
+
+/// Hidden column holding the value of a nextval() call of a query.
+pub fn column(index: usize) -> String {
+    internal_column("sequence", index)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::database::testing::TempDatabase;
+    use crate::database::Database;
+    use crate::executor::execute_sql;
+    use crate::sql::Value;
+
+    fn ids(db: &mut Database, sql: &str) -> Vec<Value> {
+        let result = execute_sql(db, sql).unwrap().unwrap();
+        result.rows.into_iter().map(|row| row[0].clone()).collect()
+    }
+
+    #[test]
+    fn test_default_nextval() {
+        let dir = TempDatabase::new("sequence-default");
+        let mut db = dir.open();
+        execute_sql(&mut db, "CREATE SEQUENCE s").unwrap();
+        execute_sql(
+            &mut db,
+            "CREATE TABLE t (id INT DEFAULT nextval(\"s\"), name TEXT)",
+        )
+        .unwrap();
+
+        // Supplied columns do not move the sequence of their DEFAULT
+        execute_sql(&mut db, "INSERT INTO t (id, name) VALUES (100, \"a\")").unwrap();
+        execute_sql(&mut db, "INSERT INTO t VALUES (101, \"b\")").unwrap();
+        execute_sql(&mut db, "INSERT INTO t (name) VALUES (\"c\"), (\"d\")").unwrap();
+
+        assert_eq!(
+            ids(&mut db, "SELECT id FROM t"),
+            [
+                Value::Int(100),
+                Value::Int(101),
+                Value::Int(1),
+                Value::Int(2)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_nextval_in_values() {
+        let dir = TempDatabase::new("sequence-values");
+        let mut db = dir.open();
+        execute_sql(&mut db, "CREATE SEQUENCE s START WITH 10").unwrap();
+        execute_sql(&mut db, "CREATE TABLE t (id INT, n INT, name TEXT)").unwrap();
+
+        execute_sql(&mut db, "INSERT INTO t VALUES (nextval(\"s\"), 3, NULL)").unwrap();
+        execute_sql(
+            &mut db,
+            "INSERT INTO t (id, n) VALUES (nextval(\"s\"), 1 + 1), (nextval(\"s\") * 2, 0)",
+        )
+        .unwrap();
+
+        assert_eq!(
+            ids(&mut db, "SELECT id FROM t"),
+            [Value::Int(10), Value::Int(11), Value::Int(24)]
+        );
+    }
+
+    #[test]
+    fn test_nextval_in_query() {
+        let dir = TempDatabase::new("sequence-query");
+        let mut db = dir.open();
+        execute_sql(&mut db, "CREATE SEQUENCE s").unwrap();
+        execute_sql(&mut db, "CREATE TABLE t (id INT)").unwrap();
+        execute_sql(&mut db, "INSERT INTO t VALUES (1), (2), (3), (4)").unwrap();
+
+        // Only the rows returned move the sequence, in their output order
+        let result = execute_sql(
+            &mut db,
+            "SELECT id, nextval(\"s\") FROM t ORDER BY id DESC LIMIT 2 OFFSET 1",
+        )
+        .unwrap()
+        .unwrap();
+        assert_eq!(
+            result.rows,
+            [
+                [Value::Int(3), Value::Int(1)],
+                [Value::Int(2), Value::Int(2)]
+            ]
+        );
+
+        assert_eq!(
+            ids(&mut db, "SELECT nextval(\"s\") FROM t WHERE id > 3"),
+            [Value::Int(3)]
+        );
+
+        assert!(execute_sql(&mut db, "SELECT DISTINCT nextval(\"s\") FROM t").is_err());
+
+        // Values are saved in blocks, reopening skips the rest of the block
+        drop(db);
+        let mut db = dir.open();
+        let block = Database::SEQUENCE_BLOCK as i64;
+        assert_eq!(db.schema().get_sequence("s").unwrap().last, Some(block));
+        assert_eq!(db.nextval("s").unwrap(), block + 1);
+        assert_eq!(db.nextval("s").unwrap(), block + 2);
+    }
+}
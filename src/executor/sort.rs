@@ -0,0 +1,211 @@
+use std::cmp::Ordering;
+
+use anyhow::Result;
+
+use super::spill::{self, SpillReader, SpillWriter};
+use crate::sql::{Direction, Nulls, OrderBy, Value};
+
+/// How one leading value of the sorted rows is ordered.
+#[derive(Debug, Clone, Copy)]
+pub struct SortKey {
+    pub descending: bool,
+    pub nulls_first: bool,
+}
+
+impl SortKey {
+    /// NULLs go last when ascending and first when descending, unless the
+    /// ORDER BY item says otherwise.
+    pub fn of(order_by: &OrderBy) -> Self {
+        let descending = order_by.direction == Direction::Descending;
+
+        Self {
+            descending,
+            nulls_first: order_by
+                .nulls
+                .map_or(descending, |nulls| nulls == Nulls::First),
+        }
+    }
+}
+
+/// Orders rows by their first `keys.len()` values.
+pub fn compare_rows(keys: &[SortKey], a: &[Value], b: &[Value]) -> Ordering {
+    for (key, (a, b)) in keys.iter().zip(a.iter().zip(b.iter())) {
+        let ordering = match (a, b) {
+            (Value::Null, Value::Null) => Ordering::Equal,
+            (Value::Null, _) if key.nulls_first => Ordering::Less,
+            (Value::Null, _) => Ordering::Greater,
+            (_, Value::Null) if key.nulls_first => Ordering::Greater,
+            (_, Value::Null) => Ordering::Less,
+            // Values of incompatible types keep their input order
+            (a, b) if key.descending => a.compare(b).unwrap_or(Ordering::Equal).reverse(),
+            (a, b) => a.compare(b).unwrap_or(Ordering::Equal),
+        };
+
+        if ordering != Ordering::Equal {
+            return ordering;
+        }
+    }
+
+    Ordering::Equal
+}
+
+/// External merge sort: rows are sorted in memory until they exceed the
+/// memory budget, then written out as a sorted run and merged at the end.
+pub struct Sorter {
+    keys: Vec<SortKey>,
+    budget: usize,
+    rows: Vec<Vec<Value>>,
+    size: usize,
+    runs: Vec<SpillReader>,
+}
+
+impl Sorter {
+    pub fn new(keys: Vec<SortKey>, budget: usize) -> Self {
+        Self {
+            keys,
+            budget,
+            rows: Vec::new(),
+            size: 0,
+            runs: Vec::new(),
+        }
+    }
+
+    pub fn push(&mut self, row: Vec<Value>) -> Result<()> {
+        self.size += spill::row_size(&row);
+        self.rows.push(row);
+
+        if self.size > self.budget {
+            self.spill()?;
+        }
+
+        Ok(())
+    }
+
+    fn sort_rows(&mut self) {
+        let keys = &self.keys;
+        self.rows.sort_by(|a, b| compare_rows(keys, a, b));
+    }
+
+    fn spill(&mut self) -> Result<()> {
+        self.sort_rows();
+
+        let mut writer = SpillWriter::new()?;
+
+        for row in self.rows.drain(..) {
+            writer.write(&row)?;
+        }
+
+        self.runs.push(writer.finish()?);
+        self.size = 0;
+
+        Ok(())
+    }
+
+    /// Returns the rows in sorted order.
+    pub fn finish(mut self) -> Result<Sorted> {
+        self.sort_rows();
+
+        let mut sources = self
+            .runs
+            .into_iter()
+            .map(|run| Box::new(run) as Box<dyn Iterator<Item = Result<Vec<Value>>>>)
+            .collect::<Vec<_>>();
+
+        // Rows still in memory come last so equal rows keep their input order
+        sources.push(Box::new(self.rows.into_iter().map(Ok)));
+
+        let heads = sources
+            .iter_mut()
+            .map(|source| source.next().transpose())
+            .collect::<Result<Vec<_>>>()?;
+
+        Ok(Sorted {
+            keys: self.keys,
+            sources,
+            heads,
+        })
+    }
+}
+
+/// Merges the sorted runs of a [`Sorter`].
+pub struct Sorted {
+    keys: Vec<SortKey>,
+    sources: Vec<Box<dyn Iterator<Item = Result<Vec<Value>>>>>,
+    heads: Vec<Option<Vec<Value>>>,
+}
+
+impl Iterator for Sorted {
+    type Item = Result<Vec<Value>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let mut smallest: Option<(usize, &Vec<Value>)> = None;
+
+        for (index, head) in self.heads.iter().enumerate() {
+            if let Some(row) = head {
+                match smallest {
+                    Some((_, min)) if compare_rows(&self.keys, row, min) != Ordering::Less => {}
+                    _ => smallest = Some((index, row)),
+                }
+            }
+        }
+
+        let (index, _) = smallest?;
+        let row = self.heads[index].take();
+
+        match self.sources[index].next().transpose() {
+            Ok(next) => self.heads[index] = next,
+            Err(err) => return Some(Err(err)),
+        }
+
+        row.map(Ok)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn keys() -> Vec<SortKey> {
+        vec![
+            SortKey {
+                descending: true,
+                nulls_first: true,
+            },
+            SortKey {
+                descending: false,
+                nulls_first: false,
+            },
+        ]
+    }
+
+    #[test]
+    fn test_sort_spills_and_merges() {
+        // A tiny budget writes a run every few rows
+        let mut sorter = Sorter::new(keys(), 256);
+
+        for i in 0..100i64 {
+            let group = match i % 7 {
+                0 => Value::Null,
+                group => Value::Int(group),
+            };
+
+            sorter.push(vec![group, Value::Int(100 - i)]).unwrap();
+        }
+
+        assert!(sorter.runs.len() > 1);
+
+        let rows = sorter
+            .finish()
+            .unwrap()
+            .collect::<Result<Vec<_>>>()
+            .unwrap();
+
+        assert_eq!(rows.len(), 100);
+        assert_eq!(rows[0], vec![Value::Null, Value::Int(2)]);
+        assert_eq!(rows.last().unwrap(), &vec![Value::Int(1), Value::Int(99)]);
+
+        for pair in rows.windows(2) {
+            assert_ne!(compare_rows(&keys(), &pair[0], &pair[1]), Ordering::Greater);
+        }
+    }
+}
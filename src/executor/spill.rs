@@ -0,0 +1,354 @@
+use std::fs::{self, File};
+use std::io::{BufReader, BufWriter, ErrorKind, Read, Write};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use anyhow::{bail, Result};
+
+use crate::sql::Value;
+use crate::types::date::{Date, Interval, Time, Timestamp};
+use crate::types::decimal::Decimal;
+
+/// Default amount of row data an operator keeps in memory before it starts
+/// writing rows to temporary files.
+pub const MEMORY_BUDGET: usize = 16 * 1024 * 1024;
+
+static NEXT_FILE: AtomicUsize = AtomicUsize::new(0);
+
+/// Writes rows to a temporary file, to be read back once with [`SpillWriter::finish`].
+/// The file is removed if the writer is dropped before that.
+pub struct SpillWriter {
+    path: PathBuf,
+    writer: BufWriter<File>,
+    buffer: Vec<u8>,
+}
+
+impl SpillWriter {
+    pub fn new() -> Result<Self> {
+        let path = std::env::temp_dir().join(format!(
+            "mini-sql-{}-spill-{}",
+            std::process::id(),
+            NEXT_FILE.fetch_add(1, Ordering::Relaxed)
+        ));
+
+        let writer = BufWriter::new(File::create(&path)?);
+
+        Ok(Self {
+            path,
+            writer,
+            buffer: Vec::new(),
+        })
+    }
+
+    pub fn write(&mut self, row: &[Value]) -> Result<()> {
+        self.buffer.clear();
+        encode_row(row, &mut self.buffer);
+        self.writer.write_all(&self.buffer)?;
+        Ok(())
+    }
+
+    pub fn finish(mut self) -> Result<SpillReader> {
+        self.writer.flush()?;
+
+        let reader = BufReader::new(File::open(&self.path)?);
+
+        // The reader removes the file from now on
+        Ok(SpillReader {
+            path: std::mem::take(&mut self.path),
+            reader,
+        })
+    }
+}
+
+impl Drop for SpillWriter {
+    fn drop(&mut self) {
+        if !self.path.as_os_str().is_empty() {
+            let _ = fs::remove_file(&self.path);
+        }
+    }
+}
+
+/// Reads the rows of a spill file in the order they were written and removes
+/// the file when dropped.
+pub struct SpillReader {
+    path: PathBuf,
+    reader: BufReader<File>,
+}
+
+impl SpillReader {
+    fn read_row(&mut self) -> Result<Option<Vec<Value>>> {
+        let mut count = [0; 4];
+
+        match self.reader.read_exact(&mut count) {
+            Ok(()) => {}
+            Err(err) if err.kind() == ErrorKind::UnexpectedEof => return Ok(None),
+            Err(err) => return Err(err.into()),
+        }
+
+        (0..u32::from_be_bytes(count))
+            .map(|_| decode(&mut self.reader))
+            .collect::<Result<Vec<_>>>()
+            .map(Some)
+    }
+}
+
+impl Iterator for SpillReader {
+    type Item = Result<Vec<Value>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.read_row().transpose()
+    }
+}
+
+impl Drop for SpillReader {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.path);
+    }
+}
+
+/// Approximate number of bytes a row takes up in memory.
+pub fn row_size(row: &[Value]) -> usize {
+    row.iter()
+        .map(|value| {
+            std::mem::size_of::<Value>()
+                + match value {
+                    Value::String(value) => value.len(),
+                    Value::Blob(bytes) => bytes.len(),
+                    Value::Json(json) => json.to_string().len(),
+                    _ => 0,
+                }
+        })
+        .sum()
+}
+
+// Each value is a tag byte followed by its payload, variable-length payloads
+// are prefixed with their length.
+const TAG_NULL: u8 = 0;
+const TAG_STRING: u8 = 1;
+const TAG_INT: u8 = 2;
+const TAG_BLOB: u8 = 3;
+const TAG_DATE: u8 = 4;
+const TAG_TIME: u8 = 5;
+const TAG_TIMESTAMP: u8 = 6;
+const TAG_INTERVAL: u8 = 7;
+const TAG_DECIMAL: u8 = 8;
+const TAG_JSON: u8 = 9;
+const TAG_BOOLEAN: u8 = 10;
+
+/// Appends the binary form of a row to `buffer`, equal rows have equal encodings.
+pub fn encode_row(row: &[Value], buffer: &mut Vec<u8>) {
+    buffer.extend((row.len() as u32).to_be_bytes());
+
+    for value in row {
+        encode(value, buffer);
+    }
+}
+
+/// Appends a key for hashing a row to `buffer`. Unlike with [`encode_row`],
+/// values that compare equal have equal encodings, such as the decimals 1.0
+/// and 1.00, the number 1 and the JSON number 1.0. Keys are not decoded.
+pub fn encode_key(row: &[Value], buffer: &mut Vec<u8>) {
+    buffer.extend((row.len() as u32).to_be_bytes());
+
+    for value in row {
+        match key_value(value) {
+            Some(value) => encode(&value, buffer),
+            None => encode(value, buffer),
+        }
+    }
+}
+
+/// Form of a number or JSON scalar in a key, `None` for other values, which
+/// are encoded as they are.
+fn key_value(value: &Value) -> Option<Value> {
+    match value {
+        Value::Json(json) if !json.is_object() && !json.is_array() => {
+            match Value::from_json(json) {
+                // Numbers beyond the range of decimals stay JSON
+                Value::Json(_) => None,
+                value => Some(key_value(&value).unwrap_or(value)),
+            }
+        }
+        Value::Decimal(decimal) => {
+            let decimal = decimal.normalize();
+
+            match i64::try_from(decimal.mantissa()) {
+                Ok(value) if decimal.scale() == 0 => Some(Value::Int(value)),
+                _ => Some(Value::Decimal(decimal)),
+            }
+        }
+        _ => None,
+    }
+}
+
+fn encode(value: &Value, buffer: &mut Vec<u8>) {
+    match value {
+        Value::Null => buffer.push(TAG_NULL),
+        Value::String(value) => {
+            buffer.push(TAG_STRING);
+            encode_bytes(value.as_bytes(), buffer);
+        }
+        Value::Int(value) => {
+            buffer.push(TAG_INT);
+            buffer.extend(value.to_be_bytes());
+        }
+        Value::Blob(bytes) => {
+            buffer.push(TAG_BLOB);
+            encode_bytes(bytes, buffer);
+        }
+        Value::Date(date) => {
+            buffer.push(TAG_DATE);
+            buffer.extend(date.0.to_be_bytes());
+        }
+        Value::Time(time) => {
+            buffer.push(TAG_TIME);
+            buffer.extend(time.0.to_be_bytes());
+        }
+        Value::Timestamp(timestamp) => {
+            buffer.push(TAG_TIMESTAMP);
+            buffer.extend(timestamp.0.to_be_bytes());
+        }
+        Value::Interval(interval) => {
+            buffer.push(TAG_INTERVAL);
+            buffer.extend(interval.months.to_be_bytes());
+            buffer.extend(interval.days.to_be_bytes());
+            buffer.extend(interval.micros.to_be_bytes());
+        }
+        Value::Decimal(decimal) => {
+            buffer.push(TAG_DECIMAL);
+            buffer.extend(decimal.mantissa().to_be_bytes());
+            buffer.extend(decimal.scale().to_be_bytes());
+        }
+        Value::Json(json) => {
+            buffer.push(TAG_JSON);
+            encode_bytes(json.to_string().as_bytes(), buffer);
+        }
+        Value::Boolean(value) => {
+            buffer.push(TAG_BOOLEAN);
+            buffer.push(*value as u8);
+        }
+    }
+}
+
+fn encode_bytes(bytes: &[u8], buffer: &mut Vec<u8>) {
+    buffer.extend((bytes.len() as u32).to_be_bytes());
+    buffer.extend(bytes);
+}
+
+fn decode(reader: &mut impl Read) -> Result<Value> {
+    let value = match read_array::<1>(reader)?[0] {
+        TAG_NULL => Value::Null,
+        TAG_STRING => Value::String(String::from_utf8(decode_bytes(reader)?)?),
+        TAG_INT => Value::Int(i64::from_be_bytes(read_array(reader)?)),
+        TAG_BLOB => Value::Blob(decode_bytes(reader)?),
+        TAG_DATE => Value::Date(Date(i32::from_be_bytes(read_array(reader)?))),
+        TAG_TIME => Value::Time(Time(i64::from_be_bytes(read_array(reader)?))),
+        TAG_TIMESTAMP => Value::Timestamp(Timestamp(i64::from_be_bytes(read_array(reader)?))),
+        TAG_INTERVAL => Value::Interval(Interval {
+            months: i32::from_be_bytes(read_array(reader)?),
+            days: i32::from_be_bytes(read_array(reader)?),
+            micros: i64::from_be_bytes(read_array(reader)?),
+        }),
+        TAG_DECIMAL => {
+            let mantissa = i128::from_be_bytes(read_array(reader)?);
+            let scale = u32::from_be_bytes(read_array(reader)?);
+            Value::Decimal(Decimal::new(mantissa, scale))
+        }
+        TAG_JSON => Value::Json(serde_json::from_slice(&decode_bytes(reader)?)?),
+        TAG_BOOLEAN => Value::Boolean(read_array::<1>(reader)?[0] != 0),
+        tag => bail!("Corrupt spill file: unknown value tag {}", tag),
+    };
+
+    Ok(value)
+}
+
+fn decode_bytes(reader: &mut impl Read) -> Result<Vec<u8>> {
+    let length = u32::from_be_bytes(read_array(reader)?) as usize;
+    let mut bytes = vec![0; length];
+    reader.read_exact(&mut bytes)?;
+    Ok(bytes)
+}
+
+fn read_array<const N: usize>(reader: &mut impl Read) -> Result<[u8; N]> {
+    let mut bytes = [0; N];
+    reader.read_exact(&mut bytes)?;
+    Ok(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_spill_roundtrip() {
+        let rows = vec![
+            vec![
+                Value::Null,
+                Value::String(String::from("hello")),
+                Value::Int(42),
+                Value::Blob(vec![1, 2, 3]),
+            ],
+            vec![
+                Value::Date(Date::parse("2026-10-18").unwrap()),
+                Value::Timestamp(Timestamp::parse("2026-10-18 12:30:00").unwrap()),
+                Value::Interval(Interval::parse("1 month -2 days").unwrap()),
+                Value::Decimal(Decimal::parse("-12.345").unwrap()),
+                Value::Json(serde_json::json!({ "a": [1, 2] })),
+            ],
+        ];
+
+        let mut writer = SpillWriter::new().unwrap();
+
+        for row in rows.iter() {
+            writer.write(row).unwrap();
+        }
+
+        let read = writer
+            .finish()
+            .unwrap()
+            .collect::<Result<Vec<_>>>()
+            .unwrap();
+
+        assert_eq!(read, rows);
+    }
+
+    #[test]
+    fn test_spill_files_are_removed() {
+        let mut writer = SpillWriter::new().unwrap();
+        writer.write(&[Value::Int(1)]).unwrap();
+
+        let path = writer.path.clone();
+        assert!(path.exists());
+        drop(writer);
+        assert!(!path.exists());
+
+        let writer = SpillWriter::new().unwrap();
+        let path = writer.path.clone();
+        let reader = writer.finish().unwrap();
+        assert!(path.exists());
+        drop(reader);
+        assert!(!path.exists());
+    }
+
+    #[test]
+    fn test_equal_keys() {
+        let key = |value: Value| {
+            let mut key = Vec::new();
+            encode_key(&[value], &mut key);
+            key
+        };
+
+        let one = key(Value::Int(1));
+        assert_eq!(key(Value::Decimal(Decimal::parse("1.00").unwrap())), one);
+        assert_eq!(key(Value::Json(serde_json::json!(1.0))), one);
+        assert_eq!(
+            key(Value::Decimal(Decimal::parse("1.50").unwrap())),
+            key(Value::Decimal(Decimal::parse("1.5").unwrap()))
+        );
+        assert_ne!(key(Value::Decimal(Decimal::parse("1.5").unwrap())), one);
+        assert_eq!(
+            key(Value::Json(serde_json::json!("a"))),
+            key(Value::String(String::from("a")))
+        );
+    }
+}
@@ -0,0 +1,533 @@
+use anyhow::{bail, Result};
+
+use super::{expression, query};
+use crate::database::Database;
+use crate::sql::{
+    Expression, Pagination, SelectStatement, Selection, TableSource, Value, WhereExpression,
+};
+
+/// Columns visible to a subquery from a query around it. `row` is `None`
+/// while the values of the enclosing row are not known yet.
+struct Scope<'s> {
+    names: &'s [String],
+    row: Option<&'s [Value]>,
+    outer: Option<&'s Scope<'s>>,
+}
+
+enum Reference {
+    /// A column of the innermost query, or one that does not exist at all
+    /// and is reported when the query runs.
+    Local,
+    /// A column of an enclosing row with a known value.
+    Value(Value),
+    /// A column of the query the given number of scopes out, whose row is not known.
+    Unbound(usize),
+}
+
+impl Scope<'_> {
+    fn lookup(&self, name: &str) -> Result<Reference> {
+        let mut depth = 0;
+        let mut scope = Some(self);
+
+        while let Some(current) = scope {
+            if declares(current.names, name) {
+                return Ok(match (depth, current.row) {
+                    (0, _) => Reference::Local,
+                    (_, Some(row)) => {
+                        Reference::Value(row[expression::find_column(name, current.names)?].clone())
+                    }
+                    (depth, None) => Reference::Unbound(depth),
+                });
+            }
+
+            depth += 1;
+            scope = current.outer;
+        }
+
+        Ok(Reference::Local)
+    }
+}
+
+/// Whether `name` refers to one of `names`, following the rules of [`expression::find_column`].
+fn declares(names: &[String], name: &str) -> bool {
+    names.iter().any(|column| {
+        column == name
+            || (!name.contains('.')
+                && column
+                    .rsplit_once('.')
+                    .is_some_and(|(_, column)| column == name))
+    })
+}
+
+/// Executes the subqueries of a condition and replaces them with their
+/// results. `names` are the columns of the rows the condition is evaluated
+/// on, subqueries that reference them are left in place while `row` is `None`.
+pub fn bind_condition(
+    db: &mut Database,
+    condition: &mut WhereExpression,
+    names: &[String],
+    row: Option<&[Value]>,
+) -> Result<()> {
+    let scope = Scope {
+        names,
+        row,
+        outer: None,
+    };
+
+    bind_condition_in(db, condition, &scope)
+}
+
+/// Like [`bind_condition`] for a scalar expression.
+pub fn bind_expression(
+    db: &mut Database,
+    expression: &mut Expression,
+    names: &[String],
+    row: Option<&[Value]>,
+) -> Result<()> {
+    let scope = Scope {
+        names,
+        row,
+        outer: None,
+    };
+
+    bind_expression_in(db, expression, &scope)
+}
+
+/// Evaluates a condition that contains correlated subqueries against one row.
+pub fn matches(
+    db: &mut Database,
+    condition: &WhereExpression,
+    names: &[String],
+    row: &[Value],
+) -> Result<bool> {
+    let mut condition = condition.clone();
+    bind_condition(db, &mut condition, names, Some(row))?;
+    expression::matches(&condition, names, row)
+}
+
+pub fn contains_subquery(condition: &WhereExpression) -> bool {
+    match condition {
+        WhereExpression::InSubquery { .. } | WhereExpression::Exists(_) => true,
+        WhereExpression::And(conditions) | WhereExpression::Or(conditions) => {
+            conditions.iter().any(contains_subquery)
+        }
+        WhereExpression::Not(condition) => contains_subquery(condition),
+        condition => condition
+            .expressions()
+            .into_iter()
+            .any(expression_contains_subquery),
+    }
+}
+
+pub fn expression_contains_subquery(expression: &Expression) -> bool {
+    match expression {
+        Expression::Subquery(_) => true,
+        Expression::Condition(condition) => contains_subquery(condition),
+        Expression::Case(case) => {
+            case.branches
+                .iter()
+                .any(|branch| contains_subquery(&branch.condition))
+                || expression
+                    .children()
+                    .into_iter()
+                    .any(expression_contains_subquery)
+        }
+        expression => expression
+            .children()
+            .into_iter()
+            .any(expression_contains_subquery),
+    }
+}
+
+fn bind_condition_in(
+    db: &mut Database,
+    condition: &mut WhereExpression,
+    scope: &Scope,
+) -> Result<()> {
+    match condition {
+        WhereExpression::And(conditions) | WhereExpression::Or(conditions) => {
+            for condition in conditions.iter_mut() {
+                bind_condition_in(db, condition, scope)?;
+            }
+        }
+        WhereExpression::Not(condition) => bind_condition_in(db, condition, scope)?,
+        WhereExpression::InSubquery {
+            expression,
+            subquery,
+        } => {
+            bind_expression_in(db, expression, scope)?;
+
+            if let Some(subquery) = prepare(db, subquery, scope)? {
+                let list = column(db, subquery)?
+                    .into_iter()
+                    .map(Expression::Value)
+                    .collect();
+
+                *condition = WhereExpression::In {
+                    expression: std::mem::replace(expression, Expression::Value(Value::Null)),
+                    list,
+                };
+            }
+        }
+        WhereExpression::Exists(subquery) => {
+            if let Some(mut subquery) = prepare(db, subquery, scope)? {
+                // One row is enough to know the answer
+                let pagination = subquery.pagination.get_or_insert(Pagination {
+                    limit: None,
+                    offset: None,
+                });
+                pagination.limit = Some(pagination.limit.map_or(1, |limit| limit.min(1)));
+
+                // An empty AND is true and an empty OR is false
+                *condition = match query(db, subquery)?.0.rows.is_empty() {
+                    true => WhereExpression::Or(Vec::new()),
+                    false => WhereExpression::And(Vec::new()),
+                };
+            }
+        }
+        condition => {
+            for expression in condition.expressions_mut() {
+                bind_expression_in(db, expression, scope)?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn bind_expression_in(db: &mut Database, expression: &mut Expression, scope: &Scope) -> Result<()> {
+    match expression {
+        Expression::Subquery(subquery) => {
+            if let Some(subquery) = prepare(db, subquery, scope)? {
+                *expression = Expression::Value(scalar(db, subquery)?);
+            }
+        }
+        Expression::Condition(condition) => bind_condition_in(db, condition, scope)?,
+        Expression::Case(case) => {
+            for branch in case.branches.iter_mut() {
+                bind_condition_in(db, &mut branch.condition, scope)?;
+                bind_expression_in(db, &mut branch.result, scope)?;
+            }
+
+            if let Some(r#else) = case.r#else.as_deref_mut() {
+                bind_expression_in(db, r#else, scope)?;
+            }
+        }
+        expression => {
+            for child in expression.children_mut() {
+                bind_expression_in(db, child, scope)?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Copy of a subquery with the values of the enclosing rows filled in, or
+/// `None` when it depends on a row that is not known yet.
+fn prepare(
+    db: &mut Database,
+    subquery: &SelectStatement,
+    scope: &Scope,
+) -> Result<Option<SelectStatement>> {
+    let mut subquery = subquery.clone();
+
+    match correlate(db, &mut subquery, scope)? {
+        0 => Ok(Some(subquery)),
+        _ => Ok(None),
+    }
+}
+
+/// Replaces references to columns of enclosing queries in `statement` with
+/// the values of their rows. Returns how many scopes out the farthest
+/// reference to a query with an unknown row reaches, 0 when there is none.
+fn correlate(db: &mut Database, statement: &mut SelectStatement, outer: &Scope) -> Result<usize> {
+    let names = scope_columns(db, statement)?;
+    let scope = Scope {
+        names: &names,
+        row: None,
+        outer: Some(outer),
+    };
+
+    let mut depth = 0;
+
+    // Derived tables cannot see the columns of their siblings, only those
+    // of the queries around this one
+    let derived = std::iter::once(&mut statement.table)
+        .chain(statement.joins.iter_mut().map(|join| &mut join.table))
+        .filter_map(|table| match &mut table.source {
+            TableSource::Subquery(subquery) => Some(subquery),
+            TableSource::Table(_) | TableSource::Rows { .. } => None,
+        });
+
+    for subquery in derived {
+        depth = depth.max(correlate(db, subquery, outer)?);
+    }
+
+    let conditions = statement
+        .joins
+        .iter_mut()
+        .filter_map(|join| join.on.as_mut())
+        .chain(statement.r#where.as_mut())
+        .chain(statement.having.as_mut());
+
+    for condition in conditions {
+        depth = depth.max(correlate_condition(db, condition, &scope)?);
+    }
+
+    let expressions = statement
+        .selections
+        .iter_mut()
+        .map(|selection| &mut selection.expression)
+        .chain(statement.group_by.iter_mut())
+        .chain(
+            statement
+                .order_by
+                .iter_mut()
+                .map(|order_by| &mut order_by.expression),
+        );
+
+    for expression in expressions {
+        depth = depth.max(correlate_expression(db, expression, &scope)?);
+    }
+
+    Ok(depth)
+}
+
+fn correlate_condition(
+    db: &mut Database,
+    condition: &mut WhereExpression,
+    scope: &Scope,
+) -> Result<usize> {
+    let mut depth = 0;
+
+    match condition {
+        WhereExpression::And(conditions) | WhereExpression::Or(conditions) => {
+            for condition in conditions.iter_mut() {
+                depth = depth.max(correlate_condition(db, condition, scope)?);
+            }
+        }
+        WhereExpression::Not(condition) => depth = correlate_condition(db, condition, scope)?,
+        WhereExpression::InSubquery {
+            expression,
+            subquery,
+        } => {
+            depth = correlate_expression(db, expression, scope)?
+                .max(correlate(db, subquery, scope)?.saturating_sub(1));
+        }
+        WhereExpression::Exists(subquery) => {
+            depth = correlate(db, subquery, scope)?.saturating_sub(1);
+        }
+        condition => {
+            for expression in condition.expressions_mut() {
+                depth = depth.max(correlate_expression(db, expression, scope)?);
+            }
+        }
+    }
+
+    Ok(depth)
+}
+
+fn correlate_expression(
+    db: &mut Database,
+    expression: &mut Expression,
+    scope: &Scope,
+) -> Result<usize> {
+    let mut depth = 0;
+
+    match expression {
+        Expression::Column(name) => match scope.lookup(name)? {
+            Reference::Local => {}
+            Reference::Value(value) => *expression = Expression::Value(value),
+            Reference::Unbound(unbound) => depth = unbound,
+        },
+        Expression::Subquery(subquery) => depth = correlate(db, subquery, scope)?.saturating_sub(1),
+        Expression::Condition(condition) => depth = correlate_condition(db, condition, scope)?,
+        Expression::Case(case) => {
+            for branch in case.branches.iter_mut() {
+                depth = depth
+                    .max(correlate_condition(db, &mut branch.condition, scope)?)
+                    .max(correlate_expression(db, &mut branch.result, scope)?);
+            }
+
+            if let Some(r#else) = case.r#else.as_deref_mut() {
+                depth = depth.max(correlate_expression(db, r#else, scope)?);
+            }
+        }
+        expression => {
+            for child in expression.children_mut() {
+                depth = depth.max(correlate_expression(db, child, scope)?);
+            }
+        }
+    }
+
+    Ok(depth)
+}
+
+/// Names visible in the expressions of a query: the qualified columns of
+/// its FROM items and the aliases of its selections.
+fn scope_columns(db: &mut Database, statement: &SelectStatement) -> Result<Vec<String>> {
+    let mut names = from_columns(db, statement)?;
+
+    names.extend(
+        statement
+            .selections
+            .iter()
+            .filter_map(|selection| selection.alias.clone()),
+    );
+
+    Ok(names)
+}
+
+/// Qualified columns of the FROM items of a query, read from the schema
+/// without running any subquery.
+pub fn from_columns(db: &mut Database, statement: &SelectStatement) -> Result<Vec<String>> {
+    let mut names = Vec::new();
+
+    let tables =
+        std::iter::once(&statement.table).chain(statement.joins.iter().map(|join| &join.table));
+
+    for table in tables {
+        match &table.source {
+            TableSource::Table(name) => match db.schema().get_view(name) {
+                Some(view) => names.extend(
+                    view.columns
+                        .iter()
+                        .map(|column| format!("{}.{}", table.qualifier(), column)),
+                ),
+                None => names.extend(
+                    db.table(name)?
+                        .schema()
+                        .columns()
+                        .iter()
+                        .map(|c| format!("{}.{}", table.qualifier(), c.name())),
+                ),
+            },
+            TableSource::Subquery(subquery) => names.extend(
+                output_columns(db, subquery)?
+                    .iter()
+                    .map(|column| format!("{}.{}", table.qualifier(), column)),
+            ),
+            TableSource::Rows { columns, .. } => names.extend(
+                columns
+                    .iter()
+                    .map(|column| format!("{}.{}", table.qualifier(), column)),
+            ),
+        }
+    }
+
+    Ok(names)
+}
+
+/// Headers of the columns a query returns.
+pub fn output_columns(db: &mut Database, statement: &SelectStatement) -> Result<Vec<String>> {
+    Ok(expand_selections(db, statement)?
+        .iter()
+        .map(|selection| selection.name())
+        .collect())
+}
+
+/// Selections of a query with `*` replaced by the columns of its FROM
+/// items, unqualified where the name is not ambiguous.
+pub fn expand_selections(db: &mut Database, statement: &SelectStatement) -> Result<Vec<Selection>> {
+    if !statement
+        .selections
+        .iter()
+        .any(|selection| selection.expression == Expression::Wildcard)
+    {
+        return Ok(statement.selections.clone());
+    }
+
+    let columns = from_columns(db, statement)?;
+    let unqualified = |name: &str| String::from(name.rsplit_once('.').map_or(name, |(_, column)| column));
+    let mut selections = Vec::new();
+
+    for selection in statement.selections.iter() {
+        if selection.expression != Expression::Wildcard {
+            selections.push(selection.clone());
+            continue;
+        }
+
+        for name in columns.iter() {
+            let column = unqualified(name);
+            let ambiguous = columns.iter().filter(|other| unqualified(other) == column).count() > 1;
+
+            selections.push(Selection {
+                expression: Expression::Column(if ambiguous { name.clone() } else { column }),
+                alias: None,
+            });
+        }
+    }
+
+    Ok(selections)
+}
+
+fn scalar(db: &mut Database, subquery: SelectStatement) -> Result<Value> {
+    let mut values = column(db, subquery)?;
+
+    if values.len() > 1 {
+        bail!("More than one row returned by a subquery used as an expression");
+    }
+
+    Ok(values.pop().unwrap_or(Value::Null))
+}
+
+/// Values of the single column of a subquery.
+fn column(db: &mut Database, subquery: SelectStatement) -> Result<Vec<Value>> {
+    let (result, _) = query(db, subquery)?;
+
+    if result.columns.len() != 1 {
+        bail!("Subquery must return only one column");
+    }
+
+    Ok(result.rows.into_iter().flatten().collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn names(names: &[&str]) -> Vec<String> {
+        names.iter().map(|name| String::from(*name)).collect()
+    }
+
+    #[test]
+    fn test_scope_lookup() {
+        let users = names(&["u.id", "u.name"]);
+        let row = [Value::Int(7), Value::String(String::from("ann"))];
+        let outer = Scope {
+            names: &users,
+            row: Some(&row),
+            outer: None,
+        };
+
+        let orders = names(&["o.id", "o.user_id"]);
+        let inner = Scope {
+            names: &orders,
+            row: None,
+            outer: Some(&outer),
+        };
+
+        assert!(matches!(inner.lookup("id").unwrap(), Reference::Local));
+        assert!(matches!(inner.lookup("missing").unwrap(), Reference::Local));
+        assert!(matches!(
+            inner.lookup("u.id").unwrap(),
+            Reference::Value(Value::Int(7))
+        ));
+        assert!(matches!(
+            inner.lookup("name").unwrap(),
+            Reference::Value(Value::String(_))
+        ));
+
+        let unbound = Scope { row: None, ..outer };
+        let inner = Scope {
+            outer: Some(&unbound),
+            ..inner
+        };
+
+        assert!(matches!(
+            inner.lookup("u.id").unwrap(),
+            Reference::Unbound(1)
+        ));
+    }
+}
@@ -0,0 +1,524 @@
+use std::fmt::{self, Display, Formatter};
+
+use anyhow::{bail, Result};
+
+use super::{aggregate, expression, function};
+use crate::schema::ColumnKind;
+use crate::sql::{
+    AggregateFunction, BinaryOperator, Expression, Operator, Value, WhereExpression, WindowCall,
+    WindowFunction,
+};
+
+/// Static type of an expression. `Unknown` is the type of NULL and of values
+/// only known at runtime, such as JSON members, and matches every type.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Type {
+    Unknown,
+    Int,
+    Decimal,
+    Text,
+    Blob,
+    Date,
+    Time,
+    Timestamp,
+    Interval,
+    Json,
+    Boolean,
+}
+
+impl Type {
+    pub fn of_kind(kind: &ColumnKind) -> Self {
+        match kind {
+            ColumnKind::Int | ColumnKind::BigInt => Type::Int,
+            ColumnKind::String(_) | ColumnKind::Text => Type::Text,
+            ColumnKind::Blob => Type::Blob,
+            ColumnKind::Date => Type::Date,
+            ColumnKind::Time => Type::Time,
+            ColumnKind::Timestamp => Type::Timestamp,
+            ColumnKind::Decimal(_) => Type::Decimal,
+            ColumnKind::Json => Type::Json,
+        }
+    }
+
+    pub fn of_value(value: &Value) -> Self {
+        match value {
+            Value::Null => Type::Unknown,
+            Value::String(_) => Type::Text,
+            Value::Int(_) => Type::Int,
+            Value::Blob(_) => Type::Blob,
+            Value::Date(_) => Type::Date,
+            Value::Time(_) => Type::Time,
+            Value::Timestamp(_) => Type::Timestamp,
+            Value::Interval(_) => Type::Interval,
+            Value::Decimal(_) => Type::Decimal,
+            Value::Json(_) => Type::Json,
+            Value::Boolean(_) => Type::Boolean,
+        }
+    }
+
+    /// Type of a column holding values of both types, as combined by UNION.
+    pub fn common(self, other: Self) -> Option<Self> {
+        match (self, other) {
+            (Type::Unknown, other) | (other, Type::Unknown) => Some(other),
+            (a, b) if a == b => Some(a),
+            (a, b) if a.is_numeric() && b.is_numeric() => Some(Type::Decimal),
+            _ => None,
+        }
+    }
+
+    fn is_numeric(self) -> bool {
+        matches!(self, Type::Int | Type::Decimal)
+    }
+
+    /// Whether the type can be an operand of LIKE, JSON strings included.
+    fn is_text(self) -> bool {
+        matches!(self, Type::Text | Type::Unknown | Type::Json)
+    }
+
+    fn is_temporal(self) -> bool {
+        matches!(self, Type::Date | Type::Time | Type::Timestamp)
+    }
+
+    /// Whether values of both types can be compared, mirroring [`Value::compare`].
+    fn comparable(self, other: Self) -> bool {
+        match (self, other) {
+            (Type::Unknown | Type::Json, _) | (_, Type::Unknown | Type::Json) => true,
+            (a, b) if a == b => true,
+            (a, b) if a.is_numeric() && b.is_numeric() => true,
+            (Type::Date, Type::Timestamp) | (Type::Timestamp, Type::Date) => true,
+            (Type::Text, other) | (other, Type::Text) => other.is_temporal(),
+            _ => false,
+        }
+    }
+
+    /// Whether a value of this type can be stored in a column, strings are
+    /// parsed as literals of the column type.
+    pub fn assignable_to(self, kind: &ColumnKind) -> bool {
+        match (kind, self) {
+            (_, Type::Unknown) => true,
+            (ColumnKind::Int | ColumnKind::BigInt, Type::Int) => true,
+            (ColumnKind::String(_) | ColumnKind::Text, Type::Text) => true,
+            (ColumnKind::Decimal(_), Type::Int | Type::Decimal | Type::Text) => true,
+            (ColumnKind::Timestamp, Type::Date) => true,
+            (
+                ColumnKind::Blob
+                | ColumnKind::Date
+                | ColumnKind::Time
+                | ColumnKind::Timestamp
+                | ColumnKind::Json,
+                Type::Text,
+            ) => true,
+            (kind, other) => Type::of_kind(kind) == other,
+        }
+    }
+
+    /// Whether `CAST` from this type to a column type can succeed, mirroring [`Value::cast`].
+    fn castable_to(self, kind: &ColumnKind) -> bool {
+        match (kind, self) {
+            (_, Type::Unknown | Type::Text | Type::Json) => true,
+            (ColumnKind::String(_) | ColumnKind::Text, _) => true,
+            (
+                ColumnKind::Int | ColumnKind::BigInt | ColumnKind::Decimal(_) | ColumnKind::Json,
+                other,
+            ) => {
+                other.is_numeric()
+            }
+            (ColumnKind::Date | ColumnKind::Time, Type::Timestamp) => true,
+            (ColumnKind::Timestamp, Type::Date) => true,
+            (kind, other) => Type::of_kind(kind) == other,
+        }
+    }
+}
+
+impl Display for Type {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Type::Unknown => "unknown",
+            Type::Int => "int",
+            Type::Decimal => "decimal",
+            Type::Text => "text",
+            Type::Blob => "blob",
+            Type::Date => "date",
+            Type::Time => "time",
+            Type::Timestamp => "timestamp",
+            Type::Interval => "interval",
+            Type::Json => "json",
+            Type::Boolean => "boolean",
+        };
+
+        write!(f, "{}", name)
+    }
+}
+
+/// Checks expressions against the columns of the rows they are evaluated on,
+/// `types` holds the type of each column in `columns`.
+pub struct Checker<'c> {
+    columns: &'c [String],
+    types: &'c [Type],
+    aggregates: bool,
+}
+
+impl<'c> Checker<'c> {
+    /// Checker for expressions evaluated per row, where aggregates are not allowed.
+    pub fn new(columns: &'c [String], types: &'c [Type]) -> Self {
+        Self {
+            columns,
+            types,
+            aggregates: false,
+        }
+    }
+
+    /// Checker for the selections, HAVING and ORDER BY of a grouped query.
+    pub fn with_aggregates(columns: &'c [String], types: &'c [Type]) -> Self {
+        Self {
+            aggregates: true,
+            ..Self::new(columns, types)
+        }
+    }
+
+    pub fn check(&self, expression: &Expression) -> Result<Type> {
+        match expression {
+            Expression::Value(value) => Ok(Type::of_value(value)),
+            Expression::Column(name) => {
+                Ok(self.types[expression::find_column(name, self.columns)?])
+            }
+            Expression::Wildcard => bail!("* is only allowed in a select list"),
+            Expression::Function(call) => {
+                let arguments = call
+                    .arguments
+                    .iter()
+                    .map(|argument| self.check(argument))
+                    .collect::<Result<Vec<_>>>()?;
+
+                function::return_type(&call.name, &arguments)
+            }
+            Expression::Aggregate(call) => {
+                if !self.aggregates {
+                    bail!(
+                        "Aggregate function {}() is not allowed here",
+                        call.function.name()
+                    );
+                }
+
+                let argument = match &call.argument {
+                    Some(argument) if aggregate::contains_aggregate(argument) => {
+                        bail!("Aggregate function calls cannot be nested")
+                    }
+                    Some(argument) => Checker::new(self.columns, self.types).check(argument)?,
+                    None => Type::Unknown,
+                };
+
+                aggregate_type(call.function, argument)
+            }
+            // Window functions are replaced by columns before their query is checked
+            Expression::Window(call) => bail!(
+                "Window function {}() is not allowed here",
+                call.function.name()
+            ),
+            Expression::Negate(expression) => match self.check(expression)? {
+                Type::Json => Ok(Type::Unknown),
+                other @ (Type::Unknown | Type::Int | Type::Decimal | Type::Interval) => Ok(other),
+                other => bail!("Cannot negate a value of type {}", other),
+            },
+            Expression::Binary {
+                left,
+                operator,
+                right,
+            } => binary(self.check(left)?, *operator, self.check(right)?),
+            Expression::Case(case) => {
+                let mut result = Type::Unknown;
+
+                for branch in case.branches.iter() {
+                    self.check_condition(&branch.condition)?;
+                    result = unify(result, self.check(&branch.result)?)?;
+                }
+
+                match &case.r#else {
+                    Some(expression) => unify(result, self.check(expression)?),
+                    None => Ok(result),
+                }
+            }
+            Expression::Cast { expression, kind } => {
+                let from = self.check(expression)?;
+
+                if !from.castable_to(kind) {
+                    bail!("Cannot cast type {} to {}", from, kind);
+                }
+
+                Ok(Type::of_kind(kind))
+            }
+            Expression::Condition(condition) => {
+                self.check_condition(condition)?;
+                Ok(Type::Boolean)
+            }
+            // Correlated subqueries are checked each time they are executed
+            Expression::Subquery(_) => Ok(Type::Unknown),
+        }
+    }
+
+    /// Checks a window function call, whose arguments and window are
+    /// evaluated on the rows of the query.
+    pub fn check_window(&self, call: &WindowCall) -> Result<Type> {
+        let arguments = call
+            .arguments
+            .iter()
+            .map(|argument| self.check(argument))
+            .collect::<Result<Vec<_>>>()?;
+
+        for expression in call.window.partition_by.iter() {
+            self.check(expression)?;
+        }
+
+        for order_by in call.window.order_by.iter() {
+            self.check(&order_by.expression)?;
+        }
+
+        let name = call.function.name();
+
+        match (call.function, arguments.as_slice()) {
+            (WindowFunction::RowNumber | WindowFunction::Rank | WindowFunction::DenseRank, []) => {
+                Ok(Type::Int)
+            }
+            (WindowFunction::Lag | WindowFunction::Lead, [argument, rest @ ..]) if rest.len() <= 2 => {
+                if let Some(offset) = rest.first() {
+                    if !matches!(offset, Type::Int | Type::Unknown) {
+                        bail!("The offset of {}() must be an integer, not {}", name, offset);
+                    }
+                }
+
+                match rest.get(1) {
+                    Some(default) => unify(*argument, *default),
+                    None => Ok(*argument),
+                }
+            }
+            (WindowFunction::Aggregate(AggregateFunction::Count), []) => Ok(Type::Int),
+            (WindowFunction::Aggregate(function), [argument]) => aggregate_type(function, *argument),
+            _ => bail!("Wrong number of arguments for window function {}()", name),
+        }
+    }
+
+    pub fn check_condition(&self, condition: &WhereExpression) -> Result<()> {
+        match condition {
+            WhereExpression::And(conditions) | WhereExpression::Or(conditions) => conditions
+                .iter()
+                .try_for_each(|condition| self.check_condition(condition)),
+            WhereExpression::Not(condition) => self.check_condition(condition),
+            WhereExpression::Condition(condition) => {
+                let left = self.check(&condition.field)?;
+                let right = self.check(&condition.value)?;
+
+                let valid = match condition.operator {
+                    Operator::Like | Operator::ILike => left.is_text() && right.is_text(),
+                    Operator::Contains => {
+                        (left.is_text() && right.is_text())
+                            || matches!((left, right), (Type::Blob, Type::Blob))
+                    }
+                    _ => left.comparable(right),
+                };
+
+                if !valid {
+                    let name = match condition.operator {
+                        Operator::Like => "LIKE",
+                        Operator::ILike => "ILIKE",
+                        Operator::Contains => "CONTAINS",
+                        _ => bail!("Cannot compare {} with {}", left, right),
+                    };
+
+                    bail!(
+                        "Operator {} cannot be applied to {} and {}",
+                        name,
+                        left,
+                        right
+                    );
+                }
+
+                Ok(())
+            }
+            WhereExpression::In { expression, list } => {
+                let left = self.check(expression)?;
+
+                for item in list {
+                    let right = self.check(item)?;
+
+                    if !left.comparable(right) {
+                        bail!("Cannot compare {} with {}", left, right);
+                    }
+                }
+
+                Ok(())
+            }
+            WhereExpression::InSubquery { expression, .. } | WhereExpression::IsNull(expression) => {
+                self.check(expression).map(|_| ())
+            }
+            WhereExpression::Exists(_) => Ok(()),
+        }
+    }
+}
+
+/// Result type of a binary operator, mirroring the arithmetic of [`Value`].
+fn binary(left: Type, operator: BinaryOperator, right: Type) -> Result<Type> {
+    let symbol = match operator {
+        BinaryOperator::Concat => return Ok(Type::Text),
+        BinaryOperator::JsonGet | BinaryOperator::JsonGetText => {
+            if !matches!(left, Type::Json | Type::Unknown)
+                || !matches!(right, Type::Text | Type::Int | Type::Unknown)
+            {
+                bail!("Cannot apply a JSON operator to {} and {}", left, right);
+            }
+
+            return Ok(match operator {
+                BinaryOperator::JsonGet => Type::Json,
+                _ => Type::Text,
+            });
+        }
+        BinaryOperator::Add => "+",
+        BinaryOperator::Subtract => "-",
+        BinaryOperator::Multiply => "*",
+        BinaryOperator::Divide => "/",
+    };
+
+    let result = match (operator, left, right) {
+        (_, Type::Unknown | Type::Json, _) | (_, _, Type::Unknown | Type::Json) => {
+            Some(Type::Unknown)
+        }
+        (_, Type::Int, Type::Int) => Some(Type::Int),
+        (_, left, right) if left.is_numeric() && right.is_numeric() => Some(Type::Decimal),
+        (BinaryOperator::Add, Type::Interval, other) | (BinaryOperator::Add, other, Type::Interval)
+            if other.is_temporal() || other == Type::Interval =>
+        {
+            Some(other)
+        }
+        (BinaryOperator::Subtract, other, Type::Interval)
+            if other.is_temporal() || other == Type::Interval =>
+        {
+            Some(other)
+        }
+        (BinaryOperator::Subtract, Type::Date, Type::Date)
+        | (BinaryOperator::Subtract, Type::Timestamp, Type::Timestamp) => Some(Type::Interval),
+        _ => None,
+    };
+
+    match result {
+        Some(result) => Ok(result),
+        None => bail!(
+            "Operator {} cannot be applied to {} and {}",
+            symbol,
+            left,
+            right
+        ),
+    }
+}
+
+/// Common type of the results of a CASE expression.
+/// Result type of an aggregate function for values of type `argument`.
+fn aggregate_type(function: AggregateFunction, argument: Type) -> Result<Type> {
+    match (function, argument) {
+        (AggregateFunction::Count, _) => Ok(Type::Int),
+        (AggregateFunction::Min | AggregateFunction::Max, argument) => Ok(argument),
+        (AggregateFunction::Avg, Type::Unknown | Type::Json) => Ok(Type::Decimal),
+        (AggregateFunction::Avg, argument) if argument.is_numeric() => Ok(Type::Decimal),
+        (
+            AggregateFunction::Sum,
+            argument @ (Type::Unknown | Type::Int | Type::Decimal | Type::Interval),
+        ) => Ok(argument),
+        (AggregateFunction::Sum, Type::Json) => Ok(Type::Unknown),
+        (function, argument) => bail!(
+            "Function {}() does not accept an argument of type {}",
+            function.name(),
+            argument
+        ),
+    }
+}
+
+fn unify(a: Type, b: Type) -> Result<Type> {
+    match (a, b) {
+        (Type::Unknown, other) | (other, Type::Unknown) => Ok(other),
+        (a, b) if a == b => Ok(a),
+        (a, b) if a.is_numeric() && b.is_numeric() => Ok(Type::Decimal),
+        (Type::Date, Type::Timestamp) | (Type::Timestamp, Type::Date) => Ok(Type::Timestamp),
+        (a, b) => bail!("CASE types {} and {} cannot be matched", a, b),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::schema::StringColumn;
+    use crate::sql::parser::Parser;
+    use crate::sql::tokenizer::Tokenizer;
+    use crate::sql::{SelectStatement, Statement};
+
+    fn select(sql: &str) -> SelectStatement {
+        let tokens = Tokenizer::new(sql).parse().unwrap();
+
+        match Parser::new(tokens).parse().unwrap() {
+            Statement::Select(select) => *select,
+            _ => panic!("Expected a SELECT statement"),
+        }
+    }
+
+    fn check(sql: &str) -> Result<Vec<Type>> {
+        let columns = vec![
+            String::from("users.name"),
+            String::from("users.age"),
+            String::from("users.born"),
+        ];
+        let types = vec![Type::Text, Type::Int, Type::Date];
+
+        let statement = select(sql);
+        let checker = Checker::new(&columns, &types);
+
+        if let Some(r#where) = &statement.r#where {
+            checker.check_condition(r#where)?;
+        }
+
+        statement
+            .selections
+            .iter()
+            .map(|selection| checker.check(&selection.expression))
+            .collect()
+    }
+
+    #[test]
+    fn test_check_expressions() {
+        assert_eq!(
+            check(
+                "SELECT age + 1, age / 2.5, name || age, born + INTERVAL \"1 day\", CASE WHEN age > 1 THEN 1 ELSE 2.5 END, CAST(age AS text) FROM users WHERE born > \"2000-01-01\""
+            )
+            .unwrap(),
+            vec![
+                Type::Int,
+                Type::Decimal,
+                Type::Text,
+                Type::Date,
+                Type::Decimal,
+                Type::Text,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_check_errors() {
+        let error = |sql| check(sql).err().unwrap().to_string();
+
+        assert!(error("SELECT name + 1 FROM users").contains("cannot be applied to text and int"));
+        assert!(error("SELECT name FROM users WHERE age = name").contains("Cannot compare int with text"));
+        assert!(error("SELECT CASE WHEN age > 1 THEN name ELSE 1 END FROM users").contains("CASE types"));
+        assert!(error("SELECT CAST(born AS int) FROM users").contains("Cannot cast type date"));
+        assert!(error("SELECT name FROM users WHERE count(*) > 1").contains("not allowed here"));
+        assert!(error("SELECT missing FROM users").contains("does not exist"));
+        assert!(error("SELECT name FROM users WHERE age LIKE \"1%\"").contains("LIKE cannot"));
+        assert!(error("SELECT name FROM users WHERE age IN (1, \"a\")").contains("Cannot compare"));
+    }
+
+    #[test]
+    fn test_assignable() {
+        let string = ColumnKind::String(StringColumn { length: 8 });
+
+        assert!(Type::Text.assignable_to(&string));
+        assert!(Type::Text.assignable_to(&ColumnKind::Date));
+        assert!(Type::Unknown.assignable_to(&ColumnKind::Int));
+        assert!(!Type::Int.assignable_to(&string));
+        assert!(!Type::Decimal.assignable_to(&ColumnKind::Int));
+    }
+}
@@ -0,0 +1,296 @@
+use std::collections::HashSet;
+
+use anyhow::{bail, Result};
+
+use super::constraint::{Change, Constraints};
+use super::typecheck::Checker;
+use super::{expression, subquery, table_columns};
+use crate::database::Database;
+use crate::sql::{ConflictAction, Expression, OnConflict, WhereExpression};
+use crate::table::{Row, RowId, Table};
+
+/// Action taken when an inserted row has the same values as a row of the
+/// table, or one inserted before it, in the key of the conflict target.
+/// Conflicting rows are looked up in the keys of [`Constraints`], which are
+/// read from the whole table at the start of each INSERT.
+pub struct Conflicts {
+    /// Key matching the target, without a target DO NOTHING takes any key.
+    key: Option<usize>,
+    action: Action,
+    /// Rows inserted or updated by the statement.
+    changed: HashSet<RowId>,
+}
+
+enum Action {
+    Nothing,
+    Update {
+        // Columns of the table followed by those of the excluded row
+        names: Vec<String>,
+        assignments: Vec<(usize, Expression)>,
+        r#where: Option<Box<WhereExpression>>,
+    },
+}
+
+impl Conflicts {
+    pub fn new(
+        db: &mut Database,
+        table: &str,
+        on_conflict: OnConflict,
+        constraints: &Constraints,
+    ) -> Result<Self> {
+        let (mut names, mut types) = table_columns(db, table)?;
+        let schema = db.table(table)?.schema().clone();
+        let columns = schema.columns();
+
+        let mut target = Vec::with_capacity(on_conflict.target.len());
+
+        for name in on_conflict.target.iter() {
+            match columns.iter().position(|c| c.name() == name) {
+                Some(index) => target.push(index),
+                None => bail!("Column \"{}\" of table \"{}\" does not exist", name, table),
+            }
+        }
+
+        target.sort();
+
+        let key = match target.is_empty() {
+            true => None,
+            false => {
+                let key = constraints.keys().iter().position(|key| {
+                    let mut columns = key.columns().to_vec();
+                    columns.sort();
+                    columns == target
+                });
+
+                match key {
+                    Some(key) => Some(key),
+                    None => bail!(
+                        "There is no unique constraint matching the ON CONFLICT specification"
+                    ),
+                }
+            }
+        };
+
+        if key.is_none() && matches!(on_conflict.action, ConflictAction::Update { .. }) {
+            bail!("ON CONFLICT DO UPDATE requires a conflict target");
+        }
+
+        let action = match on_conflict.action {
+            ConflictAction::Nothing => Action::Nothing,
+            ConflictAction::Update {
+                assignments,
+                mut r#where,
+            } => {
+                names.extend(columns.iter().map(|c| format!("excluded.{}", c.name())));
+                types.extend_from_within(..);
+
+                let checker = Checker::new(&names, &types);
+
+                if let Some(r#where) = &mut r#where {
+                    subquery::bind_condition(db, r#where, &names, None)?;
+
+                    if subquery::contains_subquery(r#where) {
+                        bail!("Correlated subqueries are not supported in ON CONFLICT");
+                    }
+
+                    checker.check_condition(r#where)?;
+                }
+
+                let mut bound = Vec::with_capacity(assignments.len());
+
+                for mut assignment in assignments {
+                    let Some(index) = columns.iter().position(|c| c.name() == assignment.field)
+                    else {
+                        bail!("Column \"{}\" does not exist", assignment.field);
+                    };
+
+                    subquery::bind_expression(db, &mut assignment.value, &names, None)?;
+
+                    if subquery::expression_contains_subquery(&assignment.value) {
+                        bail!("Correlated subqueries are not supported in ON CONFLICT");
+                    }
+
+                    let kind = columns[index].kind();
+                    let r#type = checker.check(&assignment.value)?;
+
+                    if !r#type.assignable_to(kind) {
+                        bail!(
+                            "Column \"{}\" is of type {} but the expression is of type {}",
+                            assignment.field,
+                            kind,
+                            r#type
+                        );
+                    }
+
+                    bound.push((index, assignment.value));
+                }
+
+                Action::Update {
+                    names,
+                    assignments: bound,
+                    r#where,
+                }
+            }
+        };
+
+        Ok(Self {
+            key,
+            action,
+            changed: HashSet::new(),
+        })
+    }
+
+    /// Inserts the row, or takes the action when it conflicts with a row of
+    /// the table or one inserted before it. Returns the row written, if any.
+    pub fn insert(
+        &mut self,
+        constraints: &mut Constraints,
+        table: &mut Table,
+        row: Row,
+        changes: &mut Vec<Change>,
+    ) -> Result<Option<Row>> {
+        let conflict = match self.key {
+            Some(key) => constraints.conflict(key, row.values()),
+            None => (0..constraints.keys().len())
+                .find_map(|key| constraints.conflict(key, row.values())),
+        };
+
+        let Some(id) = conflict else {
+            let (id, row) = constraints.insert(table, row, changes)?;
+            self.changed.insert(id);
+            return Ok(Some(row));
+        };
+
+        let Action::Update {
+            names,
+            assignments,
+            r#where,
+        } = &self.action
+        else {
+            return Ok(None);
+        };
+
+        if self.changed.contains(&id) {
+            bail!("ON CONFLICT DO UPDATE command cannot affect row a second time");
+        }
+
+        let existing = table.get(id)?;
+        let mut values = existing.values().to_vec();
+        values.extend(row.into_values());
+
+        if let Some(r#where) = r#where {
+            if !expression::matches(r#where, names, &values)? {
+                return Ok(None);
+            }
+        }
+
+        // Every expression sees the values of the row before the update
+        let mut updated = existing.values().to_vec();
+
+        for (index, value) in assignments.iter() {
+            updated[*index] = expression::evaluate(value, names, &values)?;
+        }
+
+        let updated = Row::new(updated);
+        let moved = constraints.update(table, id, existing, &updated, changes)?;
+        self.changed.insert(moved);
+
+        Ok(Some(updated))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::database::testing::TempDatabase;
+    use crate::database::Database;
+    use crate::executor::{execute_error, execute_sql, query_rows};
+
+    fn items(db: &mut Database) -> Vec<String> {
+        query_rows(db, "SELECT id, name, qty FROM items ORDER BY id")
+    }
+
+    #[test]
+    fn test_on_conflict() {
+        let dir = TempDatabase::new("upsert");
+        let mut db = dir.open();
+        execute_sql(
+            &mut db,
+            "CREATE TABLE items (id INT PRIMARY KEY, name TEXT UNIQUE, qty INT CHECK (qty >= 0))",
+        )
+        .unwrap();
+        execute_sql(
+            &mut db,
+            "INSERT INTO items VALUES (1, \"a\", 1), (2, \"b\", 2)",
+        )
+        .unwrap();
+
+        // Without a target any unique constraint is a conflict
+        execute_sql(
+            &mut db,
+            "INSERT INTO items VALUES (1, \"x\", 5), (9, \"b\", 5), (3, \"c\", 3) ON CONFLICT DO NOTHING",
+        )
+        .unwrap();
+        assert_eq!(items(&mut db), ["1 a 1", "2 b 2", "3 c 3"]);
+
+        execute_sql(
+            &mut db,
+            "INSERT INTO items VALUES (1, \"z\", 10), (4, \"d\", 4) ON CONFLICT (id) \
+             DO UPDATE SET qty = items.qty + excluded.qty WHERE excluded.name <> \"skip\"",
+        )
+        .unwrap();
+        assert_eq!(items(&mut db), ["1 a 11", "2 b 2", "3 c 3", "4 d 4"]);
+
+        execute_sql(
+            &mut db,
+            "INSERT INTO items VALUES (2, \"skip\", 1) ON CONFLICT (id) \
+             DO UPDATE SET qty = excluded.qty WHERE excluded.name <> \"skip\"",
+        )
+        .unwrap();
+        assert_eq!(items(&mut db), ["1 a 11", "2 b 2", "3 c 3", "4 d 4"]);
+
+        assert_eq!(
+            execute_error(
+                &mut db,
+                "INSERT INTO items VALUES (5, \"e\", 5), (5, \"e\", 6) ON CONFLICT (id) DO UPDATE SET qty = 0",
+            ),
+            "ON CONFLICT DO UPDATE command cannot affect row a second time"
+        );
+        assert_eq!(items(&mut db), ["1 a 11", "2 b 2", "3 c 3", "4 d 4"]);
+    }
+
+    #[test]
+    fn test_on_conflict_rollback() {
+        let dir = TempDatabase::new("upsert-rollback");
+        let mut db = dir.open();
+        execute_sql(
+            &mut db,
+            "CREATE TABLE items (id INT PRIMARY KEY, name TEXT UNIQUE, qty INT CHECK (qty >= 0))",
+        )
+        .unwrap();
+        execute_sql(
+            &mut db,
+            "INSERT INTO items VALUES (1, \"a\", 1), (2, \"b\", 2)",
+        )
+        .unwrap();
+
+        // The rows inserted and updated before the failing update are reverted
+        assert_eq!(
+            execute_error(
+                &mut db,
+                "INSERT INTO items VALUES (5, \"e\", 5), (2, \"b\", 7), (1, \"a\", -1) \
+                 ON CONFLICT (id) DO UPDATE SET qty = excluded.qty",
+            ),
+            "New row for relation \"items\" violates check constraint \"items_qty_check\": Failing row contains (1, a, -1)"
+        );
+        assert_eq!(items(&mut db), ["1 a 1", "2 b 2"]);
+
+        assert_eq!(
+            execute_error(
+                &mut db,
+                "INSERT INTO items VALUES (6, \"f\", 6), (1, \"a\", 0) ON CONFLICT (id) DO UPDATE SET name = \"b\"",
+            ),
+            "Duplicate key value violates unique constraint \"items_name_key\": Key (name)=(b) already exists"
+        );
+        assert_eq!(items(&mut db), ["1 a 1", "2 b 2"]);
+    }
+}
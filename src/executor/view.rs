@@ -0,0 +1,204 @@
+use anyhow::{anyhow, bail, Result};
+
+use super::{run, subquery};
+use crate::database::Database;
+use crate::schema::View;
+use crate::sql::parser::Parser;
+use crate::sql::tokenizer::Tokenizer;
+use crate::sql::{CreateViewStatement, Distinct, Query, SetExpression, TableReference, TableSource};
+
+/// Creates a view. Its columns are named after the selections of its first
+/// SELECT, the query itself only runs when the view is read.
+pub fn create(db: &mut Database, statement: CreateViewStatement) -> Result<()> {
+    if db.schema().get_table_schema(&statement.name).is_some() {
+        bail!("Table \"{}\" already exists", statement.name);
+    }
+
+    if db.schema().get_view(&statement.name).is_some() {
+        bail!("View \"{}\" already exists", statement.name);
+    }
+
+    let mut columns = columns(db, &statement.query)?;
+
+    if statement.columns.len() > columns.len() {
+        bail!("CREATE VIEW specifies more column names than columns");
+    }
+
+    for (column, name) in columns.iter_mut().zip(statement.columns) {
+        *column = name;
+    }
+
+    for (index, column) in columns.iter().enumerate() {
+        if columns[..index].contains(column) {
+            bail!("Column \"{}\" specified more than once", column);
+        }
+    }
+
+    db.create_view(
+        &statement.name,
+        View {
+            columns,
+            query: statement.sql,
+        },
+    )
+}
+
+/// Names of the columns of a query, taken from its first SELECT. The tables
+/// it reads from must exist unless they may be common table expressions.
+fn columns(db: &mut Database, query: &Query) -> Result<Vec<String>> {
+    let mut body = &query.body;
+
+    loop {
+        match body {
+            SetExpression::Select(statement) => {
+                if query.with.is_empty() {
+                    subquery::from_columns(db, statement)?;
+                }
+
+                return subquery::output_columns(db, statement);
+            }
+            SetExpression::Operation { left, .. } => body = left,
+        }
+    }
+}
+
+/// Replaces a view read in FROM by its query. A single SELECT becomes a
+/// derived table, with its selections renamed after the columns of the view,
+/// so the outer query decides which rows and columns are computed. Other
+/// queries, and those whose ORDER BY or DISTINCT ON could refer to a renamed
+/// selection, run first like a materialized common table expression.
+pub fn expand(db: &mut Database, table: &mut TableReference) -> Result<()> {
+    let TableSource::Table(name) = &table.source else {
+        return Ok(());
+    };
+
+    let Some(view) = db.schema().get_view(name).cloned() else {
+        return Ok(());
+    };
+
+    let name = name.clone();
+    let query = Parser::new(Tokenizer::new(&view.query).parse()?)
+        .parse_query()
+        .map_err(|err| anyhow!("{}", err))?;
+
+    table.source = match query {
+        Query {
+            with,
+            body: SetExpression::Select(mut statement),
+            order_by,
+            pagination: None,
+            ..
+        } if with.is_empty() && order_by.is_empty() => {
+            statement.selections = subquery::expand_selections(db, &statement)?;
+
+            let renamed = statement
+                .selections
+                .iter()
+                .zip(view.columns.iter())
+                .any(|(selection, column)| selection.name() != *column);
+            let aliased = !statement.order_by.is_empty()
+                || statement.having.is_some()
+                || matches!(statement.distinct, Some(Distinct::On(_)));
+
+            if renamed && aliased {
+                let query = Query {
+                    recursive: false,
+                    with,
+                    body: SetExpression::Select(statement),
+                    order_by,
+                    pagination: None,
+                };
+
+                materialize(db, query, view.columns)?
+            } else {
+                for (selection, column) in statement.selections.iter_mut().zip(view.columns) {
+                    selection.alias = Some(column);
+                }
+
+                TableSource::Subquery(statement)
+            }
+        }
+        query => materialize(db, query, view.columns)?,
+    };
+
+    table.alias.get_or_insert(name);
+
+    Ok(())
+}
+
+fn materialize(db: &mut Database, query: Query, columns: Vec<String>) -> Result<TableSource> {
+    let (result, _) = run(db, query)?;
+
+    Ok(TableSource::Rows {
+        columns,
+        rows: result.rows,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::database::testing::TempDatabase;
+    use crate::executor::{execute_error, execute_sql, query_rows};
+
+    #[test]
+    fn test_views() {
+        let dir = TempDatabase::new("views");
+        let mut db = dir.open();
+        execute_sql(&mut db, "CREATE TABLE users (id int, age int)").unwrap();
+        execute_sql(&mut db, "INSERT INTO users VALUES (1, 30), (2, 12)").unwrap();
+        execute_sql(
+            &mut db,
+            "CREATE VIEW adults (user_id) AS SELECT id FROM users WHERE age >= 18",
+        )
+        .unwrap();
+
+        let query = "SELECT a.user_id FROM adults a";
+        let result = execute_sql(&mut db, query).unwrap().unwrap();
+        assert_eq!(result.columns, ["user_id"]);
+        assert_eq!(result.rows.len(), 1);
+
+        // The query of the view runs again on every read
+        execute_sql(&mut db, "INSERT INTO users VALUES (3, 40)").unwrap();
+        let result = execute_sql(&mut db, query).unwrap().unwrap();
+        assert_eq!(result.rows.len(), 2);
+
+        assert_eq!(
+            execute_error(&mut db, "DELETE FROM adults"),
+            "\"adults\" is a view, not a table"
+        );
+        assert_eq!(
+            execute_error(&mut db, "CREATE TABLE adults (id int)"),
+            "View \"adults\" already exists"
+        );
+
+        // Views are kept in the schema
+        drop(db);
+        let db = dir.open();
+        assert_eq!(
+            db.schema().get_view("adults").unwrap().query,
+            "SELECT id FROM users WHERE age >= 18"
+        );
+    }
+
+    #[test]
+    fn test_view_is_expanded_in_query() {
+        let dir = TempDatabase::new("views-expanded");
+        let mut db = dir.open();
+        execute_sql(&mut db, "CREATE SEQUENCE s").unwrap();
+        execute_sql(&mut db, "CREATE TABLE t (name TEXT)").unwrap();
+        execute_sql(&mut db, "INSERT INTO t VALUES (\"a\"), (\"b\"), (\"c\")").unwrap();
+
+        // Neither creating the view nor reading other columns moves the sequence
+        execute_sql(&mut db, "CREATE VIEW v (n) AS SELECT nextval(\"s\"), name FROM t").unwrap();
+        assert_eq!(query_rows(&mut db, "SELECT name FROM v"), ["a", "b", "c"]);
+
+        let result = execute_sql(&mut db, "SELECT * FROM v LIMIT 2").unwrap().unwrap();
+        assert_eq!(result.columns, ["n", "name"]);
+        assert_eq!(query_rows(&mut db, "SELECT n FROM v LIMIT 1 OFFSET 1"), ["4"]);
+
+        assert_eq!(
+            execute_error(&mut db, "CREATE VIEW w AS SELECT id FROM missing"),
+            "Table \"missing\" does not exist"
+        );
+    }
+}
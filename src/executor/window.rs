@@ -0,0 +1,400 @@
+use std::cmp::Ordering;
+
+use anyhow::Result;
+
+use super::aggregate::Accumulator;
+use super::expression;
+use super::sort::{self, SortKey};
+use crate::sql::{FrameBound, Value, WindowCall, WindowFunction};
+
+/// Computes the window function calls for `rows`, appending one value per
+/// call to every row. The rows keep their order, each call sorts a list of
+/// row positions by its PARTITION BY and ORDER BY values and walks the
+/// partitions in that order.
+pub fn compute(
+    mut rows: Vec<Vec<Value>>,
+    names: &[String],
+    calls: &[WindowCall],
+) -> Result<Vec<Vec<Value>>> {
+    let mut results = Vec::with_capacity(calls.len());
+
+    for call in calls {
+        results.push(values(&rows, names, call)?);
+    }
+
+    for (index, row) in rows.iter_mut().enumerate() {
+        for values in results.iter_mut() {
+            row.push(std::mem::replace(&mut values[index], Value::Null));
+        }
+    }
+
+    Ok(rows)
+}
+
+/// Values of one call, in the order of `rows`.
+fn values(rows: &[Vec<Value>], names: &[String], call: &WindowCall) -> Result<Vec<Value>> {
+    let window = &call.window;
+
+    // Partitions only need equal values together, any order will do
+    let partition_len = window.partition_by.len();
+    let keys = window
+        .partition_by
+        .iter()
+        .map(|_| SortKey {
+            descending: false,
+            nulls_first: false,
+        })
+        .chain(window.order_by.iter().map(SortKey::of))
+        .collect::<Vec<_>>();
+
+    let sort_rows = rows
+        .iter()
+        .map(|row| {
+            window
+                .partition_by
+                .iter()
+                .chain(window.order_by.iter().map(|order_by| &order_by.expression))
+                .map(|expression| expression::evaluate(expression, names, row))
+                .collect::<Result<Vec<_>>>()
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    // Stable, so rows ordered equal keep their input order
+    let mut positions = (0..rows.len()).collect::<Vec<_>>();
+    positions.sort_by(|a, b| sort::compare_rows(&keys, &sort_rows[*a], &sort_rows[*b]));
+
+    let mut values = vec![Value::Null; rows.len()];
+    let mut start = 0;
+
+    while start < positions.len() {
+        let first = &sort_rows[positions[start]];
+        let len = positions[start..]
+            .iter()
+            .position(|position| {
+                sort::compare_rows(&keys[..partition_len], first, &sort_rows[*position])
+                    != Ordering::Equal
+            })
+            .unwrap_or(positions.len() - start);
+
+        let partition = Partition {
+            rows,
+            names,
+            positions: &positions[start..start + len],
+            peers: peer_groups(
+                &keys[partition_len..],
+                &positions[start..start + len],
+                |position| &sort_rows[position][partition_len..],
+            ),
+        };
+
+        for (position, value) in partition.positions.iter().zip(partition.values(call)?) {
+            values[*position] = value;
+        }
+
+        start += len;
+    }
+
+    Ok(values)
+}
+
+/// For each row of a sorted partition, the index of the first and one past
+/// the last row ordered equal to it.
+fn peer_groups<'r>(
+    keys: &[SortKey],
+    positions: &[usize],
+    order: impl Fn(usize) -> &'r [Value],
+) -> Vec<(usize, usize)> {
+    let mut peers = Vec::with_capacity(positions.len());
+    let mut start = 0;
+
+    for index in 1..=positions.len() {
+        let last = index == positions.len()
+            || sort::compare_rows(keys, order(positions[start]), order(positions[index]))
+                != Ordering::Equal;
+
+        if last {
+            peers.extend((start..index).map(|_| (start, index)));
+            start = index;
+        }
+    }
+
+    peers
+}
+
+/// Rows of one partition, in window order.
+struct Partition<'p> {
+    rows: &'p [Vec<Value>],
+    names: &'p [String],
+    positions: &'p [usize],
+    peers: Vec<(usize, usize)>,
+}
+
+impl Partition<'_> {
+    fn row(&self, index: usize) -> &[Value] {
+        &self.rows[self.positions[index]]
+    }
+
+    fn values(&self, call: &WindowCall) -> Result<Vec<Value>> {
+        let len = self.positions.len();
+
+        match call.function {
+            WindowFunction::RowNumber => Ok((1..=len as i64).map(Value::Int).collect()),
+            WindowFunction::Rank => Ok(self
+                .peers
+                .iter()
+                .map(|(start, _)| Value::Int(*start as i64 + 1))
+                .collect()),
+            WindowFunction::DenseRank => {
+                let mut rank = 0;
+
+                Ok(self
+                    .peers
+                    .iter()
+                    .enumerate()
+                    .map(|(index, (start, _))| {
+                        if index == *start {
+                            rank += 1;
+                        }
+
+                        Value::Int(rank)
+                    })
+                    .collect())
+            }
+            WindowFunction::Lag | WindowFunction::Lead => {
+                (0..len).map(|index| self.shifted(call, index)).collect()
+            }
+            WindowFunction::Aggregate(function) => {
+                let inputs = (0..len)
+                    .map(|index| {
+                        call.arguments
+                            .first()
+                            .map(|argument| {
+                                expression::evaluate(argument, self.names, self.row(index))
+                            })
+                            .transpose()
+                    })
+                    .collect::<Result<Vec<_>>>()?;
+
+                let aggregate = |range: std::ops::Range<usize>| {
+                    let mut accumulator = Accumulator::new(function);
+
+                    for input in inputs[range].iter() {
+                        accumulator.update(input.clone())?;
+                    }
+
+                    accumulator.finish()
+                };
+
+                match call.window.frame {
+                    Some(frame) => (0..len)
+                        .map(|index| {
+                            let start = bound(frame.start, index, len).max(0) as usize;
+                            let end = (bound(frame.end, index, len) + 1).min(len as i64);
+
+                            match end > start as i64 {
+                                true => aggregate(start..end as usize),
+                                false => aggregate(0..0),
+                            }
+                        })
+                        .collect(),
+                    None if call.window.order_by.is_empty() => {
+                        let value = aggregate(0..len)?;
+                        Ok(vec![value; len])
+                    }
+                    // Running aggregate up to the last peer of each row
+                    None => {
+                        let mut accumulator = Accumulator::new(function);
+                        let mut values = Vec::with_capacity(len);
+                        let mut start = 0;
+
+                        while start < len {
+                            let end = self.peers[start].1;
+
+                            for input in inputs[start..end].iter() {
+                                accumulator.update(input.clone())?;
+                            }
+
+                            let value = accumulator.clone().finish()?;
+                            values.extend((start..end).map(|_| value.clone()));
+                            start = end;
+                        }
+
+                        Ok(values)
+                    }
+                }
+            }
+        }
+    }
+
+    /// LAG and LEAD, the default is used past the ends of the partition.
+    fn shifted(&self, call: &WindowCall, index: usize) -> Result<Value> {
+        let row = self.row(index);
+
+        let offset = match call.arguments.get(1) {
+            Some(offset) => match expression::evaluate(offset, self.names, row)? {
+                Value::Int(offset) => offset,
+                _ => return Ok(Value::Null),
+            },
+            None => 1,
+        };
+
+        let target = match call.function {
+            WindowFunction::Lag => (index as i64).checked_sub(offset),
+            _ => (index as i64).checked_add(offset),
+        };
+
+        match target.filter(|target| (0..self.positions.len() as i64).contains(target)) {
+            Some(target) => {
+                expression::evaluate(&call.arguments[0], self.names, self.row(target as usize))
+            }
+            None => match call.arguments.get(2) {
+                Some(default) => expression::evaluate(default, self.names, row),
+                None => Ok(Value::Null),
+            },
+        }
+    }
+}
+
+/// Index of a frame bound for the row at `index`, may lie outside the partition.
+fn bound(bound: FrameBound, index: usize, len: usize) -> i64 {
+    let index = index as i64;
+
+    match bound {
+        FrameBound::UnboundedPreceding => 0,
+        FrameBound::Preceding(offset) => index - offset as i64,
+        FrameBound::CurrentRow => index,
+        FrameBound::Following(offset) => index + offset as i64,
+        FrameBound::UnboundedFollowing => len as i64 - 1,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sql::{AggregateFunction, Direction, Expression, OrderBy, Window, WindowFrame};
+
+    fn names() -> Vec<String> {
+        vec![String::from("t.dept"), String::from("t.salary")]
+    }
+
+    fn rows() -> Vec<Vec<Value>> {
+        [(1, 10), (2, 5), (1, 20), (1, 20), (2, 7)]
+            .into_iter()
+            .map(|(dept, salary)| vec![Value::Int(dept), Value::Int(salary)])
+            .collect()
+    }
+
+    fn call(
+        function: WindowFunction,
+        arguments: Vec<Expression>,
+        frame: Option<WindowFrame>,
+    ) -> WindowCall {
+        WindowCall {
+            function,
+            arguments,
+            window: Window {
+                partition_by: vec![Expression::Column(String::from("dept"))],
+                order_by: vec![OrderBy {
+                    expression: Expression::Column(String::from("salary")),
+                    direction: Direction::Ascending,
+                    nulls: None,
+                }],
+                frame,
+            },
+        }
+    }
+
+    fn computed(call: WindowCall) -> Vec<Value> {
+        compute(rows(), &names(), &[call])
+            .unwrap()
+            .into_iter()
+            .map(|mut row| row.pop().unwrap())
+            .collect()
+    }
+
+    fn ints(values: &[i64]) -> Vec<Value> {
+        values.iter().copied().map(Value::Int).collect()
+    }
+
+    #[test]
+    fn test_ranks() {
+        assert_eq!(
+            computed(call(WindowFunction::RowNumber, Vec::new(), None)),
+            ints(&[1, 1, 2, 3, 2])
+        );
+        assert_eq!(
+            computed(call(WindowFunction::Rank, Vec::new(), None)),
+            ints(&[1, 1, 2, 2, 2])
+        );
+        assert_eq!(
+            computed(call(WindowFunction::DenseRank, Vec::new(), None)),
+            ints(&[1, 1, 2, 2, 2])
+        );
+    }
+
+    #[test]
+    fn test_lag_lead() {
+        let salary = Expression::Column(String::from("salary"));
+
+        assert_eq!(
+            computed(call(WindowFunction::Lag, vec![salary.clone()], None)),
+            vec![
+                Value::Null,
+                Value::Null,
+                Value::Int(10),
+                Value::Int(20),
+                Value::Int(5)
+            ]
+        );
+        assert_eq!(
+            computed(call(
+                WindowFunction::Lead,
+                vec![
+                    salary,
+                    Expression::Value(Value::Int(2)),
+                    Expression::Value(Value::Int(0))
+                ],
+                None
+            )),
+            ints(&[20, 0, 0, 0, 0])
+        );
+    }
+
+    #[test]
+    fn test_aggregate_frames() {
+        let sum = WindowFunction::Aggregate(AggregateFunction::Sum);
+        let salary = vec![Expression::Column(String::from("salary"))];
+
+        // Peers are included in the running sum
+        assert_eq!(
+            computed(call(sum, salary.clone(), None)),
+            ints(&[10, 5, 50, 50, 12])
+        );
+
+        let frame = WindowFrame {
+            start: FrameBound::Preceding(1),
+            end: FrameBound::CurrentRow,
+        };
+
+        assert_eq!(
+            computed(call(sum, salary.clone(), Some(frame))),
+            ints(&[10, 5, 30, 40, 12])
+        );
+
+        let frame = WindowFrame {
+            start: FrameBound::Following(1),
+            end: FrameBound::UnboundedFollowing,
+        };
+
+        assert_eq!(
+            computed(call(sum, salary, Some(frame))),
+            vec![
+                Value::Int(40),
+                Value::Int(7),
+                Value::Int(20),
+                Value::Null,
+                Value::Null
+            ]
+        );
+    }
+}
@@ -0,0 +1,152 @@
+use crate::pager::{Page, Pager};
+
+/// Keeps one byte per page of a table file describing how much room the page
+/// has left, so inserts can find a page without reading every page.
+///
+/// The map is stored in a chain of pages referenced by the table header.
+pub struct FreeSpaceMap {
+    entries: Vec<u8>,
+    pages: Vec<usize>,
+}
+
+impl FreeSpaceMap {
+    // Entry of a page that is not used at all and can be allocated for anything
+    const FREE: u8 = u8::MAX;
+    // Each step of an entry stands for this many bytes of free space
+    const GRANULARITY: usize = 16;
+
+    // Map pages: kind (1), next page (4), entries
+    const HEADER_SIZE: usize = 5;
+    const ENTRIES_PER_PAGE: usize = Page::PAGE_SIZE - Self::HEADER_SIZE;
+    const END_OF_CHAIN: u32 = 0;
+
+    /// Reads the map starting at `first_page`, page 0 means there is no map yet.
+    pub fn load(pager: &mut Pager, first_page: u32) -> Self {
+        let mut entries = Vec::new();
+        let mut pages = Vec::new();
+        let mut page_index = first_page;
+
+        while page_index != Self::END_OF_CHAIN {
+            let page = pager.get_page(page_index as usize);
+            let page = page.lock().unwrap();
+
+            entries.extend_from_slice(&page[Self::HEADER_SIZE..]);
+            pages.push(page_index as usize);
+            page_index = page.read_u32(1);
+        }
+
+        entries.resize(pager.page_count(), 0);
+
+        for (page_index, entry) in entries.iter().enumerate() {
+            if *entry == Self::FREE {
+                pager.free_page(page_index);
+            }
+        }
+
+        Self { entries, pages }
+    }
+
+    /// Writes the map back and returns its first page.
+    pub fn save(&mut self, pager: &mut Pager) -> u32 {
+        for entry in self.entries.iter_mut() {
+            if *entry == Self::FREE {
+                *entry = 0;
+            }
+        }
+
+        // Pages may have been allocated or freed by the pager, e.g. for overflow chains
+        self.entries.resize(pager.page_count(), 0);
+
+        for page_index in pager.free_pages() {
+            self.entries[*page_index] = Self::FREE;
+        }
+
+        while self.pages.len() * Self::ENTRIES_PER_PAGE < self.entries.len() {
+            let page_index = pager.allocate_page();
+            self.pages.push(page_index);
+            self.entries.resize(pager.page_count(), 0);
+            self.entries[page_index] = 0;
+        }
+
+        for (i, page_index) in self.pages.iter().enumerate() {
+            let page = pager.get_page(*page_index);
+            let mut page = page.lock().unwrap();
+
+            let next = self
+                .pages
+                .get(i + 1)
+                .map(|&next| next as u32)
+                .unwrap_or(Self::END_OF_CHAIN);
+
+            let start = (i * Self::ENTRIES_PER_PAGE).min(self.entries.len());
+            let end = ((i + 1) * Self::ENTRIES_PER_PAGE).min(self.entries.len());
+            let entries = &self.entries[start..end];
+
+            page.fill(0);
+            page[0] = Page::KIND_FREE_SPACE_MAP;
+            page.write_u32(1, next);
+            page[Self::HEADER_SIZE..Self::HEADER_SIZE + entries.len()].copy_from_slice(entries);
+        }
+
+        self.pages
+            .first()
+            .map(|&page| page as u32)
+            .unwrap_or(Self::END_OF_CHAIN)
+    }
+
+    /// Records the free space of a data page.
+    pub fn set(&mut self, page_index: usize, free_space: usize) {
+        if self.entries.len() <= page_index {
+            self.entries.resize(page_index + 1, 0);
+        }
+
+        self.entries[page_index] =
+            (free_space / Self::GRANULARITY).min(Self::FREE as usize - 1) as u8;
+    }
+
+    /// Finds a data page with at least `required` bytes of free space.
+    pub fn find(&self, required: usize) -> Option<usize> {
+        self.entries.iter().position(|&entry| {
+            entry != Self::FREE && entry as usize * Self::GRANULARITY >= required
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::testing::TempDatabase;
+
+    #[test]
+    fn test_free_space_map_roundtrip() {
+        let dir = TempDatabase::new("fsm");
+        let path = dir.path("table.db");
+        let mut pager = Pager::new(&path);
+
+        for _ in 0..5 {
+            pager.allocate_page();
+        }
+
+        let mut map = FreeSpaceMap::load(&mut pager, 0);
+        map.set(1, 100);
+        map.set(2, 2000);
+        pager.free_page(3);
+        pager.free_page(4);
+
+        assert_eq!(map.find(90), Some(1));
+        assert_eq!(map.find(1000), Some(2));
+        assert_eq!(map.find(3000), None);
+
+        let first_page = map.save(&mut pager);
+        pager.flush();
+        drop(pager);
+
+        let mut pager = Pager::new(&path);
+        let map = FreeSpaceMap::load(&mut pager, first_page);
+
+        assert_eq!(map.find(1000), Some(2));
+        // One of the freed pages now holds the map itself
+        assert_eq!(pager.free_pages().len(), 1);
+        assert_eq!(pager.page_count(), 5);
+    }
+}
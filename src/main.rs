@@ -1,15 +1,19 @@
+mod database;
+mod executor;
+mod free_space;
 mod pager;
 mod schema;
 mod table;
 mod sql;
+mod types;
 
 use std::{io::{self, Write}, path::PathBuf};
 
 use clap::Parser;
 
-use sql::{Statement, tokenizer::Tokenizer};
-use schema::{DatabaseSchema, TableSchema};
-use table::{Row, Table};
+use database::Database;
+use sql::tokenizer::Tokenizer;
+use schema::TableSchema;
 
 #[derive(Debug, Parser)]
 #[command(author, version, about, long_about = None)]
@@ -20,11 +24,8 @@ struct Args {
 
 fn main() {
     let args = Args::parse();
-    let schema_file = args.data_dir.join("./schema.json");
 
-    let mut table = Table::new();
-    let mut db_schema =
-        DatabaseSchema::load(&schema_file).expect("Failed to load database schema");
+    let mut db = Database::open(&args.data_dir).expect("Failed to load database schema");
 
     loop {
         let mut buffer = String::new();
@@ -36,16 +37,21 @@ fn main() {
         match buffer.trim() {
             special_cmd if special_cmd.starts_with('.') => match special_cmd {
                 ".exit" => {
-                    table.flush();
-                    std::mem::drop(table);
+                    db.flush();
+                    std::mem::drop(db);
                     std::process::exit(0);
                 },
 
                 ".list" => {
-                    let tables = db_schema.list_tables();
+                    let tables = db.schema().list_tables();
                     for table in tables {
                         println!("{}", table);
                     }
+
+                    let views = db.schema().list_views();
+                    for view in views {
+                        println!("{} (view)", view);
+                    }
                 },
 
                 cmd if cmd.starts_with(".create") => {
@@ -55,20 +61,26 @@ fn main() {
                     let table_name = args.get(1).expect("Argument for table name was not provided");
                     let path = args.get(2).expect("Argument for path was not provided");
 
-                    let schema = TableSchema::load(path).expect("Failed to load table schema");
-                    db_schema.add_table(table_name, schema);
-                    db_schema.save(&schema_file).expect("Failed to save schema");
+                    let created = TableSchema::load(path).and_then(|schema| {
+                        executor::create_table_from_schema(&mut db, table_name, schema)
+                    });
+
+                    if let Err(err) = created {
+                        eprintln!("{}", err);
+                    }
                 },
 
                 cmd if cmd.starts_with(".table") => {
                     let args = cmd.split(' ').collect::<Vec<&str>>();
                     let table_name = args.get(1).expect("Argument for table name was not provided");
 
-                    let schema = db_schema.get_table_schema(table_name);
+                    let schema = db.schema().get_table_schema(table_name);
+                    let view = db.schema().get_view(table_name);
 
-                    match schema {
-                        Some(schema) => { print!("{}", schema) },
-                        None => { eprintln!("Table \"{}\" does not exist!", &table_name)}
+                    match (schema, view) {
+                        (Some(schema), _) => { print!("{}", schema) },
+                        (None, Some(view)) => { print!("{}", view) },
+                        (None, None) => { eprintln!("Table \"{}\" does not exist!", &table_name)}
                     }
 
                 },
@@ -77,50 +89,30 @@ fn main() {
                 }
             },
             sql => {
-                execute_sql(sql, &mut table);
+                execute_sql(sql, &mut db);
             }
         }
     }
 }
 
-fn execute_sql(sql: &str, table: &mut Table) {
+fn execute_sql(sql: &str, db: &mut Database) {
     let tokenizer = Tokenizer::new(sql);
-    let tokens = tokenizer.parse();
+    let tokens = match tokenizer.parse() {
+        Ok(tokens) => tokens,
+        Err(err) => {
+            eprintln!("{}", err);
+            return;
+        }
+    };
     let parser = sql::parser::Parser::new(tokens);
     let statement = parser.parse();
 
     match statement {
-        Ok(Statement::Select(_)) => {
-            let row = table.get_row(0);
-
-            if let Some(row) = row {
-                println!("{}", row);
-            } else {
-                println!("No data");
-            }
-        }
-        Ok(Statement::Insert(stmt)) => {
-            let id = stmt.values.get(0);
-            let username = stmt.values.get(1);
-            let email = stmt.values.get(2);
-
-            use sql::Value;
-
-            match (id, username, email) {
-                (
-                    Some(Value::Int(id)),
-                    Some(Value::String(username)),
-                    Some(Value::String(email)),
-                ) => {
-                    let row = Row::new(*id, username, email);
-                    table.insert(row);
-                }
-                _ => {
-                    eprintln!("Invalid values");
-                }
-            };
-        }
-        Ok(_) => eprintln!("Not implemented"),
-        Err(_) => eprintln!("Invalid SQL"),
+        Ok(statement) => match executor::execute(db, statement) {
+            Ok(Some(result)) => print!("{}", result),
+            Ok(None) => {}
+            Err(err) => eprintln!("{}", err),
+        },
+        Err(err) => eprintln!("{}", err),
     }
 }
@@ -9,10 +9,219 @@ use std::{
     path::Path,
 };
 
+use anyhow::{bail, Result};
+
 pub struct Page(Box<[u8; Self::PAGE_SIZE]>);
 
 impl Page {
     pub const PAGE_SIZE: usize = 4096;
+
+    pub const KIND_FREE: u8 = 0;
+    pub const KIND_DATA: u8 = 1;
+    pub const KIND_OVERFLOW: u8 = 2;
+    pub const KIND_HEADER: u8 = 3;
+    pub const KIND_FREE_SPACE_MAP: u8 = 4;
+
+    // Data pages: kind (1), slot count (2), start of the cell area (2),
+    // followed by the slot directory growing towards the end of the page
+    // and the cell area growing towards the start.
+    const DATA_HEADER_SIZE: usize = 5;
+    // Each slot holds the offset (2) and length (2) of its cell, an offset
+    // of 0 marks a deleted cell whose slot can be reused
+    pub const SLOT_SIZE: usize = 4;
+
+    // Overflow pages: kind (1), next page (4), used bytes (2)
+    const OVERFLOW_HEADER_SIZE: usize = 7;
+    pub const OVERFLOW_CAPACITY: usize = Self::PAGE_SIZE - Self::OVERFLOW_HEADER_SIZE;
+
+    /// Largest cell that fits into an empty data page.
+    pub const MAX_CELL_SIZE: usize = Self::PAGE_SIZE - Self::DATA_HEADER_SIZE - Self::SLOT_SIZE;
+
+    fn zeroed() -> Self {
+        Self(Box::new([0; Self::PAGE_SIZE]))
+    }
+
+    pub fn kind(&self) -> u8 {
+        self[0]
+    }
+
+    pub fn read_u16(&self, offset: usize) -> u16 {
+        u16::from_be_bytes([self[offset], self[offset + 1]])
+    }
+
+    pub fn write_u16(&mut self, offset: usize, value: u16) {
+        self[offset..offset + 2].copy_from_slice(&value.to_be_bytes());
+    }
+
+    pub fn read_u32(&self, offset: usize) -> u32 {
+        let mut bytes = [0u8; 4];
+        bytes.copy_from_slice(&self[offset..offset + 4]);
+        u32::from_be_bytes(bytes)
+    }
+
+    pub fn write_u32(&mut self, offset: usize, value: u32) {
+        self[offset..offset + 4].copy_from_slice(&value.to_be_bytes());
+    }
+
+    pub fn read_i64(&self, offset: usize) -> i64 {
+        let mut bytes = [0u8; 8];
+        bytes.copy_from_slice(&self[offset..offset + 8]);
+        i64::from_be_bytes(bytes)
+    }
+
+    pub fn write_i64(&mut self, offset: usize, value: i64) {
+        self[offset..offset + 8].copy_from_slice(&value.to_be_bytes());
+    }
+
+    /// Turns the page into an empty slotted data page.
+    pub fn init_data(&mut self) {
+        self.0.fill(0);
+        self[0] = Self::KIND_DATA;
+        self.write_u16(1, 0);
+        self.write_u16(3, Self::PAGE_SIZE as u16);
+    }
+
+    /// Number of slots, including the ones of deleted cells.
+    pub fn slot_count(&self) -> usize {
+        self.read_u16(1).into()
+    }
+
+    fn cell_area_start(&self) -> usize {
+        self.read_u16(3).into()
+    }
+
+    fn directory_end(&self) -> usize {
+        Self::DATA_HEADER_SIZE + self.slot_count() * Self::SLOT_SIZE
+    }
+
+    fn slot(&self, slot: usize) -> (usize, usize) {
+        let entry = Self::DATA_HEADER_SIZE + slot * Self::SLOT_SIZE;
+        (self.read_u16(entry).into(), self.read_u16(entry + 2).into())
+    }
+
+    fn set_slot(&mut self, slot: usize, offset: usize, length: usize) {
+        let entry = Self::DATA_HEADER_SIZE + slot * Self::SLOT_SIZE;
+        self.write_u16(entry, offset as u16);
+        self.write_u16(entry + 2, length as u16);
+    }
+
+    /// Bytes available for new cells once the page is compacted.
+    pub fn free_space(&self) -> usize {
+        let used = (0..self.slot_count())
+            .map(|slot| self.slot(slot).1)
+            .sum::<usize>();
+
+        Self::PAGE_SIZE - self.directory_end() - used
+    }
+
+    /// Space a cell of `length` bytes needs, counting a new slot if no deleted one can be reused.
+    fn required_space(&self, length: usize) -> usize {
+        match self.free_slot() {
+            Some(_) => length,
+            None => length + Self::SLOT_SIZE,
+        }
+    }
+
+    fn free_slot(&self) -> Option<usize> {
+        (0..self.slot_count()).find(|&slot| self.slot(slot).0 == 0)
+    }
+
+    /// Returns the cell stored in `slot`, or `None` if the slot is empty or out of range.
+    pub fn cell(&self, slot: usize) -> Option<&[u8]> {
+        if slot >= self.slot_count() {
+            return None;
+        }
+
+        match self.slot(slot) {
+            (0, _) => None,
+            (offset, length) => Some(&self[offset..offset + length]),
+        }
+    }
+
+    /// Stores a cell and returns its slot, or `None` if the page is full.
+    pub fn insert_cell(&mut self, data: &[u8]) -> Option<usize> {
+        let required = self.required_space(data.len());
+
+        if self.free_space() < required {
+            return None;
+        }
+
+        // Compact first, a new slot must not overwrite the start of the cell area
+        if self.cell_area_start() - self.directory_end() < required {
+            self.compact();
+        }
+
+        let slot = match self.free_slot() {
+            Some(slot) => slot,
+            None => {
+                let slot = self.slot_count();
+                self.write_u16(1, (slot + 1) as u16);
+                self.set_slot(slot, 0, 0);
+                slot
+            }
+        };
+
+        self.write_cell(slot, data);
+        Some(slot)
+    }
+
+    /// Replaces the cell in `slot`, returns `false` if the new content does not fit.
+    pub fn update_cell(&mut self, slot: usize, data: &[u8]) -> bool {
+        let (_, length) = self.slot(slot);
+
+        if self.free_space() + length < data.len() {
+            return false;
+        }
+
+        self.set_slot(slot, 0, 0);
+        self.write_cell(slot, data);
+        true
+    }
+
+    pub fn delete_cell(&mut self, slot: usize) {
+        self.set_slot(slot, 0, 0);
+
+        // Trailing empty slots are dropped so the directory does not only grow
+        let mut count = self.slot_count();
+        while count > 0 && self.slot(count - 1).0 == 0 {
+            count -= 1;
+        }
+        self.write_u16(1, count as u16);
+
+        if count == 0 {
+            self.init_data();
+        }
+    }
+
+    fn write_cell(&mut self, slot: usize, data: &[u8]) {
+        if self.cell_area_start() - self.directory_end() < data.len() {
+            self.compact();
+        }
+
+        let offset = self.cell_area_start() - data.len();
+        self[offset..offset + data.len()].copy_from_slice(data);
+        self.set_slot(slot, offset, data.len());
+        self.write_u16(3, offset as u16);
+    }
+
+    /// Moves all cells to the end of the page, merging the gaps left by deleted cells.
+    fn compact(&mut self) {
+        let cells = (0..self.slot_count())
+            .map(|slot| self.cell(slot).map(Vec::from))
+            .collect::<Vec<_>>();
+
+        let mut offset = Self::PAGE_SIZE;
+
+        for (slot, cell) in cells.into_iter().enumerate() {
+            if let Some(cell) = cell {
+                offset -= cell.len();
+                self[offset..offset + cell.len()].copy_from_slice(&cell);
+                self.set_slot(slot, offset, cell.len());
+            }
+        }
+
+        self.write_u16(3, offset as u16);
+    }
 }
 
 impl Debug for Page {
@@ -35,13 +244,15 @@ impl DerefMut for Page {
     }
 }
 
-const MAX_PAGES: usize = 100;
 pub struct Pager {
     file: File,
-    pages: [Option<Arc<Mutex<Page>>>; MAX_PAGES],
+    pages: Vec<Option<Arc<Mutex<Page>>>>,
+    free_pages: Vec<usize>,
 }
 
 impl Pager {
+    const END_OF_CHAIN: u32 = u32::MAX;
+
     pub fn new<P>(filepath: P) -> Self
     where
         P: AsRef<Path>,
@@ -51,27 +262,35 @@ impl Pager {
         let file = match file {
             Ok(file) => file,
             Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
-                let mut file = File::create(&filepath).unwrap();
-
-                for _ in 0..MAX_PAGES {
-                    let buffer = [0; Page::PAGE_SIZE];
-                    file.write_all(&buffer).unwrap();
-                }
-
-                file
+                File::options()
+                    .read(true)
+                    .write(true)
+                    .create_new(true)
+                    .open(&filepath)
+                    .unwrap()
             }
             Err(_) => {
                 panic!("idk")
             }
         };
 
-        let pages = std::array::from_fn(|_| None);
+        let length = file.metadata().expect("Failed to read file metadata").len();
+        let page_count = length as usize / Page::PAGE_SIZE;
+        let pages = (0..page_count).map(|_| None).collect();
+
+        Self {
+            file,
+            pages,
+            free_pages: Vec::new(),
+        }
+    }
 
-        Self { file, pages }
+    pub fn page_count(&self) -> usize {
+        self.pages.len()
     }
 
     pub fn get_page(&mut self, page_index: usize) -> Arc<Mutex<Page>> {
-        if page_index > MAX_PAGES {
+        if page_index >= self.pages.len() {
             panic!("Index too large");
         }
 
@@ -100,8 +319,127 @@ impl Pager {
         page
     }
 
+    /// Returns the index of a zeroed page, reusing freed pages before growing the file.
+    pub fn allocate_page(&mut self) -> usize {
+        if let Some(page_index) = self.free_pages.pop() {
+            let page = Arc::new(Mutex::new(Page::zeroed()));
+            self.pages[page_index] = Some(page);
+            return page_index;
+        }
+
+        let page = Arc::new(Mutex::new(Page::zeroed()));
+        self.pages.push(Some(page));
+        self.pages.len() - 1
+    }
+
+    /// Marks a page as unused so that `allocate_page` can hand it out again.
+    pub fn free_page(&mut self, page_index: usize) {
+        let page = self.get_page(page_index);
+        let mut page = page.lock().unwrap();
+        page.0.fill(0);
+        page[0] = Page::KIND_FREE;
+        drop(page);
+
+        if !self.free_pages.contains(&page_index) {
+            self.free_pages.push(page_index);
+        }
+    }
+
+    pub fn free_pages(&self) -> &[usize] {
+        &self.free_pages
+    }
+
+    /// Stores `data` in a chain of overflow pages and returns the first page of the chain.
+    pub fn write_overflow(&mut self, data: &[u8]) -> u32 {
+        let chunks = data.chunks(Page::OVERFLOW_CAPACITY).collect::<Vec<_>>();
+        let indices = chunks
+            .iter()
+            .map(|_| self.allocate_page())
+            .collect::<Vec<_>>();
+
+        for (i, chunk) in chunks.iter().enumerate() {
+            let page = self.get_page(indices[i]);
+            let mut page = page.lock().unwrap();
+
+            let next = indices
+                .get(i + 1)
+                .map(|&index| index as u32)
+                .unwrap_or(Self::END_OF_CHAIN);
+
+            page[0] = Page::KIND_OVERFLOW;
+            page.write_u32(1, next);
+            page.write_u16(5, chunk.len() as u16);
+            page[Page::OVERFLOW_HEADER_SIZE..Page::OVERFLOW_HEADER_SIZE + chunk.len()]
+                .copy_from_slice(chunk);
+        }
+
+        indices[0] as u32
+    }
+
+    /// Returns the pages of the overflow chain starting at `first_page`.
+    /// Corrupt chains, such as links to pages that are not overflow pages or
+    /// cycles, are reported instead of being followed.
+    fn overflow_chain(&mut self, first_page: u32) -> Result<Vec<usize>> {
+        let mut chain = Vec::new();
+        let mut page_index = first_page;
+
+        while chain.len() < self.page_count() {
+            if page_index as usize >= self.page_count() {
+                bail!("Overflow page {} does not exist", page_index);
+            }
+
+            let page = self.get_page(page_index as usize);
+            let page = page.lock().unwrap();
+
+            if page.kind() != Page::KIND_OVERFLOW {
+                bail!("Page {} is not an overflow page", page_index);
+            }
+
+            let used: usize = page.read_u16(5).into();
+
+            if used > Page::OVERFLOW_CAPACITY {
+                bail!("Overflow page {} claims to hold {} bytes", page_index, used);
+            }
+
+            chain.push(page_index as usize);
+
+            match page.read_u32(1) {
+                Self::END_OF_CHAIN => return Ok(chain),
+                next => page_index = next,
+            }
+        }
+
+        bail!("Overflow chain starting at page {} does not end", first_page)
+    }
+
+    /// Reads a chain of overflow pages written by [`Pager::write_overflow`].
+    pub fn read_overflow(&mut self, first_page: u32) -> Result<Vec<u8>> {
+        let mut data = Vec::new();
+
+        for page_index in self.overflow_chain(first_page)? {
+            let page = self.get_page(page_index);
+            let page = page.lock().unwrap();
+
+            let used: usize = page.read_u16(5).into();
+            data.extend_from_slice(
+                &page[Page::OVERFLOW_HEADER_SIZE..Page::OVERFLOW_HEADER_SIZE + used],
+            );
+        }
+
+        Ok(data)
+    }
+
+    /// Frees every page of an overflow chain. Nothing is freed when the chain is corrupt.
+    pub fn free_overflow(&mut self, first_page: u32) -> Result<()> {
+        for page_index in self.overflow_chain(first_page)? {
+            self.free_page(page_index);
+        }
+
+        Ok(())
+    }
+
     pub fn flush(&mut self) {
-        for page_index in 0..MAX_PAGES {
+        for page_index in 0..self.pages.len() {
             let page = self.pages.get(page_index).and_then(|p| p.as_ref());
 
             match page {
@@ -3,12 +3,16 @@ use std::fs::{File, OpenOptions};
 use std::io::{Read, Write};
 use std::{collections::HashMap, path::Path};
 
-use anyhow::Result;
+use anyhow::{bail, Result};
 use serde::{Deserialize, Serialize};
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct DatabaseSchema {
     tables: HashMap<String, TableSchema>,
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    sequences: HashMap<String, Sequence>,
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    views: HashMap<String, View>,
 }
 
 impl DatabaseSchema {
@@ -29,7 +33,7 @@ impl DatabaseSchema {
     where
         P: AsRef<Path>,
     {
-        let mut file = OpenOptions::new().write(true).open(&path)?;
+        let mut file = OpenOptions::new().write(true).truncate(true).open(&path)?;
 
         let content = serde_json::to_string_pretty(self)?;
         file.write_all(content.as_bytes())?;
@@ -41,6 +45,10 @@ impl DatabaseSchema {
         self.tables.insert(String::from(name), schema);
     }
 
+    pub fn remove_table(&mut self, name: &str) {
+        self.tables.remove(name);
+    }
+
     pub fn get_table_schema(&self, name: &str) -> Option<&TableSchema> {
         self.tables.get(name)
     }
@@ -48,14 +56,282 @@ impl DatabaseSchema {
     pub fn list_tables(&self) -> Vec<String> {
         self.tables.keys().cloned().collect()
     }
+
+    pub fn add_sequence(&mut self, name: &str, sequence: Sequence) {
+        self.sequences.insert(String::from(name), sequence);
+    }
+
+    pub fn get_sequence(&self, name: &str) -> Option<&Sequence> {
+        self.sequences.get(name)
+    }
+
+    pub fn get_sequence_mut(&mut self, name: &str) -> Option<&mut Sequence> {
+        self.sequences.get_mut(name)
+    }
+
+    pub fn add_view(&mut self, name: &str, view: View) {
+        self.views.insert(String::from(name), view);
+    }
+
+    pub fn get_view(&self, name: &str) -> Option<&View> {
+        self.views.get(name)
+    }
+
+    pub fn list_views(&self) -> Vec<String> {
+        self.views.keys().cloned().collect()
+    }
+
+    /// Checks the foreign keys of a new table against the tables they
+    /// reference, which may be the new table itself. Foreign keys without
+    /// referenced columns reference the primary key.
+    pub fn resolve_references(&self, table: &str, schema: &mut TableSchema) -> Result<()> {
+        let own = schema.clone();
+
+        for constraint in schema.constraints.iter_mut() {
+            let ConstraintKind::ForeignKey {
+                columns,
+                table: parent,
+                references,
+                ..
+            } = &mut constraint.kind
+            else {
+                continue;
+            };
+
+            let referenced = match parent == table {
+                true => &own,
+                false => match self.tables.get(parent.as_str()) {
+                    Some(schema) => schema,
+                    None => bail!("Table \"{}\" does not exist", parent),
+                },
+            };
+
+            if references.is_empty() {
+                match referenced.primary_key() {
+                    Some(key) if key.len() == columns.len() => *references = key.to_vec(),
+                    Some(_) => bail!(
+                        "Number of referencing and referenced columns for foreign key disagree"
+                    ),
+                    None => bail!(
+                        "There is no primary key for referenced table \"{}\"",
+                        parent
+                    ),
+                }
+            }
+
+            let mut sorted = references.clone();
+            sorted.sort();
+
+            let unique = referenced.constraints.iter().any(|other| match &other.kind {
+                ConstraintKind::PrimaryKey { columns } | ConstraintKind::Unique { columns } => {
+                    let mut columns = columns.clone();
+                    columns.sort();
+                    columns == sorted
+                }
+                _ => false,
+            });
+
+            if !unique {
+                bail!(
+                    "There is no unique constraint matching given keys for referenced table \"{}\"",
+                    parent
+                );
+            }
+
+            for (name, reference) in columns.iter().zip(references.iter()) {
+                let kind = own.column(name).unwrap().kind();
+                let referenced_kind = referenced.column(reference).unwrap().kind();
+
+                if !kind.comparable(referenced_kind) {
+                    bail!(
+                        "Foreign key constraint \"{}\" cannot be implemented: Key columns \"{}\" and \"{}\" are of incompatible types: {} and {}",
+                        constraint.name,
+                        name,
+                        reference,
+                        kind,
+                        referenced_kind
+                    );
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Foreign keys of every table that reference `table`, with the name of
+    /// the table they belong to.
+    pub fn references_to(&self, table: &str) -> Vec<(String, Constraint)> {
+        let mut references = self
+            .tables
+            .iter()
+            .flat_map(|(name, schema)| {
+                schema
+                    .constraints
+                    .iter()
+                    .filter(|constraint| {
+                        matches!(&constraint.kind, ConstraintKind::ForeignKey { table: parent, .. } if parent == table)
+                    })
+                    .map(|constraint| (name.clone(), constraint.clone()))
+            })
+            .collect::<Vec<_>>();
+
+        references.sort_by(|a, b| (&a.0, &a.1.name).cmp(&(&b.0, &b.1.name)));
+        references
+    }
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct TableSchema {
     columns: Vec<Column>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    constraints: Vec<Constraint>,
 }
 
 impl TableSchema {
+    pub fn new(columns: Vec<Column>, constraints: Vec<Constraint>) -> Self {
+        Self {
+            columns,
+            constraints,
+        }
+    }
+
+    /// Checks that the columns and constraints of a new table are valid and
+    /// names the constraints without a name after the table, the primary key
+    /// of `users` is `users_pkey`.
+    pub fn prepare(&mut self, table: &str) -> Result<()> {
+        for (index, column) in self.columns.iter().enumerate() {
+            if self.columns[..index].iter().any(|other| other.name == column.name) {
+                bail!("Column \"{}\" specified more than once", column.name);
+            }
+        }
+
+        let autoincrement = self.columns.iter().filter(|column| column.autoincrement);
+
+        for (index, column) in autoincrement.enumerate() {
+            if index > 0 {
+                bail!("Table \"{}\" can have only one AUTOINCREMENT column", table);
+            }
+
+            if column.kind != ColumnKind::Int {
+                bail!("AUTOINCREMENT column \"{}\" must be of type int", column.name);
+            }
+
+            if column.default.is_some() {
+                bail!(
+                    "Column \"{}\" cannot have both AUTOINCREMENT and a DEFAULT",
+                    column.name
+                );
+            }
+        }
+
+        let primary_keys = self
+            .constraints
+            .iter()
+            .filter(|constraint| matches!(constraint.kind, ConstraintKind::PrimaryKey { .. }))
+            .count();
+
+        if primary_keys > 1 {
+            bail!("Multiple primary keys for table \"{}\" are not allowed", table);
+        }
+
+        for constraint in self.constraints.iter() {
+            if let ConstraintKind::PrimaryKey { columns } | ConstraintKind::Unique { columns } =
+                &constraint.kind
+            {
+                for name in columns {
+                    if !self.columns.iter().any(|column| column.name == *name) {
+                        bail!("Column \"{}\" named in key does not exist", name);
+                    }
+                }
+            }
+
+            if let ConstraintKind::ForeignKey {
+                columns,
+                references,
+                ..
+            } = &constraint.kind
+            {
+                for name in columns {
+                    if !self.columns.iter().any(|column| column.name == *name) {
+                        bail!(
+                            "Column \"{}\" referenced in foreign key constraint does not exist",
+                            name
+                        );
+                    }
+                }
+
+                if !references.is_empty() && references.len() != columns.len() {
+                    bail!("Number of referencing and referenced columns for foreign key disagree");
+                }
+            }
+        }
+
+        // Primary key columns are NOT NULL whether declared so or not
+        for constraint in self.constraints.iter() {
+            if let ConstraintKind::PrimaryKey { columns } = &constraint.kind {
+                for column in self.columns.iter_mut() {
+                    if columns.contains(&column.name) {
+                        column.not_null = true;
+                    }
+                }
+            }
+        }
+
+        let mut names = Vec::<String>::new();
+
+        for index in 0..self.constraints.len() {
+            let constraint = &self.constraints[index];
+
+            let name = match constraint.name.is_empty() {
+                true => {
+                    let base = match &constraint.kind {
+                        ConstraintKind::PrimaryKey { .. } => format!("{}_pkey", table),
+                        ConstraintKind::Unique { columns } => {
+                            format!("{}_{}_key", table, columns.join("_"))
+                        }
+                        ConstraintKind::Check { .. } => format!("{}_check", table),
+                        ConstraintKind::ForeignKey { columns, .. } => {
+                            format!("{}_{}_fkey", table, columns.join("_"))
+                        }
+                    };
+
+                    // Names given later keep theirs, generated ones make way
+                    let taken = |name: &str| {
+                        names.iter().any(|other| other == name)
+                            || self.constraints[index + 1..]
+                                .iter()
+                                .any(|other| other.name == name)
+                    };
+
+                    match taken(&base) {
+                        true => (1..)
+                            .map(|suffix| format!("{}{}", base, suffix))
+                            .find(|name| !taken(name))
+                            .unwrap(),
+                        false => base,
+                    }
+                }
+                false => constraint.name.clone(),
+            };
+
+            if names.contains(&name) {
+                bail!(
+                    "Constraint \"{}\" for relation \"{}\" already exists",
+                    name,
+                    table
+                );
+            }
+
+            names.push(name);
+        }
+
+        for (constraint, name) in self.constraints.iter_mut().zip(names) {
+            constraint.name = name;
+        }
+
+        Ok(())
+    }
+
     pub fn load<P>(path: P) -> Result<Self>
     where
         P: AsRef<Path>,
@@ -69,6 +345,28 @@ impl TableSchema {
         Ok(value)
     }
 
+    pub fn columns(&self) -> &[Column] {
+        &self.columns
+    }
+
+    pub fn constraints(&self) -> &[Constraint] {
+        &self.constraints
+    }
+
+    pub fn column(&self, name: &str) -> Option<&Column> {
+        self.columns.iter().find(|column| column.name == name)
+    }
+
+    /// Columns of the primary key, if the table has one.
+    pub fn primary_key(&self) -> Option<&[String]> {
+        self.constraints
+            .iter()
+            .find_map(|constraint| match &constraint.kind {
+                ConstraintKind::PrimaryKey { columns } => Some(columns.as_slice()),
+                _ => None,
+            })
+    }
+
     pub fn offset(&self, column_name: &str) -> Option<usize> {
 
         let mut total = 0;
@@ -84,7 +382,13 @@ impl TableSchema {
         None
     }
 
+    /// Number of bytes of the fixed part of a row: the columns followed by
+    /// one bit per column marking NULL values.
     pub fn size(&self) -> usize {
+        self.null_bitmap_offset() + self.columns.len().div_ceil(8)
+    }
+
+    pub fn null_bitmap_offset(&self) -> usize {
         self.columns.iter().map(|c| c.size()).sum()
     }
 }
@@ -93,54 +397,343 @@ impl Display for TableSchema {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
         writeln!(
             f,
-            "{0: <16} | {1: <16} | {2: <6} | {3: <6}",
+            "{0: <16} | {1: <16} | {2: <6} | {3: <6} | modifiers",
             "column", "type", "size", "offset"
         )?;
         for column in self.columns.iter() {
-            let kind = match column.kind {
-                ColumnKind::Int => String::from("int"),
-                ColumnKind::String(StringColumn { length }) => format!("string({})", length),
-            };
+            let kind = column.kind.to_string();
 
             let size = column.size();
             let offset = self.offset(&column.name).unwrap();
 
+            let mut modifiers = Vec::new();
+
+            if column.not_null {
+                modifiers.push(String::from("not null"));
+            }
+
+            if let Some(default) = &column.default {
+                modifiers.push(format!("default {}", default));
+            }
+
+            if column.autoincrement {
+                modifiers.push(String::from("autoincrement"));
+            }
+
             writeln!(
                 f,
-                "{0: <16} | {1: <16} | {2: <6} | {3: <6}",
-                column.name, kind, size, offset
+                "{0: <16} | {1: <16} | {2: <6} | {3: <6} | {4}",
+                column.name,
+                kind,
+                size,
+                offset,
+                modifiers.join(" ")
             )?;
         }
 
+        if !self.constraints.is_empty() {
+            writeln!(f)?;
+            writeln!(f, "{0: <16} | definition", "constraint")?;
+
+            for constraint in self.constraints.iter() {
+                writeln!(f, "{0: <16} | {1}", constraint.name, constraint.kind)?;
+            }
+        }
+
         Ok(())
     }
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Column {
     name: String,
     kind: ColumnKind,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    not_null: bool,
+    /// SQL expression giving the value of the column when an INSERT leaves it out.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    default: Option<String>,
+    /// Takes the next value counted in the table file when inserted as NULL.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    autoincrement: bool,
 }
 
 impl Column {
+    pub fn new(name: &str, kind: ColumnKind) -> Self {
+        Self {
+            name: String::from(name),
+            kind,
+            not_null: false,
+            default: None,
+            autoincrement: false,
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn kind(&self) -> &ColumnKind {
+        &self.kind
+    }
+
+    pub fn not_null(&self) -> bool {
+        self.not_null
+    }
+
+    pub fn set_not_null(&mut self, not_null: bool) {
+        self.not_null = not_null;
+    }
+
+    pub fn default(&self) -> Option<&str> {
+        self.default.as_deref()
+    }
+
+    pub fn set_default(&mut self, default: Option<String>) {
+        self.default = default;
+    }
+
+    pub fn autoincrement(&self) -> bool {
+        self.autoincrement
+    }
+
+    pub fn set_autoincrement(&mut self, autoincrement: bool) {
+        self.autoincrement = autoincrement;
+    }
+
+    /// Number of bytes the column occupies in the fixed part of a row.
+    ///
+    /// Variable-length columns only store a reference here, their content is
+    /// appended after the fixed part or moved to overflow pages.
     pub fn size(&self) -> usize {
         match self.kind {
             ColumnKind::Int => 4,
+            ColumnKind::BigInt => 8,
             ColumnKind::String(StringColumn { length }) => length,
+            ColumnKind::Text | ColumnKind::Blob | ColumnKind::Json => 8,
+            ColumnKind::Date => 4,
+            ColumnKind::Time | ColumnKind::Timestamp => 8,
+            ColumnKind::Decimal(DecimalColumn { precision, .. }) => match precision {
+                0..=9 => 4,
+                10..=18 => 8,
+                _ => 16,
+            },
         }
     }
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 #[serde(tag = "type")]
 pub enum ColumnKind {
     #[serde(rename = "string")]
     String(StringColumn),
     #[serde(rename = "int")]
     Int,
+    #[serde(rename = "bigint")]
+    BigInt,
+    #[serde(rename = "text")]
+    Text,
+    #[serde(rename = "blob")]
+    Blob,
+    #[serde(rename = "date")]
+    Date,
+    #[serde(rename = "time")]
+    Time,
+    #[serde(rename = "timestamp")]
+    Timestamp,
+    #[serde(rename = "decimal")]
+    Decimal(DecimalColumn),
+    #[serde(rename = "json")]
+    Json,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+impl ColumnKind {
+    /// Whether values of the two kinds can equal each other, as needed for
+    /// a foreign key. Fixed and variable length strings hold the same values.
+    pub fn comparable(&self, other: &ColumnKind) -> bool {
+        match (self, other) {
+            (
+                ColumnKind::String(_) | ColumnKind::Text,
+                ColumnKind::String(_) | ColumnKind::Text,
+            ) => true,
+            (ColumnKind::Int | ColumnKind::BigInt, ColumnKind::Int | ColumnKind::BigInt) => true,
+            (ColumnKind::Decimal(a), ColumnKind::Decimal(b)) => a.scale == b.scale,
+            _ => std::mem::discriminant(self) == std::mem::discriminant(other),
+        }
+    }
+}
+
+impl Display for ColumnKind {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            ColumnKind::Int => write!(f, "int"),
+            ColumnKind::BigInt => write!(f, "bigint"),
+            ColumnKind::String(StringColumn { length }) => write!(f, "string({})", length),
+            ColumnKind::Text => write!(f, "text"),
+            ColumnKind::Blob => write!(f, "blob"),
+            ColumnKind::Date => write!(f, "date"),
+            ColumnKind::Time => write!(f, "time"),
+            ColumnKind::Timestamp => write!(f, "timestamp"),
+            ColumnKind::Decimal(DecimalColumn { precision, scale }) => {
+                write!(f, "decimal({},{})", precision, scale)
+            }
+            ColumnKind::Json => write!(f, "json"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct StringColumn {
-    length: usize,
+    pub length: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DecimalColumn {
+    pub precision: u32,
+    pub scale: u32,
+}
+
+/// Query stored under a name and read like a table, it runs again every
+/// time the view is read.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct View {
+    pub columns: Vec<String>,
+    pub query: String,
+}
+
+impl Display for View {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        writeln!(f, "column")?;
+
+        for column in self.columns.iter() {
+            writeln!(f, "{}", column)?;
+        }
+
+        writeln!(f)?;
+        writeln!(f, "definition")?;
+        writeln!(f, "{}", self.query)
+    }
+}
+
+/// Counter handing out values with `nextval()`, each call moves it by the
+/// increment, starting at `start`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Sequence {
+    pub start: i64,
+    pub increment: i64,
+    /// Value of the last call, `None` before the first.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last: Option<i64>,
+}
+
+impl Sequence {
+    pub fn new(start: i64, increment: i64) -> Self {
+        Self {
+            start,
+            increment,
+            last: None,
+        }
+    }
+
+    pub fn next(&mut self) -> Option<i64> {
+        let next = match self.last {
+            Some(last) => last.checked_add(self.increment)?,
+            None => self.start,
+        };
+
+        self.last = Some(next);
+        Some(next)
+    }
+}
+
+/// Rule on the rows of a table, violating it fails the statement.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Constraint {
+    #[serde(default)]
+    pub name: String,
+    #[serde(flatten)]
+    pub kind: ConstraintKind,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type")]
+pub enum ConstraintKind {
+    /// Unique and not NULL, at most one per table.
+    #[serde(rename = "primary_key")]
+    PrimaryKey { columns: Vec<String> },
+    /// No two rows have the same values, rows with a NULL among them never conflict.
+    #[serde(rename = "unique")]
+    Unique { columns: Vec<String> },
+    /// SQL condition no row may make false, NULL passes.
+    #[serde(rename = "check")]
+    Check { expression: String },
+    /// Values in the columns are those of a row of the referenced table,
+    /// unless one of them is NULL.
+    #[serde(rename = "foreign_key")]
+    ForeignKey {
+        columns: Vec<String>,
+        table: String,
+        /// PRIMARY KEY or UNIQUE columns of the referenced table.
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        references: Vec<String>,
+        #[serde(default)]
+        on_delete: ReferentialAction,
+    },
+}
+
+/// What deleting a referenced row does to the rows referencing it.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ReferentialAction {
+    /// Fails the statement if a referencing row remains, as does RESTRICT
+    /// since constraints are never deferred.
+    #[default]
+    NoAction,
+    Restrict,
+    /// Deletes the referencing rows.
+    Cascade,
+    /// Sets the referencing columns to NULL.
+    SetNull,
+}
+
+impl Display for ReferentialAction {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            ReferentialAction::NoAction => write!(f, "NO ACTION"),
+            ReferentialAction::Restrict => write!(f, "RESTRICT"),
+            ReferentialAction::Cascade => write!(f, "CASCADE"),
+            ReferentialAction::SetNull => write!(f, "SET NULL"),
+        }
+    }
+}
+
+impl Display for ConstraintKind {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            ConstraintKind::PrimaryKey { columns } => {
+                write!(f, "PRIMARY KEY ({})", columns.join(", "))
+            }
+            ConstraintKind::Unique { columns } => write!(f, "UNIQUE ({})", columns.join(", ")),
+            ConstraintKind::Check { expression } => write!(f, "CHECK ({})", expression),
+            ConstraintKind::ForeignKey {
+                columns,
+                table,
+                references,
+                on_delete,
+            } => {
+                write!(
+                    f,
+                    "FOREIGN KEY ({}) REFERENCES {} ({})",
+                    columns.join(", "),
+                    table,
+                    references.join(", ")
+                )?;
+
+                match on_delete {
+                    ReferentialAction::NoAction => Ok(()),
+                    action => write!(f, " ON DELETE {}", action),
+                }
+            }
+        }
+    }
 }
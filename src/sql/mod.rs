@@ -1,75 +1,626 @@
 pub mod tokenizer;
 pub mod parser;
+mod value;
+
+pub use value::Value;
+
+use crate::schema::{ColumnKind, TableSchema};
 
 pub enum Statement {
-    Select(SelectStatement),
+    Select(Box<SelectStatement>),
+    Query(Box<Query>),
     Insert(InsertStatement),
     Delete(DeleteStatement),
     Update(UpdateStatement),
+    CreateTable(CreateTableStatement),
+    CreateSequence(CreateSequenceStatement),
+    CreateView(CreateViewStatement),
 }
 
+#[derive(Debug, Clone, PartialEq)]
 pub struct SelectStatement {
-    selections: Vec<Selection>,
-    table: String,
-    r#where: Option<WhereExpression>,
-    pagination: Option<Pagination>,
+    pub distinct: Option<Distinct>,
+    pub selections: Vec<Selection>,
+    pub table: TableReference,
+    pub joins: Vec<Join>,
+    pub r#where: Option<WhereExpression>,
+    pub group_by: Vec<Expression>,
+    pub having: Option<WhereExpression>,
+    pub order_by: Vec<OrderBy>,
+    pub pagination: Option<Pagination>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Distinct {
+    /// `SELECT DISTINCT`, removes duplicate rows.
+    Rows,
+    /// `SELECT DISTINCT ON (expressions)`, keeps the first row of each set
+    /// of rows with equal values of the expressions.
+    On(Vec<Expression>),
+}
+
+/// SELECT statements combined by set operations, optionally preceded by
+/// common table expressions, `WITH [RECURSIVE] name [(columns)] AS (query)`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Query {
+    pub recursive: bool,
+    pub with: Vec<CommonTableExpression>,
+    pub body: SetExpression,
+    /// Ordering of the combined rows, ORDER BY after the last SELECT of a
+    /// set operation. Single SELECT statements keep their own.
+    pub order_by: Vec<OrderBy>,
+    pub pagination: Option<Pagination>,
+}
+
+/// Named query of a WITH clause, referenced like a table by the queries
+/// after it and, with RECURSIVE, by its own recursive term.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CommonTableExpression {
+    pub name: String,
+    /// Names replacing those of the first columns of the query.
+    pub columns: Vec<String>,
+    pub query: Query,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum SetExpression {
+    Select(Box<SelectStatement>),
+    /// `left UNION | INTERSECT | EXCEPT [ALL] right`, the columns are named
+    /// after those of `left`. Without ALL the result has no duplicate rows.
+    Operation {
+        operator: SetOperator,
+        left: Box<SetExpression>,
+        right: Box<SetExpression>,
+        all: bool,
+    },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SetOperator {
+    Union,
+    Intersect,
+    Except,
+}
+
+impl SetOperator {
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Union => "UNION",
+            Self::Intersect => "INTERSECT",
+            Self::Except => "EXCEPT",
+        }
+    }
+}
+
+/// Item of a FROM clause, rows of `name AS alias` are referenced as `alias.column`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TableReference {
+    pub source: TableSource,
+    pub alias: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum TableSource {
+    Table(String),
+    /// Derived table `(SELECT ...) AS alias`, the parser requires the alias.
+    Subquery(Box<SelectStatement>),
+    /// Rows computed before the query runs, such as a materialized common
+    /// table expression. Never produced by the parser.
+    Rows {
+        columns: Vec<String>,
+        rows: Vec<Vec<Value>>,
+    },
+}
+
+impl TableReference {
+    /// Name that qualifies the columns of the table.
+    pub fn qualifier(&self) -> &str {
+        match (&self.alias, &self.source) {
+            (Some(alias), _) => alias,
+            (None, TableSource::Table(name)) => name,
+            (None, TableSource::Subquery(_) | TableSource::Rows { .. }) => "",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Join {
+    pub kind: JoinKind,
+    pub table: TableReference,
+    pub on: Option<WhereExpression>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum JoinKind {
+    Inner,
+    Left,
+    Cross,
 }
 
 pub struct InsertStatement {
     pub table: String,
+    /// Columns the values are for, all columns in table order without a list.
     pub columns: Option<Vec<String>>,
-    pub values: Vec<Value>,
+    pub source: InsertSource,
+    pub on_conflict: Option<OnConflict>,
+    pub returning: Option<Returning>,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum InsertSource {
+    /// `VALUES (expression, ...), ...`, evaluated for each row as it is inserted.
+    Values(Vec<Vec<Expression>>),
+    /// `INSERT INTO t [(columns)] query`, the query runs before any row is inserted.
+    Query(Box<Query>),
+}
+
+/// `ON CONFLICT [(columns)] DO NOTHING | DO UPDATE SET ... [WHERE ...]`, a
+/// row conflicts with another that has the same values in the columns of the
+/// PRIMARY KEY or UNIQUE constraint matching the target. Without a target,
+/// only DO NOTHING, any of them.
+pub struct OnConflict {
+    pub target: Vec<String>,
+    pub action: ConflictAction,
+}
+
+pub enum ConflictAction {
+    Nothing,
+    /// Updates the existing row, the row that was not inserted is `excluded`.
+    Update {
+        assignments: Vec<UpdateAssignment>,
+        r#where: Option<Box<WhereExpression>>,
+    },
+}
+
+/// `CREATE SEQUENCE name [START [WITH] n] [INCREMENT [BY] n]`
+pub struct CreateSequenceStatement {
+    pub name: String,
+    pub start: Option<i64>,
+    pub increment: Option<i64>,
+}
+
+/// `CREATE VIEW name [(columns)] AS query`
+pub struct CreateViewStatement {
+    pub name: String,
+    /// Names replacing those of the first columns of the query.
+    pub columns: Vec<String>,
+    pub query: Box<Query>,
+    /// Text of the query, stored to run it whenever the view is read.
+    pub sql: String,
+}
+
+pub struct CreateTableStatement {
+    pub table: String,
+    pub definition: TableDefinition,
+}
+
+pub enum TableDefinition {
+    /// `(column type [constraints], ..., [table constraints])`
+    Schema(TableSchema),
+    /// `[(columns)] AS query`, the columns are named and typed after those
+    /// of the query unless renamed by the list.
+    Query {
+        columns: Vec<String>,
+        query: Box<Query>,
+    },
 }
 
 pub struct DeleteStatement {
-    table: String,
-    r#where: Option<WhereExpression>,
+    pub table: String,
+    pub r#where: Option<WhereExpression>,
+    pub returning: Option<Returning>,
 }
 
 pub struct UpdateStatement {
-    assignments: Vec<UpdateAssignment>,
-    table: String,
-    r#where: Option<WhereExpression>,
+    pub assignments: Vec<UpdateAssignment>,
+    pub table: String,
+    pub r#where: Option<WhereExpression>,
+    pub returning: Option<Returning>,
+}
+
+/// `RETURNING * | selection, ...` of a write statement, evaluated for each
+/// row it inserted, updated or deleted.
+#[derive(Debug, PartialEq)]
+pub enum Returning {
+    All,
+    Selections(Vec<Selection>),
 }
 
 pub struct UpdateAssignment {
-    field: String,
-    value: Value,
+    pub field: String,
+    pub value: Expression,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct OrderBy {
+    pub expression: Expression,
+    pub direction: Direction,
+    /// Where NULLs go, by default last when ascending and first when descending.
+    pub nulls: Option<Nulls>,
 }
 
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Direction {
+    Ascending,
+    Descending,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Nulls {
+    First,
+    Last,
+}
+
+#[derive(Debug, Clone, PartialEq)]
 pub struct Pagination {
-    limit: Option<usize>,
-    offset: Option<usize>,
+    pub limit: Option<usize>,
+    pub offset: Option<usize>,
 }
 
+/// Boolean expression of a WHERE, ON, HAVING or CASE WHEN clause.
+/// `x BETWEEN a AND b` is parsed into `x >= a AND x <= b`.
+#[derive(Debug, Clone, PartialEq)]
 pub enum WhereExpression {
     And(Vec<Self>),
     Or(Vec<Self>),
     Not(Box<Self>),
     Condition(WhereCondition),
+    /// `expression IN (list)`
+    In {
+        expression: Expression,
+        list: Vec<Expression>,
+    },
+    /// `expression IN (SELECT ...)`
+    InSubquery {
+        expression: Expression,
+        subquery: Box<SelectStatement>,
+    },
+    Exists(Box<SelectStatement>),
+    /// `expression IS NULL`, `IS NOT NULL` is its negation.
+    IsNull(Expression),
+}
+
+impl WhereExpression {
+    /// Operands of all conditions, without the expressions of subqueries.
+    pub fn expressions(&self) -> Vec<&Expression> {
+        match self {
+            WhereExpression::And(expressions) | WhereExpression::Or(expressions) => {
+                expressions.iter().flat_map(|e| e.expressions()).collect()
+            }
+            WhereExpression::Not(expression) => expression.expressions(),
+            WhereExpression::Condition(condition) => vec![&condition.field, &condition.value],
+            WhereExpression::In { expression, list } => {
+                std::iter::once(expression).chain(list.iter()).collect()
+            }
+            WhereExpression::InSubquery { expression, .. } => vec![expression],
+            WhereExpression::Exists(_) => Vec::new(),
+            WhereExpression::IsNull(expression) => vec![expression],
+        }
+    }
+
+    pub fn expressions_mut(&mut self) -> Vec<&mut Expression> {
+        match self {
+            WhereExpression::And(expressions) | WhereExpression::Or(expressions) => expressions
+                .iter_mut()
+                .flat_map(|e| e.expressions_mut())
+                .collect(),
+            WhereExpression::Not(expression) => expression.expressions_mut(),
+            WhereExpression::Condition(condition) => {
+                vec![&mut condition.field, &mut condition.value]
+            }
+            WhereExpression::In { expression, list } => {
+                std::iter::once(expression).chain(list.iter_mut()).collect()
+            }
+            WhereExpression::InSubquery { expression, .. } => vec![expression],
+            WhereExpression::Exists(_) => Vec::new(),
+            WhereExpression::IsNull(expression) => vec![expression],
+        }
+    }
 }
 
+#[derive(Debug, Clone, PartialEq)]
 pub struct WhereCondition {
-    field: String,
-    operator: Operator,
-    value: Value,
+    pub field: Expression,
+    pub operator: Operator,
+    pub value: Expression,
 }
 
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub enum Operator {
     GreaterThan,
     GreaterThanEquals,
     Equals,
+    NotEquals,
     SmallerThanEquals,
     SmallerThan,
+    /// `LIKE` with `%` matching any text and `_` any single character.
+    Like,
+    /// Case-insensitive `LIKE`.
+    ILike,
+    /// Whether the text or bytes of the right side occur in the left side.
     Contains,
 }
 
-pub enum Value {
-    String(String),
-    Int(u32),
+#[derive(Debug, Clone, PartialEq)]
+pub enum Expression {
+    Value(Value),
+    Column(String),
+    /// `*` in a select list, replaced by the columns of the FROM items
+    /// before the query runs.
+    Wildcard,
+    Function(FunctionCall),
+    Aggregate(AggregateCall),
+    Negate(Box<Expression>),
+    Binary {
+        left: Box<Expression>,
+        operator: BinaryOperator,
+        right: Box<Expression>,
+    },
+    Case(CaseExpression),
+    Cast {
+        expression: Box<Expression>,
+        kind: ColumnKind,
+    },
+    /// Scalar subquery, returning one column and at most one row.
+    Subquery(Box<SelectStatement>),
+    /// Window function call, computed over the rows of its window.
+    Window(WindowCall),
+    /// Condition used as a value, true, false or NULL when it is unknown.
+    Condition(Box<WhereExpression>),
 }
 
+impl Expression {
+    /// Direct subexpressions, including the conditions of CASE branches.
+    /// Subqueries have their own scope and are not descended into.
+    pub fn children(&self) -> Vec<&Expression> {
+        match self {
+            Expression::Value(_)
+            | Expression::Column(_)
+            | Expression::Wildcard
+            | Expression::Subquery(_) => Vec::new(),
+            Expression::Function(call) => call.arguments.iter().collect(),
+            Expression::Window(call) => call
+                .arguments
+                .iter()
+                .chain(call.window.partition_by.iter())
+                .chain(call.window.order_by.iter().map(|order_by| &order_by.expression))
+                .collect(),
+            Expression::Aggregate(call) => call.argument.iter().map(|a| a.as_ref()).collect(),
+            Expression::Negate(expression) | Expression::Cast { expression, .. } => {
+                vec![expression]
+            }
+            Expression::Binary { left, right, .. } => vec![left, right],
+            Expression::Condition(condition) => condition.expressions(),
+            Expression::Case(case) => {
+                let mut children = Vec::new();
+
+                for branch in case.branches.iter() {
+                    children.extend(branch.condition.expressions());
+                    children.push(&branch.result);
+                }
+
+                children.extend(case.r#else.as_deref());
+                children
+            }
+        }
+    }
+
+    pub fn children_mut(&mut self) -> Vec<&mut Expression> {
+        match self {
+            Expression::Value(_)
+            | Expression::Column(_)
+            | Expression::Wildcard
+            | Expression::Subquery(_) => Vec::new(),
+            Expression::Function(call) => call.arguments.iter_mut().collect(),
+            Expression::Window(call) => call
+                .arguments
+                .iter_mut()
+                .chain(call.window.partition_by.iter_mut())
+                .chain(
+                    call.window
+                        .order_by
+                        .iter_mut()
+                        .map(|order_by| &mut order_by.expression),
+                )
+                .collect(),
+            Expression::Aggregate(call) => call.argument.iter_mut().map(|a| a.as_mut()).collect(),
+            Expression::Negate(expression) | Expression::Cast { expression, .. } => {
+                vec![expression]
+            }
+            Expression::Binary { left, right, .. } => vec![left, right],
+            Expression::Condition(condition) => condition.expressions_mut(),
+            Expression::Case(case) => {
+                let mut children = Vec::new();
+
+                for branch in case.branches.iter_mut() {
+                    children.extend(branch.condition.expressions_mut());
+                    children.push(&mut branch.result);
+                }
+
+                children.extend(case.r#else.as_deref_mut());
+                children
+            }
+        }
+    }
+}
+
+/// `CASE WHEN condition THEN result ... [ELSE result] END`, the simple form
+/// `CASE operand WHEN value ...` is parsed into `operand = value` conditions.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CaseExpression {
+    pub branches: Vec<CaseBranch>,
+    pub r#else: Option<Box<Expression>>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct CaseBranch {
+    pub condition: WhereExpression,
+    pub result: Expression,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct FunctionCall {
+    pub name: String,
+    pub arguments: Vec<Expression>,
+}
+
+/// Call of an aggregate function, `argument` is `None` for `COUNT(*)`.
+/// With `distinct` every value is aggregated once, as in `COUNT(DISTINCT x)`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AggregateCall {
+    pub function: AggregateFunction,
+    pub argument: Option<Box<Expression>>,
+    pub distinct: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum AggregateFunction {
+    Count,
+    Sum,
+    Avg,
+    Min,
+    Max,
+}
+
+impl AggregateFunction {
+    pub fn parse(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "count" => Some(Self::Count),
+            "sum" => Some(Self::Sum),
+            "avg" => Some(Self::Avg),
+            "min" => Some(Self::Min),
+            "max" => Some(Self::Max),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Count => "count",
+            Self::Sum => "sum",
+            Self::Avg => "avg",
+            Self::Min => "min",
+            Self::Max => "max",
+        }
+    }
+}
+
+/// `function(arguments) OVER (window)`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct WindowCall {
+    pub function: WindowFunction,
+    pub arguments: Vec<Expression>,
+    pub window: Window,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum WindowFunction {
+    RowNumber,
+    Rank,
+    DenseRank,
+    /// `LAG(expression [, offset [, default]])`, the value `offset` rows before.
+    Lag,
+    /// `LEAD(expression [, offset [, default]])`, the value `offset` rows after.
+    Lead,
+    /// Aggregate function over the frame of each row.
+    Aggregate(AggregateFunction),
+}
+
+impl WindowFunction {
+    /// Functions that are only valid with an OVER clause.
+    pub fn parse(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "row_number" => Some(Self::RowNumber),
+            "rank" => Some(Self::Rank),
+            "dense_rank" => Some(Self::DenseRank),
+            "lag" => Some(Self::Lag),
+            "lead" => Some(Self::Lead),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::RowNumber => "row_number",
+            Self::Rank => "rank",
+            Self::DenseRank => "dense_rank",
+            Self::Lag => "lag",
+            Self::Lead => "lead",
+            Self::Aggregate(function) => function.name(),
+        }
+    }
+}
+
+/// `OVER ([PARTITION BY expression, ...] [ORDER BY ...] [ROWS frame])`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Window {
+    pub partition_by: Vec<Expression>,
+    pub order_by: Vec<OrderBy>,
+    /// Rows an aggregate is computed over. Without it the frame is the whole
+    /// partition, or with ORDER BY the partition up to the last row ordered
+    /// equal to the current one.
+    pub frame: Option<WindowFrame>,
+}
+
+/// `ROWS BETWEEN start AND end`, `ROWS start` ends at the current row.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct WindowFrame {
+    pub start: FrameBound,
+    pub end: FrameBound,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FrameBound {
+    UnboundedPreceding,
+    Preceding(usize),
+    CurrentRow,
+    Following(usize),
+    UnboundedFollowing,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BinaryOperator {
+    Add,
+    Subtract,
+    Multiply,
+    Divide,
+    Concat,
+    JsonGet,
+    JsonGetText,
+}
+
+#[derive(Debug, Clone, PartialEq)]
 pub struct Selection {
-    column: String,
-    alias: Option<String>,
+    pub expression: Expression,
+    pub alias: Option<String>,
+}
+
+impl Selection {
+    /// Header of the output column.
+    pub fn name(&self) -> String {
+        if let Some(alias) = &self.alias {
+            return alias.clone();
+        }
+
+        match &self.expression {
+            Expression::Column(name) => match name.rsplit_once('.') {
+                Some((_, column)) => String::from(column),
+                None => name.clone(),
+            },
+            Expression::Function(call) => call.name.clone(),
+            Expression::Aggregate(call) => String::from(call.function.name()),
+            Expression::Window(call) => String::from(call.function.name()),
+            Expression::Case(_) => String::from("case"),
+            Expression::Subquery(subquery) => match subquery.selections.as_slice() {
+                [selection] => selection.name(),
+                _ => String::from("?column?"),
+            },
+            Expression::Cast { expression, .. } => Selection {
+                expression: (**expression).clone(),
+                alias: None,
+            }
+            .name(),
+            _ => String::from("?column?"),
+        }
+    }
 }
\ No newline at end of file
@@ -1,9 +1,63 @@
-use super::tokenizer::{Keyword, Token, TokenKind};
-use super::{InsertStatement, SelectStatement, Selection, Statement, Value};
+use std::cell::Cell;
+use std::fmt::{self, Display, Formatter};
+
+use super::tokenizer::{self, Keyword, Token, TokenKind};
+use super::{
+    AggregateCall, AggregateFunction, BinaryOperator, CaseBranch, CaseExpression,
+    CommonTableExpression, ConflictAction, CreateSequenceStatement, CreateTableStatement,
+    CreateViewStatement, DeleteStatement, Direction, Distinct, Expression, FrameBound,
+    FunctionCall, InsertSource, InsertStatement, Join, JoinKind, Nulls, OnConflict, Operator,
+    OrderBy, Pagination, Query, Returning, SelectStatement, Selection, SetExpression, SetOperator,
+    Statement, TableDefinition, TableReference, TableSource, UpdateAssignment, UpdateStatement,
+    Value, WhereCondition, WhereExpression, Window, WindowCall, WindowFrame, WindowFunction,
+};
+use crate::schema::{
+    Column, ColumnKind, Constraint, ConstraintKind, DecimalColumn, ReferentialAction,
+    StringColumn, TableSchema,
+};
+use crate::types::date::{Date, Interval, Time, Timestamp};
+use crate::types::decimal::Decimal;
 
 #[derive(Debug)]
 pub enum Error {
     NoMatch,
+    InvalidLiteral(String),
+    /// Statement that parses but is not valid SQL, such as an ORDER BY in
+    /// the middle of a set operation.
+    Invalid(String),
+    UnexpectedInput(tokenizer::Error),
+    /// First token no rule could parse, reported in place of `NoMatch`.
+    UnexpectedToken { found: String, position: usize },
+    UnexpectedEnd,
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::NoMatch => write!(f, "Invalid SQL"),
+            Error::InvalidLiteral(message) => write!(f, "Invalid SQL: {}", message),
+            Error::Invalid(message) => write!(f, "Invalid SQL: {}", message),
+            Error::UnexpectedInput(err) => write!(f, "{}", err),
+            Error::UnexpectedToken { found, position } => write!(
+                f,
+                "Invalid SQL: Unexpected \"{}\" at position {}",
+                found, position
+            ),
+            Error::UnexpectedEnd => write!(f, "Invalid SQL: Unexpected end of input"),
+        }
+    }
+}
+
+thread_local! {
+    /// Fewest tokens left when a token failed to match, the furthest the
+    /// parser got in the input.
+    static FURTHEST: Cell<usize> = const { Cell::new(usize::MAX) };
+}
+
+impl From<tokenizer::Error> for Error {
+    fn from(err: tokenizer::Error) -> Self {
+        Error::UnexpectedInput(err)
+    }
 }
 
 pub type Result<T> = std::result::Result<T, Error>;
@@ -19,305 +73,2943 @@ impl Parser {
     }
 
     pub fn parse(&self) -> Result<Statement> {
-        let tokens = self.tokens.as_slice();
+        self.report(Self::statement)
+    }
+
+    /// Parses the tokens as a condition alone, such as a stored CHECK constraint.
+    pub fn parse_condition(&self) -> Result<WhereExpression> {
+        self.report(|tokens| {
+            let (rest, condition) = Self::where_expression(tokens)?;
+            Self::end(rest)?;
+            Ok(condition)
+        })
+    }
+
+    /// Parses the tokens as a query alone, such as the definition of a view.
+    pub fn parse_query(&self) -> Result<Query> {
+        self.report(|tokens| {
+            let (rest, query) = Self::query(tokens)?;
+            Self::end(rest)?;
+            Ok(query)
+        })
+    }
+
+    /// Parses the tokens as an expression alone, such as a stored column DEFAULT.
+    pub fn parse_expression(&self) -> Result<Expression> {
+        self.report(|tokens| {
+            let (rest, expression) = Self::expression(tokens)?;
+            Self::end(rest)?;
+            Ok(expression)
+        })
+    }
+
+    /// Runs a parse over all tokens, a failure to match any rule is reported
+    /// at the furthest token reached.
+    fn report<T>(&self, parse: impl FnOnce(&[Token]) -> Result<T>) -> Result<T> {
+        FURTHEST.with(|furthest| furthest.set(usize::MAX));
+
+        match parse(&self.tokens) {
+            Err(Error::NoMatch) => {
+                let left = FURTHEST.with(Cell::get).min(self.tokens.len());
+
+                match Self::whitespace(&self.tokens[self.tokens.len() - left..]).first() {
+                    Some(token) => Err(Error::UnexpectedToken {
+                        found: token.to_string(),
+                        position: token.span.start,
+                    }),
+                    None => Err(Error::UnexpectedEnd),
+                }
+            }
+            result => result,
+        }
+    }
+
+    /// Fails to match at the first token of the input.
+    fn no_match(input: &[Token]) -> Error {
+        FURTHEST.with(|furthest| furthest.set(furthest.get().min(input.len())));
+        Error::NoMatch
+    }
+
+    fn statement(tokens: &[Token]) -> Result<Statement> {
+
+        match Self::query(tokens) {
+            Ok((rest, query)) => {
+                Self::end(rest)?;
+
+                return Ok(match query {
+                    Query {
+                        with,
+                        body: SetExpression::Select(statement),
+                        ..
+                    } if with.is_empty() => Statement::Select(statement),
+                    query => Statement::Query(Box::new(query)),
+                });
+            }
+            Err(Error::NoMatch) => {}
+            Err(err) => return Err(err),
+        }
+
+        match Self::insert_statement(tokens) {
+            Ok((rest, statement)) => {
+                Self::end(rest)?;
+                return Ok(Statement::Insert(statement));
+            }
+            Err(Error::NoMatch) => {}
+            Err(err) => return Err(err),
+        }
+
+        match Self::delete_statement(tokens) {
+            Ok((rest, statement)) => {
+                Self::end(rest)?;
+                return Ok(Statement::Delete(statement));
+            }
+            Err(Error::NoMatch) => {}
+            Err(err) => return Err(err),
+        }
+
+        match Self::update_statement(tokens) {
+            Ok((rest, statement)) => {
+                Self::end(rest)?;
+                return Ok(Statement::Update(statement));
+            }
+            Err(Error::NoMatch) => {}
+            Err(err) => return Err(err),
+        }
+
+        match Self::create_table_statement(tokens) {
+            Ok((rest, statement)) => {
+                Self::end(rest)?;
+                return Ok(Statement::CreateTable(statement));
+            }
+            Err(Error::NoMatch) => {}
+            Err(err) => return Err(err),
+        }
 
-        if let Ok((_, statement)) = Self::select_statement(tokens) {
-            return Ok(Statement::Select(statement));
+        match Self::create_sequence_statement(tokens) {
+            Ok((rest, statement)) => {
+                Self::end(rest)?;
+                return Ok(Statement::CreateSequence(statement));
+            }
+            Err(Error::NoMatch) => {}
+            Err(err) => return Err(err),
         }
 
-        if let Ok((_, statement)) = Self::insert_statement(tokens) {
-            return Ok(Statement::Insert(statement));
+        match Self::create_view_statement(tokens) {
+            Ok((rest, statement)) => {
+                Self::end(rest)?;
+                return Ok(Statement::CreateView(statement));
+            }
+            Err(Error::NoMatch) => {}
+            Err(err) => return Err(err),
         }
 
         Err(Error::NoMatch)
     }
 
+    fn end(input: &[Token]) -> Result<()> {
+        let rest = Self::whitespace(input);
+        let rest = Self::symbol(rest, TokenKind::SemiColon).map_or(rest, |(rest, _)| rest);
+
+        let rest = Self::whitespace(rest);
+
+        if rest.is_empty() {
+            Ok(())
+        } else {
+            Err(Self::no_match(rest))
+        }
+    }
+
+    fn whitespace(input: &[Token]) -> &[Token] {
+        match input.first() {
+            Some(Token {
+                kind: TokenKind::Whitespace,
+                ..
+            }) => &input[1..],
+            _ => input,
+        }
+    }
+
+    fn keyword(input: &[Token], keyword: Keyword) -> Result<(&[Token], ())> {
+        let input = Self::whitespace(input);
+
+        match input.first() {
+            Some(Token {
+                kind: TokenKind::Keyword(found),
+                ..
+            }) if *found == keyword => Ok((&input[1..], ())),
+            _ => Err(Self::no_match(input)),
+        }
+    }
+
+    fn symbol(input: &[Token], kind: TokenKind) -> Result<(&[Token], ())> {
+        let input = Self::whitespace(input);
+
+        match input.first() {
+            Some(token) if token.kind == kind => Ok((&input[1..], ())),
+            _ => Err(Self::no_match(input)),
+        }
+    }
+
+    fn identifier(input: &[Token]) -> Result<(&[Token], String)> {
+        let input = Self::whitespace(input);
+
+        match input.first() {
+            Some(Token {
+                kind: TokenKind::Identifier(identifier),
+                ..
+            }) => Ok((&input[1..], identifier.clone())),
+            Some(Token {
+                kind: TokenKind::Keyword(keyword),
+                ..
+            }) if !keyword.is_reserved() => Ok((&input[1..], String::from(keyword.name()))),
+            _ => Err(Self::no_match(input)),
+        }
+    }
+
     fn insert_statement(input: &[Token]) -> Result<(&[Token], InsertStatement)> {
         let (rest, _) = Self::insert_keyword(input)?;
         let (rest, _) = Self::into_keyword(rest)?;
         let (rest, table) = Self::table_name(rest)?;
-        let (rest, _) = Self::values_keyword(rest)?;
-        let (rest, values) = Self::values(rest)?;
+
+        let (rest, columns) = match Self::column_list(rest) {
+            Ok((rest, columns)) => (rest, Some(columns)),
+            Err(Error::NoMatch) => (rest, None),
+            Err(err) => return Err(err),
+        };
+
+        let (rest, source) = match Self::values_keyword(rest) {
+            Ok((rest, _)) => {
+                let (rest, rows) = Self::rows(rest)?;
+                (rest, InsertSource::Values(rows))
+            }
+            Err(Error::NoMatch) => {
+                let (rest, query) = Self::query(rest)?;
+                (rest, InsertSource::Query(Box::new(query)))
+            }
+            Err(err) => return Err(err),
+        };
+
+        let (rest, on_conflict) = match Self::on_conflict(rest) {
+            Ok((rest, on_conflict)) => (rest, Some(on_conflict)),
+            Err(Error::NoMatch) => (rest, None),
+            Err(err) => return Err(err),
+        };
+
+        let (rest, returning) = Self::returning(rest)?;
 
         Ok((
             rest,
             InsertStatement {
                 table,
-                values,
-                columns: None,
+                columns,
+                source,
+                on_conflict,
+                returning,
             },
         ))
     }
 
-    fn values_keyword(input: &[Token]) -> Result<(&[Token], ())> {
-        let mut cutoff = 0;
+    fn returning(input: &[Token]) -> Result<(&[Token], Option<Returning>)> {
+        let Ok((rest, _)) = Self::keyword(input, Keyword::Returning) else {
+            return Ok((input, None));
+        };
 
-        if let Some(Token {
-            kind: TokenKind::Whitespace,
-            ..
-        }) = input.get(cutoff)
-        {
-            cutoff += 1;
+        if let Ok((rest, _)) = Self::symbol(rest, TokenKind::Star) {
+            return Ok((rest, Some(Returning::All)));
         }
 
-        if let Some(Token {
-            kind: TokenKind::Keyword(Keyword::Values),
-            ..
-        }) = input.get(cutoff)
-        {
-            Ok((&input[cutoff + 1..], ()))
-        } else {
-            Err(Error::NoMatch)
-        }
+        let (rest, selections) = Self::selections(rest)?;
+        Ok((rest, Some(Returning::Selections(selections))))
     }
 
-    fn insert_keyword(input: &[Token]) -> Result<(&[Token], ())> {
-        let mut cutoff = 0;
+    fn on_conflict(input: &[Token]) -> Result<(&[Token], OnConflict)> {
+        let (rest, _) = Self::keyword(input, Keyword::On)?;
+        let (rest, _) = Self::keyword(rest, Keyword::Conflict)?;
 
-        if let Some(Token {
-            kind: TokenKind::Whitespace,
-            ..
-        }) = input.get(cutoff)
-        {
-            cutoff += 1;
-        }
+        let (rest, target) = match Self::column_list(rest) {
+            Ok((rest, target)) => (rest, target),
+            Err(Error::NoMatch) => (rest, Vec::new()),
+            Err(err) => return Err(err),
+        };
+        let (rest, _) = Self::keyword(rest, Keyword::Do)?;
 
-        if let Some(Token {
-            kind: TokenKind::Keyword(Keyword::Insert),
-            ..
-        }) = input.get(cutoff)
-        {
-            Ok((&input[cutoff + 1..], ()))
-        } else {
-            Err(Error::NoMatch)
+        if let Ok((rest, _)) = Self::keyword(rest, Keyword::Nothing) {
+            return Ok((
+                rest,
+                OnConflict {
+                    target,
+                    action: ConflictAction::Nothing,
+                },
+            ));
         }
+
+        let (rest, _) = Self::keyword(rest, Keyword::Update)?;
+        let (rest, assignments) = Self::assignments(rest)?;
+
+        let (rest, r#where) = match Self::where_clause(rest) {
+            Ok((rest, r#where)) => (rest, Some(Box::new(r#where))),
+            Err(Error::NoMatch) => (rest, None),
+            Err(err) => return Err(err),
+        };
+
+        Ok((
+            rest,
+            OnConflict {
+                target,
+                action: ConflictAction::Update {
+                    assignments,
+                    r#where,
+                },
+            },
+        ))
     }
 
-    fn into_keyword(input: &[Token]) -> Result<(&[Token], ())> {
-        let mut cutoff = 0;
+    /// Parses the rows of a VALUES clause, a single row may also be written
+    /// without parentheses.
+    fn rows(input: &[Token]) -> Result<(&[Token], Vec<Vec<Expression>>)> {
+        match Self::row_values(input) {
+            Ok((mut rest, first)) => {
+                let mut rows = vec![first];
 
-        if let Some(Token {
-            kind: TokenKind::Whitespace,
-            ..
-        }) = input.get(cutoff)
-        {
-            cutoff += 1;
-        }
+                while let Ok((next, _)) = Self::symbol(rest, TokenKind::Comma) {
+                    let (next, row) = Self::row_values(next)?;
+                    rows.push(row);
+                    rest = next;
+                }
 
-        if let Some(Token {
-            kind: TokenKind::Keyword(Keyword::Into),
-            ..
-        }) = input.get(cutoff)
-        {
-            Ok((&input[cutoff + 1..], ()))
-        } else {
-            Err(Error::NoMatch)
+                Ok((rest, rows))
+            }
+            Err(Error::NoMatch) => {
+                let (rest, values) = Self::values(input)?;
+                Ok((rest, vec![values]))
+            }
+            Err(err) => Err(err),
         }
     }
 
-    fn select_statement(input: &[Token]) -> Result<(&[Token], SelectStatement)> {
-        let (rest, _) = Self::select_keyword(input)?;
-        let (rest, columns) = Self::columns(rest)?;
-        let (rest, _) = Self::from_keyword(rest)?;
+    fn create_table_statement(input: &[Token]) -> Result<(&[Token], CreateTableStatement)> {
+        let (rest, _) = Self::keyword(input, Keyword::Create)?;
+        let (rest, _) = Self::keyword(rest, Keyword::Table)?;
         let (rest, table) = Self::table_name(rest)?;
+
+        match Self::table_schema(rest, &table) {
+            Ok((rest, schema)) => {
+                return Ok((
+                    rest,
+                    CreateTableStatement {
+                        table,
+                        definition: TableDefinition::Schema(schema),
+                    },
+                ))
+            }
+            Err(Error::NoMatch) => {}
+            Err(err) => return Err(err),
+        }
+
+        let (rest, columns) = match Self::column_list(rest) {
+            Ok((rest, columns)) => (rest, columns),
+            Err(Error::NoMatch) => (rest, Vec::new()),
+            Err(err) => return Err(err),
+        };
+
+        let (rest, _) = Self::keyword(rest, Keyword::As)?;
+        let (rest, query) = Self::query(rest)?;
+
         Ok((
             rest,
-            SelectStatement {
-                selections: columns
-                    .into_iter()
-                    .map(|c| Selection {
-                        column: c,
-                        alias: None,
-                    })
-                    .collect(),
+            CreateTableStatement {
                 table,
-                r#where: None,
-                pagination: None,
+                definition: TableDefinition::Query {
+                    columns,
+                    query: Box::new(query),
+                },
             },
         ))
     }
 
-    fn table_name(input: &[Token]) -> Result<(&[Token], String)> {
-        let mut position = input;
+    fn create_sequence_statement(input: &[Token]) -> Result<(&[Token], CreateSequenceStatement)> {
+        let (rest, _) = Self::keyword(input, Keyword::Create)?;
+        let (rest, _) = Self::keyword(rest, Keyword::Sequence)?;
+        let (mut rest, name) = Self::identifier(rest)?;
 
-        if let Some(Token {
-            kind: TokenKind::Whitespace,
-            ..
-        }) = position.get(0)
-        {
-            position = &position[1..];
-        }
+        let mut start = None;
+        let mut increment = None;
 
-        if let Some(Token {
-            kind: TokenKind::Identifier(table),
-            ..
-        }) = position.get(0)
-        {
-            match position.get(1) {
-                Some(Token {
-                    kind: TokenKind::Whitespace,
-                    ..
-                }) => Ok((&position[2..], table.clone())),
-                _ => Ok((&position[1..], table.clone())),
+        // Each option may be given once, in any order
+        loop {
+            if let (None, Ok((next, _))) = (start, Self::keyword(rest, Keyword::Start)) {
+                let next = Self::keyword(next, Keyword::With).map_or(next, |(next, _)| next);
+                let (next, value) = Self::signed_integer(next)?;
+                start = Some(value);
+                rest = next;
+            } else if let (None, Ok((next, _))) =
+                (increment, Self::keyword(rest, Keyword::Increment))
+            {
+                let next = Self::keyword(next, Keyword::By).map_or(next, |(next, _)| next);
+                let (next, value) = Self::signed_integer(next)?;
+                increment = Some(value);
+                rest = next;
+            } else {
+                break;
             }
-        } else {
-            Err(Error::NoMatch)
         }
+
+        Ok((
+            rest,
+            CreateSequenceStatement {
+                name,
+                start,
+                increment,
+            },
+        ))
     }
 
-    fn from_keyword(input: &[Token]) -> Result<(&[Token], ())> {
-        if let Some(Token {
-            kind: TokenKind::Keyword(Keyword::From),
-            ..
-        }) = input.get(0)
-        {
-            Ok((&input[1..], ()))
-        } else {
-            Err(Error::NoMatch)
-        }
+    fn create_view_statement(input: &[Token]) -> Result<(&[Token], CreateViewStatement)> {
+        let (rest, _) = Self::keyword(input, Keyword::Create)?;
+        let (rest, _) = Self::keyword(rest, Keyword::View)?;
+        let (rest, name) = Self::table_name(rest)?;
+
+        let (rest, columns) = match Self::column_list(rest) {
+            Ok((rest, columns)) => (rest, columns),
+            Err(Error::NoMatch) => (rest, Vec::new()),
+            Err(err) => return Err(err),
+        };
+
+        let (start, _) = Self::keyword(rest, Keyword::As)?;
+        let (rest, query) = Self::query(start)?;
+
+        Ok((
+            rest,
+            CreateViewStatement {
+                name,
+                columns,
+                query: Box::new(query),
+                sql: Self::text(start, rest),
+            },
+        ))
     }
 
-    fn select_keyword(input: &[Token]) -> Result<(&[Token], ())> {
-        if let Some(Token {
-            kind: TokenKind::Keyword(Keyword::Select),
-            ..
-        }) = input.get(0)
-        {
-            Ok((&input[1..], ()))
-        } else {
-            Err(Error::NoMatch)
+    fn signed_integer(input: &[Token]) -> Result<(&[Token], i64)> {
+        let (rest, negative) = match Self::symbol(input, TokenKind::Minus) {
+            Ok((rest, _)) => (rest, true),
+            Err(_) => (input, false),
+        };
+
+        match Self::whitespace(rest).first() {
+            Some(Token {
+                kind: TokenKind::Integer(value),
+                ..
+            }) => {
+                let value = if negative { -value } else { *value };
+                Ok((&Self::whitespace(rest)[1..], value))
+            }
+            _ => Err(Self::no_match(Self::whitespace(rest))),
         }
     }
 
-    fn values(input: &[Token]) -> Result<(&[Token], Vec<Value>)> {
-        let mut position = input;
-        let mut values = Vec::new();
-        let mut expect_value = true;
+    /// Parses `(column type [constraints], ..., [table constraints])`, the
+    /// constraints of a column become table constraints on that column.
+    fn table_schema<'i>(input: &'i [Token], table: &str) -> Result<(&'i [Token], TableSchema)> {
+        let (mut rest, _) = Self::symbol(input, TokenKind::LeftParenthesis)?;
+        let mut columns = Vec::new();
+        let mut constraints = Vec::new();
 
-        while !position.is_empty() {
-            let token = position.get(0);
+        loop {
+            rest = match Self::table_constraint(rest) {
+                Ok((rest, constraint)) => {
+                    constraints.push(constraint);
+                    rest
+                }
+                Err(Error::NoMatch) => {
+                    let (rest, column) = Self::column_definition(rest, table, &mut constraints)?;
+                    columns.push(column);
+                    rest
+                }
+                Err(err) => return Err(err),
+            };
 
-            if let Some(Token {
-                kind: TokenKind::Whitespace,
-                ..
-            }) = token
-            {
-                position = &position[1..];
-                continue;
+            match Self::symbol(rest, TokenKind::Comma) {
+                Ok((next, _)) => rest = next,
+                Err(_) => break,
             }
+        }
 
-            if let (
-                Some(Token {
-                    kind: TokenKind::String(value),
-                    ..
-                }),
-                true,
-            ) = (token, expect_value)
-            {
-                position = &position[1..];
-                values.push(Value::String(value.clone()));
-                expect_value = false;
-                continue;
-            }
+        let (rest, _) = Self::symbol(rest, TokenKind::RightParenthesis)?;
+        Ok((rest, TableSchema::new(columns, constraints)))
+    }
 
-            if let (
-                Some(Token {
-                    kind: TokenKind::Integer(value),
-                    ..
-                }),
-                true,
-            ) = (token, expect_value)
-            {
-                position = &position[1..];
-                values.push(Value::Int(*value));
-                expect_value = false;
-                continue;
-            }
+    /// Parses `name type` or `name SERIAL` followed by `NOT NULL`, `NULL`,
+    /// `AUTOINCREMENT`, `DEFAULT expression`
+    /// and `[CONSTRAINT name] PRIMARY KEY | UNIQUE | CHECK (condition) |
+    /// REFERENCES table [(column)] [ON DELETE action]`.
+    fn column_definition<'i>(
+        input: &'i [Token],
+        table: &str,
+        constraints: &mut Vec<Constraint>,
+    ) -> Result<(&'i [Token], Column)> {
+        let (rest, name) = Self::identifier(input)?;
 
-            if let (
-                Some(Token {
-                    kind: TokenKind::Comma,
-                    ..
-                }),
-                false,
-            ) = (token, expect_value)
-            {
-                position = &position[1..];
-                expect_value = true;
-                continue;
+        // SERIAL is short for int NOT NULL AUTOINCREMENT
+        let (mut rest, mut column) = match Self::identifier(rest) {
+            Ok((rest, kind)) if kind.eq_ignore_ascii_case("serial") => {
+                let mut column = Column::new(&name, ColumnKind::Int);
+                column.set_not_null(true);
+                column.set_autoincrement(true);
+                (rest, column)
+            }
+            _ => {
+                let (rest, kind) = Self::column_kind(rest)?;
+                (rest, Column::new(&name, kind))
             }
+        };
 
-            if !expect_value {
+        loop {
+            let (next, constraint_name) = Self::constraint_name(rest)?;
+
+            let kind = if let Ok((next, _)) = Self::keyword(next, Keyword::Primary) {
+                let (next, _) = Self::keyword(next, Keyword::Key)?;
+                rest = next;
+                ConstraintKind::PrimaryKey {
+                    columns: vec![name.clone()],
+                }
+            } else if let Ok((next, _)) = Self::keyword(next, Keyword::Unique) {
+                rest = next;
+                ConstraintKind::Unique {
+                    columns: vec![name.clone()],
+                }
+            } else if let Ok((next, expression)) = Self::check(next) {
+                rest = next;
+                ConstraintKind::Check { expression }
+            } else if let Ok((next, kind)) = Self::references(next, vec![name.clone()]) {
+                rest = next;
+                kind
+            } else if constraint_name.is_some() {
+                return Err(Error::NoMatch);
+            } else if let Ok((next, _)) = Self::keyword(next, Keyword::Not) {
+                let (next, _) = Self::keyword(next, Keyword::Null)?;
+                column.set_not_null(true);
+                rest = next;
+                continue;
+            } else if let Ok((next, _)) = Self::keyword(next, Keyword::Null) {
+                column.set_not_null(false);
+                rest = next;
+                continue;
+            } else if let Ok((next, _)) = Self::keyword(next, Keyword::Autoincrement) {
+                column.set_autoincrement(true);
+                rest = next;
+                continue;
+            } else if let Ok((next, _)) = Self::keyword(next, Keyword::Default) {
+                let (after, _) = Self::expression(next)?;
+                column.set_default(Some(Self::text(next, after)));
+                rest = after;
+                continue;
+            } else {
                 break;
-            }
-        }
+            };
 
-        if values.is_empty() {
-            return Err(Error::NoMatch);
+            // Checks on a column are named after it rather than the table
+            let name = match (constraint_name, &kind) {
+                (Some(name), _) => name,
+                (None, ConstraintKind::Check { .. }) => format!("{}_{}_check", table, name),
+                (None, _) => String::new(),
+            };
+
+            constraints.push(Constraint { name, kind });
         }
 
-        Ok((position, values))
+        Ok((rest, column))
     }
 
-    fn columns(input: &[Token]) -> Result<(&[Token], Vec<String>)> {
-        let mut position = input;
-        let mut columns = Vec::new();
-        let mut expect_identifier = true;
+    /// Parses `[CONSTRAINT name] PRIMARY KEY (columns) | UNIQUE (columns) |
+    /// CHECK (condition) | FOREIGN KEY (columns) REFERENCES ...`.
+    fn table_constraint(input: &[Token]) -> Result<(&[Token], Constraint)> {
+        let (rest, name) = Self::constraint_name(input)?;
 
-        while !position.is_empty() {
-            let token = position.get(0);
+        let (rest, kind) = if let Ok((rest, _)) = Self::keyword(rest, Keyword::Primary) {
+            let (rest, _) = Self::keyword(rest, Keyword::Key)?;
+            let (rest, columns) = Self::column_list(rest)?;
+            (rest, ConstraintKind::PrimaryKey { columns })
+        } else if let Ok((rest, _)) = Self::keyword(rest, Keyword::Unique) {
+            let (rest, columns) = Self::column_list(rest)?;
+            (rest, ConstraintKind::Unique { columns })
+        } else if let Ok((rest, _)) = Self::keyword(rest, Keyword::Foreign) {
+            let (rest, _) = Self::keyword(rest, Keyword::Key)?;
+            let (rest, columns) = Self::column_list(rest)?;
+            Self::references(rest, columns)?
+        } else {
+            let (rest, expression) = Self::check(rest)?;
+            (rest, ConstraintKind::Check { expression })
+        };
 
-            if let Some(Token {
-                kind: TokenKind::Whitespace,
-                ..
-            }) = token
-            {
-                position = &position[1..];
-                continue;
-            }
+        let name = name.unwrap_or_default();
+        Ok((rest, Constraint { name, kind }))
+    }
 
-            if let (
-                Some(Token {
-                    kind: TokenKind::Identifier(iden),
-                    ..
-                }),
-                true,
-            ) = (token, expect_identifier)
-            {
-                columns.push(iden.clone());
-                position = &position[1..];
-                expect_identifier = false;
-                continue;
-            }
+    /// Parses `REFERENCES table [(columns)] [ON DELETE action]` of a foreign
+    /// key on `columns`.
+    fn references(input: &[Token], columns: Vec<String>) -> Result<(&[Token], ConstraintKind)> {
+        let (rest, _) = Self::keyword(input, Keyword::References)?;
+        let (rest, table) = Self::table_name(rest)?;
 
-            if let (
-                Some(Token {
-                    kind: TokenKind::Comma,
-                    ..
-                }),
-                false,
-            ) = (token, expect_identifier)
-            {
-                position = &position[1..];
-                expect_identifier = true;
-                continue;
-            }
+        let (rest, references) = match Self::column_list(rest) {
+            Ok((rest, references)) => (rest, references),
+            Err(Error::NoMatch) => (rest, Vec::new()),
+            Err(err) => return Err(err),
+        };
 
-            if !expect_identifier {
-                break;
+        let (rest, on_delete) = match Self::keyword(rest, Keyword::On) {
+            Ok((rest, _)) => {
+                let (rest, _) = Self::keyword(rest, Keyword::Delete)?;
+                Self::referential_action(rest)?
             }
+            Err(_) => (rest, ReferentialAction::NoAction),
+        };
+
+        Ok((
+            rest,
+            ConstraintKind::ForeignKey {
+                columns,
+                table,
+                references,
+                on_delete,
+            },
+        ))
+    }
+
+    fn referential_action(input: &[Token]) -> Result<(&[Token], ReferentialAction)> {
+        if let Ok((rest, _)) = Self::keyword(input, Keyword::Cascade) {
+            return Ok((rest, ReferentialAction::Cascade));
         }
 
-        if columns.is_empty() {
-            return Err(Error::NoMatch);
+        if let Ok((rest, _)) = Self::keyword(input, Keyword::Restrict) {
+            return Ok((rest, ReferentialAction::Restrict));
         }
 
-        match position.get(0) {
-            Some(Token {
-                kind: TokenKind::Whitespace,
-                ..
-            }) => Ok((&position[1..], columns)),
-            _ => Ok((position, columns)),
+        if let Ok((rest, _)) = Self::keyword(input, Keyword::Set) {
+            let (rest, _) = Self::keyword(rest, Keyword::Null)?;
+            return Ok((rest, ReferentialAction::SetNull));
+        }
+
+        let (rest, _) = Self::keyword(input, Keyword::No)?;
+        let (rest, _) = Self::keyword(rest, Keyword::Action)?;
+        Ok((rest, ReferentialAction::NoAction))
+    }
+
+    fn constraint_name(input: &[Token]) -> Result<(&[Token], Option<String>)> {
+        match Self::keyword(input, Keyword::Constraint) {
+            Ok((rest, _)) => {
+                let (rest, name) = Self::identifier(rest)?;
+                Ok((rest, Some(name)))
+            }
+            Err(_) => Ok((input, None)),
+        }
+    }
+
+    /// Parses `CHECK (condition)`, returning the condition as SQL.
+    fn check(input: &[Token]) -> Result<(&[Token], String)> {
+        let (rest, _) = Self::keyword(input, Keyword::Check)?;
+        let (start, _) = Self::symbol(rest, TokenKind::LeftParenthesis)?;
+        let (end, _) = Self::where_expression(start)?;
+        let (rest, _) = Self::symbol(end, TokenKind::RightParenthesis)?;
+        Ok((rest, Self::text(start, end)))
+    }
+
+    /// SQL of the tokens consumed from `input` to get to `rest`.
+    fn text(input: &[Token], rest: &[Token]) -> String {
+        input[..input.len() - rest.len()]
+            .iter()
+            .map(|token| token.to_string())
+            .collect::<String>()
+            .trim()
+            .to_string()
+    }
+
+    /// Parses `(column, ...)`.
+    fn column_list(input: &[Token]) -> Result<(&[Token], Vec<String>)> {
+        let (rest, _) = Self::symbol(input, TokenKind::LeftParenthesis)?;
+        let (mut rest, first) = Self::identifier(rest)?;
+        let mut columns = vec![first];
+
+        while let Ok((next, _)) = Self::symbol(rest, TokenKind::Comma) {
+            let (next, column) = Self::identifier(next)?;
+            columns.push(column);
+            rest = next;
         }
+
+        let (rest, _) = Self::symbol(rest, TokenKind::RightParenthesis)?;
+        Ok((rest, columns))
+    }
+
+    /// Parses `(expression, ...)`, one row of a VALUES clause.
+    fn row_values(input: &[Token]) -> Result<(&[Token], Vec<Expression>)> {
+        let (rest, _) = Self::symbol(input, TokenKind::LeftParenthesis)?;
+        let (rest, values) = Self::values(rest)?;
+        let (rest, _) = Self::symbol(rest, TokenKind::RightParenthesis)?;
+        Ok((rest, values))
+    }
+
+    fn values_keyword(input: &[Token]) -> Result<(&[Token], ())> {
+        Self::keyword(input, Keyword::Values)
+    }
+
+    fn insert_keyword(input: &[Token]) -> Result<(&[Token], ())> {
+        Self::keyword(input, Keyword::Insert)
+    }
+
+    fn into_keyword(input: &[Token]) -> Result<(&[Token], ())> {
+        Self::keyword(input, Keyword::Into)
+    }
+
+    fn delete_statement(input: &[Token]) -> Result<(&[Token], DeleteStatement)> {
+        let (rest, _) = Self::keyword(input, Keyword::Delete)?;
+        let (rest, _) = Self::from_keyword(rest)?;
+        let (rest, table) = Self::table_name(rest)?;
+
+        let (rest, r#where) = match Self::where_clause(rest) {
+            Ok((rest, r#where)) => (rest, Some(r#where)),
+            Err(Error::NoMatch) => (rest, None),
+            Err(err) => return Err(err),
+        };
+
+        let (rest, returning) = Self::returning(rest)?;
+
+        Ok((
+            rest,
+            DeleteStatement {
+                table,
+                r#where,
+                returning,
+            },
+        ))
+    }
+
+    fn update_statement(input: &[Token]) -> Result<(&[Token], UpdateStatement)> {
+        let (rest, _) = Self::keyword(input, Keyword::Update)?;
+        let (rest, table) = Self::table_name(rest)?;
+        let (rest, assignments) = Self::assignments(rest)?;
+
+        let (rest, r#where) = match Self::where_clause(rest) {
+            Ok((rest, r#where)) => (rest, Some(r#where)),
+            Err(Error::NoMatch) => (rest, None),
+            Err(err) => return Err(err),
+        };
+
+        let (rest, returning) = Self::returning(rest)?;
+
+        Ok((
+            rest,
+            UpdateStatement {
+                assignments,
+                table,
+                r#where,
+                returning,
+            },
+        ))
+    }
+
+    /// Parses `SET field = expression, ...`.
+    fn assignments(input: &[Token]) -> Result<(&[Token], Vec<UpdateAssignment>)> {
+        let (rest, _) = Self::keyword(input, Keyword::Set)?;
+        let (mut rest, first) = Self::assignment(rest)?;
+        let mut assignments = vec![first];
+
+        while let Ok((next, _)) = Self::symbol(rest, TokenKind::Comma) {
+            let (next, assignment) = Self::assignment(next)?;
+            assignments.push(assignment);
+            rest = next;
+        }
+
+        Ok((rest, assignments))
+    }
+
+    fn assignment(input: &[Token]) -> Result<(&[Token], UpdateAssignment)> {
+        let (rest, field) = Self::identifier(input)?;
+        let (rest, _) = Self::symbol(rest, TokenKind::Equals)?;
+        let (rest, value) = Self::expression(rest)?;
+
+        Ok((rest, UpdateAssignment { field, value }))
+    }
+
+    fn select_statement(input: &[Token]) -> Result<(&[Token], SelectStatement)> {
+        let (rest, _) = Self::select_keyword(input)?;
+        let (rest, distinct) = Self::distinct(rest)?;
+        let (rest, selections) = Self::selections(rest)?;
+        let (rest, _) = Self::from_keyword(rest)?;
+        let (mut rest, table) = Self::table_reference(rest)?;
+        let mut joins = Vec::new();
+
+        loop {
+            match Self::join(rest) {
+                Ok((next, join)) => {
+                    joins.push(join);
+                    rest = next;
+                }
+                Err(Error::NoMatch) => break,
+                Err(err) => return Err(err),
+            }
+        }
+
+        let (rest, r#where) = match Self::where_clause(rest) {
+            Ok((rest, r#where)) => (rest, Some(r#where)),
+            Err(Error::NoMatch) => (rest, None),
+            Err(err) => return Err(err),
+        };
+
+        let (rest, group_by) = match Self::group_by_clause(rest) {
+            Ok((rest, group_by)) => (rest, group_by),
+            Err(Error::NoMatch) => (rest, Vec::new()),
+            Err(err) => return Err(err),
+        };
+
+        let (rest, having) = match Self::having_clause(rest) {
+            Ok((rest, having)) => (rest, Some(having)),
+            Err(Error::NoMatch) => (rest, None),
+            Err(err) => return Err(err),
+        };
+
+        let (rest, order_by) = match Self::order_by_clause(rest) {
+            Ok((rest, order_by)) => (rest, order_by),
+            Err(Error::NoMatch) => (rest, Vec::new()),
+            Err(err) => return Err(err),
+        };
+
+        let (rest, pagination) = Self::pagination(rest)?;
+
+        Ok((
+            rest,
+            SelectStatement {
+                distinct,
+                selections,
+                table,
+                joins,
+                r#where,
+                group_by,
+                having,
+                order_by,
+                pagination,
+            },
+        ))
+    }
+
+    /// Parses `[WITH [RECURSIVE] name [(columns)] AS (query), ...]` followed
+    /// by SELECT statements combined with UNION, INTERSECT and EXCEPT.
+    fn query(input: &[Token]) -> Result<(&[Token], Query)> {
+        let (rest, recursive, with) = match Self::keyword(input, Keyword::With) {
+            Ok((rest, _)) => {
+                let (rest, recursive) = match Self::keyword(rest, Keyword::Recursive) {
+                    Ok((rest, _)) => (rest, true),
+                    Err(_) => (rest, false),
+                };
+
+                let (mut rest, first) = Self::common_table_expression(rest)?;
+                let mut with = vec![first];
+
+                while let Ok((next, _)) = Self::symbol(rest, TokenKind::Comma) {
+                    let (next, table) = Self::common_table_expression(next)?;
+                    with.push(table);
+                    rest = next;
+                }
+
+                (rest, recursive, with)
+            }
+            Err(_) => (input, false, Vec::new()),
+        };
+
+        let (rest, query) = Self::set_expression(rest)?;
+
+        Ok((
+            rest,
+            Query {
+                recursive,
+                with,
+                ..query
+            },
+        ))
+    }
+
+    fn common_table_expression(input: &[Token]) -> Result<(&[Token], CommonTableExpression)> {
+        let (rest, name) = Self::identifier(input)?;
+
+        let (rest, columns) = match Self::column_list(rest) {
+            Ok((rest, columns)) => (rest, columns),
+            Err(Error::NoMatch) => (rest, Vec::new()),
+            Err(err) => return Err(err),
+        };
+
+        let (rest, _) = Self::keyword(rest, Keyword::As)?;
+        let (rest, _) = Self::symbol(rest, TokenKind::LeftParenthesis)?;
+        let (rest, query) = Self::query(rest)?;
+        let (rest, _) = Self::symbol(rest, TokenKind::RightParenthesis)?;
+
+        Ok((
+            rest,
+            CommonTableExpression {
+                name,
+                columns,
+                query,
+            },
+        ))
+    }
+
+    /// Parses SELECT statements combined with `UNION | INTERSECT | EXCEPT [ALL]`.
+    /// INTERSECT binds tighter than the others, which apply from left to
+    /// right. ORDER BY and LIMIT after the last SELECT apply to the combined
+    /// rows. The query has no WITH clause.
+    fn set_expression(input: &[Token]) -> Result<(&[Token], Query)> {
+        let (mut rest, first) = Self::select_statement(input)?;
+        let mut statements = vec![first];
+        let mut operators = Vec::new();
+
+        loop {
+            let (next, operator) = match Self::set_operator(rest) {
+                Ok(result) => result,
+                Err(Error::NoMatch) => break,
+                Err(err) => return Err(err),
+            };
+
+            let (next, all) = match Self::keyword(next, Keyword::All) {
+                Ok((next, _)) => (next, true),
+                Err(_) => (next, false),
+            };
+
+            let (next, statement) = Self::select_statement(next)?;
+
+            statements.push(statement);
+            operators.push((operator, all));
+            rest = next;
+        }
+
+        let (order_by, pagination) = match operators.is_empty() {
+            true => (Vec::new(), None),
+            false => {
+                let last = statements.last_mut().unwrap();
+                (std::mem::take(&mut last.order_by), last.pagination.take())
+            }
+        };
+
+        if !operators.is_empty()
+            && statements
+                .iter()
+                .any(|statement| !statement.order_by.is_empty() || statement.pagination.is_some())
+        {
+            return Err(Error::Invalid(String::from(
+                "ORDER BY and LIMIT must follow the last query of a set operation",
+            )));
+        }
+
+        let mut statements = statements
+            .into_iter()
+            .map(|statement| SetExpression::Select(Box::new(statement)));
+
+        let mut terms = vec![statements.next().unwrap()];
+        let mut term_operators = Vec::new();
+
+        for ((operator, all), right) in operators.into_iter().zip(statements) {
+            if operator == SetOperator::Intersect {
+                let left = terms.pop().unwrap();
+
+                terms.push(SetExpression::Operation {
+                    operator,
+                    left: Box::new(left),
+                    right: Box::new(right),
+                    all,
+                });
+            } else {
+                terms.push(right);
+                term_operators.push((operator, all));
+            }
+        }
+
+        let mut terms = terms.into_iter();
+        let mut expression = terms.next().unwrap();
+
+        for ((operator, all), right) in term_operators.into_iter().zip(terms) {
+            expression = SetExpression::Operation {
+                operator,
+                left: Box::new(expression),
+                right: Box::new(right),
+                all,
+            };
+        }
+
+        Ok((
+            rest,
+            Query {
+                recursive: false,
+                with: Vec::new(),
+                body: expression,
+                order_by,
+                pagination,
+            },
+        ))
+    }
+
+    fn set_operator(input: &[Token]) -> Result<(&[Token], SetOperator)> {
+        [
+            (Keyword::Union, SetOperator::Union),
+            (Keyword::Intersect, SetOperator::Intersect),
+            (Keyword::Except, SetOperator::Except),
+        ]
+        .into_iter()
+        .find_map(|(keyword, operator)| {
+            Self::keyword(input, keyword)
+                .ok()
+                .map(|(rest, _)| (rest, operator))
+        })
+        .ok_or(Error::NoMatch)
+    }
+
+    /// Parses the optional `DISTINCT [ON (expression, ...)]` of a SELECT.
+    fn distinct(input: &[Token]) -> Result<(&[Token], Option<Distinct>)> {
+        let Ok((rest, _)) = Self::keyword(input, Keyword::Distinct) else {
+            return Ok((input, None));
+        };
+
+        let Ok((rest, _)) = Self::keyword(rest, Keyword::On) else {
+            return Ok((rest, Some(Distinct::Rows)));
+        };
+
+        let (rest, _) = Self::symbol(rest, TokenKind::LeftParenthesis)?;
+        let (mut rest, first) = Self::expression(rest)?;
+        let mut expressions = vec![first];
+
+        while let Ok((next, _)) = Self::symbol(rest, TokenKind::Comma) {
+            let (next, expression) = Self::expression(next)?;
+            expressions.push(expression);
+            rest = next;
+        }
+
+        let (rest, _) = Self::symbol(rest, TokenKind::RightParenthesis)?;
+
+        Ok((rest, Some(Distinct::On(expressions))))
+    }
+
+    fn group_by_clause(input: &[Token]) -> Result<(&[Token], Vec<Expression>)> {
+        let (rest, _) = Self::keyword(input, Keyword::Group)?;
+        let (rest, _) = Self::keyword(rest, Keyword::By)?;
+        let (mut rest, first) = Self::expression(rest)?;
+        let mut group_by = vec![first];
+
+        while let Ok((next, _)) = Self::symbol(rest, TokenKind::Comma) {
+            let (next, expression) = Self::expression(next)?;
+            group_by.push(expression);
+            rest = next;
+        }
+
+        Ok((rest, group_by))
+    }
+
+    fn having_clause(input: &[Token]) -> Result<(&[Token], WhereExpression)> {
+        let (rest, _) = Self::keyword(input, Keyword::Having)?;
+        Self::where_expression(rest)
+    }
+
+    fn order_by_clause(input: &[Token]) -> Result<(&[Token], Vec<OrderBy>)> {
+        let (rest, _) = Self::keyword(input, Keyword::Order)?;
+        let (rest, _) = Self::keyword(rest, Keyword::By)?;
+        let (mut rest, first) = Self::order_by(rest)?;
+        let mut order_by = vec![first];
+
+        while let Ok((next, _)) = Self::symbol(rest, TokenKind::Comma) {
+            let (next, item) = Self::order_by(next)?;
+            order_by.push(item);
+            rest = next;
+        }
+
+        Ok((rest, order_by))
+    }
+
+    fn order_by(input: &[Token]) -> Result<(&[Token], OrderBy)> {
+        let (rest, expression) = Self::expression(input)?;
+
+        let (rest, direction) = if let Ok((rest, _)) = Self::keyword(rest, Keyword::Desc) {
+            (rest, Direction::Descending)
+        } else if let Ok((rest, _)) = Self::keyword(rest, Keyword::Asc) {
+            (rest, Direction::Ascending)
+        } else {
+            (rest, Direction::Ascending)
+        };
+
+        let (rest, nulls) = match Self::keyword(rest, Keyword::Nulls) {
+            Ok((rest, _)) => {
+                if let Ok((rest, _)) = Self::keyword(rest, Keyword::First) {
+                    (rest, Some(Nulls::First))
+                } else {
+                    let (rest, _) = Self::keyword(rest, Keyword::Last)?;
+                    (rest, Some(Nulls::Last))
+                }
+            }
+            Err(_) => (rest, None),
+        };
+
+        Ok((
+            rest,
+            OrderBy {
+                expression,
+                direction,
+                nulls,
+            },
+        ))
+    }
+
+    /// Parses `[LIMIT n] [OFFSET m]`, in either order.
+    fn pagination(input: &[Token]) -> Result<(&[Token], Option<Pagination>)> {
+        let mut rest = input;
+        let mut limit = None;
+        let mut offset = None;
+
+        loop {
+            if limit.is_none() {
+                if let Ok((next, _)) = Self::keyword(rest, Keyword::Limit) {
+                    let (next, count) = Self::count(next)?;
+                    limit = Some(count);
+                    rest = next;
+                    continue;
+                }
+            }
+
+            if offset.is_none() {
+                if let Ok((next, _)) = Self::keyword(rest, Keyword::Offset) {
+                    let (next, count) = Self::count(next)?;
+                    offset = Some(count);
+                    rest = next;
+                    continue;
+                }
+            }
+
+            break;
+        }
+
+        match (limit, offset) {
+            (None, None) => Ok((rest, None)),
+            (limit, offset) => Ok((rest, Some(Pagination { limit, offset }))),
+        }
+    }
+
+    /// Parses a non-negative integer such as a LIMIT or the length of a type.
+    fn count(input: &[Token]) -> Result<(&[Token], usize)> {
+        let input = Self::whitespace(input);
+
+        match input.first().map(|t| &t.kind) {
+            Some(TokenKind::Integer(value)) => Ok((&input[1..], *value as usize)),
+            _ => Err(Error::InvalidLiteral(String::from(
+                "Expected a non-negative integer",
+            ))),
+        }
+    }
+
+    fn table_name(input: &[Token]) -> Result<(&[Token], String)> {
+        Self::identifier(input)
+    }
+
+    /// Parses `table [[AS] alias]` or `(SELECT ...) [AS] alias`.
+    fn table_reference(input: &[Token]) -> Result<(&[Token], TableReference)> {
+        let (rest, source) = match Self::subquery(input) {
+            Ok((rest, subquery)) => (rest, TableSource::Subquery(Box::new(subquery))),
+            Err(Error::NoMatch) => {
+                let (rest, name) = Self::table_name(input)?;
+                (rest, TableSource::Table(name))
+            }
+            Err(err) => return Err(err),
+        };
+
+        let (rest, alias) = match Self::keyword(rest, Keyword::As) {
+            Ok((rest, _)) => {
+                let (rest, alias) = Self::identifier(rest)?;
+                (rest, Some(alias))
+            }
+            Err(_) => match Self::identifier(rest) {
+                Ok((rest, alias)) => (rest, Some(alias)),
+                Err(_) => (rest, None),
+            },
+        };
+
+        if alias.is_none() && matches!(source, TableSource::Subquery(_)) {
+            return Err(Error::Invalid(String::from(
+                "Subquery in FROM must have an alias",
+            )));
+        }
+
+        Ok((rest, TableReference { source, alias }))
+    }
+
+    /// Parses a parenthesised SELECT statement.
+    fn subquery(input: &[Token]) -> Result<(&[Token], SelectStatement)> {
+        let (rest, _) = Self::symbol(input, TokenKind::LeftParenthesis)?;
+        let (rest, subquery) = Self::select_statement(rest)?;
+        let (rest, _) = Self::symbol(rest, TokenKind::RightParenthesis)?;
+
+        Ok((rest, subquery))
+    }
+
+    /// Parses one join of a FROM clause, a comma is a cross join.
+    fn join(input: &[Token]) -> Result<(&[Token], Join)> {
+        let (rest, kind) = if let Ok((rest, _)) = Self::symbol(input, TokenKind::Comma) {
+            let (rest, table) = Self::table_reference(rest)?;
+
+            return Ok((
+                rest,
+                Join {
+                    kind: JoinKind::Cross,
+                    table,
+                    on: None,
+                },
+            ));
+        } else if let Ok((rest, _)) = Self::keyword(input, Keyword::Cross) {
+            (rest, JoinKind::Cross)
+        } else if let Ok((rest, _)) = Self::keyword(input, Keyword::Left) {
+            let rest = Self::keyword(rest, Keyword::Outer).map_or(rest, |(rest, _)| rest);
+            (rest, JoinKind::Left)
+        } else if let Ok((rest, _)) = Self::keyword(input, Keyword::Inner) {
+            (rest, JoinKind::Inner)
+        } else {
+            (input, JoinKind::Inner)
+        };
+
+        let (rest, _) = Self::keyword(rest, Keyword::Join)?;
+        let (rest, table) = Self::table_reference(rest)?;
+
+        if kind == JoinKind::Cross {
+            return Ok((
+                rest,
+                Join {
+                    kind,
+                    table,
+                    on: None,
+                },
+            ));
+        }
+
+        let (rest, _) = Self::keyword(rest, Keyword::On)?;
+        let (rest, on) = Self::where_expression(rest)?;
+
+        Ok((
+            rest,
+            Join {
+                kind,
+                table,
+                on: Some(on),
+            },
+        ))
+    }
+
+    fn from_keyword(input: &[Token]) -> Result<(&[Token], ())> {
+        Self::keyword(input, Keyword::From)
+    }
+
+    fn select_keyword(input: &[Token]) -> Result<(&[Token], ())> {
+        Self::keyword(input, Keyword::Select)
+    }
+
+    fn where_clause(input: &[Token]) -> Result<(&[Token], WhereExpression)> {
+        let (rest, _) = Self::keyword(input, Keyword::Where)?;
+        Self::where_expression(rest)
+    }
+
+    fn where_expression(input: &[Token]) -> Result<(&[Token], WhereExpression)> {
+        let (mut rest, first) = Self::where_and(input)?;
+        let mut expressions = vec![first];
+
+        while let Ok((next, _)) = Self::keyword(rest, Keyword::Or) {
+            let (next, expression) = Self::where_and(next)?;
+            expressions.push(expression);
+            rest = next;
+        }
+
+        match expressions.len() {
+            1 => Ok((rest, expressions.remove(0))),
+            _ => Ok((rest, WhereExpression::Or(expressions))),
+        }
+    }
+
+    fn where_and(input: &[Token]) -> Result<(&[Token], WhereExpression)> {
+        let (mut rest, first) = Self::where_not(input)?;
+        let mut expressions = vec![first];
+
+        while let Ok((next, _)) = Self::keyword(rest, Keyword::And) {
+            let (next, expression) = Self::where_not(next)?;
+            expressions.push(expression);
+            rest = next;
+        }
+
+        match expressions.len() {
+            1 => Ok((rest, expressions.remove(0))),
+            _ => Ok((rest, WhereExpression::And(expressions))),
+        }
+    }
+
+    fn where_not(input: &[Token]) -> Result<(&[Token], WhereExpression)> {
+        if let Ok((rest, _)) = Self::keyword(input, Keyword::Not) {
+            let (rest, expression) = Self::where_not(rest)?;
+            return Ok((rest, WhereExpression::Not(Box::new(expression))));
+        }
+
+        if let Ok((rest, _)) = Self::keyword(input, Keyword::Exists) {
+            let (rest, subquery) = Self::subquery(rest)?;
+            return Ok((rest, WhereExpression::Exists(Box::new(subquery))));
+        }
+
+        // A parenthesis opens either a nested condition or an operand such as `(a + b) > c`
+        if let Ok((rest, _)) = Self::symbol(input, TokenKind::LeftParenthesis) {
+            if let Ok((rest, expression)) = Self::where_expression(rest) {
+                if let Ok((rest, _)) = Self::symbol(rest, TokenKind::RightParenthesis) {
+                    return Ok((rest, expression));
+                }
+            }
+        }
+
+        Self::predicate(input)
+    }
+
+    /// Parses a comparison, `IS [NOT] NULL`, `[NOT] LIKE`, `[NOT] ILIKE`,
+    /// `[NOT] CONTAINS`, `[NOT] IN (list)`, `[NOT] IN (SELECT ...)` or
+    /// `[NOT] BETWEEN low AND high`.
+    fn predicate(input: &[Token]) -> Result<(&[Token], WhereExpression)> {
+        let (rest, field) = Self::expression(input)?;
+
+        if let Ok((rest, operator)) = Self::operator(rest) {
+            let (rest, value) = Self::expression(rest)?;
+            return Ok((rest, Self::condition(field, operator, value)));
+        }
+
+        if let Ok((rest, _)) = Self::keyword(rest, Keyword::Is) {
+            let (rest, negated) = match Self::keyword(rest, Keyword::Not) {
+                Ok((rest, _)) => (rest, true),
+                Err(_) => (rest, false),
+            };
+
+            let (rest, _) = Self::keyword(rest, Keyword::Null)?;
+            let predicate = WhereExpression::IsNull(field);
+
+            return match negated {
+                true => Ok((rest, WhereExpression::Not(Box::new(predicate)))),
+                false => Ok((rest, predicate)),
+            };
+        }
+
+        let (rest, negated) = match Self::keyword(rest, Keyword::Not) {
+            Ok((rest, _)) => (rest, true),
+            Err(_) => (rest, false),
+        };
+
+        let operator = [
+            (Keyword::Like, Operator::Like),
+            (Keyword::Ilike, Operator::ILike),
+            (Keyword::Contains, Operator::Contains),
+        ]
+        .into_iter()
+        .find_map(|(keyword, operator)| {
+            Self::keyword(rest, keyword)
+                .ok()
+                .map(|(rest, _)| (rest, operator))
+        });
+
+        let (rest, predicate) = if let Some((rest, operator)) = operator {
+            let (rest, value) = Self::expression(rest)?;
+            (rest, Self::condition(field, operator, value))
+        } else if let Ok((rest, _)) = Self::keyword(rest, Keyword::In) {
+            match Self::subquery(rest) {
+                Ok((rest, subquery)) => (
+                    rest,
+                    WhereExpression::InSubquery {
+                        expression: field,
+                        subquery: Box::new(subquery),
+                    },
+                ),
+                Err(Error::NoMatch) => {
+                    let (rest, _) = Self::symbol(rest, TokenKind::LeftParenthesis)?;
+                    let (rest, list) = Self::arguments(rest)?;
+
+                    if list.is_empty() {
+                        return Err(Error::InvalidLiteral(String::from(
+                            "Expected at least one value in the IN list",
+                        )));
+                    }
+
+                    (
+                        rest,
+                        WhereExpression::In {
+                            expression: field,
+                            list,
+                        },
+                    )
+                }
+                Err(err) => return Err(err),
+            }
+        } else if let Ok((rest, _)) = Self::keyword(rest, Keyword::Between) {
+            let (rest, low) = Self::expression(rest)?;
+            let (rest, _) = Self::keyword(rest, Keyword::And)?;
+            let (rest, high) = Self::expression(rest)?;
+
+            let between = WhereExpression::And(vec![
+                Self::condition(field.clone(), Operator::GreaterThanEquals, low),
+                Self::condition(field, Operator::SmallerThanEquals, high),
+            ]);
+
+            (rest, between)
+        } else {
+            return Err(Error::NoMatch);
+        };
+
+        match negated {
+            true => Ok((rest, WhereExpression::Not(Box::new(predicate)))),
+            false => Ok((rest, predicate)),
+        }
+    }
+
+    fn condition(field: Expression, operator: Operator, value: Expression) -> WhereExpression {
+        WhereExpression::Condition(WhereCondition {
+            field,
+            operator,
+            value,
+        })
+    }
+
+    fn operator(input: &[Token]) -> Result<(&[Token], Operator)> {
+        let input = Self::whitespace(input);
+
+        let operator = match input.first().map(|t| &t.kind) {
+            Some(TokenKind::Equals) => Operator::Equals,
+            Some(TokenKind::NotEquals) => Operator::NotEquals,
+            Some(TokenKind::GreaterThan) => Operator::GreaterThan,
+            Some(TokenKind::GreaterThanEquals) => Operator::GreaterThanEquals,
+            Some(TokenKind::SmallerThan) => Operator::SmallerThan,
+            Some(TokenKind::SmallerThanEquals) => Operator::SmallerThanEquals,
+            _ => return Err(Self::no_match(input)),
+        };
+
+        Ok((&input[1..], operator))
+    }
+
+    fn expression(input: &[Token]) -> Result<(&[Token], Expression)> {
+        let (mut rest, mut expression) = Self::sum(input)?;
+
+        while let Ok((next, _)) = Self::symbol(rest, TokenKind::Concat) {
+            let (next, right) = Self::sum(next)?;
+            rest = next;
+            expression = Expression::Binary {
+                left: Box::new(expression),
+                operator: BinaryOperator::Concat,
+                right: Box::new(right),
+            };
+        }
+
+        Ok((rest, expression))
+    }
+
+    fn sum(input: &[Token]) -> Result<(&[Token], Expression)> {
+        let (mut rest, mut expression) = Self::product(input)?;
+
+        loop {
+            let operator = if let Ok((next, _)) = Self::symbol(rest, TokenKind::Plus) {
+                rest = next;
+                BinaryOperator::Add
+            } else if let Ok((next, _)) = Self::symbol(rest, TokenKind::Minus) {
+                rest = next;
+                BinaryOperator::Subtract
+            } else {
+                break;
+            };
+
+            let (next, right) = Self::product(rest)?;
+            rest = next;
+            expression = Expression::Binary {
+                left: Box::new(expression),
+                operator,
+                right: Box::new(right),
+            };
+        }
+
+        Ok((rest, expression))
+    }
+
+    fn product(input: &[Token]) -> Result<(&[Token], Expression)> {
+        let (mut rest, mut expression) = Self::term(input)?;
+
+        loop {
+            let operator = if let Ok((next, _)) = Self::symbol(rest, TokenKind::Star) {
+                rest = next;
+                BinaryOperator::Multiply
+            } else if let Ok((next, _)) = Self::symbol(rest, TokenKind::Slash) {
+                rest = next;
+                BinaryOperator::Divide
+            } else {
+                break;
+            };
+
+            let (next, right) = Self::term(rest)?;
+            rest = next;
+            expression = Expression::Binary {
+                left: Box::new(expression),
+                operator,
+                right: Box::new(right),
+            };
+        }
+
+        Ok((rest, expression))
+    }
+
+    fn term(input: &[Token]) -> Result<(&[Token], Expression)> {
+        let (mut rest, mut expression) = Self::primary(input)?;
+
+        loop {
+            let operator = if let Ok((next, _)) = Self::symbol(rest, TokenKind::Arrow) {
+                rest = next;
+                BinaryOperator::JsonGet
+            } else if let Ok((next, _)) = Self::symbol(rest, TokenKind::DoubleArrow) {
+                rest = next;
+                BinaryOperator::JsonGetText
+            } else {
+                break;
+            };
+
+            let (next, key) = Self::primary(rest)?;
+            rest = next;
+            expression = Expression::Binary {
+                left: Box::new(expression),
+                operator,
+                right: Box::new(key),
+            };
+        }
+
+        Ok((rest, expression))
+    }
+
+    fn primary(input: &[Token]) -> Result<(&[Token], Expression)> {
+        match Self::literal(input) {
+            Ok((rest, value)) => return Ok((rest, Expression::Value(value))),
+            Err(Error::NoMatch) => {}
+            Err(err) => return Err(err),
+        }
+
+        match Self::subquery(input) {
+            Ok((rest, subquery)) => return Ok((rest, Expression::Subquery(Box::new(subquery)))),
+            Err(Error::NoMatch) => {}
+            Err(err) => return Err(err),
+        }
+
+        if let Ok((rest, _)) = Self::symbol(input, TokenKind::LeftParenthesis) {
+            let (rest, expression) = Self::expression(rest)?;
+            let (rest, _) = Self::symbol(rest, TokenKind::RightParenthesis)?;
+            return Ok((rest, expression));
+        }
+
+        if let Ok((rest, _)) = Self::symbol(input, TokenKind::Minus) {
+            let (rest, expression) = Self::term(rest)?;
+            return Ok((rest, Expression::Negate(Box::new(expression))));
+        }
+
+        if let Ok((rest, _)) = Self::keyword(input, Keyword::Case) {
+            let (rest, case) = Self::case(rest)?;
+            return Ok((rest, Expression::Case(case)));
+        }
+
+        if let Ok((rest, _)) = Self::keyword(input, Keyword::Cast) {
+            let (rest, _) = Self::symbol(rest, TokenKind::LeftParenthesis)?;
+            let (rest, expression) = Self::expression(rest)?;
+            let (rest, _) = Self::keyword(rest, Keyword::As)?;
+            let (rest, kind) = Self::column_kind(rest)?;
+            let (rest, _) = Self::symbol(rest, TokenKind::RightParenthesis)?;
+
+            return Ok((
+                rest,
+                Expression::Cast {
+                    expression: Box::new(expression),
+                    kind,
+                },
+            ));
+        }
+
+        let (rest, name) = Self::identifier(input)?;
+
+        let Ok((rest, _)) = Self::symbol(rest, TokenKind::LeftParenthesis) else {
+            // `current_date` is called without parentheses in standard SQL
+            if name.eq_ignore_ascii_case("current_date") {
+                let arguments = Vec::new();
+                return Ok((rest, Expression::Function(FunctionCall { name, arguments })));
+            }
+
+            return Ok((rest, Expression::Column(name)));
+        };
+
+        if let Some(function) = AggregateFunction::parse(&name) {
+            let (rest, call) = Self::aggregate(rest, function)?;
+
+            return match Self::over(rest)? {
+                (_, Some(_)) if call.distinct => Err(Error::Invalid(String::from(
+                    "DISTINCT is not supported for window functions",
+                ))),
+                (rest, Some(window)) => Ok((
+                    rest,
+                    Expression::Window(WindowCall {
+                        function: WindowFunction::Aggregate(function),
+                        arguments: call.argument.into_iter().map(|a| *a).collect(),
+                        window,
+                    }),
+                )),
+                (rest, None) => Ok((rest, Expression::Aggregate(call))),
+            };
+        }
+
+        let (rest, arguments) = Self::arguments(rest)?;
+
+        match (WindowFunction::parse(&name), Self::over(rest)?) {
+            (Some(function), (rest, Some(window))) => Ok((
+                rest,
+                Expression::Window(WindowCall {
+                    function,
+                    arguments,
+                    window,
+                }),
+            )),
+            (Some(function), (_, None)) => Err(Error::Invalid(format!(
+                "Window function {}() requires an OVER clause",
+                function.name()
+            ))),
+            (None, (_, Some(_))) => Err(Error::Invalid(format!(
+                "{}() is not a window or aggregate function",
+                name
+            ))),
+            (None, (rest, None)) => Ok((rest, Expression::Function(FunctionCall { name, arguments }))),
+        }
+    }
+
+    /// Parses the optional `OVER ([PARTITION BY ...] [ORDER BY ...] [ROWS frame])`
+    /// after a function call.
+    fn over(input: &[Token]) -> Result<(&[Token], Option<Window>)> {
+        let Ok((rest, _)) = Self::keyword(input, Keyword::Over) else {
+            return Ok((input, None));
+        };
+
+        let (rest, _) = Self::symbol(rest, TokenKind::LeftParenthesis)?;
+
+        let (rest, partition_by) = match Self::keyword(rest, Keyword::Partition) {
+            Ok((rest, _)) => {
+                let (rest, _) = Self::keyword(rest, Keyword::By)?;
+                let (mut rest, first) = Self::expression(rest)?;
+                let mut partition_by = vec![first];
+
+                while let Ok((next, _)) = Self::symbol(rest, TokenKind::Comma) {
+                    let (next, expression) = Self::expression(next)?;
+                    partition_by.push(expression);
+                    rest = next;
+                }
+
+                (rest, partition_by)
+            }
+            Err(_) => (rest, Vec::new()),
+        };
+
+        let (rest, order_by) = match Self::order_by_clause(rest) {
+            Ok((rest, order_by)) => (rest, order_by),
+            Err(Error::NoMatch) => (rest, Vec::new()),
+            Err(err) => return Err(err),
+        };
+
+        let (rest, frame) = match Self::keyword(rest, Keyword::Rows) {
+            Ok((rest, _)) => {
+                let (rest, frame) = Self::frame(rest)?;
+                (rest, Some(frame))
+            }
+            Err(_) => (rest, None),
+        };
+
+        let (rest, _) = Self::symbol(rest, TokenKind::RightParenthesis)?;
+
+        Ok((
+            rest,
+            Some(Window {
+                partition_by,
+                order_by,
+                frame,
+            }),
+        ))
+    }
+
+    /// Parses `BETWEEN start AND end` or `start` after ROWS.
+    fn frame(input: &[Token]) -> Result<(&[Token], WindowFrame)> {
+        let (rest, start, end) = match Self::keyword(input, Keyword::Between) {
+            Ok((rest, _)) => {
+                let (rest, start) = Self::frame_bound(rest)?;
+                let (rest, _) = Self::keyword(rest, Keyword::And)?;
+                let (rest, end) = Self::frame_bound(rest)?;
+                (rest, start, end)
+            }
+            Err(_) => {
+                let (rest, start) = Self::frame_bound(input)?;
+                (rest, start, FrameBound::CurrentRow)
+            }
+        };
+
+        if start == FrameBound::UnboundedFollowing {
+            return Err(Error::Invalid(String::from(
+                "Frame start cannot be UNBOUNDED FOLLOWING",
+            )));
+        }
+
+        if end == FrameBound::UnboundedPreceding {
+            return Err(Error::Invalid(String::from(
+                "Frame end cannot be UNBOUNDED PRECEDING",
+            )));
+        }
+
+        Ok((rest, WindowFrame { start, end }))
+    }
+
+    fn frame_bound(input: &[Token]) -> Result<(&[Token], FrameBound)> {
+        if let Ok((rest, _)) = Self::keyword(input, Keyword::Unbounded) {
+            if let Ok((rest, _)) = Self::keyword(rest, Keyword::Preceding) {
+                return Ok((rest, FrameBound::UnboundedPreceding));
+            }
+
+            let (rest, _) = Self::keyword(rest, Keyword::Following)?;
+            return Ok((rest, FrameBound::UnboundedFollowing));
+        }
+
+        if let Ok((rest, _)) = Self::keyword(input, Keyword::Current) {
+            let (rest, _) = Self::keyword(rest, Keyword::Row)?;
+            return Ok((rest, FrameBound::CurrentRow));
+        }
+
+        let (rest, count) = Self::count(input)?;
+
+        if let Ok((rest, _)) = Self::keyword(rest, Keyword::Preceding) {
+            return Ok((rest, FrameBound::Preceding(count)));
+        }
+
+        let (rest, _) = Self::keyword(rest, Keyword::Following)?;
+        Ok((rest, FrameBound::Following(count)))
+    }
+
+    /// Parses a CASE expression after the CASE keyword up to END.
+    fn case(input: &[Token]) -> Result<(&[Token], CaseExpression)> {
+        let (mut rest, operand) = match Self::keyword(input, Keyword::When) {
+            Ok(_) => (input, None),
+            Err(_) => {
+                let (rest, operand) = Self::expression(input)?;
+                (rest, Some(operand))
+            }
+        };
+
+        let mut branches = Vec::new();
+
+        while let Ok((next, _)) = Self::keyword(rest, Keyword::When) {
+            let (next, condition) = match &operand {
+                Some(operand) => {
+                    let (next, value) = Self::expression(next)?;
+
+                    let condition = WhereExpression::Condition(WhereCondition {
+                        field: operand.clone(),
+                        operator: Operator::Equals,
+                        value,
+                    });
+
+                    (next, condition)
+                }
+                None => Self::where_expression(next)?,
+            };
+
+            let (next, _) = Self::keyword(next, Keyword::Then)?;
+            let (next, result) = Self::expression(next)?;
+            branches.push(CaseBranch { condition, result });
+            rest = next;
+        }
+
+        if branches.is_empty() {
+            return Err(Error::NoMatch);
+        }
+
+        let (rest, r#else) = match Self::keyword(rest, Keyword::Else) {
+            Ok((rest, _)) => {
+                let (rest, result) = Self::expression(rest)?;
+                (rest, Some(Box::new(result)))
+            }
+            Err(_) => (rest, None),
+        };
+
+        let (rest, _) = Self::keyword(rest, Keyword::End)?;
+
+        Ok((rest, CaseExpression { branches, r#else }))
+    }
+
+    /// Parses a type name such as `int`, `text`, `string(16)` or `decimal(8, 2)`.
+    fn column_kind(input: &[Token]) -> Result<(&[Token], ColumnKind)> {
+        if let Ok((rest, _)) = Self::keyword(input, Keyword::Date) {
+            return Ok((rest, ColumnKind::Date));
+        }
+
+        if let Ok((rest, _)) = Self::keyword(input, Keyword::Time) {
+            return Ok((rest, ColumnKind::Time));
+        }
+
+        if let Ok((rest, _)) = Self::keyword(input, Keyword::Timestamp) {
+            return Ok((rest, ColumnKind::Timestamp));
+        }
+
+        let (rest, name) = Self::identifier(input)?;
+
+        let (rest, modifiers) = match Self::symbol(rest, TokenKind::LeftParenthesis) {
+            Ok((rest, _)) => {
+                let (mut rest, first) = Self::count(rest)?;
+                let mut modifiers = vec![first];
+
+                while let Ok((next, _)) = Self::symbol(rest, TokenKind::Comma) {
+                    let (next, modifier) = Self::count(next)?;
+                    modifiers.push(modifier);
+                    rest = next;
+                }
+
+                let (rest, _) = Self::symbol(rest, TokenKind::RightParenthesis)?;
+                (rest, modifiers)
+            }
+            Err(_) => (rest, Vec::new()),
+        };
+
+        let kind = match (name.to_lowercase().as_str(), modifiers.as_slice()) {
+            ("int" | "integer", []) => ColumnKind::Int,
+            ("bigint", []) => ColumnKind::BigInt,
+            ("text", []) => ColumnKind::Text,
+            ("blob", []) => ColumnKind::Blob,
+            ("json", []) => ColumnKind::Json,
+            ("string" | "varchar", [length]) => ColumnKind::String(StringColumn { length: *length }),
+            ("decimal" | "numeric", modifiers) if modifiers.len() <= 2 => {
+                let precision = modifiers.first().map_or(Decimal::MAX_PRECISION, |&p| p as u32);
+                let scale = modifiers.get(1).map_or(0, |&s| s as u32);
+
+                if precision == 0 || precision > Decimal::MAX_PRECISION || scale > precision {
+                    return Err(Error::InvalidLiteral(format!(
+                        "Invalid precision and scale for decimal({}, {})",
+                        precision, scale
+                    )));
+                }
+
+                ColumnKind::Decimal(DecimalColumn { precision, scale })
+            }
+            _ => return Err(Error::InvalidLiteral(format!("Unknown type {}", name))),
+        };
+
+        Ok((rest, kind))
+    }
+
+    /// Parses `[DISTINCT] argument` of an aggregate function up to the closing parenthesis.
+    fn aggregate(
+        input: &[Token],
+        function: AggregateFunction,
+    ) -> Result<(&[Token], AggregateCall)> {
+        if let Ok((rest, _)) = Self::keyword(input, Keyword::Distinct) {
+            let (rest, argument) = Self::expression(rest)?;
+            let (rest, _) = Self::symbol(rest, TokenKind::RightParenthesis)?;
+
+            return Ok((
+                rest,
+                AggregateCall {
+                    function,
+                    argument: Some(Box::new(argument)),
+                    distinct: true,
+                },
+            ));
+        }
+
+        if function == AggregateFunction::Count {
+            if let Ok((rest, _)) = Self::symbol(input, TokenKind::Star) {
+                let (rest, _) = Self::symbol(rest, TokenKind::RightParenthesis)?;
+                return Ok((
+                    rest,
+                    AggregateCall {
+                        function,
+                        argument: None,
+                        distinct: false,
+                    },
+                ));
+            }
+        }
+
+        let (rest, argument) = Self::expression(input)?;
+        let (rest, _) = Self::symbol(rest, TokenKind::RightParenthesis)?;
+
+        Ok((
+            rest,
+            AggregateCall {
+                function,
+                argument: Some(Box::new(argument)),
+                distinct: false,
+            },
+        ))
+    }
+
+    /// Parses a comma separated list of expressions up to the closing parenthesis.
+    fn arguments(input: &[Token]) -> Result<(&[Token], Vec<Expression>)> {
+        if let Ok((rest, _)) = Self::symbol(input, TokenKind::RightParenthesis) {
+            return Ok((rest, Vec::new()));
+        }
+
+        let (mut rest, first) = Self::expression(input)?;
+        let mut arguments = vec![first];
+
+        while let Ok((next, _)) = Self::symbol(rest, TokenKind::Comma) {
+            let (next, argument) = Self::expression(next)?;
+            arguments.push(argument);
+            rest = next;
+        }
+
+        let (rest, _) = Self::symbol(rest, TokenKind::RightParenthesis)?;
+        Ok((rest, arguments))
+    }
+
+    fn literal(input: &[Token]) -> Result<(&[Token], Value)> {
+        let input = Self::whitespace(input);
+
+        match input.first().map(|t| &t.kind) {
+            Some(TokenKind::String(value)) => Ok((&input[1..], Value::String(value.clone()))),
+            Some(TokenKind::Integer(value)) => Ok((&input[1..], Value::Int(*value))),
+            Some(TokenKind::Keyword(Keyword::Null)) => Ok((&input[1..], Value::Null)),
+            Some(TokenKind::Decimal(value)) => {
+                let decimal =
+                    Decimal::parse(value).map_err(|err| Error::InvalidLiteral(err.to_string()))?;
+                Ok((&input[1..], Value::Decimal(decimal)))
+            }
+            Some(TokenKind::Minus) => {
+                let rest = Self::whitespace(&input[1..]);
+
+                let value = match rest.first().map(|t| &t.kind) {
+                    Some(TokenKind::Integer(value)) => Value::Int(-value),
+                    Some(TokenKind::Decimal(value)) => Value::Decimal(
+                        Decimal::parse(&format!("-{}", value))
+                            .map_err(|err| Error::InvalidLiteral(err.to_string()))?,
+                    ),
+                    _ => return Err(Error::NoMatch),
+                };
+
+                Ok((&rest[1..], value))
+            }
+            Some(TokenKind::Keyword(
+                keyword @ (Keyword::Date | Keyword::Time | Keyword::Timestamp | Keyword::Interval),
+            )) => {
+                let rest = Self::whitespace(&input[1..]);
+
+                let text = match rest.first().map(|t| &t.kind) {
+                    Some(TokenKind::String(text)) => text,
+                    _ => return Err(Error::NoMatch),
+                };
+
+                let value = match keyword {
+                    Keyword::Date => Date::parse(text).map(Value::Date),
+                    Keyword::Time => Time::parse(text).map(Value::Time),
+                    Keyword::Timestamp => Timestamp::parse(text).map(Value::Timestamp),
+                    _ => Interval::parse(text).map(Value::Interval),
+                }
+                .map_err(|err| Error::InvalidLiteral(err.to_string()))?;
+
+                Ok((&rest[1..], value))
+            }
+            _ => Err(Self::no_match(input)),
+        }
+    }
+
+    fn values(input: &[Token]) -> Result<(&[Token], Vec<Expression>)> {
+        let (mut rest, first) = Self::expression(input)?;
+        let mut values = vec![first];
+
+        while let Ok((next, _)) = Self::symbol(rest, TokenKind::Comma) {
+            let (next, value) = Self::expression(next)?;
+            values.push(value);
+            rest = next;
+        }
+
+        Ok((rest, values))
+    }
+
+    /// Parses an expression that may also be a condition such as `a < b`,
+    /// whose value is a boolean.
+    fn scalar(input: &[Token]) -> Result<(&[Token], Expression)> {
+        match Self::where_expression(input) {
+            Ok((rest, condition)) => Ok((rest, Expression::Condition(Box::new(condition)))),
+            Err(Error::NoMatch) => Self::expression(input),
+            Err(err) => Err(err),
+        }
+    }
+
+    fn selections(input: &[Token]) -> Result<(&[Token], Vec<Selection>)> {
+        let (mut rest, first) = Self::selection(input)?;
+        let mut selections = vec![first];
+
+        while let Ok((next, _)) = Self::symbol(rest, TokenKind::Comma) {
+            let (next, selection) = Self::selection(next)?;
+            selections.push(selection);
+            rest = next;
+        }
+
+        Ok((rest, selections))
+    }
+
+    /// Parses `expression [[AS] alias]`, the expression may be a condition.
+    fn selection(input: &[Token]) -> Result<(&[Token], Selection)> {
+        if let Ok((rest, _)) = Self::symbol(input, TokenKind::Star) {
+            let expression = Expression::Wildcard;
+            return Ok((rest, Selection { expression, alias: None }));
+        }
+
+        let (rest, expression) = Self::scalar(input)?;
+
+        let (rest, alias) = match Self::keyword(rest, Keyword::As) {
+            Ok((rest, _)) => {
+                let (rest, alias) = Self::identifier(rest)?;
+                (rest, Some(alias))
+            }
+            Err(_) => match Self::identifier(rest) {
+                Ok((rest, alias)) => (rest, Some(alias)),
+                Err(_) => (rest, None),
+            },
+        };
+
+        Ok((rest, Selection { expression, alias }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sql::tokenizer::Tokenizer;
+
+    fn parse(input: &str) -> Result<Statement> {
+        Parser::new(Tokenizer::new(input).parse()?).parse()
+    }
+
+    #[test]
+    fn test_parse_non_reserved_keywords_as_identifiers() {
+        let Statement::CreateTable(create) =
+            parse("CREATE TABLE r (date DATE, key TEXT, first TEXT, action TEXT)").unwrap()
+        else {
+            panic!("Expected a CREATE TABLE statement");
+        };
+
+        let TableDefinition::Schema(schema) = create.definition else {
+            panic!("Expected a column list");
+        };
+
+        let names = schema.columns().iter().map(|c| c.name()).collect::<Vec<_>>();
+        assert_eq!(names, ["date", "key", "first", "action"]);
+
+        let Statement::Select(select) =
+            parse("SELECT date FROM r WHERE date > DATE \"2026-01-31\" ORDER BY first NULLS FIRST")
+                .unwrap()
+        else {
+            panic!("Expected a SELECT statement");
+        };
+
+        assert!(matches!(&select.selections[0].expression, Expression::Column(name) if name == "date"));
+        assert_eq!(select.order_by[0].nulls, Some(Nulls::First));
+
+        // Reserved words still cannot name a column
+        assert_eq!(
+            parse("CREATE TABLE t (select TEXT)").err().unwrap().to_string(),
+            "Invalid SQL: Unexpected \"SELECT\" at position 16"
+        );
+    }
+
+    #[test]
+    fn test_parse_error_position() {
+        let error = |input| parse(input).err().unwrap().to_string();
+
+        assert_eq!(
+            error("SELECT id FROM t WHERE id = = 2"),
+            "Invalid SQL: Unexpected \"=\" at position 28"
+        );
+        assert_eq!(
+            error("SELECT id FROM t LIMIT 1 garbage"),
+            "Invalid SQL: Unexpected \"garbage\" at position 25"
+        );
+        assert_eq!(error("SELECT id FROM"), "Invalid SQL: Unexpected end of input");
+    }
+
+    #[test]
+    fn test_parse_current_date_without_parentheses() {
+        for input in ["SELECT current_date FROM t", "SELECT CURRENT_DATE() FROM t"] {
+            let Statement::Select(select) = parse(input).unwrap() else {
+                panic!("Expected a SELECT statement");
+            };
+
+            assert!(matches!(
+                &select.selections[0].expression,
+                Expression::Function(call) if call.name.eq_ignore_ascii_case("current_date") && call.arguments.is_empty()
+            ));
+        }
+    }
+
+    #[test]
+    fn test_parse_select_where_date() {
+        let statement = parse(
+            "SELECT id FROM events WHERE day >= DATE \"2026-01-31\" AND created < now() - INTERVAL \"1 day\"",
+        )
+        .unwrap();
+
+        let Statement::Select(select) = statement else {
+            panic!("Expected a SELECT statement");
+        };
+
+        let Some(WhereExpression::And(conditions)) = select.r#where else {
+            panic!("Expected a conjunction");
+        };
+
+        let WhereExpression::Condition(first) = &conditions[0] else {
+            panic!("Expected a condition");
+        };
+
+        assert!(matches!(&first.field, Expression::Column(name) if name == "day"));
+        assert!(matches!(
+            first.value,
+            Expression::Value(Value::Date(date)) if date.to_string() == "2026-01-31"
+        ));
+
+        let WhereExpression::Condition(second) = &conditions[1] else {
+            panic!("Expected a condition");
+        };
+
+        assert!(matches!(
+            second.value,
+            Expression::Binary {
+                operator: BinaryOperator::Subtract,
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn test_parse_arithmetic_precedence() {
+        let statement = parse("SELECT id FROM orders WHERE total > 1.50 + price * -2").unwrap();
+
+        let Statement::Select(select) = statement else {
+            panic!("Expected a SELECT statement");
+        };
+
+        let Some(WhereExpression::Condition(condition)) = select.r#where else {
+            panic!("Expected a condition");
+        };
+
+        let Expression::Binary {
+            operator: BinaryOperator::Add,
+            right,
+            ..
+        } = condition.value
+        else {
+            panic!("Expected an addition");
+        };
+
+        assert!(matches!(
+            *right,
+            Expression::Binary {
+                operator: BinaryOperator::Multiply,
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn test_parse_json_operators() {
+        let statement =
+            parse("SELECT data->\"a\"->>\"b\", json_extract(data, \"$.c\") FROM docs").unwrap();
+
+        let Statement::Select(select) = statement else {
+            panic!("Expected a SELECT statement");
+        };
+
+        let Expression::Binary {
+            left,
+            operator: BinaryOperator::JsonGetText,
+            ..
+        } = &select.selections[0].expression
+        else {
+            panic!("Expected ->>");
+        };
+
+        assert!(matches!(
+            **left,
+            Expression::Binary {
+                operator: BinaryOperator::JsonGet,
+                ..
+            }
+        ));
+        assert_eq!(select.selections[1].name(), "json_extract");
+    }
+
+    #[test]
+    fn test_parse_update_and_delete() {
+        let Statement::Update(update) =
+            parse("UPDATE users SET name = \"bob\", age = 42 WHERE id = 1").unwrap()
+        else {
+            panic!("Expected an UPDATE statement");
+        };
+
+        assert_eq!(update.table, "users");
+        assert_eq!(update.assignments.len(), 2);
+        assert_eq!(update.assignments[1].field, "age");
+        assert_eq!(update.assignments[1].value, Expression::Value(Value::Int(42)));
+        assert!(update.r#where.is_some());
+
+        let Statement::Delete(delete) = parse("DELETE FROM users;").unwrap() else {
+            panic!("Expected a DELETE statement");
+        };
+
+        assert_eq!(delete.table, "users");
+        assert!(delete.r#where.is_none());
+
+        // Unknown input fails the statement instead of cutting off its WHERE
+        assert!(parse("DELETE FROM users @WHERE id = 1").is_err());
+        assert!(parse("UPDATE users SET name = \"zzz\" % WHERE id = 1").is_err());
+    }
+
+    #[test]
+    fn test_parse_insert() {
+        let Statement::Insert(insert) =
+            parse("INSERT INTO users (name, id) VALUES (\"ann\", 1), (NULL, -2)").unwrap()
+        else {
+            panic!("Expected an INSERT statement");
+        };
+
+        assert_eq!(insert.table, "users");
+        assert_eq!(
+            insert.columns,
+            Some(vec![String::from("name"), String::from("id")])
+        );
+        assert_eq!(
+            insert.source,
+            InsertSource::Values(vec![
+                vec![
+                    Expression::Value(Value::String(String::from("ann"))),
+                    Expression::Value(Value::Int(1))
+                ],
+                vec![
+                    Expression::Value(Value::Null),
+                    Expression::Value(Value::Int(-2))
+                ],
+            ])
+        );
+
+        let Statement::Insert(insert) =
+            parse("INSERT INTO users VALUES (1 + 1, upper(\"ann\"))").unwrap()
+        else {
+            panic!("Expected an INSERT statement");
+        };
+
+        assert!(matches!(
+            insert.source,
+            InsertSource::Values(rows) if matches!(
+                rows[0].as_slice(),
+                [Expression::Binary { .. }, Expression::Function(_)]
+            )
+        ));
+
+        let Statement::Insert(insert) = parse("INSERT INTO users VALUES 1, \"ann\"").unwrap() else {
+            panic!("Expected an INSERT statement");
+        };
+
+        assert_eq!(insert.columns, None);
+        assert!(matches!(insert.source, InsertSource::Values(rows) if rows.len() == 1));
+
+        let Statement::Insert(insert) =
+            parse("INSERT INTO archive (id) SELECT id FROM users UNION SELECT id FROM admins")
+                .unwrap()
+        else {
+            panic!("Expected an INSERT statement");
+        };
+
+        assert!(matches!(
+            insert.source,
+            InsertSource::Query(query) if matches!(query.body, SetExpression::Operation { .. })
+        ));
+
+        let Statement::Insert(insert) = parse(
+            "INSERT INTO users VALUES (1, \"ann\") ON CONFLICT (id) \
+             DO UPDATE SET name = excluded.name WHERE users.name <> excluded.name",
+        )
+        .unwrap() else {
+            panic!("Expected an INSERT statement");
+        };
+
+        let on_conflict = insert.on_conflict.unwrap();
+        assert_eq!(on_conflict.target, vec![String::from("id")]);
+        assert!(matches!(
+            on_conflict.action,
+            ConflictAction::Update { assignments, r#where: Some(_) } if assignments.len() == 1
+        ));
+
+        let Statement::Insert(insert) =
+            parse("INSERT INTO users SELECT id, name FROM admins ON CONFLICT (id) DO NOTHING")
+                .unwrap()
+        else {
+            panic!("Expected an INSERT statement");
+        };
+
+        assert!(matches!(insert.on_conflict.unwrap().action, ConflictAction::Nothing));
+
+        assert!(parse("INSERT INTO users () VALUES (1)").is_err());
+        assert!(parse("INSERT INTO users VALUES (1), 2").is_err());
+    }
+
+    #[test]
+    fn test_parse_returning() {
+        let Statement::Insert(insert) =
+            parse("INSERT INTO users (name) VALUES (\"ann\") RETURNING *").unwrap()
+        else {
+            panic!("Expected an INSERT statement");
+        };
+
+        assert_eq!(insert.returning, Some(Returning::All));
+
+        let Statement::Update(update) =
+            parse("UPDATE users SET age = age + 1 WHERE id = 1 RETURNING id, age AS years").unwrap()
+        else {
+            panic!("Expected an UPDATE statement");
+        };
+
+        let Some(Returning::Selections(selections)) = update.returning else {
+            panic!("Expected RETURNING selections");
+        };
+
+        assert_eq!(selections.len(), 2);
+        assert_eq!(selections[1].alias.as_deref(), Some("years"));
+
+        let Statement::Delete(delete) = parse("DELETE FROM users").unwrap() else {
+            panic!("Expected a DELETE statement");
+        };
+
+        assert_eq!(delete.returning, None);
+        assert!(parse("DELETE FROM users RETURNING").is_err());
+    }
+
+    #[test]
+    fn test_parse_create_table_as() {
+        let Statement::CreateTable(create) =
+            parse("CREATE TABLE adults (id, years) AS SELECT id, age FROM users WHERE age >= 18")
+                .unwrap()
+        else {
+            panic!("Expected a CREATE TABLE statement");
+        };
+
+        assert_eq!(create.table, "adults");
+
+        let TableDefinition::Query { columns, query } = create.definition else {
+            panic!("Expected CREATE TABLE AS");
+        };
+
+        assert_eq!(columns, vec![String::from("id"), String::from("years")]);
+        assert!(matches!(query.body, SetExpression::Select(_)));
+
+        assert!(parse("CREATE TABLE adults AS").is_err());
+    }
+
+    #[test]
+    fn test_parse_create_table_constraints() {
+        let Statement::CreateTable(create) = parse(
+            "CREATE TABLE users (id int PRIMARY KEY, email string(32) NOT NULL UNIQUE, \
+             age int DEFAULT 18 CHECK (age >= 0), name text NULL, \
+             CONSTRAINT adult CHECK (age >= 18 OR name = \"kid\"), UNIQUE (name, age))",
+        )
+        .unwrap() else {
+            panic!("Expected a CREATE TABLE statement");
+        };
+
+        let TableDefinition::Schema(schema) = create.definition else {
+            panic!("Expected column definitions");
+        };
+
+        let columns = schema.columns();
+        assert_eq!(columns.len(), 4);
+        assert!(columns[1].not_null());
+        assert_eq!(columns[2].default(), Some("18"));
+        assert!(!columns[3].not_null());
+
+        let constraint = |name: &str, kind| Constraint {
+            name: String::from(name),
+            kind,
+        };
+        let names = |names: &[&str]| names.iter().map(|name| String::from(*name)).collect();
+
+        assert_eq!(
+            schema.constraints(),
+            [
+                constraint("", ConstraintKind::PrimaryKey { columns: names(&["id"]) }),
+                constraint("", ConstraintKind::Unique { columns: names(&["email"]) }),
+                constraint(
+                    "users_age_check",
+                    ConstraintKind::Check {
+                        expression: String::from("age >= 0")
+                    }
+                ),
+                constraint(
+                    "adult",
+                    ConstraintKind::Check {
+                        expression: String::from("age >= 18 OR name = \"kid\"")
+                    }
+                ),
+                constraint("", ConstraintKind::Unique { columns: names(&["name", "age"]) }),
+            ]
+        );
+
+        assert!(parse("CREATE TABLE users (id int PRIMARY)").is_err());
+        assert!(parse("CREATE TABLE users (id int CONSTRAINT)").is_err());
+    }
+
+    #[test]
+    fn test_parse_foreign_keys() {
+        let Statement::CreateTable(create) = parse(
+            "CREATE TABLE orders (id int, user_id int REFERENCES users, \
+             shop int, item int, \
+             CONSTRAINT item_fk FOREIGN KEY (shop, item) REFERENCES items (shop, id) ON DELETE SET NULL)",
+        )
+        .unwrap() else {
+            panic!("Expected a CREATE TABLE statement");
+        };
+
+        let TableDefinition::Schema(schema) = create.definition else {
+            panic!("Expected column definitions");
+        };
+
+        let names = |names: &[&str]| -> Vec<String> {
+            names.iter().map(|name| String::from(*name)).collect()
+        };
+
+        assert_eq!(
+            schema.constraints(),
+            [
+                Constraint {
+                    name: String::new(),
+                    kind: ConstraintKind::ForeignKey {
+                        columns: names(&["user_id"]),
+                        table: String::from("users"),
+                        references: Vec::new(),
+                        on_delete: ReferentialAction::NoAction,
+                    },
+                },
+                Constraint {
+                    name: String::from("item_fk"),
+                    kind: ConstraintKind::ForeignKey {
+                        columns: names(&["shop", "item"]),
+                        table: String::from("items"),
+                        references: names(&["shop", "id"]),
+                        on_delete: ReferentialAction::SetNull,
+                    },
+                },
+            ]
+        );
+
+        assert!(parse("CREATE TABLE orders (user_id int REFERENCES users ON DELETE)").is_err());
+    }
+
+    #[test]
+    fn test_parse_sequences() {
+        let Statement::CreateTable(create) =
+            parse("CREATE TABLE users (id SERIAL, n int AUTOINCREMENT NOT NULL)").unwrap()
+        else {
+            panic!("Expected a CREATE TABLE statement");
+        };
+
+        let TableDefinition::Schema(schema) = create.definition else {
+            panic!("Expected column definitions");
+        };
+
+        let columns = schema.columns();
+        assert!(columns[0].autoincrement() && columns[0].not_null());
+        assert_eq!(columns[0].kind(), &ColumnKind::Int);
+        assert!(columns[1].autoincrement() && columns[1].not_null());
+
+        let sequence = |sql: &str| match parse(sql).unwrap() {
+            Statement::CreateSequence(sequence) => {
+                (sequence.name, sequence.start, sequence.increment)
+            }
+            _ => panic!("Expected a CREATE SEQUENCE statement"),
+        };
+
+        assert_eq!(
+            sequence("CREATE SEQUENCE ids INCREMENT BY -2 START 10"),
+            (String::from("ids"), Some(10), Some(-2))
+        );
+        assert_eq!(
+            sequence("CREATE SEQUENCE ids START WITH 5"),
+            (String::from("ids"), Some(5), None)
+        );
+
+        assert!(parse("CREATE SEQUENCE ids START WITH").is_err());
+        assert!(parse("CREATE SEQUENCE ids START 1 START 2").is_err());
+    }
+
+    #[test]
+    fn test_parse_create_view() {
+        let Statement::CreateView(create) = parse(
+            "CREATE VIEW adults (who) AS SELECT name FROM users WHERE age >= 18 AND city = \"Oslo\"",
+        )
+        .unwrap() else {
+            panic!("Expected a CREATE VIEW statement");
+        };
+
+        assert_eq!(create.name, "adults");
+        assert_eq!(create.columns, [String::from("who")]);
+        assert_eq!(
+            create.sql,
+            "SELECT name FROM users WHERE age >= 18 AND city = \"Oslo\""
+        );
+
+        // The stored text parses back to the same query
+        let tokens = Tokenizer::new(&create.sql).parse().unwrap();
+        assert_eq!(Parser::new(tokens).parse_query().unwrap(), *create.query);
+
+        assert!(parse("CREATE VIEW adults AS").is_err());
+        assert!(parse("CREATE VIEW adults SELECT name FROM users").is_err());
+    }
+
+    #[test]
+    fn test_parse_limit_offset() {
+        let Statement::Select(select) =
+            parse("SELECT id FROM users WHERE id > 3 LIMIT 10 OFFSET 20").unwrap()
+        else {
+            panic!("Expected a SELECT statement");
+        };
+
+        let pagination = select.pagination.unwrap();
+        assert_eq!(pagination.limit, Some(10));
+        assert_eq!(pagination.offset, Some(20));
+
+        let Statement::Select(select) = parse("SELECT id FROM users OFFSET 5").unwrap() else {
+            panic!("Expected a SELECT statement");
+        };
+
+        let pagination = select.pagination.unwrap();
+        assert_eq!(pagination.limit, None);
+        assert_eq!(pagination.offset, Some(5));
+
+        assert!(parse("SELECT id FROM users LIMIT -1").is_err());
+    }
+
+    #[test]
+    fn test_parse_order_by() {
+        let Statement::Select(select) =
+            parse("SELECT id FROM users ORDER BY age DESC NULLS LAST, name LIMIT 3").unwrap()
+        else {
+            panic!("Expected a SELECT statement");
+        };
+
+        assert_eq!(select.order_by.len(), 2);
+        assert_eq!(select.order_by[0].direction, Direction::Descending);
+        assert_eq!(select.order_by[0].nulls, Some(Nulls::Last));
+        assert_eq!(select.order_by[1].direction, Direction::Ascending);
+        assert_eq!(select.order_by[1].nulls, None);
+        assert!(select.pagination.is_some());
+    }
+
+    #[test]
+    fn test_parse_group_by_having() {
+        let Statement::Select(select) = parse(
+            "SELECT city, COUNT(*), AVG(age) FROM users GROUP BY city HAVING count(age) > 1",
+        )
+        .unwrap() else {
+            panic!("Expected a SELECT statement");
+        };
+
+        assert_eq!(
+            select.selections[1].expression,
+            Expression::Aggregate(AggregateCall {
+                function: AggregateFunction::Count,
+                argument: None,
+                distinct: false,
+            })
+        );
+        assert_eq!(select.selections[2].name(), "avg");
+        assert_eq!(select.group_by, vec![Expression::Column(String::from("city"))]);
+
+        let Some(WhereExpression::Condition(having)) = select.having else {
+            panic!("Expected a condition");
+        };
+
+        assert!(matches!(having.field, Expression::Aggregate(_)));
+        assert!(parse("SELECT SUM(*) FROM users").is_err());
+    }
+
+    #[test]
+    fn test_parse_aliases() {
+        let Statement::Select(select) =
+            parse("SELECT username AS name, count(*) total, age FROM users").unwrap()
+        else {
+            panic!("Expected a SELECT statement");
+        };
+
+        let names = select
+            .selections
+            .iter()
+            .map(|selection| selection.name())
+            .collect::<Vec<_>>();
+
+        assert_eq!(names, vec!["name", "total", "age"]);
+        assert!(parse("SELECT username AS FROM users").is_err());
+    }
+
+    #[test]
+    fn test_parse_joins() {
+        let Statement::Select(select) = parse(
+            "SELECT u.name, o.total FROM users AS u JOIN orders o ON o.user_id = u.id LEFT JOIN notes ON notes.user_id = u.id CROSS JOIN days, tags",
+        )
+        .unwrap() else {
+            panic!("Expected a SELECT statement");
+        };
+
+        assert_eq!(select.table.qualifier(), "u");
+        assert_eq!(select.selections[0].name(), "name");
+
+        let kinds = select
+            .joins
+            .iter()
+            .map(|join| (join.kind, join.table.qualifier(), join.on.is_some()))
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            kinds,
+            vec![
+                (JoinKind::Inner, "o", true),
+                (JoinKind::Left, "notes", true),
+                (JoinKind::Cross, "days", false),
+                (JoinKind::Cross, "tags", false),
+            ]
+        );
+
+        assert!(parse("SELECT id FROM users JOIN orders").is_err());
+    }
+
+    #[test]
+    fn test_parse_case_cast_concat() {
+        let statement = parse(
+            "SELECT CASE WHEN age < 18 THEN \"minor\" ELSE \"adult\" END, CAST(total AS decimal(10, 2)), name || -id FROM users WHERE age != min_age",
+        )
+        .unwrap();
+
+        let Statement::Select(select) = statement else {
+            panic!("Expected a SELECT statement");
+        };
+
+        let Expression::Case(case) = &select.selections[0].expression else {
+            panic!("Expected a CASE expression");
+        };
+        assert_eq!(case.branches.len(), 1);
+        assert_eq!(
+            case.r#else.as_deref(),
+            Some(&Expression::Value(Value::String(String::from("adult"))))
+        );
+
+        assert_eq!(
+            select.selections[1].expression,
+            Expression::Cast {
+                expression: Box::new(Expression::Column(String::from("total"))),
+                kind: ColumnKind::Decimal(DecimalColumn {
+                    precision: 10,
+                    scale: 2
+                }),
+            }
+        );
+
+        assert_eq!(
+            select.selections[2].expression,
+            Expression::Binary {
+                left: Box::new(Expression::Column(String::from("name"))),
+                operator: BinaryOperator::Concat,
+                right: Box::new(Expression::Negate(Box::new(Expression::Column(
+                    String::from("id")
+                )))),
+            }
+        );
+
+        assert!(matches!(
+            select.r#where,
+            Some(WhereExpression::Condition(WhereCondition {
+                operator: Operator::NotEquals,
+                value: Expression::Column(_),
+                ..
+            }))
+        ));
+    }
+
+    #[test]
+    fn test_parse_like_in_between() {
+        let statement = parse(
+            "SELECT id FROM users WHERE name NOT LIKE \"a%\" AND id IN (1, 2, 3) AND age BETWEEN 18 AND 65 AND (age + 1) > 2 AND bio CONTAINS \"rust\"",
+        )
+        .unwrap();
+
+        let Statement::Select(select) = statement else {
+            panic!("Expected a SELECT statement");
+        };
+
+        let Some(WhereExpression::And(conditions)) = select.r#where else {
+            panic!("Expected a conjunction");
+        };
+
+        assert_eq!(conditions.len(), 5);
+        assert!(matches!(
+            &conditions[0],
+            WhereExpression::Not(like) if matches!(
+                **like,
+                WhereExpression::Condition(WhereCondition { operator: Operator::Like, .. })
+            )
+        ));
+        assert!(matches!(&conditions[1], WhereExpression::In { list, .. } if list.len() == 3));
+        assert!(matches!(&conditions[2], WhereExpression::And(bounds) if bounds.len() == 2));
+        assert!(matches!(
+            &conditions[3],
+            WhereExpression::Condition(WhereCondition {
+                field: Expression::Binary { .. },
+                ..
+            })
+        ));
+        assert!(matches!(
+            &conditions[4],
+            WhereExpression::Condition(WhereCondition {
+                operator: Operator::Contains,
+                ..
+            })
+        ));
+
+        assert!(parse("SELECT id FROM users WHERE id IN ()").is_err());
+    }
+
+    #[test]
+    fn test_parse_is_null() {
+        let statement =
+            parse("SELECT id FROM users WHERE name IS NULL AND age + 1 IS NOT NULL").unwrap();
+
+        let Statement::Select(select) = statement else {
+            panic!("Expected a SELECT statement");
+        };
+
+        let Some(WhereExpression::And(conditions)) = select.r#where else {
+            panic!("Expected a conjunction");
+        };
+
+        assert!(matches!(
+            &conditions[0],
+            WhereExpression::IsNull(Expression::Column(name)) if name == "name"
+        ));
+        assert!(matches!(
+            &conditions[1],
+            WhereExpression::Not(is_null) if matches!(
+                **is_null,
+                WhereExpression::IsNull(Expression::Binary { .. })
+            )
+        ));
+
+        assert!(parse("SELECT id FROM users WHERE name IS 1").is_err());
+    }
+
+    #[test]
+    fn test_parse_subqueries() {
+        let statement = parse(
+            "SELECT name, (SELECT max(total) FROM orders WHERE orders.user_id = u.id) FROM (SELECT id, name FROM users) AS u WHERE id IN (SELECT user_id FROM orders) AND NOT EXISTS (SELECT id FROM bans WHERE bans.user_id = u.id)",
+        )
+        .unwrap();
+
+        let Statement::Select(select) = statement else {
+            panic!("Expected a SELECT statement");
+        };
+
+        assert!(matches!(
+            select.selections[1].expression,
+            Expression::Subquery(_)
+        ));
+        assert_eq!(select.selections[1].name(), "max");
+
+        assert!(matches!(select.table.source, TableSource::Subquery(_)));
+        assert_eq!(select.table.qualifier(), "u");
+
+        let Some(WhereExpression::And(conditions)) = select.r#where else {
+            panic!("Expected a conjunction");
+        };
+
+        assert!(matches!(conditions[0], WhereExpression::InSubquery { .. }));
+        assert!(matches!(
+            &conditions[1],
+            WhereExpression::Not(exists) if matches!(**exists, WhereExpression::Exists(_))
+        ));
+
+        assert!(matches!(
+            parse("SELECT id FROM (SELECT id FROM users)"),
+            Err(Error::Invalid(_))
+        ));
+    }
+
+    #[test]
+    fn test_parse_with() {
+        let statement = parse(
+            "WITH RECURSIVE tree(id, depth) AS (SELECT id, 0 FROM nodes WHERE parent_id = 0 UNION ALL SELECT nodes.id, depth + 1 FROM nodes JOIN tree ON nodes.parent_id = tree.id), leaves AS (SELECT id FROM tree) SELECT id FROM leaves",
+        )
+        .unwrap();
+
+        let Statement::Query(query) = statement else {
+            panic!("Expected a query");
+        };
+
+        assert!(query.recursive);
+        assert_eq!(query.with.len(), 2);
+        assert_eq!(query.with[0].name, "tree");
+        assert_eq!(query.with[0].columns, vec!["id", "depth"]);
+        assert!(matches!(
+            query.with[0].query.body,
+            SetExpression::Operation {
+                operator: SetOperator::Union,
+                all: true,
+                ..
+            }
+        ));
+        assert!(query.with[1].columns.is_empty());
+
+        let SetExpression::Select(body) = query.body else {
+            panic!("Expected a SELECT statement");
+        };
+
+        assert_eq!(body.table.source, TableSource::Table(String::from("leaves")));
+
+        assert!(parse("WITH t AS SELECT id FROM users SELECT id FROM t").is_err());
+    }
+
+    #[test]
+    fn test_parse_set_operations() {
+        let statement = parse(
+            "SELECT id FROM a UNION ALL SELECT id FROM b INTERSECT SELECT id FROM c EXCEPT SELECT id FROM d ORDER BY id DESC LIMIT 5",
+        )
+        .unwrap();
+
+        let Statement::Query(query) = statement else {
+            panic!("Expected a query");
+        };
+
+        assert_eq!(query.order_by.len(), 1);
+        assert_eq!(query.pagination.as_ref().and_then(|p| p.limit), Some(5));
+
+        // (a UNION ALL (b INTERSECT c)) EXCEPT d
+        let SetExpression::Operation {
+            operator: SetOperator::Except,
+            left,
+            right,
+            all: false,
+        } = query.body
+        else {
+            panic!("Expected EXCEPT");
+        };
+
+        let SetExpression::Select(last) = *right else {
+            panic!("Expected a SELECT statement");
+        };
+
+        assert!(last.order_by.is_empty());
+        assert!(last.pagination.is_none());
+
+        let SetExpression::Operation {
+            operator: SetOperator::Union,
+            right,
+            all: true,
+            ..
+        } = *left
+        else {
+            panic!("Expected UNION ALL");
+        };
+
+        assert!(matches!(
+            *right,
+            SetExpression::Operation {
+                operator: SetOperator::Intersect,
+                ..
+            }
+        ));
+
+        assert!(matches!(
+            parse("SELECT id FROM a ORDER BY id UNION SELECT id FROM b"),
+            Err(Error::Invalid(_))
+        ));
+    }
+
+    #[test]
+    fn test_parse_distinct() {
+        let Statement::Select(select) = parse("SELECT DISTINCT city FROM users").unwrap() else {
+            panic!("Expected a SELECT statement");
+        };
+
+        assert_eq!(select.distinct, Some(Distinct::Rows));
+
+        let Statement::Select(select) =
+            parse("SELECT DISTINCT ON (city, age) city, count(DISTINCT name) FROM users").unwrap()
+        else {
+            panic!("Expected a SELECT statement");
+        };
+
+        assert_eq!(
+            select.distinct,
+            Some(Distinct::On(vec![
+                Expression::Column(String::from("city")),
+                Expression::Column(String::from("age")),
+            ]))
+        );
+        assert!(matches!(
+            &select.selections[1].expression,
+            Expression::Aggregate(AggregateCall { distinct: true, .. })
+        ));
+
+        let Statement::Select(select) = parse("SELECT city FROM users").unwrap() else {
+            panic!("Expected a SELECT statement");
+        };
+
+        assert_eq!(select.distinct, None);
+    }
+
+    #[test]
+    fn test_parse_window_functions() {
+        let Statement::Select(select) = parse(
+            "SELECT row_number() OVER (PARTITION BY city ORDER BY age DESC), \
+             sum(age) OVER (ORDER BY age ROWS BETWEEN 2 PRECEDING AND CURRENT ROW), \
+             lag(age, 1) OVER () FROM users",
+        )
+        .unwrap() else {
+            panic!("Expected a SELECT statement");
+        };
+
+        let Expression::Window(row_number) = &select.selections[0].expression else {
+            panic!("Expected a window function");
+        };
+
+        assert_eq!(row_number.function, WindowFunction::RowNumber);
+        assert_eq!(
+            row_number.window.partition_by,
+            vec![Expression::Column(String::from("city"))]
+        );
+        assert_eq!(row_number.window.order_by[0].direction, Direction::Descending);
+        assert_eq!(row_number.window.frame, None);
+
+        let Expression::Window(sum) = &select.selections[1].expression else {
+            panic!("Expected a window function");
+        };
+
+        assert_eq!(sum.function, WindowFunction::Aggregate(AggregateFunction::Sum));
+        assert_eq!(
+            sum.window.frame,
+            Some(WindowFrame {
+                start: FrameBound::Preceding(2),
+                end: FrameBound::CurrentRow,
+            })
+        );
+
+        let Expression::Window(lag) = &select.selections[2].expression else {
+            panic!("Expected a window function");
+        };
+
+        assert_eq!(lag.arguments.len(), 2);
+        assert!(lag.window.order_by.is_empty());
+
+        let invalid = |sql: &str| matches!(parse(sql), Err(Error::Invalid(_)));
+        assert!(invalid("SELECT rank() FROM users"));
+        assert!(invalid("SELECT lower(name) OVER () FROM users"));
+        assert!(invalid("SELECT count(DISTINCT age) OVER () FROM users"));
+        assert!(invalid("SELECT sum(age) OVER (ROWS BETWEEN UNBOUNDED FOLLOWING AND CURRENT ROW) FROM users"));
+    }
+
+    #[test]
+    fn test_parse_invalid_date_literal() {
+        assert!(matches!(
+            parse("SELECT id FROM events WHERE day = DATE \"2026-02-30\""),
+            Err(Error::InvalidLiteral(_))
+        ));
     }
 }
@@ -1,14 +1,35 @@
+use std::fmt::{self, Display, Formatter};
+
 type Input<'i> = (&'i str, usize);
 
 #[derive(Debug)]
 enum ParserError {
     InputEnded,
     NoMatch,
-    UnknownKeyword(String),
 }
 
 type ParserResult<'i, O> = Result<(Input<'i>, O), ParserError>;
 
+/// Input that does not start any token, such as a stray symbol or an
+/// unterminated string.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Error {
+    pub found: char,
+    pub position: usize,
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Invalid SQL: Unexpected \"{}\" at position {}",
+            self.found, self.position
+        )
+    }
+}
+
+impl std::error::Error for Error {}
+
 // tag, take_while, alt, map
 fn tag<'i, 'v>(value: &'v str) -> impl FnMut(Input<'i>) -> ParserResult<'i, ()> {
     let length = value.len();
@@ -16,7 +37,7 @@ fn tag<'i, 'v>(value: &'v str) -> impl FnMut(Input<'i>) -> ParserResult<'i, ()>
 
     move |input: Input<'i>| match input.0.get(0..length) {
         Some(slice) => {
-            if slice == &value {
+            if slice == value {
                 Ok(((&input.0[length..], input.1 + length), ()))
             } else {
                 Err(ParserError::NoMatch)
@@ -32,12 +53,8 @@ where
 {
     move |input: Input<'i>| {
         let mut value = String::new();
-        let mut offset = 0;
-        let mut position = input.0;
 
         for char in input.0.chars() {
-            position = &position[1..];
-            offset += 1;
             if check(char) {
                 value.push(char);
             } else {
@@ -45,14 +62,16 @@ where
             }
         }
 
-        Ok(((position, input.1 + offset), value))
+        let length = value.len();
+
+        Ok(((&input.0[length..], input.1 + length), value))
     }
 }
 
 #[derive(Debug, PartialEq)]
 pub struct Span {
-    start: usize,
-    end: usize,
+    pub start: usize,
+    pub end: usize,
 }
 
 #[derive(Debug, PartialEq)]
@@ -61,23 +80,82 @@ pub struct Token {
     pub kind: TokenKind,
 }
 
+/// Writes the token back as SQL that reads as the same token, whitespace
+/// becomes a single space.
+impl Display for Token {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match &self.kind {
+            TokenKind::Keyword(keyword) => {
+                write!(f, "{}", keyword.name().to_uppercase())
+            }
+            TokenKind::Comma => write!(f, ","),
+            TokenKind::LeftParenthesis => write!(f, "("),
+            TokenKind::RightParenthesis => write!(f, ")"),
+            TokenKind::Plus => write!(f, "+"),
+            TokenKind::Minus => write!(f, "-"),
+            TokenKind::Arrow => write!(f, "->"),
+            TokenKind::DoubleArrow => write!(f, "->>"),
+            TokenKind::Star => write!(f, "*"),
+            TokenKind::Slash => write!(f, "/"),
+            TokenKind::Equals => write!(f, "="),
+            TokenKind::GreaterThan => write!(f, ">"),
+            TokenKind::GreaterThanEquals => write!(f, ">="),
+            TokenKind::SmallerThan => write!(f, "<"),
+            TokenKind::SmallerThanEquals => write!(f, "<="),
+            TokenKind::NotEquals => write!(f, "<>"),
+            TokenKind::Concat => write!(f, "||"),
+            TokenKind::Whitespace => write!(f, " "),
+            TokenKind::SemiColon => write!(f, ";"),
+            TokenKind::Identifier(identifier) => {
+                // Quoted when it would otherwise read as a keyword
+                let plain = identifier
+                    .chars()
+                    .next()
+                    .is_some_and(|c| c.is_alphabetic() || c == '_')
+                    && !keywords().any(|(keyword, _)| keyword.eq_ignore_ascii_case(identifier));
+
+                match plain {
+                    true => write!(f, "{}", identifier),
+                    false => write!(f, "'{}'", identifier),
+                }
+            }
+            TokenKind::String(value) => {
+                write!(f, "\"{}\"", value.replace('\\', "\\\\").replace('"', "\\\""))
+            }
+            TokenKind::Integer(value) => write!(f, "{}", value),
+            TokenKind::Decimal(value) => write!(f, "{}", value),
+        }
+    }
+}
+
 #[derive(Debug, PartialEq)]
 pub enum TokenKind {
     Keyword(Keyword),
     Comma,
+    LeftParenthesis,
+    RightParenthesis,
+    Plus,
+    Minus,
+    Arrow,
+    DoubleArrow,
+    Star,
+    Slash,
     Equals,
     GreaterThan,
     GreaterThanEquals,
     SmallerThan,
     SmallerThanEquals,
+    NotEquals,
+    Concat,
     Whitespace,
     SemiColon,
     Identifier(String),
     String(String),
-    Integer(u32),
+    Integer(i64),
+    Decimal(String),
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub enum Keyword {
     Select,
     From,
@@ -85,24 +163,209 @@ pub enum Keyword {
     Into,
     Values,
     Where,
+    And,
+    Or,
+    Not,
+    Date,
+    Time,
+    Timestamp,
+    Interval,
+    Delete,
+    Update,
+    Set,
+    Limit,
+    Offset,
+    Order,
+    By,
+    Asc,
+    Desc,
+    Nulls,
+    First,
+    Last,
+    Group,
+    Having,
+    As,
+    Join,
+    Inner,
+    Left,
+    Outer,
+    Cross,
+    On,
+    Case,
+    When,
+    Then,
+    Else,
+    End,
+    Cast,
+    Like,
+    Ilike,
+    In,
+    Between,
+    Contains,
+    Exists,
+    With,
+    Recursive,
+    Union,
+    Intersect,
+    Except,
+    All,
+    Distinct,
+    Over,
+    Partition,
+    Rows,
+    Unbounded,
+    Preceding,
+    Following,
+    Current,
+    Row,
+    Null,
+    Create,
+    Table,
+    Conflict,
+    Do,
+    Nothing,
+    Returning,
+    Primary,
+    Key,
+    Unique,
+    Check,
+    Default,
+    Constraint,
+    Foreign,
+    References,
+    Cascade,
+    Restrict,
+    No,
+    Action,
+    Sequence,
+    Start,
+    Increment,
+    Autoincrement,
+    View,
+    Is,
+}
+
+/// Words that are always keywords, they can only name a column or table
+/// when quoted.
+const RESERVED: &[(&str, Keyword)] = &[
+    ("select", Keyword::Select),
+    ("from", Keyword::From),
+    ("insert", Keyword::Insert),
+    ("into", Keyword::Into),
+    ("values", Keyword::Values),
+    ("where", Keyword::Where),
+    ("and", Keyword::And),
+    ("or", Keyword::Or),
+    ("not", Keyword::Not),
+    ("delete", Keyword::Delete),
+    ("update", Keyword::Update),
+    ("set", Keyword::Set),
+    ("limit", Keyword::Limit),
+    ("offset", Keyword::Offset),
+    ("order", Keyword::Order),
+    ("by", Keyword::By),
+    ("asc", Keyword::Asc),
+    ("desc", Keyword::Desc),
+    ("group", Keyword::Group),
+    ("having", Keyword::Having),
+    ("as", Keyword::As),
+    ("join", Keyword::Join),
+    ("inner", Keyword::Inner),
+    ("left", Keyword::Left),
+    ("outer", Keyword::Outer),
+    ("cross", Keyword::Cross),
+    ("on", Keyword::On),
+    ("case", Keyword::Case),
+    ("when", Keyword::When),
+    ("then", Keyword::Then),
+    ("else", Keyword::Else),
+    ("end", Keyword::End),
+    ("cast", Keyword::Cast),
+    ("like", Keyword::Like),
+    ("ilike", Keyword::Ilike),
+    ("in", Keyword::In),
+    ("between", Keyword::Between),
+    ("contains", Keyword::Contains),
+    ("exists", Keyword::Exists),
+    ("with", Keyword::With),
+    ("recursive", Keyword::Recursive),
+    ("union", Keyword::Union),
+    ("intersect", Keyword::Intersect),
+    ("except", Keyword::Except),
+    ("all", Keyword::All),
+    ("distinct", Keyword::Distinct),
+    ("over", Keyword::Over),
+    ("null", Keyword::Null),
+    ("create", Keyword::Create),
+    ("table", Keyword::Table),
+    ("do", Keyword::Do),
+    ("returning", Keyword::Returning),
+    ("primary", Keyword::Primary),
+    ("unique", Keyword::Unique),
+    ("check", Keyword::Check),
+    ("default", Keyword::Default),
+    ("constraint", Keyword::Constraint),
+    ("foreign", Keyword::Foreign),
+    ("references", Keyword::References),
+    ("is", Keyword::Is),
+];
+
+/// Keywords only where the grammar expects them, elsewhere the parser
+/// reads them as identifiers, such as a column named `date` or `key`.
+const NON_RESERVED: &[(&str, Keyword)] = &[
+    ("date", Keyword::Date),
+    ("time", Keyword::Time),
+    ("timestamp", Keyword::Timestamp),
+    ("interval", Keyword::Interval),
+    ("nulls", Keyword::Nulls),
+    ("first", Keyword::First),
+    ("last", Keyword::Last),
+    ("partition", Keyword::Partition),
+    ("rows", Keyword::Rows),
+    ("unbounded", Keyword::Unbounded),
+    ("preceding", Keyword::Preceding),
+    ("following", Keyword::Following),
+    ("current", Keyword::Current),
+    ("row", Keyword::Row),
+    ("conflict", Keyword::Conflict),
+    ("nothing", Keyword::Nothing),
+    ("key", Keyword::Key),
+    ("cascade", Keyword::Cascade),
+    ("restrict", Keyword::Restrict),
+    ("no", Keyword::No),
+    ("action", Keyword::Action),
+    ("sequence", Keyword::Sequence),
+    ("start", Keyword::Start),
+    ("increment", Keyword::Increment),
+    ("autoincrement", Keyword::Autoincrement),
+    ("view", Keyword::View),
+];
+
+fn keywords() -> impl Iterator<Item = &'static (&'static str, Keyword)> {
+    RESERVED.iter().chain(NON_RESERVED.iter())
+}
+
+impl Keyword {
+    /// The keyword in lowercase, as it reads when used as an identifier.
+    pub fn name(self) -> &'static str {
+        keywords().find(|(_, keyword)| *keyword == self).unwrap().0
+    }
+
+    pub fn is_reserved(self) -> bool {
+        RESERVED.iter().any(|(_, keyword)| *keyword == self)
+    }
 }
 
 pub struct Tokenizer<'i> {
     input: &'i str,
-    index: usize,
-    position: &'i str,
 }
 
 impl<'i> Tokenizer<'i> {
     pub fn new(input: &'i str) -> Tokenizer<'i> {
-        Self {
-            input,
-            index: 0,
-            position: input,
-        }
+        Self { input }
     }
 
-    pub fn parse(&self) -> Vec<Token> {
+    pub fn parse(&self) -> Result<Vec<Token>, Error> {
         let input = self.input;
         let mut output = Vec::new();
         let mut current_position = input;
@@ -144,108 +407,61 @@ impl<'i> Tokenizer<'i> {
                 continue;
             }
 
-            match Self::keyword((offset, current_position)) {
+            match Self::word((offset, current_position)) {
                 Ok(((rest, _), token)) => {
                     current_position = rest;
                     offset = token.span.end;
                     output.push(token);
                     continue;
                 }
-                Err(err) => {
-                    dbg!(err);
-                    break;
+                Err(_) => {
+                    return Err(Error {
+                        found: current_position.chars().next().unwrap(),
+                        position: offset,
+                    })
                 }
             }
         }
 
-        output
+        Ok(output)
     }
 
-    fn keyword((offset, input): (usize, &str)) -> ParserResult<Token> {
-        if input.to_lowercase().starts_with("select") {
-            return Ok((
-                (&input[6..], offset + 6),
-                Token {
-                    span: Span {
-                        start: offset,
-                        end: offset + 6,
-                    },
-                    kind: TokenKind::Keyword(Keyword::Select),
-                },
-            ));
+    fn word((position, input): (usize, &str)) -> ParserResult<'_, Token> {
+        match input.chars().next() {
+            Some(c) if c.is_alphabetic() || c == '_' => {}
+            Some(_) => return Err(ParserError::NoMatch),
+            None => return Err(ParserError::InputEnded),
         }
 
-        if input.to_lowercase().starts_with("insert") {
-            return Ok((
-                (&input[6..], offset + 6),
-                Token {
-                    span: Span {
-                        start: offset,
-                        end: offset + 6,
-                    },
-                    kind: TokenKind::Keyword(Keyword::Insert),
-                },
-            ));
-        }
+        let (rest, word) =
+            take_while(|c| c.is_alphanumeric() || c == '_' || c == '.')((input, position))?;
 
-        if input.to_lowercase().starts_with("from") {
-            return Ok((
-                (&input[4..], offset + 4),
-                Token {
-                    span: Span {
-                        start: offset,
-                        end: offset + 4,
-                    },
-                    kind: TokenKind::Keyword(Keyword::From),
-                },
-            ));
-        }
+        let kind = match keywords().find(|(keyword, _)| keyword.eq_ignore_ascii_case(&word))
+        {
+            Some((_, keyword)) => TokenKind::Keyword(*keyword),
+            None => TokenKind::Identifier(word),
+        };
 
-        if input.to_lowercase().starts_with("where") {
-            return Ok((
-                (&input[5..], offset + 5),
-                Token {
-                    span: Span {
-                        start: offset,
-                        end: offset + 5,
-                    },
-                    kind: TokenKind::Keyword(Keyword::Where),
+        Ok((
+            rest,
+            Token {
+                span: Span {
+                    start: position,
+                    end: rest.1,
                 },
-            ));
-        }
+                kind,
+            },
+        ))
+    }
 
-        if input.to_lowercase().starts_with("values") {
-            return Ok((
-                (&input[6..], offset + 6),
-                Token {
-                    span: Span {
-                        start: offset,
-                        end: offset + 6,
-                    },
-                    kind: TokenKind::Keyword(Keyword::Values),
-                },
-            ));
-        }
+    fn whitespace((position, input): (usize, &str)) -> ParserResult<'_, Token> {
+        let (rest, value) = take_while(|c| c.is_whitespace())((input, position))?;
 
-        if input.to_lowercase().starts_with("into") {
-            return Ok((
-                (&input[4..], offset + 4),
-                Token {
-                    span: Span {
-                        start: offset,
-                        end: offset + 4,
-                    },
-                    kind: TokenKind::Keyword(Keyword::Into),
-                },
-            ));
+        if value.is_empty() {
+            return Err(ParserError::NoMatch);
         }
 
-        Err(ParserError::UnknownKeyword(String::from("idk")))
-    }
-
-    fn whitespace((position, input): (usize, &str)) -> ParserResult<Token> {
-        let (rest, _) = take_while(|c| c.is_whitespace())((input, position))?;
-        return Ok((
+        Ok((
             rest,
             Token {
                 span: Span {
@@ -254,135 +470,51 @@ impl<'i> Tokenizer<'i> {
                 },
                 kind: TokenKind::Whitespace,
             },
-        ));
-        // let start = position;
-        // let mut end = position;
-        // let mut current = input;
-
-        // for (i, char) in input.chars().enumerate() {
-        //     if i == 0 && !char.is_whitespace() {
-        //         return Err(ParserError::NoMatch);
-        //     }
-
-        //     if char.is_whitespace() {
-        //         current = &current[1..];
-        //         end += 1;
-        //     } else {
-        //         return Ok((
-        //             (current, end),
-        //             Token {
-        //                 span: Span { start, end },
-        //                 kind: TokenKind::Whitespace,
-        //             },
-        //         ));
-        //     }
-        // }
-
-        // Ok((
-        //     (current, end),
-        //     Token {
-        //         span: Span { start, end },
-        //         kind: TokenKind::Whitespace,
-        //     },
-        // ))
+        ))
     }
 
-    fn special((position, input): (usize, &str)) -> ParserResult<Token> {
-        if let Ok((rest, _)) = tag(",")((input, position)) {
-            return Ok((
-                rest,
-                Token {
-                    span: Span {
-                        start: position,
-                        end: rest.1,
-                    },
-                    kind: TokenKind::Comma,
-                },
-            ));
-        }
-
-        if let Ok((rest, _)) = tag("=")((input, position)) {
-            return Ok((
-                rest,
-                Token {
-                    span: Span {
-                        start: position,
-                        end: rest.1,
-                    },
-                    kind: TokenKind::Equals,
-                },
-            ));
-        }
-
-        if let Ok((rest, _)) = tag(";")((input, position)) {
-            return Ok((
-                rest,
-                Token {
-                    span: Span {
-                        start: position,
-                        end: rest.1,
-                    },
-                    kind: TokenKind::SemiColon,
-                },
-            ));
-        }
-
-        if let Ok((rest, _)) = tag(">=")((input, position)) {
-            return Ok((
-                rest,
-                Token {
-                    span: Span {
-                        start: position,
-                        end: rest.1,
-                    },
-                    kind: TokenKind::GreaterThanEquals,
-                },
-            ));
-        }
-
-        if let Ok((rest, _)) = tag(">")((input, position)) {
-            return Ok((
-                rest,
-                Token {
-                    span: Span {
-                        start: position,
-                        end: rest.1,
-                    },
-                    kind: TokenKind::GreaterThan,
-                },
-            ));
-        }
-
-        if let Ok((rest, _)) = tag("<=")((input, position)) {
-            return Ok((
-                rest,
-                Token {
-                    span: Span {
-                        start: position,
-                        end: rest.1,
-                    },
-                    kind: TokenKind::SmallerThanEquals,
-                },
-            ));
-        }
-
-        if let Ok((rest, _)) = tag("<")((input, position)) {
-            return Ok((
-                rest,
-                Token {
-                    span: Span {
-                        start: position,
-                        end: rest.1,
+    fn special((position, input): (usize, &str)) -> ParserResult<'_, Token> {
+        // Longer symbols come first so that ">=" is not read as ">"
+        let symbols = [
+            ("->>", TokenKind::DoubleArrow),
+            ("->", TokenKind::Arrow),
+            (">=", TokenKind::GreaterThanEquals),
+            ("<=", TokenKind::SmallerThanEquals),
+            ("<>", TokenKind::NotEquals),
+            ("!=", TokenKind::NotEquals),
+            ("||", TokenKind::Concat),
+            (",", TokenKind::Comma),
+            ("(", TokenKind::LeftParenthesis),
+            (")", TokenKind::RightParenthesis),
+            ("+", TokenKind::Plus),
+            ("-", TokenKind::Minus),
+            ("*", TokenKind::Star),
+            ("/", TokenKind::Slash),
+            ("=", TokenKind::Equals),
+            (";", TokenKind::SemiColon),
+            (">", TokenKind::GreaterThan),
+            ("<", TokenKind::SmallerThan),
+        ];
+
+        for (symbol, kind) in symbols {
+            if let Ok((rest, _)) = tag(symbol)((input, position)) {
+                return Ok((
+                    rest,
+                    Token {
+                        span: Span {
+                            start: position,
+                            end: rest.1,
+                        },
+                        kind,
                     },
-                    kind: TokenKind::SmallerThan,
-                },
-            ));
+                ));
+            }
         }
 
         Err(ParserError::NoMatch)
     }
 
-    fn identifier((position, input): (usize, &str)) -> ParserResult<Token> {
+    fn identifier((position, input): (usize, &str)) -> ParserResult<'_, Token> {
         let mut iden = String::new();
         let mut current_position = input;
         let start = position;
@@ -393,8 +525,8 @@ impl<'i> Tokenizer<'i> {
                 return Err(ParserError::NoMatch);
             }
 
-            end += 1;
-            current_position = &current_position[1..];
+            end += char.len_utf8();
+            current_position = &current_position[char.len_utf8()..];
 
             if i != 0 && char == '\'' {
                 return Ok((
@@ -406,7 +538,7 @@ impl<'i> Tokenizer<'i> {
                 ));
             }
 
-            if char.is_alphabetic() || char == '.' {
+            if char.is_alphanumeric() || char == '_' || char == '.' {
                 iden.push(char)
             }
         }
@@ -414,19 +546,32 @@ impl<'i> Tokenizer<'i> {
         Err(ParserError::NoMatch)
     }
 
-    fn string((position, input): (usize, &str)) -> ParserResult<Token> {
+    fn string((position, input): (usize, &str)) -> ParserResult<'_, Token> {
         let mut value = String::new();
         let mut current_position = input;
         let start = position;
         let mut end = position;
+        let mut escaped = false;
 
         for (i, char) in input.chars().enumerate() {
             if i == 0 && char != '\"' {
                 return Err(ParserError::NoMatch);
             }
 
-            end += 1;
-            current_position = &current_position[1..];
+            end += char.len_utf8();
+            current_position = &current_position[char.len_utf8()..];
+
+            // A backslash keeps the next character, so strings can contain quotes
+            if escaped {
+                value.push(char);
+                escaped = false;
+                continue;
+            }
+
+            if i != 0 && char == '\\' {
+                escaped = true;
+                continue;
+            }
 
             if i != 0 && char == '\"' {
                 return Ok((
@@ -446,49 +591,40 @@ impl<'i> Tokenizer<'i> {
         Err(ParserError::NoMatch)
     }
 
-    fn number((position, input): (usize, &str)) -> ParserResult<Token> {
-        let mut number = String::new();
-        let start = position;
-        let mut end = position;
-        let mut current = input;
+    fn number((position, input): (usize, &str)) -> ParserResult<'_, Token> {
+        match input.chars().next() {
+            Some(c) if c.is_ascii_digit() => {}
+            Some(_) => return Err(ParserError::NoMatch),
+            None => return Err(ParserError::InputEnded),
+        }
 
-        for (i, char) in input.chars().enumerate() {
-            if i == 0 && !char.is_numeric() {
-                return Err(ParserError::NoMatch);
-            }
+        let (rest, integer) = take_while(|c| c.is_ascii_digit())((input, position))?;
 
-            if !char.is_numeric() {
-                break;
+        let (rest, kind) = match tag(".")(rest) {
+            Ok((after_point, _)) => {
+                let (rest, fraction) = take_while(|c| c.is_ascii_digit())(after_point)?;
+                (rest, TokenKind::Decimal(format!("{}.{}", integer, fraction)))
             }
-
-            end += 1;
-            current = &current[1..];
-
-            number.push(char);
-        }
-
-        let n: u32 = number
-            .parse()
-            .expect("Failed to parse number. Probably too big");
+            Err(_) => match integer.parse() {
+                Ok(n) => (rest, TokenKind::Integer(n)),
+                // Too large for an integer, keep it exact
+                Err(_) => (rest, TokenKind::Decimal(integer)),
+            },
+        };
 
         Ok((
-            (current, end),
+            rest,
             Token {
-                span: Span { start, end },
-                kind: TokenKind::Integer(n),
+                span: Span {
+                    start: position,
+                    end: rest.1,
+                },
+                kind,
             },
         ))
     }
 }
 
-impl<'i> Iterator for Tokenizer<'i> {
-    type Item = Token;
-
-    fn next(&mut self) -> Option<Self::Item> {
-        todo!()
-    }
-}
-
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -499,7 +635,7 @@ mod tests {
 
         let tokenizer = Tokenizer::new(input);
 
-        let tokens = tokenizer.parse();
+        let tokens = tokenizer.parse().unwrap();
 
         assert_eq!(
             tokens,
@@ -524,7 +660,7 @@ mod tests {
     fn test_tokenize_comma() {
         let input = "Select,Insert";
         let tokenizer = Tokenizer::new(input);
-        let tokens = tokenizer.parse();
+        let tokens = tokenizer.parse().unwrap();
 
         assert_eq!(
             tokens,
@@ -550,7 +686,7 @@ mod tests {
         let input = "Select   'id'";
 
         let tokenizer = Tokenizer::new(input);
-        let tokens = tokenizer.parse();
+        let tokens = tokenizer.parse().unwrap();
 
         assert_eq!(
             tokens,
@@ -571,12 +707,36 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_tokenize_string_escape_and_arrows() {
+        let input = "data->\"a\"->>\"say \\\"hi\\\"\"";
+
+        let tokenizer = Tokenizer::new(input);
+        let kinds = tokenizer
+            .parse()
+            .unwrap()
+            .into_iter()
+            .map(|t| t.kind)
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            kinds,
+            vec![
+                TokenKind::Identifier(String::from("data")),
+                TokenKind::Arrow,
+                TokenKind::String(String::from("a")),
+                TokenKind::DoubleArrow,
+                TokenKind::String(String::from("say \"hi\"")),
+            ]
+        );
+    }
+
     #[test]
     fn test_tokenize_select_with_where() {
         let input = "SELECT 'id' FROM 'users' WHERE 'id' = 2;";
 
         let tokenizer = Tokenizer::new(input);
-        let tokens = tokenizer.parse();
+        let tokens = tokenizer.parse().unwrap();
 
         assert_eq!(
             tokens,
@@ -648,4 +808,46 @@ mod tests {
             ]
         )
     }
+
+    #[test]
+    fn test_display_tokens() {
+        let sql = "select  'key', name->>\"a\\\"b\" FROM t WHERE x >= 1.5";
+        let printed = Tokenizer::new(sql)
+            .parse()
+            .unwrap()
+            .iter()
+            .map(|token| token.to_string())
+            .collect::<String>();
+
+        assert_eq!(printed, "SELECT 'key', name->>\"a\\\"b\" FROM t WHERE x >= 1.5");
+
+        // Reads back as the same tokens, apart from the whitespace
+        let kinds = |sql: &str| {
+            Tokenizer::new(sql)
+                .parse()
+                .unwrap()
+                .into_iter()
+                .map(|token| token.kind)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(kinds(&printed), kinds(sql));
+    }
+
+    #[test]
+    fn test_tokenize_unexpected_input() {
+        assert_eq!(
+            Tokenizer::new("DELETE FROM u @WHERE id = 1").parse(),
+            Err(Error {
+                found: '@',
+                position: 14
+            })
+        );
+        assert_eq!(
+            Tokenizer::new("SELECT \"open FROM t")
+                .parse()
+                .unwrap_err()
+                .to_string(),
+            "Invalid SQL: Unexpected \"\"\" at position 7"
+        );
+    }
 }
@@ -0,0 +1,359 @@
+use std::cmp::Ordering;
+use std::fmt::{self, Display, Formatter};
+
+use anyhow::{anyhow, bail, Result};
+
+use crate::schema::{ColumnKind, DecimalColumn, StringColumn};
+use crate::types::date::{Date, Interval, Time, Timestamp};
+use crate::types::decimal::Decimal;
+use crate::types::json;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Null,
+    String(String),
+    Int(i64),
+    Blob(Vec<u8>),
+    Date(Date),
+    Time(Time),
+    Timestamp(Timestamp),
+    Interval(Interval),
+    Decimal(Decimal),
+    Json(serde_json::Value),
+    Boolean(bool),
+}
+
+impl Value {
+    /// Orders two values of compatible types.
+    ///
+    /// Dates are compared with timestamps as midnight of that day, and string
+    /// literals are parsed when compared with a temporal value. Returns `None`
+    /// when the values cannot be compared.
+    pub fn compare(&self, other: &Self) -> Option<Ordering> {
+        match (self, other) {
+            (Value::Json(json), _) if !json.is_object() && !json.is_array() => {
+                Value::from_json(json).compare(other)
+            }
+            (_, Value::Json(json)) if !json.is_object() && !json.is_array() => {
+                self.compare(&Value::from_json(json))
+            }
+            (Value::Json(a), Value::Json(b)) => (a == b).then_some(Ordering::Equal),
+            (Value::Int(a), Value::Int(b)) => Some(a.cmp(b)),
+            (Value::String(a), Value::String(b)) => Some(a.cmp(b)),
+            (Value::Blob(a), Value::Blob(b)) => Some(a.cmp(b)),
+            (Value::Boolean(a), Value::Boolean(b)) => Some(a.cmp(b)),
+            (Value::Date(a), Value::Date(b)) => Some(a.cmp(b)),
+            (Value::Time(a), Value::Time(b)) => Some(a.cmp(b)),
+            (Value::Timestamp(a), Value::Timestamp(b)) => Some(a.cmp(b)),
+            (Value::Interval(a), Value::Interval(b)) => {
+                Some(a.total_micros().cmp(&b.total_micros()))
+            }
+            (Value::Decimal(a), Value::Decimal(b)) => Some(a.cmp(b)),
+            (Value::Decimal(a), Value::Int(b)) => Some(a.cmp(&Decimal::from_int(*b))),
+            (Value::Int(a), Value::Decimal(b)) => Some(Decimal::from_int(*a).cmp(b)),
+            (Value::Date(a), Value::Timestamp(b)) => Some(Timestamp::from_date(*a).ok()?.cmp(b)),
+            (Value::Timestamp(a), Value::Date(b)) => Some(a.cmp(&Timestamp::from_date(*b).ok()?)),
+            (Value::Date(_), Value::String(s)) => self.compare(&Value::Date(Date::parse(s).ok()?)),
+            (Value::Time(_), Value::String(s)) => self.compare(&Value::Time(Time::parse(s).ok()?)),
+            (Value::Timestamp(_), Value::String(s)) => {
+                self.compare(&Value::Timestamp(Timestamp::parse(s).ok()?))
+            }
+            (Value::String(_), Value::Date(_) | Value::Time(_) | Value::Timestamp(_)) => {
+                other.compare(self).map(Ordering::reverse)
+            }
+            _ => None,
+        }
+    }
+
+    pub fn add(&self, other: &Self) -> Result<Self> {
+        if let Some((left, right)) = Self::json_scalars(self, other) {
+            return left.add(&right);
+        }
+
+        match (self, other) {
+            (Value::Int(a), Value::Int(b)) => a
+                .checked_add(*b)
+                .map(Value::Int)
+                .ok_or_else(|| anyhow!("Integer overflow in {} + {}", a, b)),
+            (Value::Timestamp(timestamp), Value::Interval(interval))
+            | (Value::Interval(interval), Value::Timestamp(timestamp)) => {
+                Ok(Value::Timestamp(timestamp.add_interval(*interval)?))
+            }
+            (Value::Date(date), Value::Interval(interval))
+            | (Value::Interval(interval), Value::Date(date)) => {
+                // Whole days keep the result a date, anything finer turns it into a timestamp
+                if interval.micros == 0 {
+                    Ok(Value::Date(date.add_months(interval.months)?.add_days(interval.days)?))
+                } else {
+                    let timestamp = Timestamp::from_date(*date)?;
+                    Ok(Value::Timestamp(timestamp.add_interval(*interval)?))
+                }
+            }
+            (Value::Time(time), Value::Interval(interval))
+            | (Value::Interval(interval), Value::Time(time)) => {
+                Ok(Value::Time(time.add_micros(interval.micros)))
+            }
+            (Value::Decimal(_) | Value::Int(_), Value::Decimal(_) | Value::Int(_)) => {
+                Ok(Value::Decimal(self.to_decimal()?.checked_add(other.to_decimal()?)?))
+            }
+            (Value::Interval(a), Value::Interval(b)) => Ok(Value::Interval(a.checked_add(*b)?)),
+            _ => bail!("Cannot add {} and {}", self, other),
+        }
+    }
+
+    pub fn subtract(&self, other: &Self) -> Result<Self> {
+        if let Some((left, right)) = Self::json_scalars(self, other) {
+            return left.subtract(&right);
+        }
+
+        match (self, other) {
+            (Value::Int(a), Value::Int(b)) => a
+                .checked_sub(*b)
+                .map(Value::Int)
+                .ok_or_else(|| anyhow!("Integer overflow in {} - {}", a, b)),
+            (Value::Decimal(_) | Value::Int(_), Value::Decimal(_) | Value::Int(_)) => {
+                Ok(Value::Decimal(self.to_decimal()?.checked_sub(other.to_decimal()?)?))
+            }
+            (Value::Timestamp(a), Value::Timestamp(b)) => Ok(Value::Interval(a.since(*b))),
+            (Value::Date(a), Value::Date(b)) => Ok(Value::Interval(Interval {
+                months: 0,
+                days: a.0.checked_sub(b.0).ok_or_else(|| anyhow!("Interval out of range"))?,
+                micros: 0,
+            })),
+            (
+                Value::Timestamp(_) | Value::Date(_) | Value::Time(_) | Value::Interval(_),
+                Value::Interval(interval),
+            ) => self.add(&Value::Interval(interval.negate()?)),
+            _ => bail!("Cannot subtract {} from {}", other, self),
+        }
+    }
+
+    pub fn multiply(&self, other: &Self) -> Result<Self> {
+        if let Some((left, right)) = Self::json_scalars(self, other) {
+            return left.multiply(&right);
+        }
+
+        match (self, other) {
+            (Value::Int(a), Value::Int(b)) => a
+                .checked_mul(*b)
+                .map(Value::Int)
+                .ok_or_else(|| anyhow!("Integer overflow in {} * {}", a, b)),
+            (Value::Decimal(_) | Value::Int(_), Value::Decimal(_) | Value::Int(_)) => {
+                Ok(Value::Decimal(self.to_decimal()?.checked_mul(other.to_decimal()?)?))
+            }
+            _ => bail!("Cannot multiply {} by {}", self, other),
+        }
+    }
+
+    pub fn divide(&self, other: &Self) -> Result<Self> {
+        if let Some((left, right)) = Self::json_scalars(self, other) {
+            return left.divide(&right);
+        }
+
+        match (self, other) {
+            (Value::Int(_), Value::Int(0)) => bail!("Division by zero"),
+            (Value::Int(a), Value::Int(b)) => a
+                .checked_div(*b)
+                .map(Value::Int)
+                .ok_or_else(|| anyhow!("Integer overflow in {} / {}", a, b)),
+            (Value::Decimal(_) | Value::Int(_), Value::Decimal(_) | Value::Int(_)) => {
+                Ok(Value::Decimal(self.to_decimal()?.checked_div(other.to_decimal()?)?))
+            }
+            _ => bail!("Cannot divide {} by {}", self, other),
+        }
+    }
+
+    pub fn negate(&self) -> Result<Self> {
+        match self {
+            Value::Null => Ok(Value::Null),
+            Value::Int(value) => value
+                .checked_neg()
+                .map(Value::Int)
+                .ok_or_else(|| anyhow!("Integer overflow in -{}", value)),
+            Value::Decimal(decimal) => Ok(Value::Decimal(Decimal::new(
+                -decimal.mantissa(),
+                decimal.scale(),
+            ))),
+            Value::Interval(interval) => Ok(Value::Interval(interval.negate()?)),
+            Value::Json(json) if !json.is_object() && !json.is_array() => {
+                Value::from_json(json).negate()
+            }
+            _ => bail!("Cannot negate {}", self),
+        }
+    }
+
+    /// Implements `a || b` on the text forms of both values.
+    pub fn concat(&self, other: &Self) -> Self {
+        Value::String(format!("{}{}", self, other))
+    }
+
+    /// Implements `CAST(value AS kind)`. NULL stays NULL and strings are
+    /// parsed as literals of the target type.
+    pub fn cast(&self, kind: &ColumnKind) -> Result<Self> {
+        if let Value::Json(json) = self {
+            if !json.is_object() && !json.is_array() && *kind != ColumnKind::Json {
+                return Value::from_json(json).cast(kind);
+            }
+        }
+
+        let value = match (kind, self) {
+            (_, Value::Null) => Value::Null,
+            (ColumnKind::Int | ColumnKind::BigInt, Value::Int(_)) => self.clone(),
+            (ColumnKind::Int | ColumnKind::BigInt, Value::Decimal(decimal)) => {
+                let mantissa = decimal.rescale(0)?.mantissa();
+                Value::Int(
+                    i64::try_from(mantissa)
+                        .map_err(|_| anyhow!("{} is out of range for type int", self))?,
+                )
+            }
+            (ColumnKind::Int | ColumnKind::BigInt, Value::String(text)) => match text.trim().parse() {
+                Ok(value) => Value::Int(value),
+                Err(_) => Value::Decimal(Decimal::parse(text).map_err(|_| {
+                    anyhow!("Invalid input for type int: \"{}\"", text)
+                })?)
+                .cast(kind)?,
+            },
+            (ColumnKind::String(StringColumn { length }), _) => {
+                let mut text = self.to_string();
+
+                if text.len() > *length {
+                    let mut end = *length;
+                    while !text.is_char_boundary(end) {
+                        end -= 1;
+                    }
+                    text.truncate(end);
+                }
+
+                Value::String(text)
+            }
+            (ColumnKind::Text, _) => Value::String(self.to_string()),
+            (ColumnKind::Blob, Value::Blob(_)) => self.clone(),
+            (ColumnKind::Blob, Value::String(text)) => Value::Blob(text.as_bytes().to_vec()),
+            (ColumnKind::Date, Value::Date(_)) => self.clone(),
+            (ColumnKind::Date, Value::Timestamp(timestamp)) => Value::Date(timestamp.date()),
+            (ColumnKind::Date, Value::String(text)) => Value::Date(Date::parse(text)?),
+            (ColumnKind::Time, Value::Time(_)) => self.clone(),
+            (ColumnKind::Time, Value::Timestamp(timestamp)) => Value::Time(timestamp.time()),
+            (ColumnKind::Time, Value::String(text)) => Value::Time(Time::parse(text)?),
+            (ColumnKind::Timestamp, Value::Timestamp(_)) => self.clone(),
+            (ColumnKind::Timestamp, Value::Date(date)) => {
+                Value::Timestamp(Timestamp::from_date(*date)?)
+            }
+            (ColumnKind::Timestamp, Value::String(text)) => {
+                Value::Timestamp(Timestamp::parse(text)?)
+            }
+            (
+                ColumnKind::Decimal(DecimalColumn { precision, scale }),
+                Value::Int(_) | Value::Decimal(_) | Value::String(_),
+            ) => Value::Decimal(self.to_decimal()?.fit(*precision, *scale)?),
+            (ColumnKind::Json, Value::Json(_)) => self.clone(),
+            (ColumnKind::Json, Value::String(text)) => Value::Json(
+                serde_json::from_str(text).map_err(|err| anyhow!("Invalid JSON: {}", err))?,
+            ),
+            (ColumnKind::Json, Value::Int(value)) => Value::Json((*value).into()),
+            (ColumnKind::Json, Value::Decimal(decimal)) => {
+                Value::Json(serde_json::from_str(&decimal.to_string())?)
+            }
+            _ => bail!("Cannot cast {} to {}", self, kind),
+        };
+
+        Ok(value)
+    }
+
+    /// Unwraps JSON scalars on either side so they can take part in arithmetic.
+    fn json_scalars(left: &Self, right: &Self) -> Option<(Self, Self)> {
+        let unwrap = |value: &Self| match value {
+            Value::Json(json) if !json.is_object() && !json.is_array() => Value::from_json(json),
+            _ => value.clone(),
+        };
+
+        match (left, right) {
+            (Value::Json(_), _) | (_, Value::Json(_)) => {
+                let (left, right) = (unwrap(left), unwrap(right));
+
+                match (&left, &right) {
+                    (Value::Json(_), _) | (_, Value::Json(_)) => None,
+                    _ => Some((left, right)),
+                }
+            }
+            _ => None,
+        }
+    }
+
+    /// Converts a JSON scalar into the matching SQL value, objects and arrays stay JSON.
+    pub fn from_json(json: &serde_json::Value) -> Self {
+        match json {
+            serde_json::Value::Null => Value::Null,
+            serde_json::Value::String(value) => Value::String(value.clone()),
+            serde_json::Value::Number(number) => match number.as_i64() {
+                Some(value) => Value::Int(value),
+                _ => match Decimal::parse(&number.to_string()) {
+                    Ok(decimal) => Value::Decimal(decimal),
+                    Err(_) => Value::Json(json.clone()),
+                },
+            },
+            _ => Value::Json(json.clone()),
+        }
+    }
+
+    /// Implements `json -> key` and, with `as_text`, `json ->> key`.
+    ///
+    /// Keys select object members, integers select array elements. Keys are
+    /// string literals, so `data->'a'->>'b'` is written `data->"a"->>"b"` in
+    /// this dialect, where single quotes delimit identifiers. Missing members
+    /// are NULL, a JSON null is kept by `->` and becomes NULL with `->>`.
+    pub fn json_get(&self, key: &Self, as_text: bool) -> Result<Self> {
+        let json = match self {
+            Value::Json(json) => json,
+            Value::Null => return Ok(Value::Null),
+            _ => bail!("Cannot apply a JSON operator to {}", self),
+        };
+
+        let step = match key {
+            Value::String(key) => json::PathStep::Key(key.clone()),
+            Value::Int(index) if *index >= 0 => json::PathStep::Index(*index as usize),
+            _ => bail!("JSON keys must be strings or integers, got {}", key),
+        };
+
+        match json::get(json, &step) {
+            None => Ok(Value::Null),
+            Some(serde_json::Value::Null) if as_text => Ok(Value::Null),
+            Some(serde_json::Value::String(text)) if as_text => Ok(Value::String(text.clone())),
+            Some(value) if as_text => Ok(Value::String(value.to_string())),
+            Some(value) => Ok(Value::Json(value.clone())),
+        }
+    }
+
+    pub fn to_decimal(&self) -> Result<Decimal> {
+        match self {
+            Value::Decimal(decimal) => Ok(*decimal),
+            Value::Int(value) => Ok(Decimal::from_int(*value)),
+            Value::String(value) => Decimal::parse(value),
+            _ => bail!("Cannot convert {} to a decimal", self),
+        }
+    }
+}
+
+impl Display for Value {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Value::Null => write!(f, "NULL"),
+            Value::String(value) => write!(f, "{}", value),
+            Value::Int(value) => write!(f, "{}", value),
+            Value::Blob(bytes) => {
+                write!(f, "\\x")?;
+                for byte in bytes {
+                    write!(f, "{:02x}", byte)?;
+                }
+                Ok(())
+            }
+            Value::Date(value) => write!(f, "{}", value),
+            Value::Time(value) => write!(f, "{}", value),
+            Value::Timestamp(value) => write!(f, "{}", value),
+            Value::Interval(value) => write!(f, "{}", value),
+            Value::Decimal(value) => write!(f, "{}", value),
+            Value::Json(value) => write!(f, "{}", value),
+            Value::Boolean(value) => write!(f, "{}", value),
+        }
+    }
+}
@@ -1,165 +1,940 @@
 use std::fmt::{self, Display, Formatter};
+use std::path::Path;
 
+use anyhow::{anyhow, bail, Result};
+
+use crate::free_space::FreeSpaceMap;
 use crate::pager::{Page, Pager};
+use crate::schema::{ColumnKind, DecimalColumn, StringColumn, TableSchema};
+use crate::sql::Value;
+use crate::types::date::{Date, Time, Timestamp};
+use crate::types::decimal::Decimal;
 
 pub struct Table {
-    rows: Vec<Row>,
     pager: Pager,
-    count: usize,
+    schema: TableSchema,
+    free_space: FreeSpaceMap,
+}
+
+/// Location of a row: its data page and slot within that page.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct RowId {
+    pub page: u32,
+    pub slot: u16,
 }
 
 impl Table {
-    const ROWS_PER_PAGE: usize = Page::PAGE_SIZE / Row::OPTIONAL_ROW_SIZE;
+    // Page 0 of every table file: kind (1), magic (4), format version (1),
+    // first page of the free space map (4), last AUTOINCREMENT value (8).
+    // Version 2 added the null bitmap to rows.
+    const MAGIC: &'static [u8; 4] = b"MSQL";
+    const VERSION: u8 = 2;
+    const HEADER_PAGE: usize = 0;
+    const FREE_SPACE_MAP_OFFSET: usize = 6;
+    const AUTOINCREMENT_OFFSET: usize = 10;
+
+    pub fn open<P>(path: P, schema: TableSchema) -> Result<Self>
+    where
+        P: AsRef<Path>,
+    {
+        let mut pager = Pager::new(&path);
+
+        if pager.page_count() == 0 {
+            let page_index = pager.allocate_page();
+            let page = pager.get_page(page_index);
+            let mut page = page.lock().unwrap();
+            page[0] = Page::KIND_HEADER;
+            page[1..5].copy_from_slice(Self::MAGIC);
+            page[5] = Self::VERSION;
+        }
+
+        let header = pager.get_page(Self::HEADER_PAGE);
+        let header = header.lock().unwrap();
+
+        if header.kind() != Page::KIND_HEADER || &header[1..5] != Self::MAGIC {
+            bail!("{} is not a table file", path.as_ref().display());
+        }
+
+        if header[5] != Self::VERSION {
+            bail!(
+                "{} uses table format version {}, expected {}",
+                path.as_ref().display(),
+                header[5],
+                Self::VERSION
+            );
+        }
+
+        let free_space_map = header.read_u32(Self::FREE_SPACE_MAP_OFFSET);
+        drop(header);
 
-    pub fn new() -> Self {
-        let pager = Pager::new("./data.hex");
+        let free_space = FreeSpaceMap::load(&mut pager, free_space_map);
 
-        Self {
-            rows: Vec::new(),
+        Ok(Self {
             pager,
-            count: 0,
+            schema,
+            free_space,
+        })
+    }
+
+    pub fn schema(&self) -> &TableSchema {
+        &self.schema
+    }
+
+    pub fn insert(&mut self, row: &Row) -> Result<RowId> {
+        let cell = self.serialize(row)?;
+        Ok(self.insert_cell(&cell))
+    }
+
+    /// Encodes a row into a cell. A cell that does not fit into a page
+    /// releases the overflow pages it was given before failing.
+    fn serialize(&mut self, row: &Row) -> Result<Vec<u8>> {
+        let cell = row.serialize(&self.schema, &mut self.pager)?;
+
+        if cell.len() > Page::MAX_CELL_SIZE {
+            for first_page in Row::overflow_pages(&cell, &self.schema) {
+                self.pager.free_overflow(first_page)?;
+            }
+
+            bail!(
+                "Row is too large ({} bytes, at most {} bytes fit into a page)",
+                cell.len(),
+                Page::MAX_CELL_SIZE
+            );
         }
+
+        Ok(cell)
     }
 
-    pub fn insert(&mut self, row: Row) {
-        let page_index = self.count / Self::ROWS_PER_PAGE;
+    fn insert_cell(&mut self, cell: &[u8]) -> RowId {
+        if let Some(page_index) = self.free_space.find(cell.len() + Page::SLOT_SIZE) {
+            let page = self.pager.get_page(page_index);
+            let mut page = page.lock().unwrap();
+            let slot = page.insert_cell(cell);
+            self.free_space.set(page_index, page.free_space());
+
+            if let Some(slot) = slot {
+                return RowId {
+                    page: page_index as u32,
+                    slot: slot as u16,
+                };
+            }
+        }
+
+        let page_index = self.pager.allocate_page();
         let page = self.pager.get_page(page_index);
         let mut page = page.lock().unwrap();
+        page.init_data();
+        let slot = page
+            .insert_cell(cell)
+            .expect("Cell does not fit into an empty page");
+        self.free_space.set(page_index, page.free_space());
+
+        RowId {
+            page: page_index as u32,
+            slot: slot as u16,
+        }
+    }
+
+    fn cell(&mut self, id: RowId) -> Result<Vec<u8>> {
+        let page = self.pager.get_page(id.page as usize);
+        let page = page.lock().unwrap();
+
+        match page.kind() {
+            Page::KIND_DATA => page
+                .cell(id.slot.into())
+                .map(Vec::from)
+                .ok_or_else(|| anyhow!("Row {:?} does not exist", id)),
+            _ => bail!("Row {:?} does not exist", id),
+        }
+    }
 
-        let index = self.count % Self::ROWS_PER_PAGE;
-        let offset_start = Row::OPTIONAL_ROW_SIZE * index;
-        let offset_end = offset_start + Row::OPTIONAL_ROW_SIZE;
-        let data = row.serialize();
-        page[offset_start..offset_end].copy_from_slice(data.as_slice());
-        self.count += 1;
+    pub fn get(&mut self, id: RowId) -> Result<Row> {
+        let cell = self.cell(id)?;
+        Row::deserialize(&cell, &self.schema, &mut self.pager)
+    }
+
+    pub fn delete(&mut self, id: RowId) -> Result<()> {
+        let cell = self.cell(id)?;
+
+        for first_page in Row::overflow_pages(&cell, &self.schema) {
+            self.pager.free_overflow(first_page)?;
+        }
+
+        let page = self.pager.get_page(id.page as usize);
+        let mut page = page.lock().unwrap();
+        page.delete_cell(id.slot.into());
+
+        if page.slot_count() == 0 {
+            drop(page);
+            self.pager.free_page(id.page as usize);
+            self.free_space.set(id.page as usize, 0);
+        } else {
+            self.free_space.set(id.page as usize, page.free_space());
+        }
+
+        Ok(())
+    }
+
+    /// Replaces a row, returning its new location if it had to move to another page.
+    pub fn update(&mut self, id: RowId, row: &Row) -> Result<RowId> {
+        let old_cell = self.cell(id)?;
+        let cell = self.serialize(row)?;
+
+        for first_page in Row::overflow_pages(&old_cell, &self.schema) {
+            self.pager.free_overflow(first_page)?;
+        }
+
+        let page = self.pager.get_page(id.page as usize);
+        let mut page = page.lock().unwrap();
+
+        if page.update_cell(id.slot.into(), &cell) {
+            self.free_space.set(id.page as usize, page.free_space());
+            return Ok(id);
+        }
+
+        page.delete_cell(id.slot.into());
+        self.free_space.set(id.page as usize, page.free_space());
+        drop(page);
+
+        Ok(self.insert_cell(&cell))
+    }
+
+    pub fn scan(&mut self) -> Scan<'_> {
+        Scan {
+            table: self,
+            page: 0,
+            slot: 0,
+        }
+    }
+
+    /// Next value of the AUTOINCREMENT column, one above the largest so far.
+    pub fn next_autoincrement(&mut self) -> Result<i64> {
+        let header = self.pager.get_page(Self::HEADER_PAGE);
+        let mut header = header.lock().unwrap();
+
+        let value = header
+            .read_i64(Self::AUTOINCREMENT_OFFSET)
+            .checked_add(1)
+            .ok_or_else(|| anyhow!("AUTOINCREMENT column reached its maximum value"))?;
+
+        header.write_i64(Self::AUTOINCREMENT_OFFSET, value);
+        Ok(value)
+    }
+
+    /// Keeps the values generated later above one written explicitly.
+    pub fn advance_autoincrement(&mut self, value: i64) {
+        let header = self.pager.get_page(Self::HEADER_PAGE);
+        let mut header = header.lock().unwrap();
+
+        if value > header.read_i64(Self::AUTOINCREMENT_OFFSET) {
+            header.write_i64(Self::AUTOINCREMENT_OFFSET, value);
+        }
     }
 
     pub fn flush(&mut self) {
+        let free_space_map = self.free_space.save(&mut self.pager);
+
+        let header = self.pager.get_page(Self::HEADER_PAGE);
+        header
+            .lock()
+            .unwrap()
+            .write_u32(Self::FREE_SPACE_MAP_OFFSET, free_space_map);
+
         self.pager.flush();
     }
+}
 
-    pub fn get_row(&mut self, index: usize) -> Option<Row> {
-        let page_index = index / Self::ROWS_PER_PAGE;
-        let page = self.pager.get_page(page_index);
-        let page = page.lock().unwrap();
-        Row::from_page(&page, index & Self::ROWS_PER_PAGE)
-    }
+/// Iterates over the rows of a table in storage order.
+pub struct Scan<'t> {
+    table: &'t mut Table,
+    page: usize,
+    slot: usize,
 }
 
-impl Display for Table {
-    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
-        for row in self.rows.iter() {
-            writeln!(f, "{}", row)?;
+impl<'t> Iterator for Scan<'t> {
+    type Item = Result<(RowId, Row)>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while self.page < self.table.pager.page_count() {
+            let page = self.table.pager.get_page(self.page);
+            let page = page.lock().unwrap();
+
+            if page.kind() != Page::KIND_DATA || self.slot >= page.slot_count() {
+                self.page += 1;
+                self.slot = 0;
+                continue;
+            }
+
+            let id = RowId {
+                page: self.page as u32,
+                slot: self.slot as u16,
+            };
+            let cell = page.cell(self.slot).map(Vec::from);
+            self.slot += 1;
+            drop(page);
+
+            if let Some(cell) = cell {
+                let row = Row::deserialize(&cell, &self.table.schema, &mut self.table.pager);
+                return Some(row.map(|row| (id, row)));
+            }
         }
 
-        Ok(())
+        None
     }
 }
 
+#[derive(Debug, Clone, PartialEq)]
 pub struct Row {
-    id: u32,
-    username: [u8; 128],
-    email: [u8; 128],
+    values: Vec<Value>,
 }
 
 impl Row {
-    pub const OPTIONAL_ROW_SIZE: usize = std::mem::size_of::<Option<Row>>();
+    /// Values longer than this are moved to a chain of overflow pages.
+    pub const MAX_INLINE_SIZE: usize = 1024;
 
-    pub fn new(id: u32, username: &str, email: &str) -> Self {
-        let username = username.as_bytes();
-        let email = email.as_bytes();
+    // Set in the length of a variable-length reference when the content lives in overflow pages
+    const OVERFLOW_FLAG: u32 = 1 << 31;
 
-        if username.len() > 128 {
-            panic!("Username is too long");
+    pub fn new(values: Vec<Value>) -> Self {
+        Self { values }
+    }
+
+    pub fn values(&self) -> &[Value] {
+        &self.values
+    }
+
+    pub fn into_values(self) -> Vec<Value> {
+        self.values
+    }
+
+    /// Encodes the row according to `schema`.
+    ///
+    /// Every column takes `Column::size()` bytes in the fixed part of the cell.
+    /// TEXT and BLOB columns store a length and a location there: either the
+    /// offset of their content in the variable part following the fixed part,
+    /// or the first overflow page when the content is too long to be inlined.
+    /// The fixed part ends with a bitmap of the NULL columns, whose bytes are zero.
+    /// Overflow pages written for a row that fails to encode are freed again.
+    pub fn serialize(&self, schema: &TableSchema, pager: &mut Pager) -> Result<Vec<u8>> {
+        let mut overflow = Vec::new();
+        let result = self.encode(schema, pager, &mut overflow);
+
+        if result.is_err() {
+            for first_page in overflow {
+                pager.free_overflow(first_page)?;
+            }
         }
 
-        if email.len() > 128 {
-            panic!("Email is too long");
+        result
+    }
+
+    fn encode(
+        &self,
+        schema: &TableSchema,
+        pager: &mut Pager,
+        overflow: &mut Vec<u32>,
+    ) -> Result<Vec<u8>> {
+        let columns = schema.columns();
+
+        if self.values.len() != columns.len() {
+            bail!(
+                "Expected {} values, got {}",
+                columns.len(),
+                self.values.len()
+            );
         }
 
-        let mut username_buffer: [u8; 128] = [0; 128];
-        let mut email_buffer: [u8; 128] = [0; 128];
+        let mut fixed = Vec::with_capacity(schema.size());
+        let mut variable = Vec::new();
+        let mut nulls = vec![0u8; columns.len().div_ceil(8)];
+
+        for (index, (column, value)) in columns.iter().zip(self.values.iter()).enumerate() {
+            match (column.kind(), value) {
+                (_, Value::Null) => {
+                    nulls[index / 8] |= 1 << (index % 8);
+                    fixed.resize(fixed.len() + column.size(), 0);
+                }
+                (ColumnKind::Int, Value::Int(value)) => {
+                    let value = i32::try_from(*value).map_err(|_| {
+                        anyhow!("Value {} is out of range for column \"{}\"", value, column.name())
+                    })?;
+
+                    fixed.extend_from_slice(&value.to_be_bytes());
+                }
+                (ColumnKind::BigInt, Value::Int(value)) => {
+                    fixed.extend_from_slice(&value.to_be_bytes());
+                }
+                (ColumnKind::String(StringColumn { length }), Value::String(value)) => {
+                    let bytes = value.as_bytes();
+
+                    if bytes.len() > *length {
+                        bail!(
+                            "Value for column \"{}\" is too long ({} > {})",
+                            column.name(),
+                            bytes.len(),
+                            length
+                        );
+                    }
+
+                    fixed.extend_from_slice(bytes);
+                    fixed.resize(fixed.len() + length - bytes.len(), 0);
+                }
+                (ColumnKind::Text, Value::String(value)) => {
+                    Self::serialize_variable(
+                        value.as_bytes(),
+                        &mut fixed,
+                        &mut variable,
+                        pager,
+                        overflow,
+                    );
+                }
+                (ColumnKind::Blob, Value::Blob(value)) => {
+                    Self::serialize_variable(value, &mut fixed, &mut variable, pager, overflow);
+                }
+                (ColumnKind::Blob, Value::String(value)) => {
+                    Self::serialize_variable(
+                        value.as_bytes(),
+                        &mut fixed,
+                        &mut variable,
+                        pager,
+                        overflow,
+                    );
+                }
+                (ColumnKind::Json, Value::Json(json)) => {
+                    let bytes = serde_json::to_vec(json)?;
+                    Self::serialize_variable(&bytes, &mut fixed, &mut variable, pager, overflow);
+                }
+                (ColumnKind::Json, Value::String(text)) => {
+                    let json = serde_json::from_str::<serde_json::Value>(text).map_err(|err| {
+                        anyhow!("Invalid JSON for column \"{}\": {}", column.name(), err)
+                    })?;
+                    let bytes = serde_json::to_vec(&json)?;
+                    Self::serialize_variable(&bytes, &mut fixed, &mut variable, pager, overflow);
+                }
+                (ColumnKind::Date, Value::Date(date)) => {
+                    fixed.extend_from_slice(&date.0.to_be_bytes());
+                }
+                (ColumnKind::Date, Value::String(value)) => {
+                    fixed.extend_from_slice(&Date::parse(value)?.0.to_be_bytes());
+                }
+                (ColumnKind::Time, Value::Time(time)) => {
+                    fixed.extend_from_slice(&time.0.to_be_bytes());
+                }
+                (ColumnKind::Time, Value::String(value)) => {
+                    fixed.extend_from_slice(&Time::parse(value)?.0.to_be_bytes());
+                }
+                (ColumnKind::Timestamp, Value::Timestamp(timestamp)) => {
+                    fixed.extend_from_slice(&timestamp.0.to_be_bytes());
+                }
+                (ColumnKind::Timestamp, Value::Date(date)) => {
+                    fixed.extend_from_slice(&Timestamp::from_date(*date)?.0.to_be_bytes());
+                }
+                (ColumnKind::Timestamp, Value::String(value)) => {
+                    fixed.extend_from_slice(&Timestamp::parse(value)?.0.to_be_bytes());
+                }
+                (ColumnKind::Decimal(DecimalColumn { precision, scale }), value) => {
+                    let decimal = match value {
+                        Value::Decimal(decimal) => *decimal,
+                        Value::Int(value) => Decimal::from_int(*value),
+                        Value::String(value) => Decimal::parse(value)?,
+                        _ => bail!(
+                            "Column \"{}\" of type {} cannot store {}",
+                            column.name(),
+                            column.kind(),
+                            value
+                        ),
+                    };
+
+                    let mantissa = decimal.fit(*precision, *scale)?.mantissa();
+                    let bytes = mantissa.to_be_bytes();
+                    fixed.extend_from_slice(&bytes[bytes.len() - column.size()..]);
+                }
+                (kind, value) => {
+                    bail!(
+                        "Column \"{}\" of type {} cannot store {}",
+                        column.name(),
+                        kind,
+                        value
+                    );
+                }
+            }
+        }
 
-        username_buffer[0..username.len()].copy_from_slice(username);
-        email_buffer[0..email.len()].copy_from_slice(email);
+        fixed.extend_from_slice(&nulls);
+        fixed.extend_from_slice(&variable);
+        Ok(fixed)
+    }
 
-        Self {
-            id,
-            username: username_buffer,
-            email: email_buffer,
+    fn serialize_variable(
+        bytes: &[u8],
+        fixed: &mut Vec<u8>,
+        variable: &mut Vec<u8>,
+        pager: &mut Pager,
+        overflow: &mut Vec<u32>,
+    ) {
+        let length = bytes.len() as u32;
+
+        if bytes.len() > Self::MAX_INLINE_SIZE {
+            let first_page = pager.write_overflow(bytes);
+            overflow.push(first_page);
+            fixed.extend_from_slice(&(length | Self::OVERFLOW_FLAG).to_be_bytes());
+            fixed.extend_from_slice(&first_page.to_be_bytes());
+        } else {
+            fixed.extend_from_slice(&length.to_be_bytes());
+            fixed.extend_from_slice(&(variable.len() as u32).to_be_bytes());
+            variable.extend_from_slice(bytes);
         }
     }
 
-    pub fn from_page(page: &Page, index: usize) -> Option<Self> {
-        let offset_start = index * Self::OPTIONAL_ROW_SIZE;
+    pub fn deserialize(data: &[u8], schema: &TableSchema, pager: &mut Pager) -> Result<Self> {
+        let variable_start = schema.size();
 
-        if page[offset_start..offset_start + 4] != [0xff, 0xff, 0xff, 0xff] {
-            return None;
+        if data.len() < variable_start {
+            bail!("Row is shorter than the table schema");
         }
 
-        let id: u32 = {
-            let b1: u32 = page[offset_start + 7].into();
-            let b2: u32 = page[offset_start + 6].into();
-            let b3: u32 = page[offset_start + 5].into();
-            let b4: u32 = page[offset_start + 4].into();
+        let nulls = &data[schema.null_bitmap_offset()..variable_start];
+        let mut values = Vec::with_capacity(schema.columns().len());
+        let mut offset = 0;
+
+        for (index, column) in schema.columns().iter().enumerate() {
+            let size = column.size();
+            let field = &data[offset..offset + size];
+            offset += size;
+
+            if nulls[index / 8] & (1 << (index % 8)) != 0 {
+                values.push(Value::Null);
+                continue;
+            }
+
+            let value = match column.kind() {
+                ColumnKind::Int => Value::Int(read_u32(field) as i32 as i64),
+                ColumnKind::BigInt => Value::Int(read_i64(field)),
+                ColumnKind::String(_) => {
+                    let end = field.iter().position(|&b| b == 0).unwrap_or(field.len());
+                    Value::String(String::from_utf8_lossy(&field[..end]).into_owned())
+                }
+                ColumnKind::Text => {
+                    let bytes = Self::deserialize_variable(field, &data[variable_start..], pager)?;
+                    Value::String(String::from_utf8(bytes)?)
+                }
+                ColumnKind::Blob => {
+                    let bytes = Self::deserialize_variable(field, &data[variable_start..], pager)?;
+                    Value::Blob(bytes)
+                }
+                ColumnKind::Json => {
+                    let bytes = Self::deserialize_variable(field, &data[variable_start..], pager)?;
+                    Value::Json(serde_json::from_slice(&bytes)?)
+                }
+                ColumnKind::Date => Value::Date(Date(read_u32(field) as i32)),
+                ColumnKind::Time => Value::Time(Time(read_i64(field))),
+                ColumnKind::Timestamp => Value::Timestamp(Timestamp(read_i64(field))),
+                ColumnKind::Decimal(DecimalColumn { scale, .. }) => {
+                    // Sign-extend the stored bytes back to 128 bits
+                    let fill = if field[0] & 0x80 != 0 { 0xff } else { 0 };
+                    let mut bytes = [fill; 16];
+                    bytes[16 - size..].copy_from_slice(field);
+                    Value::Decimal(Decimal::new(i128::from_be_bytes(bytes), *scale))
+                }
+            };
+
+            values.push(value);
+        }
 
-            b1 + (b2 * 256) + (b3 * (256 * 256)) + (b4 * (256 * 256 * 256))
-        };
+        Ok(Self { values })
+    }
 
-        let username = {
-            let mut data = [0u8; 128];
-            data.copy_from_slice(&page[offset_start + 8..offset_start + 136]);
-            data
-        };
+    /// First pages of the overflow chains referenced by a serialized row.
+    pub fn overflow_pages(data: &[u8], schema: &TableSchema) -> Vec<u32> {
+        let mut pages = Vec::new();
+        let mut offset = 0;
 
-        let email = {
-            let mut data = [0u8; 128];
-            data.copy_from_slice(&page[offset_start + 136..offset_start + 264]);
-            data
-        };
+        for column in schema.columns() {
+            let size = column.size();
 
-        Some(Self {
-            id,
-            username,
-            email,
-        })
+            if let ColumnKind::Text | ColumnKind::Blob | ColumnKind::Json = column.kind() {
+                let length = read_u32(&data[offset..offset + 4]);
+
+                if length & Self::OVERFLOW_FLAG != 0 {
+                    pages.push(read_u32(&data[offset + 4..offset + 8]));
+                }
+            }
+
+            offset += size;
+        }
+
+        pages
     }
 
-    pub fn serialize(&self) -> Box<[u8; Self::OPTIONAL_ROW_SIZE]> {
-        let mut data = Box::new([0u8; Self::OPTIONAL_ROW_SIZE]);
+    fn deserialize_variable(field: &[u8], variable: &[u8], pager: &mut Pager) -> Result<Vec<u8>> {
+        let length = read_u32(&field[0..4]);
+        let location = read_u32(&field[4..8]);
+
+        if length & Self::OVERFLOW_FLAG != 0 {
+            let bytes = pager.read_overflow(location)?;
+            let length = (length & !Self::OVERFLOW_FLAG) as usize;
+
+            if bytes.len() != length {
+                bail!("Overflow chain holds {} bytes, expected {}", bytes.len(), length);
+            }
 
-        data[0..4].copy_from_slice(&[0xff, 0xff, 0xff, 0xff]); // Any non-zero value
-        data[4..8].copy_from_slice(&self.id.to_be_bytes());
-        data[8..136].copy_from_slice(&self.username);
-        data[136..264].copy_from_slice(&self.email);
+            return Ok(bytes);
+        }
+
+        let start = location as usize;
+        let end = start + length as usize;
 
-        data
+        match variable.get(start..end) {
+            Some(bytes) => Ok(bytes.to_vec()),
+            None => bail!("Variable-length value is out of bounds"),
+        }
     }
 }
 
+fn read_u32(bytes: &[u8]) -> u32 {
+    let mut buffer = [0u8; 4];
+    buffer.copy_from_slice(&bytes[0..4]);
+    u32::from_be_bytes(buffer)
+}
+
+fn read_i64(bytes: &[u8]) -> i64 {
+    let mut buffer = [0u8; 8];
+    buffer.copy_from_slice(&bytes[0..8]);
+    i64::from_be_bytes(buffer)
+}
+
 impl Display for Row {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
-        write!(
-            f,
-            "| {} | {} | {} |",
-            self.id,
-            String::from_utf8_lossy(&self.username),
-            String::from_utf8_lossy(&self.email)
-        )
+        write!(f, "|")?;
+
+        for value in self.values.iter() {
+            write!(f, " {} |", value)?;
+        }
+
+        Ok(())
     }
 }
 
 #[cfg(test)]
 mod tests {
-
     use super::*;
+    use crate::database::testing::TempDatabase;
+
+    fn users_schema() -> TableSchema {
+        serde_json::from_str(
+            r#"{
+                "columns": [
+                    { "name": "id", "kind": { "type": "int" } },
+                    { "name": "username", "kind": { "type": "string", "length": 32 } },
+                    { "name": "email", "kind": { "type": "text" } },
+                    { "name": "avatar", "kind": { "type": "blob" } }
+                ]
+            }"#,
+        )
+        .unwrap()
+    }
 
     #[test]
     fn test_row_serialize() {
-        let row = Row::new(1, "Username", "email");
+        let schema = users_schema();
+        let dir = TempDatabase::new("row-serialize");
+        let path = dir.path("table.db");
+        let mut pager = Pager::new(&path);
+
+        let row = Row::new(vec![
+            Value::Int(1),
+            Value::String(String::from("Username")),
+            Value::String(String::from("email")),
+            Value::Blob(vec![1, 2, 3]),
+        ]);
+
+        let data = row.serialize(&schema, &mut pager).unwrap();
+
+        // Fixed part followed by the inlined TEXT and BLOB content
+        assert_eq!(data.len(), schema.size() + 5 + 3);
+        assert_eq!(&data[0..4], &[0, 0, 0, 1]);
+        assert_eq!(Row::deserialize(&data, &schema, &mut pager).unwrap(), row);
+    }
+
+    #[test]
+    fn test_row_serialize_nulls() {
+        let schema = users_schema();
+        let dir = TempDatabase::new("row-nulls");
+        let path = dir.path("table.db");
+        let mut pager = Pager::new(&path);
+
+        let row = Row::new(vec![
+            Value::Int(1),
+            Value::Null,
+            Value::Null,
+            Value::Blob(vec![1, 2, 3]),
+        ]);
+
+        let data = row.serialize(&schema, &mut pager).unwrap();
+
+        // NULL columns keep their space in the fixed part but have no content
+        assert_eq!(data.len(), schema.size() + 3);
+        assert_eq!(data[schema.null_bitmap_offset()], 0b0110);
+        assert!(Row::overflow_pages(&data, &schema).is_empty());
+        assert_eq!(Row::deserialize(&data, &schema, &mut pager).unwrap(), row);
+    }
+
+    #[test]
+    fn test_row_serialize_overflow() {
+        let schema = users_schema();
+        let dir = TempDatabase::new("row-overflow");
+        let path = dir.path("table.db");
+        let mut pager = Pager::new(&path);
+
+        let email = "a".repeat(Page::PAGE_SIZE * 2);
+        let row = Row::new(vec![
+            Value::Int(1),
+            Value::String(String::from("Username")),
+            Value::String(email),
+            Value::Blob(Vec::new()),
+        ]);
+
+        let data = row.serialize(&schema, &mut pager).unwrap();
+
+        assert_eq!(data.len(), schema.size());
+        assert_eq!(pager.page_count(), 3);
+        assert_eq!(Row::deserialize(&data, &schema, &mut pager).unwrap(), row);
+    }
+
+    #[test]
+    fn test_corrupt_overflow_chain() {
+        let dir = TempDatabase::new("overflow-corrupt");
+        let path = dir.path("table.db");
+        let mut pager = Pager::new(&path);
+
+        let first = pager.write_overflow(&[1; Page::OVERFLOW_CAPACITY * 2]);
+        let second = pager.get_page(first as usize).lock().unwrap().read_u32(1);
+
+        // Used bytes beyond the page
+        pager.get_page(second as usize).lock().unwrap().write_u16(5, u16::MAX);
+        assert!(pager.read_overflow(first).is_err());
+        pager.get_page(second as usize).lock().unwrap().write_u16(5, 1);
+        assert_eq!(pager.read_overflow(first).unwrap().len(), Page::OVERFLOW_CAPACITY + 1);
+
+        // A chain that links back to its first page
+        pager.get_page(second as usize).lock().unwrap().write_u32(1, first);
+        let error = pager.read_overflow(first).unwrap_err();
+        assert_eq!(error.to_string(), format!("Overflow chain starting at page {} does not end", first));
+
+        // Links to missing pages and to pages of another kind
+        pager.get_page(second as usize).lock().unwrap().write_u32(1, 100);
+        assert!(pager.read_overflow(first).is_err());
+        pager.get_page(second as usize).lock().unwrap().init_data();
+        assert!(pager.free_overflow(first).is_err());
+        assert!(pager.free_pages().is_empty());
+    }
+
+    #[test]
+    fn test_row_serialize_decimal() {
+        let schema: TableSchema = serde_json::from_str(
+            r#"{
+                "columns": [
+                    { "name": "price", "kind": { "type": "decimal", "precision": 5, "scale": 2 } },
+                    { "name": "total", "kind": { "type": "decimal", "precision": 20, "scale": 4 } }
+                ]
+            }"#,
+        )
+        .unwrap();
+        let dir = TempDatabase::new("row-decimal");
+        let path = dir.path("table.db");
+        let mut pager = Pager::new(&path);
+
+        let row = Row::new(vec![
+            Value::Decimal(Decimal::parse("-12.345").unwrap()),
+            Value::Decimal(Decimal::parse("1234567890123.5").unwrap()),
+        ]);
+
+        let data = row.serialize(&schema, &mut pager).unwrap();
+        let row = Row::deserialize(&data, &schema, &mut pager).unwrap();
+
+        assert_eq!(data.len(), 4 + 16 + 1);
+        assert_eq!(row.values()[0].to_string(), "-12.35");
+        assert_eq!(row.values()[1].to_string(), "1234567890123.5000");
+
+        let too_large = Row::new(vec![
+            Value::Decimal(Decimal::parse("1000").unwrap()),
+            Value::Int(0),
+        ]);
+        assert!(too_large.serialize(&schema, &mut pager).is_err());
+    }
+
+    #[test]
+    fn test_row_serialize_json() {
+        let schema: TableSchema = serde_json::from_str(
+            r#"{ "columns": [{ "name": "data", "kind": { "type": "json" } }] }"#,
+        )
+        .unwrap();
+        let dir = TempDatabase::new("row-json");
+        let path = dir.path("table.db");
+        let mut pager = Pager::new(&path);
+
+        let row = Row::new(vec![Value::String(String::from(r#"{ "a": [1, 2] }"#))]);
+        let data = row.serialize(&schema, &mut pager).unwrap();
+
+        // Whitespace is dropped when the document is stored
+        assert_eq!(&data[schema.size()..], br#"{"a":[1,2]}"#);
+        assert_eq!(
+            Row::deserialize(&data, &schema, &mut pager).unwrap().values()[0],
+            Value::Json(serde_json::json!({ "a": [1, 2] }))
+        );
+
+        let invalid = Row::new(vec![Value::String(String::from("{ a: 1 }"))]);
+        assert!(invalid.serialize(&schema, &mut pager).is_err());
+    }
+
+    #[test]
+    fn test_table_insert_and_scan() {
+        let dir = TempDatabase::new("table-scan");
+        let path = dir.path("table.db");
+        let mut table = Table::open(&path, users_schema()).unwrap();
+
+        for id in 0..100 {
+            let row = Row::new(vec![
+                Value::Int(id),
+                Value::String(format!("user{}", id)),
+                Value::String(format!("user{}@example.com", id)),
+                Value::Blob(vec![0; 64]),
+            ]);
+            table.insert(&row).unwrap();
+        }
+
+        let rows = table.scan().collect::<Result<Vec<_>>>().unwrap();
+
+        assert_eq!(rows.len(), 100);
+        assert_eq!(rows[42].1.values()[0], Value::Int(42));
+        assert!(table.pager.page_count() > 1);
+    }
+
+    #[test]
+    fn test_table_insert_frees_overflow_on_error() {
+        let dir = TempDatabase::new("table-overflow-error");
+        let path = dir.path("table.db");
+        let schema: TableSchema = serde_json::from_str(
+            r#"{
+                "columns": [
+                    { "name": "a", "kind": { "type": "text" } },
+                    { "name": "b", "kind": { "type": "text" } },
+                    { "name": "c", "kind": { "type": "text" } },
+                    { "name": "d", "kind": { "type": "text" } },
+                    { "name": "e", "kind": { "type": "text" } }
+                ]
+            }"#,
+        )
+        .unwrap();
+        let mut table = Table::open(&path, schema).unwrap();
+        let page_count = table.pager.page_count();
+
+        // The first value overflows, the others are inlined into a cell that is too large
+        let mut values = vec![Value::String("a".repeat(Page::PAGE_SIZE * 2))];
+        values.extend((0..4).map(|_| Value::String("b".repeat(Row::MAX_INLINE_SIZE))));
+        assert!(table.insert(&Row::new(values)).is_err());
+
+        // A value of the wrong type fails after the first value overflowed
+        let mut values = vec![Value::String("a".repeat(Page::PAGE_SIZE * 2))];
+        values.extend((0..4).map(|_| Value::Int(1)));
+        assert!(table.insert(&Row::new(values)).is_err());
+
+        let allocated = table.pager.page_count() - page_count;
+        assert!(allocated > 0);
+        assert_eq!(table.pager.free_pages().len(), allocated);
+    }
+
+    #[test]
+    fn test_table_delete_reuses_space() {
+        let dir = TempDatabase::new("table-delete");
+        let path = dir.path("table.db");
+        let mut table = Table::open(&path, users_schema()).unwrap();
+
+        let row = |id: i64| {
+            Row::new(vec![
+                Value::Int(id),
+                Value::String(format!("user{}", id)),
+                Value::String("x".repeat(Page::PAGE_SIZE)),
+                Value::Blob(vec![0; 512]),
+            ])
+        };
+
+        let ids = (0..20)
+            .map(|id| table.insert(&row(id)).unwrap())
+            .collect::<Vec<_>>();
+        let page_count = table.pager.page_count();
+
+        for id in ids.iter().step_by(2) {
+            table.delete(*id).unwrap();
+        }
+
+        // Freed slots and overflow pages are used again instead of growing the file
+        for id in 20..30 {
+            table.insert(&row(id)).unwrap();
+        }
+
+        assert_eq!(table.pager.page_count(), page_count);
+        assert_eq!(table.scan().count(), 20);
+
+        table.flush();
+        drop(table);
+
+        let mut table = Table::open(&path, users_schema()).unwrap();
+        assert_eq!(table.scan().count(), 20);
+    }
+
+    #[test]
+    fn test_table_autoincrement() {
+        let dir = TempDatabase::new("table-autoincrement");
+        let path = dir.path("table.db");
+        let mut table = Table::open(&path, users_schema()).unwrap();
+
+        assert_eq!(table.next_autoincrement().unwrap(), 1);
+        assert_eq!(table.next_autoincrement().unwrap(), 2);
+
+        // Explicit values only ever move the counter forward
+        table.advance_autoincrement(10);
+        table.advance_autoincrement(5);
+        assert_eq!(table.next_autoincrement().unwrap(), 11);
+
+        table.flush();
+        drop(table);
+
+        let mut table = Table::open(&path, users_schema()).unwrap();
+        assert_eq!(table.next_autoincrement().unwrap(), 12);
+    }
+
+    #[test]
+    fn test_table_update_moves_row() {
+        let dir = TempDatabase::new("table-update");
+        let path = dir.path("table.db");
+        let mut table = Table::open(&path, users_schema()).unwrap();
+
+        let row = |id: i64, email: String| {
+            Row::new(vec![
+                Value::Int(id),
+                Value::String(format!("user{}", id)),
+                Value::String(email),
+                Value::Blob(Vec::new()),
+            ])
+        };
+
+        let ids = (0..8)
+            .map(|id| table.insert(&row(id, "a".repeat(480))).unwrap())
+            .collect::<Vec<_>>();
+
+        let moved = table.update(ids[1], &row(1, "b".repeat(1000))).unwrap();
+        assert_ne!(moved, ids[1]);
 
-        let data = row.serialize();
+        let same = table.update(ids[0], &row(0, String::from("short"))).unwrap();
+        assert_eq!(same, ids[0]);
 
-        assert_eq!(&data[0..4], &[0xff, 0xff, 0xff, 0xff]);
+        let rows = table
+            .scan()
+            .map(|entry| entry.unwrap().1)
+            .collect::<Vec<_>>();
+        assert_eq!(rows.len(), 8);
+        assert!(rows.contains(&row(0, String::from("short"))));
+        assert!(rows.contains(&row(1, "b".repeat(1000))));
     }
 }
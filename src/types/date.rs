@@ -0,0 +1,534 @@
+use std::fmt::{self, Display, Formatter};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use anyhow::{anyhow, bail, Result};
+
+pub const MICROS_PER_SECOND: i64 = 1_000_000;
+pub const MICROS_PER_MINUTE: i64 = 60 * MICROS_PER_SECOND;
+pub const MICROS_PER_HOUR: i64 = 60 * MICROS_PER_MINUTE;
+pub const MICROS_PER_DAY: i64 = 24 * MICROS_PER_HOUR;
+
+/// Calendar date stored as the number of days since 1970-01-01.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Date(pub i32);
+
+impl Date {
+    pub fn from_ymd(year: i32, month: u32, day: u32) -> Result<Self> {
+        if !(1..=12).contains(&month) || day == 0 || day > days_in_month(year, month) {
+            bail!("Invalid date {:04}-{:02}-{:02}", year, month, day);
+        }
+
+        Self::from_days(days_from_civil(year, month, day))
+    }
+
+    fn from_days(days: i64) -> Result<Self> {
+        i32::try_from(days)
+            .map(Self)
+            .map_err(|_| anyhow!("Date out of range"))
+    }
+
+    pub fn to_ymd(self) -> (i32, u32, u32) {
+        civil_from_days(self.0)
+    }
+
+    /// Parses `YYYY-MM-DD`.
+    pub fn parse(input: &str) -> Result<Self> {
+        let parts = input.trim().split('-').collect::<Vec<_>>();
+
+        match parts.as_slice() {
+            [year, month, day] => Self::from_ymd(
+                parse_number(year, "year")?,
+                parse_number(month, "month")?,
+                parse_number(day, "day")?,
+            ),
+            _ => bail!("Invalid date \"{}\", expected YYYY-MM-DD", input),
+        }
+    }
+
+    /// Moves the date by whole months, clamping the day to the end of the target month.
+    pub fn add_months(self, months: i32) -> Result<Self> {
+        let (year, month, day) = self.to_ymd();
+        let total = year as i64 * 12 + (month as i64 - 1) + months as i64;
+        let year = i32::try_from(total.div_euclid(12)).map_err(|_| anyhow!("Date out of range"))?;
+        let month = total.rem_euclid(12) as u32 + 1;
+        let day = day.min(days_in_month(year, month));
+
+        Self::from_days(days_from_civil(year, month, day))
+    }
+
+    /// Moves the date by whole days.
+    pub fn add_days(self, days: i32) -> Result<Self> {
+        self.0
+            .checked_add(days)
+            .map(Self)
+            .ok_or_else(|| anyhow!("Date out of range"))
+    }
+
+    /// Start of the period holding the date, which may lie before the
+    /// earliest date for the first dates that can be stored.
+    pub fn truncate(self, unit: TruncUnit) -> Result<Self> {
+        let (year, month, _) = self.to_ymd();
+
+        match unit {
+            TruncUnit::Year => Self::from_days(days_from_civil(year, 1, 1)),
+            TruncUnit::Quarter => {
+                Self::from_days(days_from_civil(year, (month - 1) / 3 * 3 + 1, 1))
+            }
+            TruncUnit::Month => Self::from_days(days_from_civil(year, month, 1)),
+            // 1970-01-01 was a Thursday, weeks start on Monday
+            TruncUnit::Week => Self::from_days(self.0 as i64 - (self.0 as i64 + 3).rem_euclid(7)),
+            TruncUnit::Day | TruncUnit::Hour | TruncUnit::Minute | TruncUnit::Second => Ok(self),
+        }
+    }
+}
+
+impl Display for Date {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let (year, month, day) = self.to_ymd();
+        write!(f, "{:04}-{:02}-{:02}", year, month, day)
+    }
+}
+
+/// Time of day stored as microseconds since midnight.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Time(pub i64);
+
+impl Time {
+    /// Parses `HH:MM[:SS[.ffffff]]`.
+    pub fn parse(input: &str) -> Result<Self> {
+        let parts = input.trim().split(':').collect::<Vec<_>>();
+
+        let (hours, minutes, seconds) = match parts.as_slice() {
+            [hours, minutes] => (*hours, *minutes, "0"),
+            [hours, minutes, seconds] => (*hours, *minutes, *seconds),
+            _ => bail!("Invalid time \"{}\", expected HH:MM:SS", input),
+        };
+
+        let hours: i64 = parse_number(hours, "hour")?;
+        let minutes: i64 = parse_number(minutes, "minute")?;
+        let micros = parse_seconds(seconds)?;
+
+        if hours > 23 || minutes > 59 || micros >= MICROS_PER_MINUTE {
+            bail!("Invalid time \"{}\"", input);
+        }
+
+        Ok(Self(hours * MICROS_PER_HOUR + minutes * MICROS_PER_MINUTE + micros))
+    }
+
+    pub fn add_micros(self, micros: i64) -> Self {
+        Self((self.0 + micros).rem_euclid(MICROS_PER_DAY))
+    }
+}
+
+impl Display for Time {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let hours = self.0 / MICROS_PER_HOUR;
+        let minutes = self.0 % MICROS_PER_HOUR / MICROS_PER_MINUTE;
+        let seconds = self.0 % MICROS_PER_MINUTE / MICROS_PER_SECOND;
+        let micros = self.0 % MICROS_PER_SECOND;
+
+        write!(f, "{:02}:{:02}:{:02}", hours, minutes, seconds)?;
+
+        if micros != 0 {
+            write!(f, ".{:06}", micros)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Point in time stored as microseconds since 1970-01-01 00:00:00 UTC.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Timestamp(pub i64);
+
+impl Timestamp {
+    pub fn now() -> Self {
+        let elapsed = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .expect("System clock is before 1970");
+
+        Self(elapsed.as_micros() as i64)
+    }
+
+    /// Start of the date, which fails for dates beyond the range of timestamps.
+    pub fn from_date(date: Date) -> Result<Self> {
+        (date.0 as i64)
+            .checked_mul(MICROS_PER_DAY)
+            .map(Self)
+            .ok_or_else(|| anyhow!("Timestamp out of range"))
+    }
+
+    pub fn date(self) -> Date {
+        Date(self.0.div_euclid(MICROS_PER_DAY) as i32)
+    }
+
+    pub fn time(self) -> Time {
+        Time(self.0.rem_euclid(MICROS_PER_DAY))
+    }
+
+    /// Parses `YYYY-MM-DD[ HH:MM:SS[.ffffff]]`, a `T` may separate date and time.
+    pub fn parse(input: &str) -> Result<Self> {
+        let input = input.trim();
+
+        match input.split_once([' ', 'T']) {
+            Some((date, time)) => {
+                let date = Self::from_date(Date::parse(date)?)?;
+                let time = Time::parse(time)?;
+
+                date.0
+                    .checked_add(time.0)
+                    .map(Self)
+                    .ok_or_else(|| anyhow!("Timestamp out of range"))
+            }
+            None => Self::from_date(Date::parse(input)?),
+        }
+    }
+
+    pub fn add_interval(self, interval: Interval) -> Result<Self> {
+        let date = self.date().add_months(interval.months)?;
+
+        Self::from_date(date)?
+            .0
+            .checked_add(self.time().0)
+            .and_then(|micros| micros.checked_add(interval.days as i64 * MICROS_PER_DAY))
+            .and_then(|micros| micros.checked_add(interval.micros))
+            .map(Self)
+            .ok_or_else(|| anyhow!("Timestamp out of range"))
+    }
+
+    /// Difference between two timestamps, expressed in days and microseconds.
+    pub fn since(self, other: Self) -> Interval {
+        let micros = self.0 - other.0;
+
+        Interval {
+            months: 0,
+            days: (micros / MICROS_PER_DAY) as i32,
+            micros: micros % MICROS_PER_DAY,
+        }
+    }
+
+    pub fn truncate(self, unit: TruncUnit) -> Result<Self> {
+        let micros = match unit {
+            TruncUnit::Year
+            | TruncUnit::Quarter
+            | TruncUnit::Month
+            | TruncUnit::Week
+            | TruncUnit::Day => {
+                return Self::from_date(self.date().truncate(unit)?);
+            }
+            TruncUnit::Hour => MICROS_PER_HOUR,
+            TruncUnit::Minute => MICROS_PER_MINUTE,
+            TruncUnit::Second => MICROS_PER_SECOND,
+        };
+
+        Ok(Self(self.0 - self.0.rem_euclid(micros)))
+    }
+}
+
+impl Display for Timestamp {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {}", self.date(), self.time())
+    }
+}
+
+/// Length of time kept as separate months, days and microseconds, since
+/// months and days do not have a fixed length.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Interval {
+    pub months: i32,
+    pub days: i32,
+    pub micros: i64,
+}
+
+impl Interval {
+    const ZERO: Self = Self {
+        months: 0,
+        days: 0,
+        micros: 0,
+    };
+
+    /// Parses a list of quantities like `1 year 2 months -3 days 4 hours`.
+    pub fn parse(input: &str) -> Result<Self> {
+        let words = input.split_whitespace().collect::<Vec<_>>();
+
+        if words.is_empty() || words.len() % 2 != 0 {
+            bail!("Invalid interval \"{}\", expected pairs of quantity and unit", input);
+        }
+
+        let mut interval = Self::ZERO;
+
+        for pair in words.chunks(2) {
+            let quantity: i64 = parse_number(pair[0], "quantity")?;
+
+            let added = match pair[1].to_lowercase().trim_end_matches('s') {
+                "year" => add_scaled(&mut interval.months, quantity, 12),
+                "month" | "mon" => add_scaled(&mut interval.months, quantity, 1),
+                "week" => add_scaled(&mut interval.days, quantity, 7),
+                "day" => add_scaled(&mut interval.days, quantity, 1),
+                "hour" => add_scaled(&mut interval.micros, quantity, MICROS_PER_HOUR),
+                "minute" | "min" => add_scaled(&mut interval.micros, quantity, MICROS_PER_MINUTE),
+                "second" | "sec" => add_scaled(&mut interval.micros, quantity, MICROS_PER_SECOND),
+                unit => bail!("Unknown interval unit \"{}\"", unit),
+            };
+
+            if added.is_none() {
+                bail!("Interval \"{}\" is out of range", input);
+            }
+        }
+
+        Ok(interval)
+    }
+
+    pub fn negate(self) -> Result<Self> {
+        Self::checked(
+            self.months.checked_neg(),
+            self.days.checked_neg(),
+            self.micros.checked_neg(),
+        )
+    }
+
+    pub fn checked_add(self, other: Self) -> Result<Self> {
+        Self::checked(
+            self.months.checked_add(other.months),
+            self.days.checked_add(other.days),
+            self.micros.checked_add(other.micros),
+        )
+    }
+
+    fn checked(months: Option<i32>, days: Option<i32>, micros: Option<i64>) -> Result<Self> {
+        match (months, days, micros) {
+            (Some(months), Some(days), Some(micros)) => Ok(Self {
+                months,
+                days,
+                micros,
+            }),
+            _ => bail!("Interval out of range"),
+        }
+    }
+
+    /// Approximate length used for ordering, with 30-day months like PostgreSQL.
+    pub fn total_micros(self) -> i128 {
+        let days = self.months as i128 * 30 + self.days as i128;
+        days * MICROS_PER_DAY as i128 + self.micros as i128
+    }
+}
+
+impl Display for Interval {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let mut parts = Vec::new();
+        let (years, months) = (self.months / 12, self.months % 12);
+
+        if years != 0 {
+            parts.push(format!("{} year{}", years, plural(years as i64)));
+        }
+
+        if months != 0 {
+            parts.push(format!("{} month{}", months, plural(months as i64)));
+        }
+
+        if self.days != 0 {
+            parts.push(format!("{} day{}", self.days, plural(self.days as i64)));
+        }
+
+        if self.micros != 0 || parts.is_empty() {
+            let sign = if self.micros < 0 { "-" } else { "" };
+            parts.push(format!("{}{}", sign, Time(self.micros.abs())));
+        }
+
+        write!(f, "{}", parts.join(" "))
+    }
+}
+
+/// Precision accepted by `date_trunc`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TruncUnit {
+    Year,
+    Quarter,
+    Month,
+    Week,
+    Day,
+    Hour,
+    Minute,
+    Second,
+}
+
+impl TruncUnit {
+    pub fn parse(input: &str) -> Result<Self> {
+        match input.to_lowercase().as_str() {
+            "year" => Ok(Self::Year),
+            "quarter" => Ok(Self::Quarter),
+            "month" => Ok(Self::Month),
+            "week" => Ok(Self::Week),
+            "day" => Ok(Self::Day),
+            "hour" => Ok(Self::Hour),
+            "minute" => Ok(Self::Minute),
+            "second" => Ok(Self::Second),
+            _ => Err(anyhow!("Unknown date_trunc unit \"{}\"", input)),
+        }
+    }
+}
+
+fn plural(quantity: i64) -> &'static str {
+    if quantity.abs() == 1 {
+        ""
+    } else {
+        "s"
+    }
+}
+
+/// Adds `quantity * factor` to the field, `None` when the result does not fit.
+fn add_scaled<T>(field: &mut T, quantity: i64, factor: i64) -> Option<()>
+where
+    T: Copy + Into<i64> + TryFrom<i64>,
+{
+    let sum = quantity.checked_mul(factor)?.checked_add((*field).into())?;
+    *field = T::try_from(sum).ok()?;
+    Some(())
+}
+
+fn parse_number<T>(input: &str, what: &str) -> Result<T>
+where
+    T: std::str::FromStr,
+{
+    input
+        .trim()
+        .parse()
+        .map_err(|_| anyhow!("Invalid {} \"{}\"", what, input))
+}
+
+fn parse_seconds(input: &str) -> Result<i64> {
+    let (seconds, fraction) = input.split_once('.').unwrap_or((input, ""));
+    let seconds: i64 = parse_number(seconds, "second")?;
+
+    if fraction.len() > 6 || !fraction.chars().all(|c| c.is_ascii_digit()) {
+        bail!("Invalid fraction of a second \"{}\"", fraction);
+    }
+
+    let fraction = format!("{:0<6}", fraction);
+    let micros: i64 = parse_number(&fraction, "fraction of a second")?;
+
+    Ok(seconds * MICROS_PER_SECOND + micros)
+}
+
+fn is_leap_year(year: i32) -> bool {
+    (year % 4 == 0 && year % 100 != 0) || year % 400 == 0
+}
+
+fn days_in_month(year: i32, month: u32) -> u32 {
+    match month {
+        2 if is_leap_year(year) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+// Conversions between the proleptic Gregorian calendar and days since the
+// epoch, see <https://howardhinnant.github.io/date_algorithms.html>
+fn days_from_civil(year: i32, month: u32, day: u32) -> i64 {
+    let year = year as i64;
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year.rem_euclid(400);
+    let month = month as i64;
+    let day_of_year =
+        (153 * (if month > 2 { month - 3 } else { month + 9 }) + 2) / 5 + day as i64 - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+
+    era * 146097 + day_of_era - 719468
+}
+
+fn civil_from_days(days: i32) -> (i32, u32, u32) {
+    let days = days as i64 + 719468;
+    let era = days.div_euclid(146097);
+    let day_of_era = days.rem_euclid(146097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let day = (day_of_year - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+
+    (year as i32, month, day)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_date_roundtrip() {
+        let date = Date::parse("2026-01-31").unwrap();
+
+        assert_eq!(date.to_string(), "2026-01-31");
+        assert_eq!(Date::parse("1970-01-01").unwrap(), Date(0));
+        assert_eq!(Date::parse("1969-12-31").unwrap(), Date(-1));
+        assert!(Date::parse("2026-02-29").is_err());
+        assert!(Date::parse("2024-02-29").is_ok());
+    }
+
+    #[test]
+    fn test_interval_out_of_range() {
+        assert_eq!(
+            Interval::parse("9223372036854775807 seconds").unwrap_err().to_string(),
+            "Interval \"9223372036854775807 seconds\" is out of range"
+        );
+        assert!(Interval::parse("9999999999999 hours").is_err());
+        assert!(Interval::parse("4294967297 months").is_err());
+        assert!(Interval::parse("2147483647 months 1 month").is_err());
+        assert_eq!(
+            Interval::parse("2147483647 months").unwrap().months,
+            i32::MAX
+        );
+
+        let longest = Interval::parse("2147483647 months").unwrap();
+        assert!(longest.checked_add(longest).is_err());
+        assert!(Interval::parse("-2147483648 days").unwrap().negate().is_err());
+
+        let date = Date::parse("2026-01-31").unwrap();
+        assert!(date.add_months(i32::MAX).is_err());
+        assert!(date.add_days(i32::MAX).is_err());
+        assert_eq!(date.add_months(-1).unwrap().to_string(), "2025-12-31");
+
+        let timestamp = Timestamp::parse("2026-01-31 12:30:00").unwrap();
+        assert!(timestamp.add_interval(Interval::parse("1000000 years").unwrap()).is_err());
+    }
+
+    #[test]
+    fn test_timestamp_interval_arithmetic() {
+        let timestamp = Timestamp::parse("2026-01-31 12:30:00").unwrap();
+        let interval = Interval::parse("1 month 2 hours").unwrap();
+
+        assert_eq!(
+            timestamp.add_interval(interval).unwrap().to_string(),
+            "2026-02-28 14:30:00"
+        );
+        assert_eq!(
+            timestamp.add_interval(interval.negate().unwrap()).unwrap().to_string(),
+            "2025-12-31 10:30:00"
+        );
+    }
+
+    #[test]
+    fn test_truncate() {
+        let timestamp = Timestamp::parse("2026-10-18T17:45:12.5").unwrap();
+
+        assert_eq!(
+            timestamp.truncate(TruncUnit::Quarter).unwrap().to_string(),
+            "2026-10-01 00:00:00"
+        );
+        assert_eq!(
+            timestamp.truncate(TruncUnit::Month).unwrap().to_string(),
+            "2026-10-01 00:00:00"
+        );
+        assert_eq!(timestamp.truncate(TruncUnit::Week).unwrap().to_string(), "2026-10-12 00:00:00");
+        assert_eq!(timestamp.truncate(TruncUnit::Hour).unwrap().to_string(), "2026-10-18 17:00:00");
+
+        let date = Date::parse("2026-08-31").unwrap();
+        assert_eq!(date.truncate(TruncUnit::Quarter).unwrap().to_string(), "2026-07-01");
+
+        let date = Date::parse("2026-03-31").unwrap();
+        assert_eq!(date.truncate(TruncUnit::Quarter).unwrap().to_string(), "2026-01-01");
+    }
+}
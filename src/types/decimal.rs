@@ -0,0 +1,373 @@
+use std::cmp::Ordering;
+use std::fmt::{self, Display, Formatter};
+
+use anyhow::{anyhow, bail, Result};
+
+/// Exact fixed-point number: `mantissa * 10^-scale`.
+#[derive(Debug, Clone, Copy)]
+pub struct Decimal {
+    mantissa: i128,
+    scale: u32,
+}
+
+impl Decimal {
+    /// Largest number of digits a decimal can hold, as in `DECIMAL(38, s)`.
+    pub const MAX_PRECISION: u32 = 38;
+
+    // Scale used for quotients of operands with fewer fractional digits
+    const MIN_DIVISION_SCALE: u32 = 6;
+
+    pub fn new(mantissa: i128, scale: u32) -> Self {
+        Self { mantissa, scale }
+    }
+
+    pub fn from_int(value: i64) -> Self {
+        Self::new(value.into(), 0)
+    }
+
+    pub fn mantissa(self) -> i128 {
+        self.mantissa
+    }
+
+    pub fn scale(self) -> u32 {
+        self.scale
+    }
+
+    /// Parses `[-]digits[.digits]`.
+    pub fn parse(input: &str) -> Result<Self> {
+        let input = input.trim();
+        let (negative, digits) = match input.strip_prefix('-') {
+            Some(digits) => (true, digits),
+            None => (false, input),
+        };
+
+        let (integer, fraction) = digits.split_once('.').unwrap_or((digits, ""));
+
+        if integer.is_empty() && fraction.is_empty()
+            || !integer.chars().chain(fraction.chars()).all(|c| c.is_ascii_digit())
+        {
+            bail!("Invalid decimal \"{}\"", input);
+        }
+
+        let mantissa: i128 = format!("{}{}", integer, fraction)
+            .parse()
+            .map_err(|_| anyhow!("Decimal \"{}\" has too many digits", input))?;
+
+        let decimal = Self::new(
+            if negative { -mantissa } else { mantissa },
+            fraction.len() as u32,
+        );
+
+        if decimal.digits() > Self::MAX_PRECISION {
+            bail!("Decimal \"{}\" has too many digits", input);
+        }
+
+        Ok(decimal)
+    }
+
+    /// Number of significant digits, at least the number of fractional digits.
+    pub fn digits(self) -> u32 {
+        let mut digits = 1;
+        let mut rest = self.mantissa.unsigned_abs() / 10;
+
+        while rest > 0 {
+            digits += 1;
+            rest /= 10;
+        }
+
+        digits.max(self.scale)
+    }
+
+    /// Changes the number of fractional digits, rounding half away from zero.
+    pub fn rescale(self, scale: u32) -> Result<Self> {
+        match scale.cmp(&self.scale) {
+            Ordering::Equal => Ok(self),
+            Ordering::Greater => {
+                let mantissa = pow10(scale - self.scale)
+                    .and_then(|factor| self.mantissa.checked_mul(factor))
+                    .ok_or_else(|| anyhow!("Numeric overflow while rescaling {}", self))?;
+
+                Ok(Self::new(mantissa, scale))
+            }
+            Ordering::Less => {
+                let factor = pow10(self.scale - scale).unwrap_or(i128::MAX);
+                Ok(Self::new(round_div(self.mantissa, factor), scale))
+            }
+        }
+    }
+
+    /// Rescales to `scale` and checks that the result fits `precision` digits.
+    pub fn fit(self, precision: u32, scale: u32) -> Result<Self> {
+        if precision == 0 || precision > Self::MAX_PRECISION || scale > precision {
+            bail!("Invalid DECIMAL({}, {})", precision, scale);
+        }
+
+        let decimal = self.rescale(scale)?;
+
+        if decimal.digits() > precision {
+            bail!(
+                "Numeric field overflow: {} does not fit DECIMAL({}, {})",
+                self,
+                precision,
+                scale
+            );
+        }
+
+        Ok(decimal)
+    }
+
+    /// The same number without trailing fractional zeros, so that equal
+    /// numbers have equal mantissas and scales.
+    pub fn normalize(self) -> Self {
+        let mut decimal = self;
+
+        while decimal.scale > 0 && decimal.mantissa % 10 == 0 {
+            decimal = Self::new(decimal.mantissa / 10, decimal.scale - 1);
+        }
+
+        decimal
+    }
+
+    fn align(self, other: Self) -> Result<(i128, i128, u32)> {
+        let scale = self.scale.max(other.scale);
+        Ok((
+            self.rescale(scale)?.mantissa,
+            other.rescale(scale)?.mantissa,
+            scale,
+        ))
+    }
+
+    pub fn checked_add(self, other: Self) -> Result<Self> {
+        let (a, b, scale) = self.align(other)?;
+        a.checked_add(b)
+            .map(|mantissa| Self::new(mantissa, scale))
+            .ok_or_else(|| anyhow!("Numeric overflow in {} + {}", self, other))
+    }
+
+    pub fn checked_sub(self, other: Self) -> Result<Self> {
+        let (a, b, scale) = self.align(other)?;
+        a.checked_sub(b)
+            .map(|mantissa| Self::new(mantissa, scale))
+            .ok_or_else(|| anyhow!("Numeric overflow in {} - {}", self, other))
+    }
+
+    /// Multiplies exactly, rounding to at most `MAX_PRECISION` fractional
+    /// digits. The result keeps the scales of both operands while it fits,
+    /// so 2.50 * 3 is 7.50.
+    pub fn checked_mul(self, other: Self) -> Result<Self> {
+        let overflow = || anyhow!("Numeric overflow in {} * {}", self, other);
+
+        // Trailing zeros of values stored at a high scale would overflow the
+        // product of small numbers
+        let (a, b) = (self.normalize(), other.normalize());
+        let scale = a.scale + b.scale;
+        let target = scale.min(Self::MAX_PRECISION);
+
+        let mut limbs = wide_mul(a.mantissa.unsigned_abs(), b.mantissa.unsigned_abs());
+        let mut dropped = 0;
+
+        for _ in target..scale {
+            dropped = wide_div10(&mut limbs);
+        }
+
+        let [low, high, 0, 0] = limbs else {
+            return Err(overflow());
+        };
+
+        let magnitude = (u128::from(high) << 64 | u128::from(low))
+            .checked_add(u128::from(dropped >= 5))
+            .and_then(|magnitude| i128::try_from(magnitude).ok())
+            .ok_or_else(overflow)?;
+
+        let mantissa = match (self.mantissa < 0) != (other.mantissa < 0) {
+            true => -magnitude,
+            false => magnitude,
+        };
+
+        let product = Self::new(mantissa, target);
+        let scale = (self.scale + other.scale).min(Self::MAX_PRECISION);
+
+        Ok(product.rescale(scale).unwrap_or(product))
+    }
+
+    /// Divides with at least `MIN_DIVISION_SCALE` fractional digits in the result.
+    pub fn checked_div(self, other: Self) -> Result<Self> {
+        if other.mantissa == 0 {
+            bail!("Division by zero");
+        }
+
+        let scale = self
+            .scale
+            .max(other.scale)
+            .max(Self::MIN_DIVISION_SCALE);
+
+        // (a / 10^sa) / (b / 10^sb) = (a * 10^(scale + sb - sa) / b) / 10^scale
+        let dividend = pow10(scale + other.scale - self.scale)
+            .and_then(|factor| self.mantissa.checked_mul(factor))
+            .ok_or_else(|| anyhow!("Numeric overflow in {} / {}", self, other))?;
+
+        Ok(Self::new(round_div(dividend, other.mantissa), scale))
+    }
+}
+
+impl PartialEq for Decimal {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Decimal {}
+
+impl PartialOrd for Decimal {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Decimal {
+    fn cmp(&self, other: &Self) -> Ordering {
+        // Integer and fractional parts are compared separately, aligning both
+        // numbers to the larger scale could overflow
+        let scale = self.scale.max(other.scale);
+        let split = |decimal: &Self| {
+            let factor = pow10(decimal.scale).expect("Scale exceeds the maximum precision");
+            let fraction = decimal.mantissa % factor;
+            let fraction = fraction * pow10(scale - decimal.scale).expect("Scale exceeds the maximum precision");
+            (decimal.mantissa / factor, fraction)
+        };
+
+        split(self).cmp(&split(other))
+    }
+}
+
+impl Display for Decimal {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let digits = self.mantissa.unsigned_abs().to_string();
+        let scale = self.scale as usize;
+        let sign = if self.mantissa < 0 { "-" } else { "" };
+
+        if scale == 0 {
+            return write!(f, "{}{}", sign, digits);
+        }
+
+        let digits = format!("{:0>width$}", digits, width = scale + 1);
+        let (integer, fraction) = digits.split_at(digits.len() - scale);
+
+        write!(f, "{}{}.{}", sign, integer, fraction)
+    }
+}
+
+fn pow10(exponent: u32) -> Option<i128> {
+    10i128.checked_pow(exponent)
+}
+
+/// Product of two magnitudes as 64-bit limbs, least significant first.
+fn wide_mul(a: u128, b: u128) -> [u64; 4] {
+    let a = [a as u64, (a >> 64) as u64];
+    let b = [b as u64, (b >> 64) as u64];
+    let mut limbs = [0u64; 4];
+
+    for i in 0..2 {
+        let mut carry = 0u128;
+
+        for j in 0..2 {
+            let sum = u128::from(a[i]) * u128::from(b[j]) + u128::from(limbs[i + j]) + carry;
+            limbs[i + j] = sum as u64;
+            carry = sum >> 64;
+        }
+
+        limbs[i + 2] = carry as u64;
+    }
+
+    limbs
+}
+
+/// Divides a number given by [`wide_mul`] by 10, returning the remainder.
+fn wide_div10(limbs: &mut [u64; 4]) -> u64 {
+    let mut remainder = 0u128;
+
+    for limb in limbs.iter_mut().rev() {
+        let current = remainder << 64 | u128::from(*limb);
+        *limb = (current / 10) as u64;
+        remainder = current % 10;
+    }
+
+    remainder as u64
+}
+
+/// Integer division rounding half away from zero.
+fn round_div(dividend: i128, divisor: i128) -> i128 {
+    let quotient = dividend / divisor;
+    let remainder = dividend % divisor;
+
+    if remainder.unsigned_abs() * 2 >= divisor.unsigned_abs() {
+        if (dividend < 0) == (divisor < 0) {
+            quotient + 1
+        } else {
+            quotient - 1
+        }
+    } else {
+        quotient
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_decimal_parse_display() {
+        assert_eq!(Decimal::parse("12.50").unwrap().to_string(), "12.50");
+        assert_eq!(Decimal::parse("-0.05").unwrap().to_string(), "-0.05");
+        assert_eq!(Decimal::parse("7").unwrap().to_string(), "7");
+        assert!(Decimal::parse("1.2.3").is_err());
+    }
+
+    #[test]
+    fn test_decimal_arithmetic_is_exact() {
+        let a = Decimal::parse("0.1").unwrap();
+        let b = Decimal::parse("0.2").unwrap();
+
+        assert_eq!(a.checked_add(b).unwrap(), Decimal::parse("0.3").unwrap());
+        assert_eq!(a.checked_mul(b).unwrap().to_string(), "0.02");
+        assert_eq!(
+            Decimal::parse("10").unwrap().checked_div(Decimal::parse("3").unwrap()).unwrap().to_string(),
+            "3.333333"
+        );
+    }
+
+    #[test]
+    fn test_decimal_chained_multiplication() {
+        // 1.5 as stored in a DECIMAL(38, 10) column
+        let m = Decimal::parse("1.5").unwrap().rescale(10).unwrap();
+
+        let square = m.checked_mul(m).unwrap();
+        assert_eq!(square.to_string(), "2.25000000000000000000");
+
+        let fourth = square.checked_mul(m).unwrap().checked_mul(m).unwrap();
+        assert_eq!(fourth, Decimal::parse("5.0625").unwrap());
+
+        // Digits beyond the maximum scale are rounded
+        let tiny = Decimal::parse("0.0000000000000000000000000000000000001").unwrap();
+        let product = Decimal::parse("-0.15").unwrap().checked_mul(tiny).unwrap();
+        assert_eq!(product.to_string(), "-0.00000000000000000000000000000000000002");
+
+        // Products of 38 digit operands are computed exactly before rounding
+        let third = Decimal::parse("0.33333333333333333333333333333333333333").unwrap();
+        assert_eq!(
+            third.checked_mul(third).unwrap().to_string(),
+            "0.11111111111111111111111111111111111111"
+        );
+
+        let large = Decimal::parse("10000000000000000000").unwrap();
+        assert!(large.checked_mul(large).unwrap().checked_mul(large).is_err());
+    }
+
+    #[test]
+    fn test_decimal_rounding_and_precision() {
+        assert_eq!(Decimal::parse("2.345").unwrap().rescale(2).unwrap().to_string(), "2.35");
+        assert_eq!(Decimal::parse("-2.345").unwrap().rescale(2).unwrap().to_string(), "-2.35");
+        assert_eq!(Decimal::parse("99.994").unwrap().fit(4, 2).unwrap().to_string(), "99.99");
+        assert!(Decimal::parse("99.995").unwrap().fit(4, 2).is_err());
+    }
+}
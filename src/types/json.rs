@@ -0,0 +1,91 @@
+use anyhow::{bail, Result};
+use serde_json::Value as Json;
+
+/// One step of a JSON path like `$.users[0].name`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum PathStep {
+    Key(String),
+    Index(usize),
+}
+
+/// Parses a path of the form `$`, `$.key`, `$.key[0]` or `$."quoted key"`.
+pub fn parse_path(input: &str) -> Result<Vec<PathStep>> {
+    let mut chars = input.trim().chars().peekable();
+    let mut steps = Vec::new();
+
+    if chars.next() != Some('$') {
+        bail!("JSON path \"{}\" must start with $", input);
+    }
+
+    while let Some(char) = chars.next() {
+        match char {
+            '.' if chars.peek() == Some(&'"') => {
+                chars.next();
+                let key = chars.by_ref().take_while(|&c| c != '"').collect();
+                steps.push(PathStep::Key(key));
+            }
+            '.' => {
+                let mut key = String::new();
+
+                while let Some(&c) = chars.peek() {
+                    if c == '.' || c == '[' {
+                        break;
+                    }
+
+                    key.push(c);
+                    chars.next();
+                }
+
+                if key.is_empty() {
+                    bail!("Empty key in JSON path \"{}\"", input);
+                }
+
+                steps.push(PathStep::Key(key));
+            }
+            '[' => {
+                let index = chars.by_ref().take_while(|&c| c != ']').collect::<String>();
+
+                match index.trim().parse() {
+                    Ok(index) => steps.push(PathStep::Index(index)),
+                    Err(_) => bail!("Invalid array index \"{}\" in JSON path", index),
+                }
+            }
+            _ => bail!("Unexpected \"{}\" in JSON path \"{}\"", char, input),
+        }
+    }
+
+    Ok(steps)
+}
+
+pub fn get<'j>(json: &'j Json, step: &PathStep) -> Option<&'j Json> {
+    match step {
+        PathStep::Key(key) => json.as_object()?.get(key),
+        PathStep::Index(index) => json.as_array()?.get(*index),
+    }
+}
+
+pub fn extract<'j>(json: &'j Json, path: &[PathStep]) -> Option<&'j Json> {
+    path.iter().try_fold(json, get)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_extract_path() {
+        let json: Json =
+            serde_json::from_str(r#"{"users": [{"name": "ann"}, {"the name": "bob"}]}"#).unwrap();
+
+        let path = parse_path("$.users[0].name").unwrap();
+        assert_eq!(extract(&json, &path), Some(&Json::from("ann")));
+
+        let path = parse_path("$.users[1].\"the name\"").unwrap();
+        assert_eq!(extract(&json, &path), Some(&Json::from("bob")));
+
+        let path = parse_path("$.users[2]").unwrap();
+        assert_eq!(extract(&json, &path), None);
+
+        assert!(parse_path("users").is_err());
+    }
+}
@@ -0,0 +1,3 @@
+pub mod date;
+pub mod decimal;
+pub mod json;